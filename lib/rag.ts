@@ -1,20 +1,595 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+export interface IngestResult {
+  doc_id: string;
+  message: string;
+  total_chunks: number;
+  /** The content was already indexed for this user; doc_id is the existing document. */
+  already_ingested: boolean;
+}
+
+export interface DocumentSummary {
+  doc_id: string;
+  title: string;
+  total_chunks: number;
+  content_hash: string | null;
+  embedding_status: 'pending' | 'processing' | 'completed' | 'failed' | null;
+  created_at: string;
+}
+
+/**
+ * paragraph: whole paragraphs where they fit, else sentences (default);
+ * sentence: whole sentences; fixed: exactly target_words words.
+ */
+export type ChunkStrategy = 'paragraph' | 'fixed' | 'sentence';
+
+export interface ChunkingOptions {
+  /** Most words in a chunk (default 500). */
+  target_words?: number;
+  /** Words repeated from the end of one chunk at the start of the next (default 100). */
+  overlap_words?: number;
+  strategy?: ChunkStrategy;
+}
 
 /**
  * Ingests a document into the local RAG database.
+ * Files whose content (or text, ignoring case and whitespace) is already indexed for the
+ * same user are skipped unless allowDuplicate is set.
+ * @param path Absolute path to the file.
+ * @param userId Owner of the document.
+ * @param allowDuplicate Index the file even if identical content exists.
+ * @param chunking How to split the file; defaults to the app's chunking settings.
+ * @returns Document id and status message.
+ */
+export async function ingestDocument(
+  path: string,
+  userId?: string,
+  allowDuplicate: boolean = false,
+  chunking?: ChunkingOptions
+): Promise<IngestResult> {
+  return await invoke('ingest_document', { path, userId, allowDuplicate, chunking });
+}
+
+/**
+ * Stops an ingestion started by ingestDocument or the ingest queue; nothing of the document is kept.
+ * @param docId The doc_id from the ingestion's progress events.
+ */
+export async function cancelIngestion(docId: string): Promise<void> {
+  return await invoke('cancel_ingestion', { docId });
+}
+
+export interface DocumentMetadata {
+  user_id?: string | null;
+  case_id?: string | null;
+  /** Defaults to the file name when empty. */
+  title: string;
+  document_type: 'user_case' | 'knowledge_base';
+}
+
+/**
+ * Ingests a PDF into the local RAG database.
+ * PDFs already indexed for the same user are skipped unless allowDuplicate is set.
+ * @param path Absolute path to the PDF.
+ * @param metadata Owner, case and type recorded with each chunk.
+ * @param allowDuplicate Index the PDF even if identical content exists.
+ * @param chunking How to split the PDF; defaults to the app's chunking settings.
+ * @returns Document id and status message.
+ */
+export async function ingestPdf(
+  path: string,
+  metadata: DocumentMetadata,
+  allowDuplicate: boolean = false,
+  chunking?: ChunkingOptions
+): Promise<IngestResult> {
+  return await invoke('ingest_pdf', { path, metadata: { document_id: null, ...metadata }, allowDuplicate, chunking });
+}
+
+/**
+ * Fetches a web page and ingests its text into the local RAG database.
+ * Only HTML pages up to 5 MB are accepted; a page already indexed for the user is not stored again.
+ * @param url The page to fetch (http or https).
+ * @param metadata Owner, case and type recorded with each chunk; the title defaults to the URL.
+ * @param chunking How to split the page; defaults to the app's chunking settings.
+ * @returns Document id and status message.
+ */
+export async function ingestUrl(
+  url: string,
+  metadata: DocumentMetadata,
+  chunking?: ChunkingOptions
+): Promise<IngestResult> {
+  return await invoke('ingest_url', { url, metadata: { document_id: null, ...metadata }, chunking });
+}
+
+/**
+ * extracting: PDF pages read; chunking: reported once with the number of chunks;
+ * embedding: chunks embedded; storing: chunks written.
+ */
+export type IngestStage = 'extracting' | 'chunking' | 'embedding' | 'storing';
+
+export interface IngestProgress {
+  doc_id: string;
+  stage: IngestStage;
+  done: number;
+  total: number;
+}
+
+/**
+ * Subscribes to progress while documents are ingested.
+ * @param callback Called with the stage and how much of it is done.
+ * @returns Function that removes the listener.
+ */
+export async function onIngestProgress(
+  callback: (progress: IngestProgress) => void
+): Promise<UnlistenFn> {
+  return await listen<IngestProgress>('rag://ingest-progress', (event) => callback(event.payload));
+}
+
+export interface IngestComplete {
+  doc_id: string;
+  total_chunks: number;
+}
+
+/**
+ * Subscribes to documents finishing ingestion, once all their chunks are stored.
+ * @returns Function that removes the listener.
+ */
+export async function onIngestComplete(
+  callback: (complete: IngestComplete) => void
+): Promise<UnlistenFn> {
+  return await listen<IngestComplete>('rag://ingest-complete', (event) => callback(event.payload));
+}
+
+export interface IngestDefaults {
+  user_id?: string | null;
+  case_id?: string | null;
+  /** Defaults to 'user_case'. */
+  document_type?: 'user_case' | 'knowledge_base' | null;
+}
+
+export interface DirectoryProgress {
+  file: string;
+  status: 'ingested' | 'skipped' | 'failed';
+  error: string | null;
+  done: number;
+  total: number;
+}
+
+export interface DirectoryIngestSummary {
+  succeeded: number;
+  /** Files whose content was already indexed. */
+  skipped: number;
+  failed: { file: string; error: string }[];
+}
+
+/**
+ * Ingests every .pdf, .docx, .txt and .md file in a directory, one at a time.
+ * @param path Absolute path to the directory.
+ * @param metadataDefaults Owner, case and type recorded for every file.
+ * @param recursive Include subdirectories (default true).
+ * @param chunking How to split each file; defaults to the app's chunking settings.
+ * @returns Counts of ingested and skipped files, and the files that failed.
+ */
+export async function ingestDirectory(
+  path: string,
+  metadataDefaults?: IngestDefaults,
+  recursive: boolean = true,
+  chunking?: ChunkingOptions
+): Promise<DirectoryIngestSummary> {
+  return await invoke('ingest_directory', { path, metadataDefaults, recursive, chunking });
+}
+
+/**
+ * Subscribes to per-file progress while a directory is ingested.
+ * @returns Function that removes the listener.
+ */
+export async function onDirectoryProgress(
+  callback: (progress: DirectoryProgress) => void
+): Promise<UnlistenFn> {
+  return await listen<DirectoryProgress>('rag://directory-progress', (event) => callback(event.payload));
+}
+
+export type IngestJobStatus = 'pending' | 'processing' | 'done' | 'failed' | 'cancelled';
+
+export interface IngestJob {
+  id: string;
+  path: string;
+  title: string;
+  user_id: string | null;
+  /** Once done, the existing document if the same content was already indexed. */
+  document_id: string;
+  status: IngestJobStatus;
+  error: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+/**
+ * Queues a file for ingestion in the background and returns without waiting.
+ * Its document is listed as 'pending' until the queue reaches it.
+ * @param path Absolute path to a .pdf, .docx, .txt or .md file.
+ * @param metadata Owner, case and type recorded with each chunk.
+ * @param allowDuplicate Index the file even if identical content exists.
+ * @param chunking How to split the file; defaults to the app's chunking settings.
+ * @returns The queued job.
+ */
+export async function queueDocumentIngest(
+  path: string,
+  metadata: DocumentMetadata,
+  allowDuplicate: boolean = false,
+  chunking?: ChunkingOptions
+): Promise<IngestJob> {
+  return await invoke('queue_document_ingest', { path, metadata: { document_id: null, ...metadata }, allowDuplicate, chunking });
+}
+
+/**
+ * Lists queued and finished ingest jobs, newest first.
+ */
+export async function getIngestJobs(): Promise<IngestJob[]> {
+  return await invoke('get_ingest_jobs');
+}
+
+/**
+ * Cancels a job the queue has not started yet.
+ * @returns The cancelled job.
+ */
+export async function cancelIngestJob(jobId: string): Promise<IngestJob> {
+  return await invoke('cancel_ingest_job', { jobId });
+}
+
+/**
+ * Subscribes to ingest jobs as they are queued and change status.
+ * @returns Function that removes the listener.
+ */
+export async function onIngestJob(
+  callback: (job: IngestJob) => void
+): Promise<UnlistenFn> {
+  return await listen<IngestJob>('rag://ingest-job', (event) => callback(event.payload));
+}
+
+/**
+ * Lists documents indexed for a user.
+ * @param userId Owner of the documents.
+ * @returns Indexed documents, newest first.
+ */
+export async function listDocuments(userId?: string): Promise<DocumentSummary[]> {
+  return await invoke('list_documents', { userId });
+}
+
+export interface DuplicateGroup {
+  /** Shared text hash (file hash for documents indexed before text hashes were recorded). */
+  hash: string;
+  /** Oldest first. */
+  documents: DocumentSummary[];
+}
+
+/**
+ * Finds documents a user has indexed more than once, for cleaning up a library.
+ * @param userId Owner of the documents.
+ * @returns Groups of two or more documents with the same content.
+ */
+export async function findDuplicateDocuments(userId?: string): Promise<DuplicateGroup[]> {
+  return await invoke('find_duplicate_documents', { userId });
+}
+
+export interface UpdateResult {
+  /** Unchanged chunks that kept their embeddings. */
+  reused: number;
+  /** New or edited chunks that were embedded. */
+  embedded: number;
+  /** Chunks no longer in the file. */
+  deleted: number;
+}
+
+/**
+ * Re-reads a file and replaces an indexed document's chunks.
+ * Only chunks whose text changed are re-embedded.
  * @param path Absolute path to the file.
- * @returns Status message.
+ * @param docId The document to update.
+ */
+export async function reingestDocument(path: string, docId: string): Promise<UpdateResult> {
+  return await invoke('reingest_document', { path, docId });
+}
+
+/**
+ * Deletes an indexed document and its chunks.
+ * @param docId The document to delete.
+ */
+export async function deleteDocument(docId: string): Promise<void> {
+  return await invoke('delete_document', { docId });
+}
+
+/** Loading state of the local embedding model. */
+export type RagStatus =
+  | { state: 'not_loaded' }
+  | { state: 'downloading'; progress: number }
+  | { state: 'ready' }
+  | { state: 'failed'; message: string };
+
+/**
+ * Reports whether the local embedding model is ready. Search and ingest fail
+ * with a `rag.error` code until it is; the rest of the app works regardless.
  */
-export async function ingestDocument(path: string): Promise<string> {
-  return await invoke('ingest_document', { path });
+export async function ragStatus(): Promise<RagStatus> {
+  return await invoke('rag_status');
+}
+
+/**
+ * Rebuilds the in-memory search index from the database.
+ * Only needed for recovery; the index is maintained automatically.
+ * @returns The number of chunks indexed.
+ */
+export async function rebuildRagIndex(): Promise<number> {
+  return await invoke('rebuild_rag_index');
+}
+
+export interface CorruptChunk {
+  chunk_id: string;
+  document_id: string;
+  error: string;
+}
+
+export interface IntegrityReport {
+  /** Chunks with a stored embedding. */
+  checked: number;
+  corrupt: CorruptChunk[];
+  /** Corrupt chunks removed; re-ingest their documents to restore them. */
+  deleted: number;
+}
+
+/**
+ * Checks every stored embedding for corruption (e.g. a truncated write).
+ * Corrupt chunks are already skipped by search; this reports them.
+ * @param deleteCorrupt Also delete the corrupt chunks.
+ */
+export async function verifyRagIntegrity(deleteCorrupt: boolean = false): Promise<IntegrityReport> {
+  return await invoke('verify_rag_integrity', { deleteCorrupt });
+}
+
+export interface ReembedProgress {
+  done: number;
+  total: number;
+}
+
+/**
+ * Re-embeds chunks that have no embedding or were embedded with a different
+ * model than the current one, e.g. after the embedding model changes.
+ * @returns The number of chunks re-embedded.
+ */
+export async function reembedAllDocuments(): Promise<number> {
+  return await invoke('reembed_all_documents');
+}
+
+/**
+ * Subscribes to progress of reembedAllDocuments.
+ * @returns A function that removes the listener.
+ */
+export async function onReembedProgress(
+  callback: (progress: ReembedProgress) => void
+): Promise<UnlistenFn> {
+  return await listen<ReembedProgress>('rag://reembed-progress', (event) => callback(event.payload));
+}
+
+/** vector: embeddings only; keyword: exact terms (BM25); hybrid: both, the default. */
+export type SearchMode = 'vector' | 'keyword' | 'hybrid';
+
+export interface ContextChunk {
+  chunk_id: string;
+  document_id: string;
+  chunk_index: number;
+  text: string;
+  source_title: string;
+  /** Heading of the part of the document the chunk is from */
+  section: string | null;
+  document_type: string;
+  /**
+   * Character offsets [start, end) in text of the sentence most relevant to the query,
+   * when highlight was requested.
+   */
+  best_sentence_span: [number, number] | null;
+}
+
+/** A context chunk an LLM answer can cite as [number]. */
+export interface CitationSource {
+  number: number;
+  chunk_id: string;
+  document_id: string;
+  chunk_index: number;
+  title: string;
+  section?: string | null;
+  document_type: string;
+}
+
+export interface ContextResult {
+  chunks: ContextChunk[];
+  incompatible_chunks: number;
+}
+
+export interface ContextFilters {
+  userId?: string;
+  documentType?: string;
+  caseId?: string;
+  mode?: SearchMode;
+  /** Minimum similarity (0-1) for a chunk to be returned; defaults to the app's rag_min_score. */
+  minScore?: number;
+  /** Skip near-duplicate chunks (maximal marginal relevance); defaults to true. */
+  diversify?: boolean;
+  /** Include shared knowledge base documents; defaults to true. */
+  includeKnowledgeBase?: boolean;
+  /** Include the user's own case documents; defaults to true. */
+  includeUserCases?: boolean;
+  /** Only search documents labelled with this topic (see `listLegalTopics`). */
+  topic?: string;
+  /**
+   * Also search paraphrases of queries under five words, written by the chat model.
+   * Defaults to true when an OpenRouter key is configured, since it costs a chat call.
+   */
+  expandQuery?: boolean;
+  /** Find each chunk's most relevant sentence; defaults to false since it costs an embedding call. */
+  highlight?: boolean;
 }
 
 /**
  * Queries the RAG database for relevant context.
  * @param query The search query.
  * @param limit Number of results to return.
- * @returns Array of matching text chunks.
+ * @param filters Restrict results to a user, source, document type or case, and pick the search mode.
+ * @returns Matching chunks tagged with their document type, and how many stored chunks were skipped because
+ * they were embedded with a different model (see reembedAllDocuments).
+ */
+export async function queryContext(
+  query: string,
+  limit: number = 5,
+  filters: ContextFilters = {}
+): Promise<ContextResult> {
+  return await invoke('query_context', { query, limit, ...filters });
+}
+
+export interface RelatedDocument {
+  document_id: string;
+  title: string;
+  document_type: string;
+  /** Similarity of the best matching passage to the source document. */
+  score: number;
+  /** Text of the best matching passage. */
+  snippet: string;
+  chunk_id: string;
+  section: string | null;
+}
+
+/**
+ * Finds documents similar to a given one, from the user's library and the knowledge base.
+ * @param userId The user whose documents are searched.
+ * @param documentId The source document, which is left out of the results.
+ * @param limit Number of documents to return (default 5, at most 20).
+ * @returns Related documents with their best matching passage, most similar first.
+ */
+export async function findRelated(userId: string, documentId: string, limit?: number): Promise<RelatedDocument[]> {
+  return await invoke('find_related', { userId, documentId, limit });
+}
+
+export interface PrecedentSuggestion {
+  title: string;
+  /** Similarity (0-1) of the best matching passage. */
+  relevance_score: number;
+  excerpt: string;
+  document_id: string;
+}
+
+/**
+ * Suggests knowledge base precedents related to a case, leaving out any
+ * already in the user's case library (matched by title).
+ * @param caseText The case facts or IRAC analysis to match against.
+ * @param userId The student whose library is checked.
+ * @param limit Number of suggestions (default 5, at most 20).
+ */
+export async function recommendPrecedents(
+  caseText: string,
+  userId: string,
+  limit?: number
+): Promise<PrecedentSuggestion[]> {
+  return await invoke('recommend_precedents', { caseText, userId, limit });
+}
+
+export interface DocumentTopicsEvent {
+  doc_id: string;
+  topics: string[];
+  /** Set when tagging failed; the document stays untagged. */
+  error?: string;
+}
+
+/**
+ * Subscribes to topic labels assigned to documents in the background after ingestion.
+ * @returns Function that removes the listener.
+ */
+export async function onDocumentTopics(
+  callback: (event: DocumentTopicsEvent) => void
+): Promise<UnlistenFn> {
+  return await listen<DocumentTopicsEvent>('rag://document-topics', (event) => callback(event.payload));
+}
+
+/**
+ * Lists the legal topics documents can be labelled with.
+ */
+export async function listLegalTopics(): Promise<string[]> {
+  return await invoke('list_legal_topics');
+}
+
+/**
+ * Lists knowledge base documents and the user's documents labelled with a topic.
+ * @param topic A topic from `listLegalTopics` (case-insensitive).
+ * @param userId Owner of the user's documents.
+ * @returns Matching documents, newest first.
+ */
+export async function getDocumentsByTopic(topic: string, userId?: string): Promise<DocumentSummary[]> {
+  return await invoke('get_documents_by_topic', { topic, userId });
+}
+
+/**
+ * Gets the topics a document is labelled with, most relevant first.
+ */
+export async function getDocumentTopics(docId: string): Promise<string[]> {
+  return await invoke('get_document_topics', { docId });
+}
+
+export interface CorpusProgress {
+  /** Documents written or read so far. */
+  done: number;
+  total: number;
+}
+
+export interface CorpusExportSummary {
+  documents: number;
+  chunks: number;
+}
+
+export interface CorpusImportSummary {
+  imported: number;
+  /** Documents whose content was already indexed. */
+  skipped: number;
+  /** Documents that could not be imported (nothing of them was stored), by title. */
+  failed: { file: string; error: string }[];
+  /** Imported chunks without an embedding from the current model. */
+  needs_reembedding: number;
+  /** Chunks re-embedded with the current model after importing. */
+  reembedded: number;
+  /** Set when re-embedding failed; retry with reembedAllDocuments. */
+  reembed_error: string | null;
+}
+
+/**
+ * Writes every indexed document, its chunks and their embeddings to a single archive.
+ * @param path Where to write the archive.
+ */
+export async function exportRagCorpus(path: string): Promise<CorpusExportSummary> {
+  return await invoke('export_rag_corpus', { path });
+}
+
+/**
+ * Imports an archive written by exportRagCorpus. Documents already indexed are skipped, and
+ * chunks embedded with a different model are re-embedded (see onReembedProgress).
+ * @param path The archive to read.
+ */
+export async function importRagCorpus(path: string): Promise<CorpusImportSummary> {
+  return await invoke('import_rag_corpus', { path });
+}
+
+/**
+ * Subscribes to progress while the corpus is exported.
+ * @returns Function that removes the listener.
+ */
+export async function onCorpusExportProgress(
+  callback: (progress: CorpusProgress) => void
+): Promise<UnlistenFn> {
+  return await listen<CorpusProgress>('rag://corpus-export-progress', (event) => callback(event.payload));
+}
+
+/**
+ * Subscribes to progress while a corpus archive is imported.
+ * @returns Function that removes the listener.
  */
-export async function queryContext(query: string, limit: number = 5): Promise<string[]> {
-  return await invoke('query_context', { query, limit });
+export async function onCorpusImportProgress(
+  callback: (progress: CorpusProgress) => void
+): Promise<UnlistenFn> {
+  return await listen<CorpusProgress>('rag://corpus-import-progress', (event) => callback(event.payload));
 }
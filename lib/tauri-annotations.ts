@@ -0,0 +1,92 @@
+/**
+ * Tauri Annotations Service Wrapper
+ * Wraps Rust backend annotation functions for use in the frontend
+ */
+
+import { invoke } from "@tauri-apps/api/core"
+
+export interface CaseAnnotation {
+  id: string
+  user_id: string
+  document_id: string
+  /** Page of the highlight, from 1 */
+  page: number
+  /** Character offsets of the highlight within the page text */
+  start_offset: number
+  end_offset: number
+  selected_text: string
+  note?: string
+  /** Hex color, e.g. #ffeb3b */
+  color: string
+  created_at: string
+}
+
+export interface CreateAnnotationRequest {
+  user_id: string
+  document_id: string
+  page: number
+  start_offset: number
+  end_offset: number
+  selected_text: string
+  note?: string
+  /** Defaults to yellow */
+  color?: string
+}
+
+class TauriAnnotationService {
+  /**
+   * Highlight a passage; the highlight and its note become searchable
+   */
+  async addAnnotation(request: CreateAnnotationRequest): Promise<CaseAnnotation> {
+    try {
+      return await invoke<CaseAnnotation>("add_annotation", { request })
+    } catch (error) {
+      console.error("Error adding annotation:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Get the annotations on a document, ordered by page and position
+   */
+  async getAnnotations(documentId: string): Promise<CaseAnnotation[]> {
+    try {
+      return await invoke<CaseAnnotation[]>("get_document_annotations", {
+        documentId,
+      })
+    } catch (error) {
+      console.error("Error getting annotations:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Replace or clear an annotation's note
+   */
+  async updateNote(id: string, note?: string): Promise<CaseAnnotation> {
+    try {
+      return await invoke<CaseAnnotation>("update_annotation_note", {
+        id,
+        note,
+      })
+    } catch (error) {
+      console.error("Error updating annotation note:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Delete an annotation
+   */
+  async deleteAnnotation(id: string): Promise<void> {
+    try {
+      await invoke("delete_annotation", { id })
+    } catch (error) {
+      console.error("Error deleting annotation:", error)
+      throw error
+    }
+  }
+}
+
+// Export singleton instance
+export const tauriAnnotationService = new TauriAnnotationService()
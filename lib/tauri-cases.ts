@@ -0,0 +1,172 @@
+/**
+ * Tauri Cases Service Wrapper
+ * Wraps Rust backend case and tag functions for use in the frontend
+ */
+
+import { invoke } from "@tauri-apps/api/core"
+
+export interface Case {
+  id: string
+  user_id: string
+  title: string
+  case_name?: string
+  file_url?: string
+  issue?: string
+  rule?: string
+  analysis?: string
+  conclusion?: string
+  created_at: string
+  updated_at: string
+}
+
+export interface CreateCaseRequest {
+  user_id: string
+  title: string
+  case_name?: string
+  file_url?: string
+  issue?: string
+  rule?: string
+  analysis?: string
+  conclusion?: string
+}
+
+/** Fields left out are not changed */
+export interface UpdateCaseRequest {
+  case_id: string
+  title?: string
+  case_name?: string
+  file_url?: string
+  issue?: string
+  rule?: string
+  analysis?: string
+  conclusion?: string
+}
+
+export interface CaseTag {
+  id: string
+  user_id: string
+  name: string
+  /** Hex color, e.g. #607d8b */
+  color: string
+}
+
+export interface CreateTagRequest {
+  user_id: string
+  name: string
+  /** Defaults to grey */
+  color?: string
+}
+
+class TauriCaseService {
+  /**
+   * Create a new case
+   */
+  async createCase(request: CreateCaseRequest): Promise<Case> {
+    try {
+      return await invoke<Case>("create_case", { request })
+    } catch (error) {
+      console.error("Error creating case:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Get a user's cases, most recently updated first
+   * @param tagFilter Only return cases with any of these tag ids
+   */
+  async getCases(userId: string, tagFilter?: string[]): Promise<Case[]> {
+    try {
+      return await invoke<Case[]>("get_cases", { userId, tagFilter })
+    } catch (error) {
+      console.error("Error getting cases:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Update a case's title or IRAC sections
+   */
+  async updateCase(request: UpdateCaseRequest): Promise<Case> {
+    try {
+      return await invoke<Case>("update_case", { request })
+    } catch (error) {
+      console.error("Error updating case:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Delete a case
+   */
+  async deleteCase(caseId: string): Promise<void> {
+    try {
+      await invoke("delete_case", { caseId })
+    } catch (error) {
+      console.error("Error deleting case:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Create a tag; names must be unique per user
+   */
+  async createTag(request: CreateTagRequest): Promise<CaseTag> {
+    try {
+      return await invoke<CaseTag>("create_tag", { request })
+    } catch (error) {
+      console.error("Error creating tag:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Get a user's tags in name order
+   */
+  async getTags(userId: string): Promise<CaseTag[]> {
+    try {
+      return await invoke<CaseTag[]>("get_tags", { userId })
+    } catch (error) {
+      console.error("Error getting tags:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Delete a tag and remove it from every case
+   */
+  async deleteTag(tagId: string): Promise<void> {
+    try {
+      await invoke("delete_tag", { tagId })
+    } catch (error) {
+      console.error("Error deleting tag:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Tag a case
+   */
+  async addTagToCase(caseId: string, tagId: string): Promise<void> {
+    try {
+      await invoke("add_tag_to_case", { caseId, tagId })
+    } catch (error) {
+      console.error("Error tagging case:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Remove a tag from a case
+   */
+  async removeTagFromCase(caseId: string, tagId: string): Promise<void> {
+    try {
+      await invoke("remove_tag_from_case", { caseId, tagId })
+    } catch (error) {
+      console.error("Error untagging case:", error)
+      throw error
+    }
+  }
+}
+
+// Export singleton instance
+export const tauriCaseService = new TauriCaseService()
@@ -3,6 +3,30 @@
  * Converts Rust error messages to user-friendly format
  */
 
+import { invoke } from "@tauri-apps/api/core"
+
+/**
+ * Error returned by every Tauri command.
+ * `code` is stable (e.g. "sync.offline") and suited to localisation lookups;
+ * `user_message` is an English fallback.
+ */
+export interface CommandError {
+  code: string
+  message: string
+  user_message: string
+}
+
+export function isCommandError(error: unknown): error is CommandError {
+  return typeof error === "object" && error !== null && "code" in error && "message" in error
+}
+
+/**
+ * Text of an error for matching: the technical message of a command error
+ */
+function errorText(error: unknown): string {
+  return isCommandError(error) ? error.message : String(error)
+}
+
 export interface UserFriendlyError {
   title: string
   message: string
@@ -14,7 +38,7 @@ export interface UserFriendlyError {
  * Parse Rust error and return user-friendly message
  */
 export function handleTauriError(error: unknown): UserFriendlyError {
-  const errorString = String(error)
+  const errorString = errorText(error)
 
   // Database errors
   if (errorString.includes("Database error") || errorString.includes("SQLite error")) {
@@ -179,7 +203,7 @@ export function getActionButtonText(action?: string): string {
  * Check if error indicates offline mode
  */
 export function isOfflineError(error: unknown): boolean {
-  const errorString = String(error)
+  const errorString = errorText(error)
   return (
     errorString.includes("Offline") ||
     errorString.includes("Network") ||
@@ -193,7 +217,7 @@ export function isOfflineError(error: unknown): boolean {
 export function shouldRetry(error: unknown, attemptCount: number = 0): boolean {
   if (attemptCount >= 3) return false
 
-  const errorString = String(error)
+  const errorString = errorText(error)
   
   // Retry transient errors
   return (
@@ -211,3 +235,16 @@ export function getRetryDelay(attemptCount: number): number {
   return Math.min(1000 * Math.pow(2, attemptCount), 10000)
 }
 
+/**
+ * Path of the current diagnostic log file, or "" if logging is unavailable
+ */
+export async function getLogPath(): Promise<string> {
+  return await invoke("get_log_path")
+}
+
+/**
+ * Write all kept diagnostic logs into one file for attaching to a bug report
+ */
+export async function exportLogs(outputPath: string): Promise<void> {
+  return await invoke("export_logs", { outputPath })
+}
@@ -22,6 +22,32 @@ export interface Flashcard {
   created_at: string
 }
 
+export type ReviewStrategy = "all_cards" | "due_only" | "weakest_first" | "random"
+
+export interface ReviewSession {
+  id: string
+  cards: Flashcard[]
+  total: number
+  created_at: string
+}
+
+export interface ReviewProgress {
+  reviewed: number
+  remaining: number
+  session_score: number
+}
+
+export interface SessionSummary {
+  session_id: string
+  total: number
+  reviewed: number
+  recalled: number
+  average_quality: number
+  session_score: number
+  duration_secs: number
+  finished_at: string
+}
+
 class TauriFlashcardService {
   /**
    * Create a new flashcard set
@@ -122,6 +148,58 @@ class TauriFlashcardService {
       throw error
     }
   }
+
+  /**
+   * Start reviewing a set, with cards picked and ordered by the strategy
+   */
+  async startReviewSession(
+    setId: string,
+    userId: string,
+    strategy: ReviewStrategy
+  ): Promise<ReviewSession> {
+    try {
+      return await invoke<ReviewSession>("start_review_session", {
+        setId,
+        userId,
+        strategy,
+      })
+    } catch (error) {
+      console.error("Error starting review session:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Record how well a card was recalled, from 0 (blackout) to 5 (perfect)
+   */
+  async recordCardReview(
+    sessionId: string,
+    flashcardId: string,
+    quality: number
+  ): Promise<ReviewProgress> {
+    try {
+      return await invoke<ReviewProgress>("record_card_review", {
+        sessionId,
+        flashcardId,
+        quality,
+      })
+    } catch (error) {
+      console.error("Error recording card review:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Finish a review session and get its summary
+   */
+  async finishReviewSession(sessionId: string): Promise<SessionSummary> {
+    try {
+      return await invoke<SessionSummary>("finish_review_session", { sessionId })
+    } catch (error) {
+      console.error("Error finishing review session:", error)
+      throw error
+    }
+  }
 }
 
 // Export singleton instance
@@ -0,0 +1,68 @@
+/**
+ * Tauri Knowledge Graph Service Wrapper
+ * Wraps Rust backend knowledge graph functions for use in the frontend
+ */
+
+import { invoke } from "@tauri-apps/api/core"
+
+export type NodeType = "concept" | "case" | "statute" | "doctrine"
+
+export interface GraphNode {
+  id: string
+  label: string
+  type: NodeType
+  /** Document the concept was first extracted from */
+  document_id: string
+}
+
+/** Named `source`/`target` so the edges can be passed to d3.forceLink as-is */
+export interface GraphEdge {
+  id: string
+  source: string
+  target: string
+  relation: string
+  /** Number of chunks mentioning both concepts */
+  weight: number
+}
+
+export interface GraphExport {
+  nodes: GraphNode[]
+  edges: GraphEdge[]
+}
+
+export interface GraphStats {
+  nodes: number
+  edges: number
+  chunks: number
+  /** Batches of chunks the model's reply couldn't be used for */
+  failed_batches: number
+}
+
+class TauriKnowledgeGraphService {
+  /**
+   * Extract a document's concepts and relations, replacing its previous graph
+   */
+  async buildGraph(documentId: string): Promise<GraphStats> {
+    try {
+      return await invoke<GraphStats>("build_knowledge_graph", { documentId })
+    } catch (error) {
+      console.error("Error building knowledge graph:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Get the graph across a user's documents and the knowledge base
+   */
+  async getGraph(userId: string): Promise<GraphExport> {
+    try {
+      return await invoke<GraphExport>("get_graph", { userId })
+    } catch (error) {
+      console.error("Error getting knowledge graph:", error)
+      throw error
+    }
+  }
+}
+
+// Export singleton instance
+export const tauriKnowledgeGraphService = new TauriKnowledgeGraphService()
@@ -4,6 +4,8 @@
  */
 
 import { invoke } from "@tauri-apps/api/core"
+import { listen, type UnlistenFn } from "@tauri-apps/api/event"
+import type { CitationSource } from "./rag"
 
 interface Message {
   role: "system" | "user" | "assistant"
@@ -15,6 +17,38 @@ interface IRACResult {
   rule: string
   analysis: string
   conclusion: string
+  truncated?: boolean
+  /** Context chunks the analysis cites as [1], [2], ... */
+  sources?: CitationSource[]
+  /** Cases, statutes and other authorities the analysis cites */
+  citations?: LegalCitation[]
+}
+
+type CitationType = "case" | "statute" | "regulation" | "article"
+
+interface LegalCitation {
+  text: string
+  citation_type: CitationType
+  /** 0-1; pattern matches score higher than references classified by the model */
+  confidence: number
+}
+
+interface OpposingArguments {
+  counter_issue: string
+  counter_rule: string
+  counter_analysis: string
+  counter_conclusion: string
+  /** Points where the original rule application could be challenged */
+  weaknesses: string[]
+}
+
+interface CaseOpposition {
+  id: string
+  case_id: string
+  /** The analysis that was argued against */
+  irac: IRACResult
+  opposition: OpposingArguments
+  created_at: string
 }
 
 interface CaseHistory {
@@ -22,6 +56,35 @@ interface CaseHistory {
   summary: string
 }
 
+/** Dimensions a brief is scored on; omitted fields default to true */
+interface ScoringRubric {
+  clarity?: boolean
+  rule_citation?: boolean
+  fact_application?: boolean
+  counter_argument?: boolean
+  conclusion_strength?: boolean
+}
+
+interface ArgumentScore {
+  /** Mean of the scored dimensions, 0-10 */
+  total: number
+  /** Score per rubric dimension, 0-10 */
+  breakdown: Record<string, number>
+  feedback: string[]
+  suggestions: string[]
+  /** Set when the score was saved as an annotation on the brief's document */
+  annotation_id: string | null
+}
+
+/** Legal system answers reason under; defaults to the configured jurisdiction */
+export type Jurisdiction =
+  | "us_federal"
+  | { us_state: string }
+  | "uk"
+  | "eu"
+  | "australia"
+  | { custom: string }
+
 class TauriLLMService {
   constructor() {
     // API key is managed by the backend
@@ -96,6 +159,7 @@ class TauriLLMService {
       userId?: string
       caseIds?: string[]
       includeContext?: boolean
+      jurisdiction?: Jurisdiction
     } = {}
   ): Promise<IRACResult> {
     try {
@@ -104,6 +168,7 @@ class TauriLLMService {
         userId: options.userId,
         caseIds: options.caseIds,
         includeContext: options.includeContext ?? true,
+        jurisdiction: options.jurisdiction,
       })
       return result
     } catch (error) {
@@ -122,17 +187,22 @@ class TauriLLMService {
       studyTopic?: string
       userId?: string
       includeContext?: boolean
+      jurisdiction?: Jurisdiction
     } = {}
   ): Promise<string> {
     try {
-      const response = await invoke<string>("tutor_chat", {
+      const response = await invoke<{ reply: string; truncated: boolean }>("tutor_chat", {
         userMessage,
         caseHistory: context.caseHistory,
         studyTopic: context.studyTopic,
         userId: context.userId,
         includeContext: context.includeContext ?? true,
+        jurisdiction: context.jurisdiction,
       })
-      return response
+      if (response.truncated) {
+        console.warn("Tutor context was trimmed to fit the model's context window")
+      }
+      return response.reply
     } catch (error) {
       console.error("Error in tutor chat:", error)
       throw error
@@ -166,13 +236,85 @@ class TauriLLMService {
       throw error
     }
   }
+
+  /**
+   * Get the legal citations found when a case was last analysed
+   */
+  async getCaseCitations(caseId: string): Promise<LegalCitation[]> {
+    try {
+      return await invoke<LegalCitation[]>("get_case_citations", { caseId })
+    } catch (error) {
+      console.error("Error getting case citations:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Generate the other side's arguments against an IRAC analysis
+   * @param caseId Save the result with this case for getCaseOppositions
+   */
+  async generateOpposingArguments(irac: IRACResult, caseId?: string): Promise<OpposingArguments> {
+    try {
+      return await invoke<OpposingArguments>("generate_opposing_arguments", {
+        iracJson: JSON.stringify(irac),
+        caseId,
+      })
+    } catch (error) {
+      console.error("Error generating opposing arguments:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Score the argument in a brief against a rubric (every dimension by default)
+   * @param save Save the score as an annotation on the brief's ingested document
+   */
+  async scoreLegalArgument(
+    briefText: string,
+    rubric?: ScoringRubric,
+    save?: { userId: string; documentId: string }
+  ): Promise<ArgumentScore> {
+    try {
+      return await invoke<ArgumentScore>("score_legal_argument", {
+        briefText,
+        rubric,
+        userId: save?.userId,
+        documentId: save?.documentId,
+      })
+    } catch (error) {
+      console.error("Error scoring legal argument:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Get a case's analyses alongside the opposing arguments generated against them, newest first
+   */
+  async getCaseOppositions(caseId: string): Promise<CaseOpposition[]> {
+    try {
+      return await invoke<CaseOpposition[]>("get_case_oppositions", { caseId })
+    } catch (error) {
+      console.error("Error getting case oppositions:", error)
+      throw error
+    }
+  }
 }
 
 // Export singleton instance
 export const tauriLLMService = new TauriLLMService()
 
+// Prefix of the error returned when the model refuses a request
+const MODEL_REFUSAL_PREFIX = "Model refused the request:"
+
+/**
+ * Whether an error from a Tauri LLM command is a model refusal
+ */
+export function isModelRefusal(error: unknown): boolean {
+  return typeof error === "string" && error.startsWith(MODEL_REFUSAL_PREFIX)
+}
+
 // Export types
-export type { Message, IRACResult, CaseHistory }
+export type { Message, IRACResult, CaseHistory, CitationType, LegalCitation, OpposingArguments, CaseOpposition, ScoringRubric, ArgumentScore }
 
 // Quiz question type
 export interface QuizQuestion {
@@ -181,3 +323,82 @@ export interface QuizQuestion {
   correct_answer: number
   explanation: string
 }
+
+// Model offered by OpenRouter
+export interface AvailableModel {
+  id: string
+  name: string
+  context_length: number
+  pricing: {
+    // null when OpenRouter doesn't publish a price
+    prompt_per_million_tokens: number | null
+    completion_per_million_tokens: number | null
+  }
+  description: string | null
+}
+
+/**
+ * Models that can be chosen in the model settings (cached for an hour)
+ */
+export async function listAvailableModels(): Promise<AvailableModel[]> {
+  return await invoke<AvailableModel[]>("list_available_models")
+}
+
+export interface CostEstimate {
+  model: string
+  estimated_prompt_tokens: number
+  estimated_completion_tokens: number
+  estimated_cost_usd: number
+}
+
+/**
+ * Estimated cost of a chat request, for confirming large requests before sending
+ */
+export async function estimateLLMCost(
+  messages: Message[],
+  model?: string,
+  maxTokens?: number
+): Promise<CostEstimate> {
+  return await invoke<CostEstimate>("estimate_llm_cost", { messages, model, maxTokens })
+}
+
+export interface ApiKeyStatus {
+  /** Key with all but its first and last characters hidden */
+  key: string
+  throttled: boolean
+  throttled_for_secs: number | null
+}
+
+/**
+ * Configured OpenRouter API keys and which are rate limited (HTTP 429) right now
+ */
+export async function getApiKeyStatus(): Promise<ApiKeyStatus[]> {
+  return await invoke<ApiKeyStatus[]>("get_api_key_status")
+}
+
+export type CircuitBreakerStatus = "closed" | "open" | "half_open"
+
+/**
+ * Whether OpenRouter calls are paused after repeated failures ("open") or being retried ("half_open")
+ */
+export async function getCircuitBreakerStatus(): Promise<CircuitBreakerStatus> {
+  return await invoke<CircuitBreakerStatus>("get_circuit_breaker_status")
+}
+
+export interface ConversationTrimmedEvent {
+  conversation_id: string | null
+  /** Earliest messages left out of the prompt to fit the model's context window */
+  removed_messages: number
+}
+
+/**
+ * Subscribe to tutor replies whose early conversation messages were left out of the prompt
+ * @returns Function that removes the listener
+ */
+export async function onConversationTrimmed(
+  callback: (event: ConversationTrimmedEvent) => void
+): Promise<UnlistenFn> {
+  return await listen<ConversationTrimmedEvent>("tutor://conversation-trimmed", (event) =>
+    callback(event.payload)
+  )
+}
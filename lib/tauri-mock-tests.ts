@@ -4,6 +4,7 @@
  */
 
 import { invoke } from "@tauri-apps/api/core"
+import type { CitationSource } from "./rag"
 
 export interface TestQuestion {
   question: string
@@ -11,6 +12,10 @@ export interface TestQuestion {
   correct_answer: number
   explanation: string
   topic?: string
+  /** Difficulty the test was generated at, if one was chosen */
+  difficulty?: string
+  /** Context chunks the explanation cites as [1], [2], ... */
+  sources?: CitationSource[]
 }
 
 export interface MockTest {
@@ -38,6 +43,33 @@ export interface TestResult {
   completed_at: string
 }
 
+export interface ReadinessScore {
+  /** Correct rate (percent) across all results, recent results weighted higher */
+  overall_score: number
+  /** Correct rate (percent) per question topic */
+  by_topic: Record<string, number>
+  tests_taken: number
+  /** 0-1 */
+  estimated_pass_probability: number
+  /** Topics with the lowest correct rates, weakest first */
+  recommended_focus: string[]
+}
+
+export interface AttemptSummary {
+  result_id: string
+  completed_at: string
+  score: number
+  by_topic: Record<string, number>
+}
+
+export interface ResultComparison {
+  attempts: AttemptSummary[]
+  slope: number
+  improved: boolean
+  best_score: number
+  worst_score: number
+}
+
 class TauriMockTestService {
   private apiKey: string
 
@@ -52,7 +84,8 @@ class TauriMockTestService {
     userId: string,
     topics: string[],
     numQuestions: number = 10,
-    includeRagContext: boolean = true
+    includeRagContext: boolean = true,
+    difficulty?: string
   ): Promise<MockTest> {
     if (!this.apiKey) {
       throw new Error("OpenRouter API key is not configured")
@@ -65,6 +98,7 @@ class TauriMockTestService {
         numQuestions,
         apiKey: this.apiKey,
         includeRagContext,
+        difficulty,
       })
       return test
     } catch (error) {
@@ -108,6 +142,54 @@ class TauriMockTestService {
       throw error
     }
   }
+
+  /**
+   * Get the user's exam readiness, computed from their local test results
+   */
+  async getReadinessScore(userId: string): Promise<ReadinessScore> {
+    try {
+      return await invoke<ReadinessScore>("get_readiness_score", { userId })
+    } catch (error) {
+      console.error("Error getting readiness score:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Compare the user's attempts at a test, oldest first, with the score trend
+   */
+  async compareResults(userId: string, testId: string): Promise<ResultComparison> {
+    try {
+      return await invoke<ResultComparison>("compare_test_results", { userId, testId })
+    } catch (error) {
+      console.error("Error comparing test results:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Write the user's results to a CSV file, optionally only those since an ISO 8601 date
+   */
+  async exportResultsToCsv(userId: string, outputPath: string, fromDate?: string): Promise<void> {
+    try {
+      await invoke("export_test_results_to_csv", { userId, fromDate, outputPath })
+    } catch (error) {
+      console.error("Error exporting test results:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Move a mock test to the recycle bin
+   */
+  async deleteTest(testId: string): Promise<void> {
+    try {
+      await invoke("delete_mock_test", { testId })
+    } catch (error) {
+      console.error("Error deleting mock test:", error)
+      throw error
+    }
+  }
 }
 
 // Export singleton instance
@@ -0,0 +1,119 @@
+/**
+ * Tauri Profiles Service Wrapper
+ * Local profiles for the people sharing this device; the current profile
+ * prefills forms, but every other call still passes its user ID
+ */
+
+import { invoke } from "@tauri-apps/api/core"
+
+export interface UserProfile {
+  id: string
+  display_name: string
+  email?: string
+  avatar_url?: string
+  created_at: string
+}
+
+export interface CreateProfileRequest {
+  /** The signed-in Supabase user's ID; generated when missing */
+  id?: string
+  display_name: string
+  email?: string
+  avatar_url?: string
+}
+
+export interface UpdateProfileRequest {
+  id: string
+  display_name: string
+  email?: string
+  avatar_url?: string
+}
+
+class TauriProfileService {
+  /**
+   * Create a profile
+   */
+  async createProfile(request: CreateProfileRequest): Promise<UserProfile> {
+    try {
+      return await invoke<UserProfile>("create_profile", { request })
+    } catch (error) {
+      console.error("Error creating profile:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Get a profile by ID
+   */
+  async getProfile(id: string): Promise<UserProfile> {
+    try {
+      return await invoke<UserProfile>("get_profile", { id })
+    } catch (error) {
+      console.error("Error getting profile:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Replace a profile's name, email and avatar
+   */
+  async updateProfile(request: UpdateProfileRequest): Promise<UserProfile> {
+    try {
+      return await invoke<UserProfile>("update_profile", { request })
+    } catch (error) {
+      console.error("Error updating profile:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Delete a profile; data saved under its ID is kept
+   */
+  async deleteProfile(id: string): Promise<void> {
+    try {
+      await invoke("delete_profile", { id })
+    } catch (error) {
+      console.error("Error deleting profile:", error)
+      throw error
+    }
+  }
+
+  /**
+   * List every profile on this device by name
+   */
+  async listProfiles(): Promise<UserProfile[]> {
+    try {
+      return await invoke<UserProfile[]>("list_profiles")
+    } catch (error) {
+      console.error("Error listing profiles:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Make a profile the current one
+   */
+  async switchUser(profileId: string): Promise<UserProfile> {
+    try {
+      return await invoke<UserProfile>("switch_user", { profileId })
+    } catch (error) {
+      console.error("Error switching user:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Get the current profile, if one has been picked
+   */
+  async getCurrentUser(): Promise<UserProfile | null> {
+    try {
+      return await invoke<UserProfile | null>("get_current_user")
+    } catch (error) {
+      console.error("Error getting current user:", error)
+      throw error
+    }
+  }
+}
+
+// Export singleton instance
+export const tauriProfileService = new TauriProfileService()
@@ -0,0 +1,67 @@
+/**
+ * Tauri Question Bank Service Wrapper
+ * Questions shared from mock tests, searchable and reusable in new tests
+ */
+
+import { invoke } from "@tauri-apps/api/core"
+import type { MockTest } from "./tauri-mock-tests"
+
+export interface BankQuestion {
+  id: string
+  contributed_by_user: string
+  question: string
+  options: string[]
+  correct_answer: number
+  explanation: string
+  topic?: string
+  difficulty?: string
+  upvotes: number
+  created_at: string
+}
+
+export interface QuestionBankSearchOptions {
+  topic?: string
+  difficulty?: string
+  limit?: number
+}
+
+class TauriQuestionBankService {
+  /**
+   * Add a test's questions to the bank, returning how many were new
+   */
+  async contributeQuestions(testId: string, userId: string): Promise<number> {
+    try {
+      return await invoke<number>("contribute_questions", { testId, userId })
+    } catch (error) {
+      console.error("Error contributing questions:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Search the bank by keyword; an empty query lists the most upvoted questions
+   */
+  async search(query: string, options: QuestionBankSearchOptions = {}): Promise<BankQuestion[]> {
+    try {
+      return await invoke<BankQuestion[]>("search_question_bank", { query, ...options })
+    } catch (error) {
+      console.error("Error searching question bank:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Create a mock test from bank questions, in the order given
+   */
+  async buildTest(userId: string, questionIds: string[]): Promise<MockTest> {
+    try {
+      return await invoke<MockTest>("build_test_from_bank", { userId, questionIds })
+    } catch (error) {
+      console.error("Error building test from question bank:", error)
+      throw error
+    }
+  }
+}
+
+// Export singleton instance
+export const tauriQuestionBankService = new TauriQuestionBankService()
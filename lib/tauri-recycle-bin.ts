@@ -0,0 +1,56 @@
+/**
+ * Tauri Recycle Bin Service Wrapper
+ * Deleted cases, mock tests, flashcard sets and study plans, kept for 30 days
+ */
+
+import { invoke } from "@tauri-apps/api/core"
+
+export type RecyclableTable = "cases" | "mock_tests" | "flashcard_sets" | "study_plans"
+
+export interface DeletedItem {
+  table: RecyclableTable
+  id: string
+  title: string
+  deleted_at: string
+}
+
+class TauriRecycleBinService {
+  /**
+   * List a user's deleted items, most recently deleted first
+   */
+  async getDeletedItems(userId: string, table?: RecyclableTable): Promise<DeletedItem[]> {
+    try {
+      return await invoke<DeletedItem[]>("get_deleted_items", { userId, table })
+    } catch (error) {
+      console.error("Error getting deleted items:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Put one of a user's deleted items back where it was
+   */
+  async restore(userId: string, table: RecyclableTable, id: string): Promise<void> {
+    try {
+      await invoke("restore_deleted_item", { userId, table, id })
+    } catch (error) {
+      console.error("Error restoring deleted item:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Delete one of a user's items for good without waiting for it to expire
+   */
+  async permanentlyDelete(userId: string, table: RecyclableTable, id: string): Promise<void> {
+    try {
+      await invoke("permanently_delete_item", { userId, table, id })
+    } catch (error) {
+      console.error("Error permanently deleting item:", error)
+      throw error
+    }
+  }
+}
+
+// Export singleton instance
+export const tauriRecycleBinService = new TauriRecycleBinService()
@@ -4,6 +4,9 @@
  */
 
 import { invoke } from "@tauri-apps/api/core"
+import { listen, type UnlistenFn } from "@tauri-apps/api/event"
+
+export type TaskStatus = "locked" | "available" | "in_progress" | "done"
 
 export interface StudyTask {
   id: string
@@ -11,6 +14,10 @@ export interface StudyTask {
   description?: string
   completed: boolean
   due_date?: string
+  /** IDs of tasks that must be done first */
+  depends_on?: string[]
+  /** Recomputed by the backend whenever the plan is saved */
+  status?: TaskStatus
 }
 
 export interface StudyPlan {
@@ -52,6 +59,28 @@ class TauriStudyPlanService {
     }
   }
 
+  /**
+   * Generate a study plan with daily tasks, spaced reviews and mock tests up to the exam
+   */
+  async generateAIPlan(
+    userId: string,
+    examDate: string,
+    topics: string[],
+    hoursPerDay: number
+  ): Promise<StudyPlan> {
+    try {
+      return await invoke<StudyPlan>("generate_ai_study_plan", {
+        userId,
+        examDate,
+        topics,
+        hoursPerDay,
+      })
+    } catch (error) {
+      console.error("Error generating study plan:", error)
+      throw error
+    }
+  }
+
   /**
    * Get all study plans for a user
    */
@@ -83,11 +112,12 @@ class TauriStudyPlanService {
   }
 
   /**
-   * Update study plan progress
+   * Update study plan tasks; progress is computed from completed tasks,
+   * and the given progress is only used for plans without tasks
    */
   async updateProgress(
     planId: string,
-    progress: number,
+    progress?: number,
     tasks?: StudyTask[]
   ): Promise<StudyPlan> {
     try {
@@ -103,6 +133,57 @@ class TauriStudyPlanService {
     }
   }
 
+  /**
+   * Mark a task completed and recompute the plan's progress
+   */
+  async completeTask(planId: string, taskId: string): Promise<StudyPlan> {
+    try {
+      return await invoke<StudyPlan>("complete_study_task", {
+        planId,
+        taskId,
+      })
+    } catch (error) {
+      console.error("Error completing study task:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Subscribe to plans reaching 100% completion
+   */
+  async onPlanCompleted(callback: (planId: string) => void): Promise<UnlistenFn> {
+    return await listen<{ plan_id: string }>("plan-completed", (event) => callback(event.payload.plan_id))
+  }
+
+  /**
+   * Get the tasks of a plan whose prerequisites are all done
+   */
+  async getAvailableTasks(planId: string): Promise<StudyTask[]> {
+    try {
+      return await invoke<StudyTask[]>("get_available_study_tasks", {
+        planId,
+      })
+    } catch (error) {
+      console.error("Error getting available study tasks:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Write a plan's dated tasks to an .ics file as all-day calendar events
+   */
+  async exportToICal(planId: string, outputPath: string): Promise<void> {
+    try {
+      await invoke("export_study_plan_to_ical", {
+        planId,
+        outputPath,
+      })
+    } catch (error) {
+      console.error("Error exporting study plan to iCalendar:", error)
+      throw error
+    }
+  }
+
   /**
    * Delete a study plan
    */
@@ -4,6 +4,7 @@
  */
 
 import { invoke } from "@tauri-apps/api/core"
+import { listen, type UnlistenFn } from "@tauri-apps/api/event"
 
 export interface SyncStatus {
   is_syncing: boolean
@@ -12,13 +13,65 @@ export interface SyncStatus {
   is_online: boolean
 }
 
+export interface SyncProgressEvent {
+  table: string
+  done: number
+  total: number
+}
+
+export interface SyncReport {
+  pushed: number
+  pulled: number
+  failed: number
+  timestamp: string
+}
+
+export interface SyncErrorEvent {
+  message: string
+}
+
+export interface ConnectivityChangedEvent {
+  online: boolean
+}
+
+export interface SyncConflict {
+  id: string
+  table_name: string
+  record_id: string
+  local_data: Record<string, unknown>
+  remote_data: Record<string, unknown>
+  detected_at: string
+}
+
+export type KeepVersion = "local" | "remote"
+
+export interface DeadLetterOperation {
+  id: number
+  operation_type: string
+  table_name: string
+  record_id: string
+  data: string
+  attempts: number
+  last_error?: string
+  created_at: string
+  failed_at: string
+}
+
+export interface SyncEventHandlers {
+  onStarted?: () => void
+  onProgress?: (event: SyncProgressEvent) => void
+  onCompleted?: (report: SyncReport) => void
+  onError?: (event: SyncErrorEvent) => void
+  onConnectivityChanged?: (event: ConnectivityChangedEvent) => void
+}
+
 class TauriSyncService {
   /**
    * Manually trigger sync
    */
-  async syncNow(): Promise<void> {
+  async syncNow(): Promise<SyncReport> {
     try {
-      await invoke("sync_now")
+      return await invoke<SyncReport>("sync_now")
     } catch (error) {
       console.error("Error triggering sync:", error)
       throw error
@@ -38,6 +91,18 @@ class TauriSyncService {
     }
   }
 
+  /**
+   * Sync every `seconds` seconds until the app restarts or the config is reloaded
+   */
+  async setSyncInterval(seconds: number): Promise<void> {
+    try {
+      await invoke("set_sync_interval", { seconds })
+    } catch (error) {
+      console.error("Error setting sync interval:", error)
+      throw error
+    }
+  }
+
   /**
    * Check if device is online
    */
@@ -51,6 +116,79 @@ class TauriSyncService {
     }
   }
 
+  /**
+   * Set the signed-in user whose records are pulled from the cloud; null stops pulling
+   */
+  async setUser(userId: string | null): Promise<void> {
+    try {
+      await invoke("set_sync_user", { userId })
+    } catch (error) {
+      console.error("Error setting sync user:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Get records whose local and remote versions conflict
+   */
+  async getConflicts(): Promise<SyncConflict[]> {
+    try {
+      const conflicts = await invoke<SyncConflict[]>("get_sync_conflicts")
+      return conflicts
+    } catch (error) {
+      console.error("Error getting sync conflicts:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Resolve a conflict by keeping the local or remote version
+   */
+  async resolveConflict(id: string, keep: KeepVersion): Promise<void> {
+    try {
+      await invoke("resolve_sync_conflict", { id, keep })
+    } catch (error) {
+      console.error("Error resolving sync conflict:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Get queued operations that ran out of retries
+   */
+  async getDeadLetterOperations(): Promise<DeadLetterOperation[]> {
+    try {
+      return await invoke<DeadLetterOperation[]>("get_dead_letter_operations")
+    } catch (error) {
+      console.error("Error getting dead-letter operations:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Put a dead-lettered operation back on the sync queue
+   */
+  async retryDeadLetter(id: number): Promise<void> {
+    try {
+      await invoke("retry_dead_letter", { id })
+    } catch (error) {
+      console.error("Error retrying dead-letter operation:", error)
+      throw error
+    }
+  }
+
+  /**
+   * Drop a dead-lettered operation without syncing it
+   */
+  async discardDeadLetter(id: number): Promise<void> {
+    try {
+      await invoke("discard_dead_letter", { id })
+    } catch (error) {
+      console.error("Error discarding dead-letter operation:", error)
+      throw error
+    }
+  }
+
   /**
    * Subscribe to sync status changes
    * Returns a function to unsubscribe
@@ -71,6 +209,24 @@ class TauriSyncService {
     // Return unsubscribe function
     return () => clearInterval(interval)
   }
+
+  /**
+   * Listen for sync events emitted by the backend
+   * Returns a function that removes all listeners
+   */
+  async subscribeToEvents(handlers: SyncEventHandlers): Promise<() => void> {
+    const unlisteners: UnlistenFn[] = await Promise.all([
+      listen("sync://started", () => handlers.onStarted?.()),
+      listen<SyncProgressEvent>("sync://progress", (e) => handlers.onProgress?.(e.payload)),
+      listen<SyncReport>("sync://completed", (e) => handlers.onCompleted?.(e.payload)),
+      listen<SyncErrorEvent>("sync://error", (e) => handlers.onError?.(e.payload)),
+      listen<ConnectivityChangedEvent>("connectivity-changed", (e) =>
+        handlers.onConnectivityChanged?.(e.payload)
+      ),
+    ])
+
+    return () => unlisteners.forEach((unlisten) => unlisten())
+  }
 }
 
 // Export singleton instance
@@ -0,0 +1,11 @@
+-- Deleted rows are kept with a tombstone so other devices drop them on their next pull
+ALTER TABLE cases ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE flashcard_sets ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE flashcards ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE mock_tests ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE test_results ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE study_plans ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS idx_flashcard_sets_deleted_at ON flashcard_sets(deleted_at);
+CREATE INDEX IF NOT EXISTS idx_flashcards_deleted_at ON flashcards(deleted_at);
+CREATE INDEX IF NOT EXISTS idx_study_plans_deleted_at ON study_plans(deleted_at);
@@ -0,0 +1,302 @@
+/**
+ * Approximate Nearest Neighbour Index
+ * Inverted-file (IVF) index over chunk embeddings for fast cosine search
+ */
+
+use std::collections::HashMap;
+
+/// Below this many vectors a single list is used, i.e. an exact scan
+const MIN_VECTORS_FOR_LISTS: usize = 1024;
+
+/// k-means iterations when training list centroids
+const KMEANS_ITERATIONS: usize = 4;
+
+/// Training sample size per list; keeps builds fast on large stores
+const TRAINING_SAMPLES_PER_LIST: usize = 16;
+
+/// Fraction of lists scanned per query (at least `MIN_PROBES`)
+const PROBE_FRACTION: usize = 10;
+const MIN_PROBES: usize = 8;
+
+/// IVF index for one embedding space: vectors are assigned to their nearest
+/// centroid, and a query only scans the lists of its nearest centroids
+#[derive(Debug, Default)]
+pub struct IvfIndex {
+    centroids: Vec<Vec<f32>>,
+    lists: Vec<Vec<(String, Vec<f32>)>>,
+    /// List holding each id
+    positions: HashMap<String, usize>,
+}
+
+impl IvfIndex {
+    /// Train centroids on `entries` and index them all
+    pub fn build(entries: Vec<(String, Vec<f32>)>) -> Self {
+        let entries: Vec<(String, Vec<f32>)> = entries
+            .into_iter()
+            .map(|(id, v)| (id, normalized(&v)))
+            .collect();
+
+        // Roughly sqrt(n) lists of sqrt(n) vectors each
+        let list_count = if entries.len() < MIN_VECTORS_FOR_LISTS {
+            1
+        } else {
+            (entries.len() as f64).sqrt().ceil() as usize
+        };
+        let centroids = train_centroids(&entries, list_count);
+
+        let mut index = Self {
+            lists: vec![Vec::new(); centroids.len()],
+            centroids,
+            positions: HashMap::new(),
+        };
+        for (id, vector) in entries {
+            index.insert_normalized(id, vector);
+        }
+
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Add or replace a vector
+    pub fn insert(&mut self, id: String, vector: &[f32]) {
+        self.remove(&id);
+        self.insert_normalized(id, normalized(vector));
+    }
+
+    fn insert_normalized(&mut self, id: String, vector: Vec<f32>) {
+        if self.centroids.is_empty() {
+            self.centroids.push(vector.clone());
+            self.lists.push(Vec::new());
+        }
+
+        let list = nearest_one(&self.centroids, &vector);
+        self.positions.insert(id.clone(), list);
+        self.lists[list].push((id, vector));
+    }
+
+    /// Remove a vector, returning whether it was indexed
+    pub fn remove(&mut self, id: &str) -> bool {
+        match self.positions.remove(id) {
+            Some(list) => {
+                self.lists[list].retain(|(entry_id, _)| entry_id != id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Up to `k` ids most similar to `query`, best first, with cosine similarity
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32)> {
+        if self.centroids.is_empty() || k == 0 {
+            return Vec::new();
+        }
+
+        let query = normalized(query);
+        let probes = (self.centroids.len() / PROBE_FRACTION).max(MIN_PROBES);
+
+        let mut scored: Vec<(&str, f32)> = nearest(&self.centroids, &query, probes)
+            .into_iter()
+            .flat_map(|list| self.lists[list].iter())
+            .map(|(id, vector)| (id.as_str(), dot(&query, vector)))
+            .collect();
+
+        let k = k.min(scored.len());
+        if k < scored.len() {
+            scored.select_nth_unstable_by(k, |a, b| b.1.total_cmp(&a.1));
+            scored.truncate(k);
+        }
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        scored.into_iter().map(|(id, score)| (id.to_string(), score)).collect()
+    }
+}
+
+/// One IVF index per embedding model and dimension
+#[derive(Debug, Default)]
+pub struct AnnIndex {
+    spaces: HashMap<(String, usize), IvfIndex>,
+}
+
+impl AnnIndex {
+    /// Build from `(chunk_id, model, vector)` rows
+    pub fn build(rows: Vec<(String, String, Vec<f32>)>) -> Self {
+        let mut grouped: HashMap<(String, usize), Vec<(String, Vec<f32>)>> = HashMap::new();
+        for (id, model, vector) in rows {
+            grouped.entry((model, vector.len())).or_default().push((id, vector));
+        }
+
+        Self {
+            spaces: grouped
+                .into_iter()
+                .map(|(space, entries)| (space, IvfIndex::build(entries)))
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.spaces.values().map(IvfIndex::len).sum()
+    }
+
+    pub fn insert(&mut self, model: &str, id: String, vector: &[f32]) {
+        self.spaces
+            .entry((model.to_string(), vector.len()))
+            .or_default()
+            .insert(id, vector);
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        for space in self.spaces.values_mut() {
+            if space.remove(id) {
+                return;
+            }
+        }
+    }
+
+    /// Nearest chunks embedded with `model`; empty when none are indexed
+    pub fn search(&self, model: &str, query: &[f32], k: usize) -> Vec<(String, f32)> {
+        self.spaces
+            .get(&(model.to_string(), query.len()))
+            .map(|space| space.search(query, k))
+            .unwrap_or_default()
+    }
+}
+
+/// k-means over an evenly spaced sample of `entries`
+fn train_centroids(entries: &[(String, Vec<f32>)], list_count: usize) -> Vec<Vec<f32>> {
+    if entries.is_empty() {
+        return Vec::new();
+    }
+
+    let sample_size = (list_count * TRAINING_SAMPLES_PER_LIST).min(entries.len());
+    let stride = entries.len() / sample_size;
+    let sample: Vec<&Vec<f32>> = entries.iter().step_by(stride.max(1)).take(sample_size).map(|(_, v)| v).collect();
+
+    let seed_stride = (sample.len() / list_count).max(1);
+    let mut centroids: Vec<Vec<f32>> = sample.iter().step_by(seed_stride).take(list_count).map(|v| (*v).clone()).collect();
+    let dim = centroids[0].len();
+
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut sums = vec![vec![0.0f32; dim]; centroids.len()];
+        let mut counts = vec![0usize; centroids.len()];
+
+        for vector in &sample {
+            let c = nearest_one(&centroids, vector);
+            counts[c] += 1;
+            for (sum, x) in sums[c].iter_mut().zip(vector.iter()) {
+                *sum += x;
+            }
+        }
+
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            // Keep empty clusters where they were
+            if count > 0 {
+                *centroid = normalized(&sum);
+            }
+        }
+    }
+
+    centroids
+}
+
+/// Index of the centroid most similar to `vector`
+fn nearest_one(centroids: &[Vec<f32>], vector: &[f32]) -> usize {
+    let mut best = (0, f32::MIN);
+    for (i, centroid) in centroids.iter().enumerate() {
+        let score = dot(centroid, vector);
+        if score > best.1 {
+            best = (i, score);
+        }
+    }
+    best.0
+}
+
+/// Indices of the `n` centroids most similar to `vector`
+fn nearest(centroids: &[Vec<f32>], vector: &[f32], n: usize) -> Vec<usize> {
+    let mut scored: Vec<(usize, f32)> = centroids
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, dot(c, vector)))
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.into_iter().take(n.max(1)).map(|(i, _)| i).collect()
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalized(v: &[f32]) -> Vec<f32> {
+    let norm = dot(v, v).sqrt();
+    if norm == 0.0 {
+        v.to_vec()
+    } else {
+        v.iter().map(|x| x / norm).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// Deterministic pseudo-random unit-ish vectors (xorshift)
+    fn synthetic_vectors(count: usize, dim: usize) -> Vec<(String, Vec<f32>)> {
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+        };
+
+        (0..count)
+            .map(|i| (format!("chunk-{}", i), (0..dim).map(|_| next()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_search_finds_exact_match_after_insert_and_remove() {
+        let mut index = IvfIndex::build(synthetic_vectors(2_000, 32));
+        let target = vec![1.0; 32];
+
+        index.insert("target".to_string(), &target);
+        assert_eq!(index.search(&target, 1)[0].0, "target");
+        assert!((index.search(&target, 1)[0].1 - 1.0).abs() < 1e-5);
+
+        assert!(index.remove("target"));
+        assert!(index.search(&target, 5).iter().all(|(id, _)| id != "target"));
+        assert_eq!(index.len(), 2_000);
+    }
+
+    #[test]
+    fn test_spaces_are_kept_apart() {
+        let mut index = AnnIndex::default();
+        index.insert("model-a", "a".to_string(), &[1.0, 0.0]);
+        index.insert("model-b", "b".to_string(), &[1.0, 0.0]);
+
+        assert_eq!(index.search("model-a", &[1.0, 0.0], 5), vec![("a".to_string(), 1.0)]);
+        assert!(index.search("model-a", &[1.0, 0.0, 0.0], 5).is_empty());
+    }
+
+    /// Building 50k vectors takes a minute in debug builds
+    #[test]
+    #[ignore = "benchmark; run with `cargo test --release -- --ignored`"]
+    fn test_search_50k_chunks_under_100ms() {
+        let entries = synthetic_vectors(50_000, 384);
+        let queries: Vec<Vec<f32>> = entries.iter().step_by(5_000).map(|(_, v)| v.clone()).collect();
+        let index = IvfIndex::build(entries);
+
+        let started = Instant::now();
+        for query in &queries {
+            let results = index.search(query, 5);
+            // Each query is an indexed vector, so it should find itself
+            assert!((results[0].1 - 1.0).abs() < 1e-5);
+        }
+        let per_query = started.elapsed() / queries.len() as u32;
+
+        assert!(per_query.as_millis() < 100, "search took {:?}", per_query);
+    }
+}
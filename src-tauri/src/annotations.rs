@@ -0,0 +1,438 @@
+/**
+ * Annotations Module
+ * Stores highlights and margin notes on ingested documents and indexes them for search
+ */
+
+use crate::db::HybridStorage;
+use crate::error::{AppError, AppResult, CommandError, CommandResult};
+use crate::llm::ArgumentScore;
+use crate::logging::LogLevel;
+use crate::rag::RAGService;
+use crate::state::AppState;
+use crate::validation::{validate_hex_color, validate_not_empty, validate_uuid};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use tauri::State;
+use uuid::Uuid;
+
+/// Highlight color used when none is given
+const DEFAULT_ANNOTATION_COLOR: &str = "#ffeb3b";
+
+/// Highlight color of argument score notes
+const ARGUMENT_SCORE_COLOR: &str = "#90caf9";
+
+/// Characters of the brief highlighted by an argument score note
+const SCORE_EXCERPT_CHARS: usize = 200;
+
+/// A highlighted passage of a document, with an optional note
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CaseAnnotation {
+    pub id: String,
+    pub user_id: String,
+    pub document_id: String,
+    /// Page of the highlight, from 1
+    pub page: u32,
+    /// Character offsets of the highlight within the page text
+    pub start_offset: u32,
+    pub end_offset: u32,
+    pub selected_text: String,
+    pub note: Option<String>,
+    /// Hex color, e.g. `#ffeb3b`
+    pub color: String,
+    pub created_at: String,
+}
+
+impl CaseAnnotation {
+    /// Text indexed for search: the highlighted passage followed by the note
+    pub fn indexed_text(&self) -> String {
+        match self.note.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+            Some(note) => format!("\"{}\"\nNote: {}", self.selected_text, note),
+            None => format!("\"{}\"", self.selected_text),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateAnnotationRequest {
+    pub user_id: String,
+    pub document_id: String,
+    pub page: u32,
+    pub start_offset: u32,
+    pub end_offset: u32,
+    pub selected_text: String,
+    pub note: Option<String>,
+    /// Defaults to yellow
+    pub color: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct AnnotationService {
+    storage: HybridStorage,
+}
+
+impl AnnotationService {
+    pub fn new(storage: HybridStorage) -> Self {
+        Self { storage }
+    }
+
+    /// Save an annotation and index it for search
+    ///
+    /// The annotation is kept if indexing fails (e.g. the embedding model
+    /// isn't loaded); it is re-indexed when its note is next updated.
+    pub async fn add_annotation(&self, rag: &RAGService, mut request: CreateAnnotationRequest) -> AppResult<CaseAnnotation> {
+        let color = validate_annotation(&mut request)?;
+
+        let annotation = CaseAnnotation {
+            id: Uuid::new_v4().to_string(),
+            user_id: request.user_id,
+            document_id: request.document_id,
+            page: request.page,
+            start_offset: request.start_offset,
+            end_offset: request.end_offset,
+            selected_text: request.selected_text,
+            note: request.note,
+            color,
+            created_at: Utc::now().to_rfc3339(),
+        };
+
+        let online = self.storage.is_online().await;
+
+        // Try to save to Supabase if online
+        if online {
+            if let Some(supabase) = self.storage.supabase() {
+                let data = serde_json::json!({
+                    "id": annotation.id,
+                    "user_id": annotation.user_id,
+                    "document_id": annotation.document_id,
+                    "page": annotation.page,
+                    "start_offset": annotation.start_offset,
+                    "end_offset": annotation.end_offset,
+                    "selected_text": annotation.selected_text,
+                    "note": annotation.note,
+                    "color": annotation.color,
+                    "created_at": annotation.created_at,
+                });
+
+                supabase
+                    .insert("annotations", &data.to_string())
+                    .await?
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to create annotation: {}", e)))?;
+            }
+        }
+
+        // Save locally
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query(
+            "INSERT INTO annotations
+             (id, user_id, document_id, page, start_offset, end_offset, selected_text, note, color, created_at, synced, dirty)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&annotation.id)
+        .bind(&annotation.user_id)
+        .bind(&annotation.document_id)
+        .bind(annotation.page)
+        .bind(annotation.start_offset)
+        .bind(annotation.end_offset)
+        .bind(&annotation.selected_text)
+        .bind(&annotation.note)
+        .bind(&annotation.color)
+        .bind(&annotation.created_at)
+        .bind(online as i64)
+        .bind(!online as i64)
+        .execute(&pool)
+        .await?;
+
+        index_annotation(rag, &annotation).await;
+
+        Ok(annotation)
+    }
+
+    /// Save a brief's argument score as a note on the opening of the brief
+    pub async fn add_argument_score(
+        &self,
+        rag: &RAGService,
+        user_id: &str,
+        document_id: &str,
+        brief_text: &str,
+        score: &ArgumentScore,
+    ) -> AppResult<CaseAnnotation> {
+        let trimmed = brief_text.trim_start();
+        let start_offset = (brief_text.chars().count() - trimmed.chars().count()) as u32;
+        let excerpt: String = trimmed.chars().take(SCORE_EXCERPT_CHARS).collect();
+
+        self.add_annotation(rag, CreateAnnotationRequest {
+            user_id: user_id.to_string(),
+            document_id: document_id.to_string(),
+            page: 1,
+            start_offset,
+            end_offset: start_offset + excerpt.chars().count() as u32,
+            selected_text: excerpt,
+            note: Some(score.summary()),
+            color: Some(ARGUMENT_SCORE_COLOR.to_string()),
+        }).await
+    }
+
+    /// Get the annotations on a document in reading order
+    pub async fn get_annotations_for_document(&self, doc_id: &str) -> AppResult<Vec<CaseAnnotation>> {
+        validate_not_empty(doc_id, "Document ID")?;
+
+        // Try Supabase first if online
+        if self.storage.is_online().await {
+            if let Some(supabase) = self.storage.supabase() {
+                let response = supabase
+                    .select("annotations")
+                    .await?
+                    .eq("document_id", doc_id)
+                    .order("page.asc,start_offset.asc")
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to fetch annotations: {}", e)))?;
+
+                let body = response.text().await?;
+                let annotations: Vec<CaseAnnotation> = serde_json::from_str(&body)?;
+                return Ok(annotations);
+            }
+        }
+
+        // Fallback to local
+        let pool = self.storage.sqlite().get_pool().await?;
+        let rows = sqlx::query(
+            "SELECT id, user_id, document_id, page, start_offset, end_offset, selected_text, note, color, created_at
+             FROM annotations
+             WHERE document_id = ?
+             ORDER BY page, start_offset"
+        )
+        .bind(doc_id)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows.iter().map(annotation_from_row).collect())
+    }
+
+    /// Get a single annotation from local storage
+    async fn get_annotation(&self, annotation_id: &str) -> AppResult<CaseAnnotation> {
+        let pool = self.storage.sqlite().get_pool().await?;
+        let row = sqlx::query(
+            "SELECT id, user_id, document_id, page, start_offset, end_offset, selected_text, note, color, created_at
+             FROM annotations
+             WHERE id = ?"
+        )
+        .bind(annotation_id)
+        .fetch_optional(&pool)
+        .await?;
+
+        row.as_ref()
+            .map(annotation_from_row)
+            .ok_or_else(|| AppError::NotFound("Annotation not found".to_string()))
+    }
+
+    /// Replace or clear an annotation's note and re-index it
+    pub async fn update_annotation_note(
+        &self,
+        rag: &RAGService,
+        annotation_id: &str,
+        note: Option<String>,
+    ) -> AppResult<CaseAnnotation> {
+        let annotation_id = &validate_uuid(annotation_id, "Annotation ID")?;
+
+        let mut annotation = self.get_annotation(annotation_id).await?;
+        annotation.note = note;
+
+        let online = self.storage.is_online().await;
+
+        // Try to update in Supabase if online
+        if online {
+            if let Some(supabase) = self.storage.supabase() {
+                let data = serde_json::json!({ "note": annotation.note });
+
+                supabase
+                    .update("annotations", &data.to_string())
+                    .await?
+                    .eq("id", &annotation.id)
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to update annotation: {}", e)))?;
+            }
+        }
+
+        // Update locally
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query("UPDATE annotations SET note = ?, dirty = ? WHERE id = ?")
+            .bind(&annotation.note)
+            .bind(!online as i64)
+            .bind(&annotation.id)
+            .execute(&pool)
+            .await?;
+
+        index_annotation(rag, &annotation).await;
+
+        Ok(annotation)
+    }
+
+    /// Delete an annotation and remove it from the search index
+    pub async fn delete_annotation(&self, rag: &RAGService, annotation_id: &str) -> AppResult<()> {
+        let annotation_id = &validate_uuid(annotation_id, "Annotation ID")?;
+
+        // Try Supabase if online
+        if self.storage.is_online().await {
+            if let Some(supabase) = self.storage.supabase() {
+                supabase
+                    .delete("annotations")
+                    .await?
+                    .eq("id", annotation_id)
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to delete annotation: {}", e)))?;
+            }
+        }
+
+        rag.remove_annotation(annotation_id).await?;
+
+        // Delete locally
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query("DELETE FROM annotations WHERE id = ?")
+            .bind(annotation_id)
+            .execute(&pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Index an annotation for search, logging rather than failing if it can't be
+async fn index_annotation(rag: &RAGService, annotation: &CaseAnnotation) {
+    if let Err(e) = rag.index_annotation(annotation).await {
+        crate::logging::log(
+            LogLevel::Warn,
+            "annotations",
+            &format!("Failed to index annotation {}: {}", annotation.id, e),
+        );
+    }
+}
+
+fn annotation_from_row(row: &sqlx::sqlite::SqliteRow) -> CaseAnnotation {
+    CaseAnnotation {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        document_id: row.get("document_id"),
+        page: row.get("page"),
+        start_offset: row.get("start_offset"),
+        end_offset: row.get("end_offset"),
+        selected_text: row.get("selected_text"),
+        note: row.get("note"),
+        color: row.get("color"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Check a new annotation and normalise its user ID, returning the color to store
+fn validate_annotation(request: &mut CreateAnnotationRequest) -> AppResult<String> {
+    request.user_id = validate_uuid(&request.user_id, "User ID")?;
+    validate_not_empty(&request.document_id, "Document ID")?;
+    validate_not_empty(&request.selected_text, "Selected text")?;
+
+    if request.page == 0 {
+        return Err(AppError::Validation("Page numbers start at 1".to_string()));
+    }
+    if request.end_offset <= request.start_offset {
+        return Err(AppError::Validation("Annotation must end after it starts".to_string()));
+    }
+
+    validate_hex_color(request.color.as_deref().unwrap_or(DEFAULT_ANNOTATION_COLOR), "Color")
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub async fn add_annotation(
+    state: State<'_, AppState>,
+    request: CreateAnnotationRequest,
+) -> CommandResult<CaseAnnotation> {
+    let service = state.annotation_service().await?;
+    let rag = state.rag_service().await?;
+    service.add_annotation(&rag, request).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn get_document_annotations(
+    state: State<'_, AppState>,
+    document_id: String,
+) -> CommandResult<Vec<CaseAnnotation>> {
+    let service = state.annotation_service().await?;
+    service.get_annotations_for_document(&document_id).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn update_annotation_note(
+    state: State<'_, AppState>,
+    id: String,
+    note: Option<String>,
+) -> CommandResult<CaseAnnotation> {
+    let service = state.annotation_service().await?;
+    let rag = state.rag_service().await?;
+    service.update_annotation_note(&rag, &id, note).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn delete_annotation(
+    state: State<'_, AppState>,
+    id: String,
+) -> CommandResult<()> {
+    let service = state.annotation_service().await?;
+    let rag = state.rag_service().await?;
+    service.delete_annotation(&rag, &id).await.map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> CreateAnnotationRequest {
+        CreateAnnotationRequest {
+            user_id: "11111111-1111-4111-8111-111111111111".to_string(),
+            document_id: "doc-1".to_string(),
+            page: 3,
+            start_offset: 10,
+            end_offset: 42,
+            selected_text: "a duty of care is owed to one's neighbour".to_string(),
+            note: None,
+            color: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_annotation() {
+        assert_eq!(validate_annotation(&mut request()).unwrap(), DEFAULT_ANNOTATION_COLOR);
+        assert_eq!(
+            validate_annotation(&mut CreateAnnotationRequest { color: Some("#FF0000".to_string()), ..request() }).unwrap(),
+            "#ff0000"
+        );
+
+        assert!(validate_annotation(&mut CreateAnnotationRequest { color: Some("red".to_string()), ..request() }).is_err());
+        assert!(validate_annotation(&mut CreateAnnotationRequest { end_offset: 10, ..request() }).is_err());
+        assert!(validate_annotation(&mut CreateAnnotationRequest { page: 0, ..request() }).is_err());
+        assert!(validate_annotation(&mut CreateAnnotationRequest { selected_text: " ".to_string(), ..request() }).is_err());
+    }
+
+    #[test]
+    fn test_indexed_text_includes_note() {
+        let mut annotation = CaseAnnotation {
+            id: "a".to_string(),
+            user_id: "u".to_string(),
+            document_id: "d".to_string(),
+            page: 1,
+            start_offset: 0,
+            end_offset: 5,
+            selected_text: "neighbour".to_string(),
+            note: Some("  Lord Atkin's test  ".to_string()),
+            color: DEFAULT_ANNOTATION_COLOR.to_string(),
+            created_at: "now".to_string(),
+        };
+        assert_eq!(annotation.indexed_text(), "\"neighbour\"\nNote: Lord Atkin's test");
+
+        annotation.note = Some(" ".to_string());
+        assert_eq!(annotation.indexed_text(), "\"neighbour\"");
+    }
+}
@@ -0,0 +1,791 @@
+/**
+ * Cases Module
+ * Case management, tags and search commands
+ */
+
+use crate::db::{CaseSummary, HybridStorage};
+use crate::error::{AppError, AppResult, CommandError, CommandResult};
+use crate::recycle_bin;
+use crate::state::AppState;
+use crate::validation::{validate_case_title, validate_hex_color, validate_length, validate_not_empty, validate_uuid};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use tauri::State;
+use uuid::Uuid;
+
+/// Default number of results for `search_cases`
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// Color given to tags created without one
+const DEFAULT_TAG_COLOR: &str = "#607d8b";
+
+/// Longest tag name accepted
+const MAX_TAG_NAME_LENGTH: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Case {
+    pub id: String,
+    pub user_id: String,
+    pub title: String,
+    pub case_name: Option<String>,
+    pub file_url: Option<String>,
+    pub issue: Option<String>,
+    pub rule: Option<String>,
+    pub analysis: Option<String>,
+    pub conclusion: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCaseRequest {
+    pub user_id: String,
+    pub title: String,
+    pub case_name: Option<String>,
+    pub file_url: Option<String>,
+    pub issue: Option<String>,
+    pub rule: Option<String>,
+    pub analysis: Option<String>,
+    pub conclusion: Option<String>,
+}
+
+/// Fields to change on a case; `None` leaves a field as it is
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateCaseRequest {
+    pub case_id: String,
+    pub title: Option<String>,
+    pub case_name: Option<String>,
+    pub file_url: Option<String>,
+    pub issue: Option<String>,
+    pub rule: Option<String>,
+    pub analysis: Option<String>,
+    pub conclusion: Option<String>,
+}
+
+/// A label a user files cases under; a case can have any number of tags
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CaseTag {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    /// Hex color, e.g. `#607d8b`
+    pub color: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateTagRequest {
+    pub user_id: String,
+    pub name: String,
+    /// Defaults to grey
+    pub color: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct CaseService {
+    storage: HybridStorage,
+}
+
+impl CaseService {
+    pub fn new(storage: HybridStorage) -> Self {
+        Self { storage }
+    }
+
+    /// Create a new case
+    pub async fn create_case(&self, mut request: CreateCaseRequest) -> AppResult<Case> {
+        request.user_id = validate_uuid(&request.user_id, "User ID")?;
+        let title = validate_case_title(&request.title)?;
+
+        let now = Utc::now().to_rfc3339();
+        let case = Case {
+            id: Uuid::new_v4().to_string(),
+            user_id: request.user_id,
+            title,
+            case_name: request.case_name,
+            file_url: request.file_url,
+            issue: request.issue,
+            rule: request.rule,
+            analysis: request.analysis,
+            conclusion: request.conclusion,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+
+        let online = self.storage.is_online().await;
+
+        // Try to save to Supabase if online
+        if online {
+            if let Some(supabase) = self.storage.supabase() {
+                supabase
+                    .insert("cases", &serde_json::to_string(&case)?)
+                    .await?
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to create case: {}", e)))?;
+            }
+        }
+
+        // Save locally
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query(
+            "INSERT INTO cases
+             (id, user_id, title, case_name, file_url, issue, rule, analysis, conclusion, created_at, updated_at, synced, dirty)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&case.id)
+        .bind(&case.user_id)
+        .bind(&case.title)
+        .bind(&case.case_name)
+        .bind(&case.file_url)
+        .bind(&case.issue)
+        .bind(&case.rule)
+        .bind(&case.analysis)
+        .bind(&case.conclusion)
+        .bind(&case.created_at)
+        .bind(&case.updated_at)
+        .bind(online as i64)
+        .bind(!online as i64)
+        .execute(&pool)
+        .await?;
+
+        Ok(case)
+    }
+
+    /// Get a user's cases, most recently updated first
+    ///
+    /// With a non-empty `tag_filter`, only cases carrying at least one of
+    /// the given tags are returned.
+    pub async fn get_cases(&self, user_id: &str, tag_filter: Option<Vec<String>>) -> AppResult<Vec<Case>> {
+        let user_id = &validate_uuid(user_id, "User ID")?;
+        let tag_ids = tag_filter
+            .unwrap_or_default()
+            .iter()
+            .map(|tag_id| validate_uuid(tag_id, "Tag ID"))
+            .collect::<AppResult<Vec<String>>>()?;
+
+        // Try Supabase first if online
+        if self.storage.is_online().await {
+            if let Some(supabase) = self.storage.supabase() {
+                let mut query = supabase
+                    .select("cases")
+                    .await?
+                    .eq("user_id", user_id)
+                    .is("deleted_at", "null");
+
+                if !tag_ids.is_empty() {
+                    let response = supabase
+                        .select("case_tags")
+                        .await?
+                        .select("case_id")
+                        .in_("tag_id", &tag_ids)
+                        .execute()
+                        .await
+                        .map_err(|e| AppError::Supabase(format!("Failed to fetch case tags: {}", e)))?;
+
+                    let body = response.text().await?;
+                    let links: Vec<serde_json::Value> = serde_json::from_str(&body)?;
+                    let mut case_ids: Vec<String> = links
+                        .iter()
+                        .filter_map(|link| link["case_id"].as_str().map(str::to_string))
+                        .collect();
+                    case_ids.sort();
+                    case_ids.dedup();
+
+                    if case_ids.is_empty() {
+                        return Ok(Vec::new());
+                    }
+                    query = query.in_("id", &case_ids);
+                }
+
+                let response = query
+                    .order("updated_at.desc")
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to fetch cases: {}", e)))?;
+
+                let body = response.text().await?;
+                let cases: Vec<Case> = serde_json::from_str(&body)?;
+                return Ok(cases);
+            }
+        }
+
+        // Fallback to local
+        let sql = format!(
+            "SELECT c.id, c.user_id, c.title, c.case_name, c.file_url, c.issue, c.rule, c.analysis, c.conclusion,
+                    c.created_at, c.updated_at
+             FROM cases c
+             WHERE c.user_id = ? AND c.deleted_at IS NULL{}
+             ORDER BY c.updated_at DESC",
+            tag_filter_sql(tag_ids.len())
+        );
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        let mut query = sqlx::query(&sql).bind(user_id);
+        for tag_id in &tag_ids {
+            query = query.bind(tag_id);
+        }
+        let rows = query.fetch_all(&pool).await?;
+
+        Ok(rows.iter().map(case_from_row).collect())
+    }
+
+    /// Get a single case from local storage, unless it is in the recycle bin
+    async fn get_case(&self, case_id: &str) -> AppResult<Case> {
+        let pool = self.storage.sqlite().get_pool().await?;
+        let row = sqlx::query(
+            "SELECT id, user_id, title, case_name, file_url, issue, rule, analysis, conclusion, created_at, updated_at
+             FROM cases
+             WHERE id = ? AND deleted_at IS NULL"
+        )
+        .bind(case_id)
+        .fetch_optional(&pool)
+        .await?;
+
+        row.as_ref()
+            .map(case_from_row)
+            .ok_or_else(|| AppError::NotFound("Case not found".to_string()))
+    }
+
+    /// Update a case's title or IRAC sections
+    pub async fn update_case(&self, mut request: UpdateCaseRequest) -> AppResult<Case> {
+        request.case_id = validate_uuid(&request.case_id, "Case ID")?;
+
+        let mut case = self.get_case(&request.case_id).await?;
+        if let Some(title) = request.title {
+            case.title = validate_case_title(&title)?;
+        }
+        if request.case_name.is_some() {
+            case.case_name = request.case_name;
+        }
+        if request.file_url.is_some() {
+            case.file_url = request.file_url;
+        }
+        if request.issue.is_some() {
+            case.issue = request.issue;
+        }
+        if request.rule.is_some() {
+            case.rule = request.rule;
+        }
+        if request.analysis.is_some() {
+            case.analysis = request.analysis;
+        }
+        if request.conclusion.is_some() {
+            case.conclusion = request.conclusion;
+        }
+        case.updated_at = Utc::now().to_rfc3339();
+
+        let online = self.storage.is_online().await;
+
+        // Try to update in Supabase if online
+        if online {
+            if let Some(supabase) = self.storage.supabase() {
+                let data = serde_json::json!({
+                    "title": case.title,
+                    "case_name": case.case_name,
+                    "file_url": case.file_url,
+                    "issue": case.issue,
+                    "rule": case.rule,
+                    "analysis": case.analysis,
+                    "conclusion": case.conclusion,
+                    "updated_at": case.updated_at,
+                });
+
+                supabase
+                    .update("cases", &data.to_string())
+                    .await?
+                    .eq("id", &case.id)
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to update case: {}", e)))?;
+            }
+        }
+
+        // Update locally
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query(
+            "UPDATE cases
+             SET title = ?, case_name = ?, file_url = ?, issue = ?, rule = ?, analysis = ?,
+                 conclusion = ?, updated_at = ?, dirty = ?
+             WHERE id = ?"
+        )
+        .bind(&case.title)
+        .bind(&case.case_name)
+        .bind(&case.file_url)
+        .bind(&case.issue)
+        .bind(&case.rule)
+        .bind(&case.analysis)
+        .bind(&case.conclusion)
+        .bind(&case.updated_at)
+        .bind(!online as i64)
+        .bind(&case.id)
+        .execute(&pool)
+        .await?;
+
+        Ok(case)
+    }
+
+    /// Move a case to the recycle bin; its tag assignments go when it is
+    /// permanently deleted
+    pub async fn delete_case(&self, case_id: &str) -> AppResult<()> {
+        let case_id = validate_uuid(case_id, "Case ID")?;
+        recycle_bin::move_to_bin(&self.storage, "cases", &case_id).await
+    }
+
+    /// Create a tag; names are unique per user
+    pub async fn create_tag(&self, mut request: CreateTagRequest) -> AppResult<CaseTag> {
+        request.user_id = validate_uuid(&request.user_id, "User ID")?;
+        let name = request.name.trim().to_string();
+        validate_not_empty(&name, "Tag name")?;
+        validate_length(&name, "Tag name", 1, MAX_TAG_NAME_LENGTH)?;
+        let color = validate_hex_color(request.color.as_deref().unwrap_or(DEFAULT_TAG_COLOR), "Tag color")?;
+
+        let tag = CaseTag {
+            id: Uuid::new_v4().to_string(),
+            user_id: request.user_id,
+            name,
+            color,
+        };
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        let existing: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tags WHERE user_id = ? AND name = ?")
+            .bind(&tag.user_id)
+            .bind(&tag.name)
+            .fetch_one(&pool)
+            .await?;
+        if existing > 0 {
+            return Err(AppError::Validation(format!("Tag '{}' already exists", tag.name)));
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let online = self.storage.is_online().await;
+
+        // Try to save to Supabase if online
+        if online {
+            if let Some(supabase) = self.storage.supabase() {
+                let data = serde_json::json!({
+                    "id": tag.id,
+                    "user_id": tag.user_id,
+                    "name": tag.name,
+                    "color": tag.color,
+                    "created_at": now,
+                    "updated_at": now,
+                });
+
+                supabase
+                    .insert("tags", &data.to_string())
+                    .await?
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to create tag: {}", e)))?;
+            }
+        }
+
+        // Save locally
+        sqlx::query(
+            "INSERT INTO tags (id, user_id, name, color, created_at, updated_at, synced, dirty)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&tag.id)
+        .bind(&tag.user_id)
+        .bind(&tag.name)
+        .bind(&tag.color)
+        .bind(&now)
+        .bind(&now)
+        .bind(online as i64)
+        .bind(!online as i64)
+        .execute(&pool)
+        .await?;
+
+        Ok(tag)
+    }
+
+    /// Get a user's tags in name order
+    pub async fn get_tags(&self, user_id: &str) -> AppResult<Vec<CaseTag>> {
+        let user_id = &validate_uuid(user_id, "User ID")?;
+
+        // Try Supabase first if online
+        if self.storage.is_online().await {
+            if let Some(supabase) = self.storage.supabase() {
+                let response = supabase
+                    .select("tags")
+                    .await?
+                    .eq("user_id", user_id)
+                    .order("name.asc")
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to fetch tags: {}", e)))?;
+
+                let body = response.text().await?;
+                let tags: Vec<CaseTag> = serde_json::from_str(&body)?;
+                return Ok(tags);
+            }
+        }
+
+        // Fallback to local
+        let pool = self.storage.sqlite().get_pool().await?;
+        let rows = sqlx::query(
+            "SELECT id, user_id, name, color
+             FROM tags
+             WHERE user_id = ?
+             ORDER BY name COLLATE NOCASE"
+        )
+        .bind(user_id)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| CaseTag {
+                id: row.get("id"),
+                user_id: row.get("user_id"),
+                name: row.get("name"),
+                color: row.get("color"),
+            })
+            .collect())
+    }
+
+    /// Delete a tag and remove it from every case
+    pub async fn delete_tag(&self, tag_id: &str) -> AppResult<()> {
+        let tag_id = &validate_uuid(tag_id, "Tag ID")?;
+
+        // Try Supabase if online
+        if self.storage.is_online().await {
+            if let Some(supabase) = self.storage.supabase() {
+                supabase
+                    .delete("tags")
+                    .await?
+                    .eq("id", tag_id)
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to delete tag: {}", e)))?;
+            }
+        }
+
+        // Delete locally; case_tags rows go with it
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query("DELETE FROM tags WHERE id = ?")
+            .bind(tag_id)
+            .execute(&pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Tag a case; tagging a case twice with the same tag is a no-op
+    pub async fn add_tag_to_case(&self, case_id: &str, tag_id: &str) -> AppResult<()> {
+        let case_id = &validate_uuid(case_id, "Case ID")?;
+        let tag_id = &validate_uuid(tag_id, "Tag ID")?;
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        let tagged: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM case_tags WHERE case_id = ? AND tag_id = ?")
+            .bind(case_id)
+            .bind(tag_id)
+            .fetch_one(&pool)
+            .await?;
+        if tagged > 0 {
+            return Ok(());
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        let online = self.storage.is_online().await;
+
+        // Try to save to Supabase if online
+        if online {
+            if let Some(supabase) = self.storage.supabase() {
+                let data = serde_json::json!({
+                    "id": id,
+                    "case_id": case_id,
+                    "tag_id": tag_id,
+                    "created_at": now,
+                    "updated_at": now,
+                });
+
+                supabase
+                    .insert("case_tags", &data.to_string())
+                    .await?
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to tag case: {}", e)))?;
+            }
+        }
+
+        // Save locally
+        sqlx::query(
+            "INSERT INTO case_tags (id, case_id, tag_id, created_at, updated_at, synced, dirty)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&id)
+        .bind(case_id)
+        .bind(tag_id)
+        .bind(&now)
+        .bind(&now)
+        .bind(online as i64)
+        .bind(!online as i64)
+        .execute(&pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Remove a tag from a case
+    pub async fn remove_tag_from_case(&self, case_id: &str, tag_id: &str) -> AppResult<()> {
+        let case_id = &validate_uuid(case_id, "Case ID")?;
+        let tag_id = &validate_uuid(tag_id, "Tag ID")?;
+
+        // Try Supabase if online
+        if self.storage.is_online().await {
+            if let Some(supabase) = self.storage.supabase() {
+                supabase
+                    .delete("case_tags")
+                    .await?
+                    .eq("case_id", case_id)
+                    .eq("tag_id", tag_id)
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to untag case: {}", e)))?;
+            }
+        }
+
+        // Delete locally
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query("DELETE FROM case_tags WHERE case_id = ? AND tag_id = ?")
+            .bind(case_id)
+            .bind(tag_id)
+            .execute(&pool)
+            .await?;
+        Ok(())
+    }
+}
+
+fn case_from_row(row: &sqlx::sqlite::SqliteRow) -> Case {
+    Case {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        title: row.get("title"),
+        case_name: row.get("case_name"),
+        file_url: row.get("file_url"),
+        issue: row.get("issue"),
+        rule: row.get("rule"),
+        analysis: row.get("analysis"),
+        conclusion: row.get("conclusion"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// `WHERE` clause restricting `cases c` to those carrying any of
+/// `tag_count` tags, bound after the user ID; empty when there is no filter
+fn tag_filter_sql(tag_count: usize) -> String {
+    if tag_count == 0 {
+        return String::new();
+    }
+
+    format!(
+        " AND EXISTS (SELECT 1 FROM case_tags ct WHERE ct.case_id = c.id AND ct.tag_id IN ({}))",
+        vec!["?"; tag_count].join(", ")
+    )
+}
+
+// Tauri Commands
+
+/// Find a user's cases by keywords from the title or IRAC sections
+#[tauri::command]
+pub async fn search_cases(
+    storage: State<'_, HybridStorage>,
+    user_id: String,
+    query: String,
+    limit: Option<usize>,
+) -> CommandResult<Vec<CaseSummary>> {
+    storage
+        .sqlite()
+        .search_cases_fulltext(&user_id, &query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn create_case(
+    state: State<'_, AppState>,
+    request: CreateCaseRequest,
+) -> CommandResult<Case> {
+    let service = state.case_service().await?;
+    service.create_case(request).await.map_err(CommandError::from)
+}
+
+/// List a user's cases, optionally only those with any of the given tags
+#[tauri::command]
+pub async fn get_cases(
+    state: State<'_, AppState>,
+    user_id: String,
+    tag_filter: Option<Vec<String>>,
+) -> CommandResult<Vec<Case>> {
+    let service = state.case_service().await?;
+    service.get_cases(&user_id, tag_filter).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn update_case(
+    state: State<'_, AppState>,
+    request: UpdateCaseRequest,
+) -> CommandResult<Case> {
+    let service = state.case_service().await?;
+    service.update_case(request).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn delete_case(
+    state: State<'_, AppState>,
+    case_id: String,
+) -> CommandResult<()> {
+    let service = state.case_service().await?;
+    service.delete_case(&case_id).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn create_tag(
+    state: State<'_, AppState>,
+    request: CreateTagRequest,
+) -> CommandResult<CaseTag> {
+    let service = state.case_service().await?;
+    service.create_tag(request).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn get_tags(
+    state: State<'_, AppState>,
+    user_id: String,
+) -> CommandResult<Vec<CaseTag>> {
+    let service = state.case_service().await?;
+    service.get_tags(&user_id).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn delete_tag(
+    state: State<'_, AppState>,
+    tag_id: String,
+) -> CommandResult<()> {
+    let service = state.case_service().await?;
+    service.delete_tag(&tag_id).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn add_tag_to_case(
+    state: State<'_, AppState>,
+    case_id: String,
+    tag_id: String,
+) -> CommandResult<()> {
+    let service = state.case_service().await?;
+    service.add_tag_to_case(&case_id, &tag_id).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn remove_tag_from_case(
+    state: State<'_, AppState>,
+    case_id: String,
+    tag_id: String,
+) -> CommandResult<()> {
+    let service = state.case_service().await?;
+    service.remove_tag_from_case(&case_id, &tag_id).await.map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{test_cache, IN_MEMORY_DB};
+    use std::path::PathBuf;
+
+    const USER_ID: &str = "11111111-1111-4111-8111-111111111111";
+
+    /// A service over a fresh in-memory database, offline
+    async fn service() -> CaseService {
+        let cache = test_cache().await;
+        CaseService::new(HybridStorage::new(PathBuf::from(IN_MEMORY_DB), None, None).with_sqlite(cache))
+    }
+
+    fn case_request(title: &str) -> CreateCaseRequest {
+        CreateCaseRequest {
+            user_id: USER_ID.to_string(),
+            title: title.to_string(),
+            case_name: None,
+            file_url: None,
+            issue: None,
+            rule: None,
+            analysis: None,
+            conclusion: None,
+        }
+    }
+
+    fn tag_request(name: &str) -> CreateTagRequest {
+        CreateTagRequest { user_id: USER_ID.to_string(), name: name.to_string(), color: None }
+    }
+
+    #[test]
+    fn test_tag_filter_sql() {
+        assert_eq!(tag_filter_sql(0), "");
+        assert_eq!(
+            tag_filter_sql(2),
+            " AND EXISTS (SELECT 1 FROM case_tags ct WHERE ct.case_id = c.id AND ct.tag_id IN (?, ?))"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cases_are_saved_dirty_offline_and_updated_in_place() {
+        let service = service().await;
+
+        let case = service.create_case(case_request("Donoghue v Stevenson")).await.unwrap();
+        let updated = service
+            .update_case(UpdateCaseRequest {
+                case_id: case.id.clone(),
+                title: None,
+                case_name: None,
+                file_url: None,
+                issue: Some("Is a duty of care owed?".to_string()),
+                rule: None,
+                analysis: None,
+                conclusion: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(updated.title, "Donoghue v Stevenson");
+
+        let cases = service.get_cases(USER_ID, None).await.unwrap();
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].issue.as_deref(), Some("Is a duty of care owed?"));
+
+        let pool = service.storage.sqlite().get_pool().await.unwrap();
+        let dirty: i64 = sqlx::query_scalar("SELECT dirty FROM cases WHERE id = ?")
+            .bind(&case.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(dirty, 1);
+
+        let missing = service.get_case(&Uuid::new_v4().to_string()).await;
+        assert!(matches!(missing, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cases_are_filtered_by_tag() {
+        let service = service().await;
+        let torts = service.create_case(case_request("Torts case")).await.unwrap();
+        service.create_case(case_request("Contracts case")).await.unwrap();
+
+        let tag = service.create_tag(tag_request("negligence")).await.unwrap();
+        let other = service.create_tag(tag_request("Appeal")).await.unwrap();
+        assert!(matches!(service.create_tag(tag_request("negligence")).await, Err(AppError::Validation(_))));
+        let names: Vec<String> = service.get_tags(USER_ID).await.unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["Appeal", "negligence"]);
+
+        service.add_tag_to_case(&torts.id, &tag.id).await.unwrap();
+        service.add_tag_to_case(&torts.id, &tag.id).await.unwrap();
+        let tagged = service.get_cases(USER_ID, Some(vec![tag.id.clone(), other.id.clone()])).await.unwrap();
+        assert_eq!(tagged.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(), vec![torts.id.as_str()]);
+
+        service.remove_tag_from_case(&torts.id, &tag.id).await.unwrap();
+        assert!(service.get_cases(USER_ID, Some(vec![tag.id.clone()])).await.unwrap().is_empty());
+
+        service.add_tag_to_case(&torts.id, &other.id).await.unwrap();
+        service.delete_tag(&other.id).await.unwrap();
+        assert!(service.get_cases(USER_ID, Some(vec![other.id])).await.unwrap().is_empty());
+        assert_eq!(service.get_cases(USER_ID, None).await.unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,372 @@
+/**
+ * Legal Citation Extraction
+ * Finds case, statute, regulation and article citations in generated analysis
+ */
+
+use crate::db::HybridStorage;
+use crate::error::{AppError, AppResult, CommandResult};
+use crate::llm::{ChatOptions, LLMService, Message};
+use crate::logging::LogLevel;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use std::ops::Range;
+use std::sync::LazyLock;
+use tauri::State;
+
+/// Kind of authority a citation refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CitationType {
+    Case,
+    Statute,
+    Regulation,
+    /// An article of a constitution, treaty or convention
+    Article,
+}
+
+impl CitationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CitationType::Case => "case",
+            CitationType::Statute => "statute",
+            CitationType::Regulation => "regulation",
+            CitationType::Article => "article",
+        }
+    }
+
+    pub fn parse(value: &str) -> AppResult<Self> {
+        match value {
+            "case" => Ok(CitationType::Case),
+            "statute" => Ok(CitationType::Statute),
+            "regulation" => Ok(CitationType::Regulation),
+            "article" => Ok(CitationType::Article),
+            other => Err(AppError::Validation(format!("Unknown citation type '{}'", other))),
+        }
+    }
+}
+
+/// A legal authority cited in generated text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Citation {
+    pub text: String,
+    pub citation_type: CitationType,
+    /// 0-1; pattern matches score higher than references classified by the model
+    pub confidence: f64,
+}
+
+/// Patterns for the regex pass, most specific first: text claimed by one
+/// pattern isn't matched again by a broader one
+const CITATION_PATTERNS: &[(&str, CitationType, f64)] = &[
+    // 29 C.F.R. § 1910.1200
+    (r"\b\d+ C\.F\.R\. (?:§+ ?)?\d+(?:\.\d+)*", CitationType::Regulation, 0.95),
+    // 42 U.S.C. § 1983, § 2-207(1)
+    (r"(?:\b\d+ [A-Z][A-Za-z\.]* )?§+ ?\d+(?:[\.\-]\d+)*(?:\([A-Za-z0-9]+\))*", CitationType::Statute, 0.9),
+    // [2020] UKSC 12
+    (r"\[\d{4}\] [A-Z]+ \d+", CitationType::Case, 0.95),
+    // 347 U.S. 483, 123 F.3d 456, 98 S. Ct. 2733
+    (r"\b\d+ [A-Z][A-Za-z0-9\.]*(?: [A-Z][A-Za-z0-9\.]*)* \d+\b", CitationType::Case, 0.9),
+    // Article 8, Art. 6(1)
+    (r"\bArt(?:icle|\.) \d+[A-Za-z]?(?:\(\d+\))*", CitationType::Article, 0.85),
+];
+
+/// `CITATION_PATTERNS`, compiled once
+static CITATION_REGEXES: LazyLock<Vec<(Regex, CitationType, f64)>> = LazyLock::new(|| {
+    CITATION_PATTERNS
+        .iter()
+        .filter_map(|(pattern, citation_type, confidence)| {
+            Regex::new(pattern).ok().map(|re| (re, *citation_type, *confidence))
+        })
+        .collect()
+});
+
+/// Month names, which the reporter pattern would otherwise take for a
+/// reporter in dates like "12 March 2020"
+const MONTHS: &[&str] = &[
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+
+pub struct CitationExtractor;
+
+impl CitationExtractor {
+    /// Citations matching the common reporter, section and neutral citation formats
+    pub fn extract(text: &str) -> Vec<Citation> {
+        let mut citations: Vec<Citation> = Vec::new();
+        for (_, citation) in pattern_matches(text) {
+            if !citations.iter().any(|c| c.text == citation.text) {
+                citations.push(citation);
+            }
+        }
+        citations
+    }
+
+    /// `extract`, plus bracketed references the patterns missed, classified by the model
+    ///
+    /// Falls back to the pattern matches alone if the model call fails.
+    pub async fn extract_with_llm(text: &str, llm: &LLMService) -> Vec<Citation> {
+        let mut citations = Self::extract(text);
+        let references = unmatched_bracketed_references(text);
+        if references.is_empty() {
+            return citations;
+        }
+
+        match classify_references(llm, &references).await {
+            Ok(classified) => {
+                for citation in classified {
+                    if !citations.iter().any(|c| c.text == citation.text) {
+                        citations.push(citation);
+                    }
+                }
+            }
+            Err(e) => crate::logging::log(
+                LogLevel::Warn,
+                "citations",
+                &format!("Citation classification failed, keeping pattern matches: {}", e),
+            ),
+        }
+        citations
+    }
+}
+
+/// Pattern matches in order of appearance, skipping text already claimed by an earlier pattern
+fn pattern_matches(text: &str) -> Vec<(Range<usize>, Citation)> {
+    let mut found: Vec<(Range<usize>, Citation)> = Vec::new();
+
+    for (re, citation_type, confidence) in CITATION_REGEXES.iter() {
+        for m in re.find_iter(text) {
+            if found.iter().any(|(span, _)| span.start < m.end() && m.start() < span.end) {
+                continue;
+            }
+            if *citation_type == CitationType::Case && is_date(m.as_str()) {
+                continue;
+            }
+            found.push((
+                m.range(),
+                Citation {
+                    text: m.as_str().to_string(),
+                    citation_type: *citation_type,
+                    confidence: *confidence,
+                },
+            ));
+        }
+    }
+
+    found.sort_by_key(|(span, _)| span.start);
+    found
+}
+
+/// Whether a reporter-style match is a date, its "reporter" being a month
+fn is_date(text: &str) -> bool {
+    text.split_whitespace().any(|word| MONTHS.contains(&word))
+}
+
+/// Bracketed text the patterns didn't match, excluding `[1]`-style context source markers
+fn unmatched_bracketed_references(text: &str) -> Vec<String> {
+    let matched = pattern_matches(text);
+    let (Ok(bracketed), Ok(source_marker)) = (
+        Regex::new(r"\[[^\[\]\n]+\]"),
+        Regex::new(r"^\[\d+(?:\s*,\s*\d+)*\]$"),
+    ) else {
+        return Vec::new();
+    };
+
+    let mut references: Vec<String> = Vec::new();
+    for m in bracketed.find_iter(text) {
+        let reference = m.as_str();
+        let overlaps = matched.iter().any(|(span, _)| span.start < m.end() && m.start() < span.end);
+        if overlaps || source_marker.is_match(reference) || references.iter().any(|r| r == reference) {
+            continue;
+        }
+        references.push(reference.to_string());
+    }
+    references
+}
+
+/// Ask the model which references are legal citations, and of what kind
+async fn classify_references(llm: &LLMService, references: &[String]) -> AppResult<Vec<Citation>> {
+    let system_prompt = "You classify legal citations. For each reference, decide whether it cites a case, statute, regulation or article (of a constitution, treaty or convention).
+Respond with a JSON array of objects with keys: text (the reference exactly as given), citation_type (one of case, statute, regulation, article) and confidence (0 to 1).
+Leave out references that are not legal citations.";
+
+    let messages = vec![
+        Message {
+            role: "system".to_string(),
+            content: system_prompt.to_string(),
+        },
+        Message {
+            role: "user".to_string(),
+            content: format!("References:\n{}", references.join("\n")),
+        },
+    ];
+    let options = ChatOptions {
+        temperature: Some(0.0),
+        max_tokens: Some(1000),
+        task: Some("citations".to_string()),
+        ..Default::default()
+    };
+
+    let response = llm.chat(messages, options).await?;
+    parse_classified_references(&response, references)
+}
+
+/// Citations from the model's classification, keeping only references that were asked about
+///
+/// Accepts a bare JSON array, optionally inside a markdown code block.
+fn parse_classified_references(response: &str, references: &[String]) -> AppResult<Vec<Citation>> {
+    let value: serde_json::Value = serde_json::from_str(response)
+        .ok()
+        .or_else(|| {
+            Regex::new(r"```(?:json)?\n([\s\S]*?)```")
+                .ok()
+                .and_then(|re| re.captures(response))
+                .and_then(|caps| caps.get(1))
+                .and_then(|m| serde_json::from_str(m.as_str()).ok())
+        })
+        .ok_or_else(|| AppError::Llm("Failed to parse LLM response as JSON".to_string()))?;
+
+    let entries = value
+        .as_array()
+        .ok_or_else(|| AppError::Llm("Invalid citations format".to_string()))?;
+
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            let text = entry["text"].as_str()?.trim();
+            if !references.iter().any(|r| r == text) {
+                return None;
+            }
+            Some(Citation {
+                text: text.to_string(),
+                citation_type: CitationType::parse(entry["citation_type"].as_str()?).ok()?,
+                confidence: entry["confidence"].as_f64().unwrap_or(0.5).clamp(0.0, 1.0),
+            })
+        })
+        .collect())
+}
+
+/// Replace the citations recorded for a case
+pub async fn save_case_citations(pool: &Pool<Sqlite>, case_id: &str, citations: &[Citation]) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM case_citations WHERE case_id = ?")
+        .bind(case_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for citation in citations {
+        sqlx::query(
+            "INSERT INTO case_citations (case_id, text, citation_type, confidence, created_at)
+             VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP)"
+        )
+        .bind(case_id)
+        .bind(&citation.text)
+        .bind(citation.citation_type.as_str())
+        .bind(citation.confidence)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Citations recorded for a case, in the order they were found
+pub async fn case_citations(pool: &Pool<Sqlite>, case_id: &str) -> AppResult<Vec<Citation>> {
+    let rows = sqlx::query("SELECT text, citation_type, confidence FROM case_citations WHERE case_id = ? ORDER BY id")
+        .bind(case_id)
+        .fetch_all(pool)
+        .await?;
+
+    rows.iter()
+        .map(|row| {
+            Ok(Citation {
+                text: row.get("text"),
+                citation_type: CitationType::parse(row.get("citation_type"))?,
+                confidence: row.get("confidence"),
+            })
+        })
+        .collect()
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub async fn get_case_citations(
+    storage: State<'_, HybridStorage>,
+    case_id: String,
+) -> CommandResult<Vec<Citation>> {
+    let pool = storage.sqlite().get_pool().await?;
+    Ok(case_citations(&pool, &case_id).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    fn texts(citations: &[Citation], citation_type: CitationType) -> Vec<&str> {
+        citations
+            .iter()
+            .filter(|c| c.citation_type == citation_type)
+            .map(|c| c.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_common_citation_formats() {
+        let text = "Under Brown v. Board of Education, 347 U.S. 483 (1954), and 123 F.3d 456, \
+                    segregation violates 42 U.S.C. § 1983 and § 2-207(1). See also [2020] UKSC 12, \
+                    29 C.F.R. § 1910.1200 and Article 8 of the Convention [1].";
+        let citations = CitationExtractor::extract(text);
+
+        assert_eq!(texts(&citations, CitationType::Case), vec!["347 U.S. 483", "123 F.3d 456", "[2020] UKSC 12"]);
+        assert_eq!(texts(&citations, CitationType::Statute), vec!["42 U.S.C. § 1983", "§ 2-207(1)"]);
+        assert_eq!(texts(&citations, CitationType::Regulation), vec!["29 C.F.R. § 1910.1200"]);
+        assert_eq!(texts(&citations, CitationType::Article), vec!["Article 8"]);
+    }
+
+    #[test]
+    fn test_dates_are_not_case_citations() {
+        let text = "Judgment was given on 12 March 2020, applying 347 U.S. 483, and appealed on 3 May 2021.";
+        let citations = CitationExtractor::extract(text);
+        assert_eq!(texts(&citations, CitationType::Case), vec!["347 U.S. 483"]);
+    }
+
+    #[test]
+    fn test_bracketed_references_skip_matches_and_source_markers() {
+        let text = "As held in [2020] UKSC 12 [1], and in [Donoghue v Stevenson] [2, 3], see [Donoghue v Stevenson].";
+        assert_eq!(unmatched_bracketed_references(text), vec!["[Donoghue v Stevenson]"]);
+    }
+
+    #[test]
+    fn test_parse_classified_references_keeps_requested_references() {
+        let references = vec!["[Donoghue v Stevenson]".to_string()];
+        let response = "```json\n[{\"text\": \"[Donoghue v Stevenson]\", \"citation_type\": \"case\", \"confidence\": 1.4},\
+                        {\"text\": \"[invented]\", \"citation_type\": \"case\", \"confidence\": 0.9}]\n```";
+
+        let citations = parse_classified_references(response, &references).unwrap();
+        assert_eq!(
+            citations,
+            vec![Citation {
+                text: "[Donoghue v Stevenson]".to_string(),
+                citation_type: CitationType::Case,
+                confidence: 1.0,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_case_citations_replaces_previous() {
+        let pool = test_pool().await;
+
+        let first = CitationExtractor::extract("347 U.S. 483 and § 1983");
+        save_case_citations(&pool, "case-1", &first).await.unwrap();
+        assert_eq!(case_citations(&pool, "case-1").await.unwrap(), first);
+
+        let second = CitationExtractor::extract("Article 8");
+        save_case_citations(&pool, "case-1", &second).await.unwrap();
+        assert_eq!(case_citations(&pool, "case-1").await.unwrap(), second);
+        assert!(case_citations(&pool, "case-2").await.unwrap().is_empty());
+    }
+}
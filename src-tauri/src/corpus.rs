@@ -0,0 +1,590 @@
+/**
+ * Corpus Module
+ * Exports the RAG corpus (documents, chunks and embeddings) to a single archive and imports it back
+ */
+
+use crate::db::HybridStorage;
+use crate::document::DocumentProcessor;
+use crate::error::{AppError, AppResult, CommandResult};
+use crate::rag::{self, ChunkIndex, FailedFile, RagState, ReembedProgressEvent, ANNOTATION_DOCUMENT_TYPE};
+use crate::state::AppState;
+use crate::topics;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use tauri::{AppHandle, Emitter, State};
+use uuid::Uuid;
+
+/// `CorpusHeader::format` of every corpus archive
+const CORPUS_FORMAT: &str = "firm-ai-rag-corpus";
+
+/// Archive layout version; bumped when older versions can't read the archive
+const CORPUS_FORMAT_VERSION: u32 = 1;
+
+/// First line of a corpus archive. Each following line is one `CorpusDocument`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CorpusHeader {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    /// Model and dimension of the most recent embeddings in the exporting database
+    pub embedding_model: Option<String>,
+    pub embedding_dim: Option<usize>,
+    pub document_count: usize,
+}
+
+/// A document with its chunks and topics
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CorpusDocument {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub case_id: Option<String>,
+    pub title: String,
+    pub document_type: String,
+    pub content_hash: Option<String>,
+    pub text_hash: Option<String>,
+    pub source_url: Option<String>,
+    pub created_at: String,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    pub chunks: Vec<CorpusChunk>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CorpusChunk {
+    pub id: String,
+    pub chunk_index: i64,
+    pub text: String,
+    pub chunk_hash: Option<String>,
+    /// `ChunkMetadata` JSON as stored
+    pub metadata: Option<String>,
+    /// Hex-encoded `compress_embedding` blob; None for chunks that still need embedding
+    pub embedding: Option<String>,
+    pub embedding_model: Option<String>,
+    pub embedding_dim: Option<i64>,
+}
+
+/// Payload of the `rag://corpus-export-progress` and `rag://corpus-import-progress` events
+#[derive(Debug, Clone, Serialize)]
+pub struct CorpusProgressEvent {
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Outcome of `export_rag_corpus`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CorpusExportSummary {
+    pub documents: usize,
+    pub chunks: usize,
+}
+
+/// Outcome of `import_rag_corpus`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CorpusImportSummary {
+    pub imported: usize,
+    /// Documents whose content this user had already indexed
+    pub skipped: usize,
+    /// Documents that couldn't be imported, by title; nothing of them was stored
+    pub failed: Vec<FailedFile>,
+    /// Imported chunks without an embedding from the current model
+    pub needs_reembedding: usize,
+    /// Chunks re-embedded with the current model after importing
+    pub reembedded: usize,
+    /// Set when re-embedding failed; `reembed_all_documents` can be retried later
+    pub reembed_error: Option<String>,
+}
+
+/// Write every indexed document (except indexed annotations, which are
+/// rebuilt from the annotations themselves) to `path`, reporting `(done, total)`
+/// after each document
+pub async fn export_corpus(
+    pool: &Pool<Sqlite>,
+    path: &Path,
+    mut on_progress: impl FnMut(usize, usize),
+) -> AppResult<CorpusExportSummary> {
+    let rows = sqlx::query(
+        "SELECT id, user_id, case_id, title, document_type, content_hash, text_hash, source_url, created_at
+         FROM documents
+         WHERE document_type IS NOT ? AND embedding_status IS NOT 'failed'
+         ORDER BY created_at, rowid"
+    )
+    .bind(ANNOTATION_DOCUMENT_TYPE)
+    .fetch_all(pool)
+    .await?;
+
+    let model = rag::recorded_embedding_model(pool).await?;
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_line(
+        &mut writer,
+        &CorpusHeader {
+            format: CORPUS_FORMAT.to_string(),
+            version: CORPUS_FORMAT_VERSION,
+            exported_at: Utc::now().to_rfc3339(),
+            embedding_model: model.as_ref().map(|(model, _)| model.clone()),
+            embedding_dim: model.map(|(_, dim)| dim),
+            document_count: rows.len(),
+        },
+    )?;
+
+    let mut summary = CorpusExportSummary::default();
+    for row in &rows {
+        let id: String = row.get("id");
+        let document = CorpusDocument {
+            topics: topics::document_topics(pool, &id).await?,
+            chunks: export_chunks(pool, &id).await?,
+            id,
+            user_id: row.get("user_id"),
+            case_id: row.get("case_id"),
+            title: row.get("title"),
+            document_type: row.get("document_type"),
+            content_hash: row.get("content_hash"),
+            text_hash: row.get("text_hash"),
+            source_url: row.get("source_url"),
+            created_at: row.get("created_at"),
+        };
+        write_line(&mut writer, &document)?;
+
+        summary.documents += 1;
+        summary.chunks += document.chunks.len();
+        on_progress(summary.documents, rows.len());
+    }
+
+    writer.flush()?;
+    Ok(summary)
+}
+
+/// A document's chunks in index order, with embeddings converted to the current blob format
+async fn export_chunks(pool: &Pool<Sqlite>, doc_id: &str) -> AppResult<Vec<CorpusChunk>> {
+    let rows = sqlx::query(
+        "SELECT id, chunk_index, chunk_text, chunk_hash, metadata, embedding, embedding_model, embedding_dim
+         FROM document_chunks
+         WHERE document_id = ?
+         ORDER BY chunk_index"
+    )
+    .bind(doc_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let id: String = row.get("id");
+            let blob: Option<Vec<u8>> = row.get("embedding");
+            let model: Option<String> = row.get("embedding_model");
+            let dim: Option<i64> = row.get("embedding_dim");
+
+            // Corrupt embeddings are left out so the chunk is re-embedded on import
+            let embedding = match (blob, &model, dim) {
+                (Some(blob), Some(_), Some(dim)) => match rag::read_embedding(&blob, dim as usize) {
+                    Ok(embedding) => Some(to_hex(&rag::compress_embedding(&embedding))),
+                    Err(e) => {
+                        rag::log_corrupt_embedding(&id, &e);
+                        None
+                    }
+                },
+                _ => None,
+            };
+
+            CorpusChunk {
+                chunk_index: row.get("chunk_index"),
+                text: row.get("chunk_text"),
+                chunk_hash: row.get("chunk_hash"),
+                metadata: row.get("metadata"),
+                embedding_model: embedding.as_ref().and(model),
+                embedding_dim: embedding.as_ref().and(dim),
+                embedding,
+                id,
+            }
+        })
+        .collect())
+}
+
+/// Import the documents in an archive written by `export_corpus`, reporting
+/// `(done, total)` after each document
+///
+/// Each document is stored in its own transaction. Documents whose content
+/// the same user has already indexed are skipped; a document whose id is
+/// taken by different content is stored under new ids.
+pub async fn import_corpus(
+    pool: &Pool<Sqlite>,
+    index: &ChunkIndex,
+    path: &Path,
+    current_model: &str,
+    mut on_progress: impl FnMut(usize, usize),
+) -> AppResult<CorpusImportSummary> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let header: CorpusHeader = match lines.next() {
+        Some(line) => serde_json::from_str(&line?)
+            .map_err(|e| AppError::Validation(format!("Not a corpus archive: {}", e)))?,
+        None => return Err(AppError::Validation("Corpus archive is empty".to_string())),
+    };
+    if header.format != CORPUS_FORMAT {
+        return Err(AppError::Validation(format!("Not a corpus archive (format '{}')", header.format)));
+    }
+    if header.version > CORPUS_FORMAT_VERSION {
+        return Err(AppError::Validation(format!(
+            "Corpus archive version {} is newer than this app supports ({}); update the app first",
+            header.version, CORPUS_FORMAT_VERSION
+        )));
+    }
+
+    let mut summary = CorpusImportSummary::default();
+    let mut done = 0;
+    for (line_number, line) in (2..).zip(lines) {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<CorpusDocument>(&line) {
+            Ok(document) => {
+                let title = document.title.clone();
+                match import_document(pool, index, document, current_model).await {
+                    Ok(Some(stale)) => {
+                        summary.imported += 1;
+                        summary.needs_reembedding += stale;
+                    }
+                    Ok(None) => summary.skipped += 1,
+                    Err(e) => summary.failed.push(FailedFile { file: title, error: e.to_string() }),
+                }
+            }
+            Err(e) => summary.failed.push(FailedFile {
+                file: format!("line {}", line_number),
+                error: e.to_string(),
+            }),
+        }
+
+        done += 1;
+        on_progress(done, header.document_count.max(done));
+    }
+
+    Ok(summary)
+}
+
+/// Store one archived document and its chunks in a single transaction,
+/// returning how many of its chunks need re-embedding, or None if its
+/// content was already indexed
+async fn import_document(
+    pool: &Pool<Sqlite>,
+    index: &ChunkIndex,
+    document: CorpusDocument,
+    current_model: &str,
+) -> AppResult<Option<usize>> {
+    let id_taken: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM documents WHERE id = ?)")
+        .bind(&document.id)
+        .fetch_one(pool)
+        .await?;
+    let already_indexed = if document.content_hash.is_none() && document.text_hash.is_none() {
+        id_taken
+    } else {
+        // An empty file hash never matches a stored one
+        rag::find_duplicate(
+            pool,
+            document.content_hash.as_deref().unwrap_or_default(),
+            document.text_hash.as_deref(),
+            document.user_id.as_deref(),
+        )
+        .await?
+        .is_some()
+    };
+    if already_indexed {
+        return Ok(None);
+    }
+
+    // Validate every embedding before anything is written
+    let embeddings = document
+        .chunks
+        .iter()
+        .map(|chunk| match (&chunk.embedding, &chunk.embedding_model, chunk.embedding_dim) {
+            (Some(hex), Some(model), Some(dim)) => {
+                let embedding = from_hex(hex)
+                    .and_then(|blob| rag::read_embedding(&blob, dim as usize))
+                    .map_err(|e| AppError::Validation(format!("Chunk {} has a corrupt embedding: {}", chunk.chunk_index, e)))?;
+                Ok(Some((model.clone(), embedding)))
+            }
+            _ => Ok(None),
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+    let doc_id = if id_taken { Uuid::new_v4().to_string() } else { document.id.clone() };
+    let case_exists: bool = match &document.case_id {
+        Some(case_id) => sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM cases WHERE id = ?)")
+            .bind(case_id)
+            .fetch_one(pool)
+            .await?,
+        None => false,
+    };
+    // Cases aren't part of the archive; keep the link only if the case is already here
+    let case_id = document.case_id.as_ref().filter(|_| case_exists);
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO documents (id, user_id, case_id, title, document_type, embedding_status, total_chunks, content_hash, text_hash, source_url, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, 'completed', ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)"
+    )
+    .bind(&doc_id)
+    .bind(&document.user_id)
+    .bind(case_id)
+    .bind(&document.title)
+    .bind(&document.document_type)
+    .bind(document.chunks.len() as i64)
+    .bind(&document.content_hash)
+    .bind(&document.text_hash)
+    .bind(&document.source_url)
+    .bind(&document.created_at)
+    .execute(&mut *tx)
+    .await?;
+
+    let mut chunk_ids = Vec::with_capacity(document.chunks.len());
+    for (chunk, embedding) in document.chunks.iter().zip(&embeddings) {
+        let chunk_id = if id_taken { Uuid::new_v4().to_string() } else { chunk.id.clone() };
+        let metadata = if id_taken {
+            chunk.metadata.as_deref().map(|m| with_document_id(m, &doc_id)).transpose()?
+        } else {
+            chunk.metadata.clone()
+        };
+        let chunk_hash = chunk
+            .chunk_hash
+            .clone()
+            .unwrap_or_else(|| DocumentProcessor::chunk_hash(&chunk.text));
+
+        sqlx::query(
+            "INSERT INTO document_chunks (id, document_id, chunk_index, chunk_text, chunk_hash, metadata, embedding, embedding_model, embedding_dim, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)"
+        )
+        .bind(&chunk_id)
+        .bind(&doc_id)
+        .bind(chunk.chunk_index)
+        .bind(&chunk.text)
+        .bind(chunk_hash)
+        .bind(metadata)
+        .bind(embedding.as_ref().map(|(_, e)| rag::compress_embedding(e)))
+        .bind(embedding.as_ref().map(|(model, _)| model))
+        .bind(embedding.as_ref().map(|(_, e)| e.len() as i64))
+        .execute(&mut *tx)
+        .await?;
+
+        chunk_ids.push(chunk_id);
+    }
+
+    for topic in document.topics.iter().filter_map(|t| topics::canonical_topic(t)) {
+        sqlx::query("INSERT OR IGNORE INTO document_topics (document_id, topic, created_at) VALUES (?, ?, CURRENT_TIMESTAMP)")
+            .bind(&doc_id)
+            .bind(topic)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    let mut stale = 0;
+    for (chunk_id, embedding) in chunk_ids.iter().zip(&embeddings) {
+        match embedding {
+            Some((model, vector)) => {
+                index.insert(model, std::slice::from_ref(chunk_id), std::slice::from_ref(vector));
+                if model != current_model {
+                    stale += 1;
+                }
+            }
+            None => stale += 1,
+        }
+    }
+
+    Ok(Some(stale))
+}
+
+/// Stored chunk metadata JSON pointing at `doc_id`
+fn with_document_id(metadata: &str, doc_id: &str) -> AppResult<String> {
+    let mut value: serde_json::Value = serde_json::from_str(metadata)?;
+    if let Some(object) = value.as_object_mut() {
+        object.insert("document_id".to_string(), serde_json::Value::String(doc_id.to_string()));
+    }
+    Ok(value.to_string())
+}
+
+fn write_line(writer: &mut impl Write, value: &impl Serialize) -> AppResult<()> {
+    serde_json::to_writer(&mut *writer, value)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> AppResult<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return Err(AppError::Validation("Hex string has an odd length".to_string()));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| AppError::Validation(format!("Invalid hex at offset {}", i)))
+        })
+        .collect()
+}
+
+// Tauri Commands
+
+/// Write every indexed document, its chunks and their embeddings to a single
+/// archive at `path`, emitting `rag://corpus-export-progress`
+#[tauri::command]
+pub async fn export_rag_corpus(
+    app: AppHandle,
+    storage: State<'_, HybridStorage>,
+    path: String,
+) -> CommandResult<CorpusExportSummary> {
+    let pool = storage.sqlite().get_pool().await?;
+    Ok(export_corpus(&pool, Path::new(&path), |done, total| {
+        let _ = app.emit("rag://corpus-export-progress", CorpusProgressEvent { done, total });
+    })
+    .await?)
+}
+
+/// Import an archive written by `export_rag_corpus`, emitting `rag://corpus-import-progress`
+///
+/// Chunks embedded with a different model than the current one are
+/// re-embedded afterwards, emitting `rag://reembed-progress`.
+#[tauri::command]
+pub async fn import_rag_corpus(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    storage: State<'_, HybridStorage>,
+    rag: State<'_, RagState>,
+    path: String,
+) -> CommandResult<CorpusImportSummary> {
+    let llm = state.configured_llm().await?;
+    let pool = storage.sqlite().get_pool().await?;
+    let current_model = rag::current_embedding_model(&llm);
+
+    let mut summary = import_corpus(&pool, rag.index(), Path::new(&path), &current_model, |done, total| {
+        let _ = app.emit("rag://corpus-import-progress", CorpusProgressEvent { done, total });
+    })
+    .await?;
+
+    if summary.needs_reembedding > 0 {
+        let reembedded = rag::reembed_stale_chunks(&pool, &rag, &llm, |done, total| {
+            let _ = app.emit("rag://reembed-progress", ReembedProgressEvent { done, total });
+        })
+        .await;
+        match reembedded {
+            Ok(count) => summary.reembedded = count,
+            Err(e) => summary.reembed_error = Some(e.to_string()),
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    async fn insert_document(pool: &Pool<Sqlite>, id: &str, text_hash: &str, model: &str) {
+        sqlx::query(
+            "INSERT INTO documents (id, user_id, document_type, title, content_hash, text_hash, embedding_status, created_at, updated_at)
+             VALUES (?, 'user-a', 'user_case', ?, ?, ?, 'completed', 'now', 'now')"
+        )
+        .bind(id)
+        .bind(id)
+        .bind(format!("file-{}", text_hash))
+        .bind(text_hash)
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO document_chunks (id, document_id, chunk_index, chunk_text, metadata, embedding, embedding_model, embedding_dim, created_at)
+             VALUES (?, ?, 0, ?, ?, ?, ?, 3, 'now')"
+        )
+        .bind(format!("{}-chunk", id))
+        .bind(id)
+        .bind(format!("text of {}", id))
+        .bind(format!(r#"{{"document_id":"{}"}}"#, id))
+        .bind(rag::compress_embedding(&[1.0, 0.0, 0.0]))
+        .bind(model)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_round_trip() {
+        let source = test_pool().await;
+        insert_document(&source, "doc-a", "text-a", "test").await;
+        insert_document(&source, "doc-b", "text-b", "old-model").await;
+        topics::save_document_topics(&source, "doc-a", &["Torts".to_string()]).await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("corpus-{}.jsonl", Uuid::new_v4()));
+        let mut progress = Vec::new();
+        let exported = export_corpus(&source, &path, |done, total| progress.push((done, total))).await.unwrap();
+        assert_eq!((exported.documents, exported.chunks), (2, 2));
+        assert_eq!(progress, vec![(1, 2), (2, 2)]);
+
+        // doc-b's content is already here; doc-a's id is taken by different content
+        let target = test_pool().await;
+        insert_document(&target, "doc-b", "text-b", "test").await;
+        insert_document(&target, "doc-a", "text-other", "test").await;
+
+        let index = ChunkIndex::default();
+        let imported = import_corpus(&target, &index, &path, "test", |_, _| {}).await.unwrap();
+        assert_eq!((imported.imported, imported.skipped), (1, 1));
+        assert!(imported.failed.is_empty());
+        assert_eq!(imported.needs_reembedding, 0);
+
+        let new_id: String = sqlx::query_scalar("SELECT id FROM documents WHERE text_hash = 'text-a'")
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        assert_ne!(new_id, "doc-a");
+        let metadata: String = sqlx::query_scalar("SELECT metadata FROM document_chunks WHERE document_id = ?")
+            .bind(&new_id)
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        assert!(metadata.contains(&new_id));
+        assert_eq!(topics::document_topics(&target, &new_id).await.unwrap(), vec!["Torts"]);
+
+        // Importing again changes nothing
+        let again = import_corpus(&target, &index, &path, "test", |_, _| {}).await.unwrap();
+        assert_eq!((again.imported, again.skipped), (0, 2));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_rolls_back_documents_with_corrupt_embeddings() {
+        let source = test_pool().await;
+        insert_document(&source, "doc-a", "text-a", "test").await;
+        insert_document(&source, "doc-b", "text-b", "old-model").await;
+
+        let path = std::env::temp_dir().join(format!("corpus-{}.jsonl", Uuid::new_v4()));
+        export_corpus(&source, &path, |_, _| {}).await.unwrap();
+        let archive = std::fs::read_to_string(&path).unwrap();
+        let mut lines: Vec<String> = archive.lines().map(String::from).collect();
+        let mut document: serde_json::Value = serde_json::from_str(&lines[1]).unwrap();
+        document["chunks"][0]["embedding"] = serde_json::Value::String("zz".to_string());
+        lines[1] = document.to_string();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let target = test_pool().await;
+        let imported = import_corpus(&target, &ChunkIndex::default(), &path, "test", |_, _| {}).await.unwrap();
+        assert_eq!(imported.imported, 1);
+        assert_eq!(imported.failed.len(), 1);
+        assert_eq!(imported.failed[0].file, "doc-a");
+        assert_eq!(imported.needs_reembedding, 1);
+
+        let ids: Vec<String> = sqlx::query_scalar("SELECT id FROM documents").fetch_all(&target).await.unwrap();
+        assert_eq!(ids, vec!["doc-b"]);
+        assert!(from_hex("abc").is_err());
+        assert_eq!(from_hex(&to_hex(&[0, 15, 255])).unwrap(), vec![0, 15, 255]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -3,28 +3,54 @@
  * Manages Supabase (cloud) and SQLite (local) databases with sync capabilities
  */
 
-use crate::error::{AppError, AppResult};
+use crate::error::{AppError, AppResult, CommandError, CommandResult};
 use postgrest::Postgrest;
-use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
+use sqlx::{Pool, Row, Sqlite};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
+use tauri::State;
 use tokio::sync::Mutex;
 
+/// How long a connectivity check waits for Supabase before treating it as unreachable
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Supabase client wrapper
 #[derive(Clone)]
 pub struct SupabaseClient {
     client: Postgrest,
     api_key: String,
+    /// PostgREST root, e.g. `https://<project>.supabase.co/rest/v1`
+    url: String,
+    /// Client for connectivity checks, with a short timeout
+    ping_client: reqwest::Client,
 }
 
 impl SupabaseClient {
     pub fn new(url: String, api_key: String) -> Self {
-        let client = Postgrest::new(url)
+        let client = Postgrest::new(url.clone())
             .insert_header("apikey", &api_key)
             .insert_header("Authorization", format!("Bearer {}", api_key));
+        let ping_client = reqwest::Client::builder()
+            .timeout(PING_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+
+        Self { client, api_key, url, ping_client }
+    }
 
-        Self { client, api_key }
+    /// Whether Supabase answers a request to the PostgREST root within
+    /// `PING_TIMEOUT`; any HTTP response counts, since only reachability matters
+    pub async fn ping(&self) -> bool {
+        self.ping_client
+            .get(&self.url)
+            .header("apikey", &self.api_key)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .send()
+            .await
+            .is_ok()
     }
 
     pub fn client(&self) -> &Postgrest {
@@ -43,6 +69,11 @@ impl SupabaseClient {
         Ok(self.client.from(table).insert(data))
     }
 
+    /// Insert `data`, updating the existing row instead when `conflict_col` matches one
+    pub async fn upsert(&self, table: &str, data: &str, conflict_col: &str) -> AppResult<postgrest::Builder> {
+        Ok(self.client.from(table).upsert(data).on_conflict(conflict_col))
+    }
+
     pub async fn update(&self, table: &str, data: &str) -> AppResult<postgrest::Builder> {
         Ok(self.client.from(table).update(data))
     }
@@ -52,17 +83,154 @@ impl SupabaseClient {
     }
 }
 
+/// Path that opens a private in-memory database instead of a file
+pub const IN_MEMORY_DB: &str = ":memory:";
+
+/// Connection pool and pragma settings for the local database
+///
+/// `max_connections = 5` is fine with WAL, since readers don't block the
+/// writer; raise it if heavy parallel reads are needed. An in-memory
+/// database is private to each connection, so use `max_connections = 1`
+/// with [`IN_MEMORY_DB`].
+#[derive(Debug, Clone)]
+pub struct SqliteCacheOptions {
+    pub max_connections: u32,
+    /// Use write-ahead logging so the sync task and user commands don't block each other
+    pub wal_mode: bool,
+    /// Page cache size per connection, in KiB
+    pub cache_size_kb: i64,
+    /// How long a connection waits on a locked database before failing
+    pub busy_timeout_ms: u64,
+}
+
+impl Default for SqliteCacheOptions {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            wal_mode: true,
+            cache_size_kb: 64_000,
+            busy_timeout_ms: 5_000,
+        }
+    }
+}
+
+impl SqliteCacheOptions {
+    /// Small single-connection pool over an in-memory database, for tests
+    pub fn in_memory() -> Self {
+        Self {
+            max_connections: 1,
+            wal_mode: false,
+            ..Default::default()
+        }
+    }
+}
+
+/// Schema migrations as `(name, sql)`, applied in order; version N is entry N - 1
+///
+/// `create_schema` builds the tables as they were when migrations began, so
+/// data migrations must be idempotent (`IF NOT EXISTS`, `WHERE ... IS NULL`,
+/// etc.) to be safe on fresh databases as well as upgraded ones. Columns added
+/// since are added only here, as SQLite's `ADD COLUMN` can't be repeated.
+/// Only append to this list.
+fn migrations() -> Vec<(&'static str, &'static str)> {
+    vec![
+        (
+            // Chunks embedded before model tracking all came from the bundled fastembed model
+            "backfill_embedding_model",
+            "UPDATE document_chunks
+             SET embedding_model = 'fastembed/all-MiniLM-L6-v2', embedding_dim = length(embedding) / 4
+             WHERE embedding IS NOT NULL AND embedding_model IS NULL",
+        ),
+        (
+            // Why a queued operation last failed
+            "add_sync_queue_last_error",
+            "ALTER TABLE sync_queue ADD COLUMN last_error TEXT",
+        ),
+        (
+            // When a failed queued operation may next be retried
+            "add_sync_queue_next_retry_at",
+            "ALTER TABLE sync_queue ADD COLUMN next_retry_at TEXT",
+        ),
+        (
+            // Operations that used to sit in the queue forever once out of retries
+            "dead_letter_exhausted_sync_operations",
+            "INSERT INTO sync_dead_letter (operation_type, table_name, record_id, data, attempts, last_error, created_at, failed_at)
+             SELECT operation_type, table_name, record_id, data, attempts, last_error, created_at, datetime('now')
+             FROM sync_queue WHERE attempts >= 5;
+             DELETE FROM sync_queue WHERE attempts >= 5;",
+        ),
+        (
+            // Page a document was fetched from by `ingest_url`
+            "add_documents_source_url",
+            "ALTER TABLE documents ADD COLUMN source_url TEXT",
+        ),
+        (
+            // Lets re-ingestion re-embed only chunks whose text changed
+            "add_document_chunks_chunk_hash",
+            "ALTER TABLE document_chunks ADD COLUMN chunk_hash TEXT",
+        ),
+        (
+            // Hash of a document's normalized text, to catch the same text saved as another file
+            "add_documents_text_hash",
+            "ALTER TABLE documents ADD COLUMN text_hash TEXT;
+             CREATE INDEX IF NOT EXISTS idx_documents_text_hash ON documents(user_id, text_hash);",
+        ),
+        (
+            // Recycle bin tombstones
+            "add_deleted_at",
+            "ALTER TABLE cases ADD COLUMN deleted_at TEXT;
+             ALTER TABLE mock_tests ADD COLUMN deleted_at TEXT;
+             ALTER TABLE flashcard_sets ADD COLUMN deleted_at TEXT;
+             ALTER TABLE study_plans ADD COLUMN deleted_at TEXT;",
+        ),
+        (
+            // Difficulty of questions contributed from tests generated at one
+            "add_question_bank_difficulty",
+            "ALTER TABLE question_bank ADD COLUMN difficulty TEXT",
+        ),
+    ]
+}
+
+/// Migrations newer than `current`, paired with their version numbers
+fn pending_migrations<'a>(current: i64, migrations: &'a [(&'a str, &'a str)]) -> Vec<(i64, &'a str, &'a str)> {
+    migrations
+        .iter()
+        .enumerate()
+        .map(|(i, (name, sql))| (i as i64 + 1, *name, *sql))
+        .filter(|(version, _, _)| *version > current)
+        .collect()
+}
+
+/// Disk usage of the local database
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageReport {
+    /// Database file size, including the WAL file
+    pub db_size_bytes: u64,
+    pub total_chunks: u64,
+    pub total_documents: u64,
+    pub total_cases: u64,
+    pub total_flashcards: u64,
+    /// Space taken by chunk embeddings (f16 or legacy f32)
+    pub estimated_embedding_bytes: u64,
+}
+
 /// SQLite local cache manager
 #[derive(Clone)]
 pub struct SqliteCache {
     db_path: PathBuf,
+    options: SqliteCacheOptions,
     pool: Arc<Mutex<Option<Pool<Sqlite>>>>,
 }
 
 impl SqliteCache {
     pub fn new(db_path: PathBuf) -> Self {
+        Self::with_options(db_path, SqliteCacheOptions::default())
+    }
+
+    pub fn with_options(db_path: PathBuf, options: SqliteCacheOptions) -> Self {
         Self {
             db_path,
+            options,
             pool: Arc::new(Mutex::new(None)),
         }
     }
@@ -70,24 +238,43 @@ impl SqliteCache {
     /// Initialize the SQLite database with schema
     pub async fn initialize(&self) -> AppResult<()> {
         let mut pool_guard = self.pool.lock().await;
-        
+        let in_memory = self.db_path == Path::new(IN_MEMORY_DB);
+
         // Create parent directory if it doesn't exist
-        if let Some(parent) = self.db_path.parent() {
-            std::fs::create_dir_all(parent)?;
+        if !in_memory {
+            if let Some(parent) = self.db_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        let connect_options = if in_memory {
+            SqliteConnectOptions::new().in_memory(true)
+        } else {
+            SqliteConnectOptions::new()
+                .filename(&self.db_path)
+                .create_if_missing(true)
+        };
+
+        // Pragmas are set on the connect options so every pooled connection gets them
+        let mut connect_options = connect_options
+            .foreign_keys(true)
+            .busy_timeout(Duration::from_millis(self.options.busy_timeout_ms))
+            .pragma("cache_size", format!("-{}", self.options.cache_size_kb));
+
+        if self.options.wal_mode && !in_memory {
+            connect_options = connect_options
+                .journal_mode(SqliteJournalMode::Wal)
+                .synchronous(SqliteSynchronous::Normal);
         }
 
         let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .connect_with(
-                sqlx::sqlite::SqliteConnectOptions::new()
-                    .filename(&self.db_path)
-                    .create_if_missing(true)
-                    .foreign_keys(true)
-            )
+            .max_connections(self.options.max_connections.max(1))
+            .connect_with(connect_options)
             .await?;
 
         // Create tables
         self.create_schema(&pool).await?;
+        self.run_migrations(&pool, &migrations()).await?;
 
         *pool_guard = Some(pool);
         Ok(())
@@ -114,6 +301,35 @@ impl SqliteCache {
             )"
         ).execute(pool).await?;
 
+        // Tags a user files cases under
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS tags (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                color TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                synced INTEGER DEFAULT 0,
+                dirty INTEGER DEFAULT 0
+            )"
+        ).execute(pool).await?;
+
+        // Which tags each case has
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS case_tags (
+                id TEXT PRIMARY KEY,
+                case_id TEXT NOT NULL,
+                tag_id TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                synced INTEGER DEFAULT 0,
+                dirty INTEGER DEFAULT 0,
+                FOREIGN KEY (case_id) REFERENCES cases(id) ON DELETE CASCADE,
+                FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+            )"
+        ).execute(pool).await?;
+
         // Documents table
         sqlx::query(
             "CREATE TABLE IF NOT EXISTS documents (
@@ -125,6 +341,7 @@ impl SqliteCache {
                 original_text TEXT,
                 embedding_status TEXT DEFAULT 'pending',
                 total_chunks INTEGER DEFAULT 0,
+                content_hash TEXT,
                 created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL,
                 synced INTEGER DEFAULT 0,
@@ -142,6 +359,8 @@ impl SqliteCache {
                 chunk_text TEXT NOT NULL,
                 metadata TEXT,
                 embedding BLOB,
+                embedding_model TEXT,
+                embedding_dim INTEGER,
                 created_at TEXT NOT NULL,
                 synced INTEGER DEFAULT 0,
                 FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
@@ -176,6 +395,45 @@ impl SqliteCache {
             )"
         ).execute(pool).await?;
 
+        // Spaced-repetition (SM-2) schedule of each reviewed flashcard, kept local
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS flashcard_reviews (
+                flashcard_id TEXT PRIMARY KEY,
+                ease_factor REAL NOT NULL,
+                interval_days INTEGER NOT NULL,
+                repetitions INTEGER NOT NULL,
+                due_at TEXT NOT NULL,
+                reviewed_at TEXT NOT NULL,
+                FOREIGN KEY (flashcard_id) REFERENCES flashcards(id) ON DELETE CASCADE
+            )"
+        ).execute(pool).await?;
+
+        // Review sessions over a flashcard set; card_ids is the JSON array of cards in review order
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS review_sessions (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                set_id TEXT NOT NULL,
+                strategy TEXT NOT NULL,
+                card_ids TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                finished_at TEXT,
+                FOREIGN KEY (set_id) REFERENCES flashcard_sets(id) ON DELETE CASCADE
+            )"
+        ).execute(pool).await?;
+
+        // Answers given during a review session, one per card
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS review_session_cards (
+                session_id TEXT NOT NULL,
+                flashcard_id TEXT NOT NULL,
+                quality INTEGER NOT NULL,
+                reviewed_at TEXT NOT NULL,
+                PRIMARY KEY (session_id, flashcard_id),
+                FOREIGN KEY (session_id) REFERENCES review_sessions(id) ON DELETE CASCADE
+            )"
+        ).execute(pool).await?;
+
         // Mock tests table
         sqlx::query(
             "CREATE TABLE IF NOT EXISTS mock_tests (
@@ -185,6 +443,7 @@ impl SqliteCache {
                 description TEXT,
                 questions TEXT NOT NULL,
                 created_at TEXT NOT NULL,
+                kind TEXT NOT NULL DEFAULT 'exam',
                 synced INTEGER DEFAULT 0,
                 dirty INTEGER DEFAULT 0
             )"
@@ -198,6 +457,7 @@ impl SqliteCache {
                 test_id TEXT NOT NULL,
                 score REAL,
                 total_questions INTEGER,
+                percentage REAL,
                 answers TEXT,
                 completed_at TEXT NOT NULL,
                 synced INTEGER DEFAULT 0,
@@ -206,6 +466,21 @@ impl SqliteCache {
             )"
         ).execute(pool).await?;
 
+        // Questions contributed from users' mock tests, shared by everyone on the device
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS question_bank (
+                id TEXT PRIMARY KEY,
+                contributed_by_user TEXT NOT NULL,
+                question TEXT NOT NULL,
+                options TEXT NOT NULL,
+                correct_answer INTEGER NOT NULL,
+                explanation TEXT,
+                topic TEXT,
+                upvotes INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL
+            )"
+        ).execute(pool).await?;
+
         // Study plans table
         sqlx::query(
             "CREATE TABLE IF NOT EXISTS study_plans (
@@ -224,6 +499,203 @@ impl SqliteCache {
             )"
         ).execute(pool).await?;
 
+        // Profiles of the people using this device
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS profiles (
+                id TEXT PRIMARY KEY,
+                display_name TEXT NOT NULL,
+                email TEXT,
+                avatar_url TEXT,
+                created_at TEXT NOT NULL
+            )"
+        ).execute(pool).await?;
+
+        // Due-task notifications already shown, so each task is announced once a day
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS sent_notifications (
+                task_id TEXT NOT NULL,
+                date TEXT NOT NULL,
+                sent_at TEXT NOT NULL,
+                PRIMARY KEY (task_id, date)
+            )"
+        ).execute(pool).await?;
+
+        // Topic outlines generated from a user's cases
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS outlines (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                topic TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                synced INTEGER DEFAULT 0,
+                dirty INTEGER DEFAULT 0
+            )"
+        ).execute(pool).await?;
+
+        // Tutor conversations (mode persists across turns)
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS tutor_conversations (
+                id TEXT PRIMARY KEY,
+                user_id TEXT,
+                mode TEXT NOT NULL DEFAULT 'explain',
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )"
+        ).execute(pool).await?;
+
+        // Tutor conversation messages
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS tutor_messages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                conversation_id TEXT NOT NULL,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (conversation_id) REFERENCES tutor_conversations(id) ON DELETE CASCADE
+            )"
+        ).execute(pool).await?;
+
+        // Highlights and margin notes on ingested documents
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS annotations (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                document_id TEXT NOT NULL,
+                page INTEGER NOT NULL,
+                start_offset INTEGER NOT NULL,
+                end_offset INTEGER NOT NULL,
+                selected_text TEXT NOT NULL,
+                note TEXT,
+                color TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                synced INTEGER DEFAULT 0,
+                dirty INTEGER DEFAULT 0,
+                FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+            )"
+        ).execute(pool).await?;
+
+        // Legal citations found in a case's IRAC analysis
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS case_citations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                case_id TEXT NOT NULL,
+                text TEXT NOT NULL,
+                citation_type TEXT NOT NULL,
+                confidence REAL NOT NULL,
+                created_at TEXT NOT NULL
+            )"
+        ).execute(pool).await?;
+
+        // Legal topics each document is labelled with
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS document_topics (
+                document_id TEXT NOT NULL,
+                topic TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (document_id, topic),
+                FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+            )"
+        ).execute(pool).await?;
+
+        // Concepts extracted from a document for the knowledge graph
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS nodes (
+                id TEXT PRIMARY KEY,
+                document_id TEXT NOT NULL,
+                label TEXT NOT NULL,
+                label_key TEXT NOT NULL,
+                node_type TEXT NOT NULL DEFAULT 'concept',
+                created_at TEXT NOT NULL,
+                UNIQUE (document_id, label_key),
+                FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+            )"
+        ).execute(pool).await?;
+
+        // Relations between knowledge graph concepts
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS edges (
+                id TEXT PRIMARY KEY,
+                from_node TEXT NOT NULL,
+                to_node TEXT NOT NULL,
+                relation TEXT NOT NULL,
+                weight REAL NOT NULL DEFAULT 1.0,
+                UNIQUE (from_node, to_node, relation),
+                FOREIGN KEY (from_node) REFERENCES nodes(id) ON DELETE CASCADE,
+                FOREIGN KEY (to_node) REFERENCES nodes(id) ON DELETE CASCADE
+            )"
+        ).execute(pool).await?;
+
+        // Opposing arguments generated against a case's IRAC analysis
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS irac_oppositions (
+                id TEXT PRIMARY KEY,
+                case_id TEXT NOT NULL,
+                irac TEXT NOT NULL,
+                counter_issue TEXT NOT NULL,
+                counter_rule TEXT NOT NULL,
+                counter_analysis TEXT NOT NULL,
+                counter_conclusion TEXT NOT NULL,
+                weaknesses TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )"
+        ).execute(pool).await?;
+
+        // Files queued for background ingestion, worked through in insertion order
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS ingest_jobs (
+                id TEXT PRIMARY KEY,
+                path TEXT NOT NULL,
+                document_id TEXT NOT NULL,
+                metadata TEXT NOT NULL,
+                chunking TEXT,
+                allow_duplicate INTEGER NOT NULL DEFAULT 0,
+                status TEXT NOT NULL DEFAULT 'pending',
+                error TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )"
+        ).execute(pool).await?;
+
+        // Records changed both locally and remotely, held back from sync until resolved
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS sync_conflicts (
+                id TEXT PRIMARY KEY,
+                table_name TEXT NOT NULL,
+                record_id TEXT NOT NULL,
+                local_data TEXT NOT NULL,
+                remote_data TEXT NOT NULL,
+                detected_at TEXT NOT NULL
+            )"
+        ).execute(pool).await?;
+
+        // Newest remote `updated_at` pulled per synced table
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS sync_watermarks (
+                table_name TEXT PRIMARY KEY,
+                last_pulled_at TEXT NOT NULL
+            )"
+        ).execute(pool).await?;
+
+        // Key/value settings of the RAG store, e.g. the embedding model in use
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS rag_meta (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )"
+        ).execute(pool).await?;
+
+        // OpenRouter model list cache
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS models_cache (
+                id TEXT PRIMARY KEY,
+                data TEXT NOT NULL,
+                fetched_at TEXT NOT NULL
+            )"
+        ).execute(pool).await?;
+
         // Sync queue table
         sqlx::query(
             "CREATE TABLE IF NOT EXISTS sync_queue (
@@ -237,21 +709,350 @@ impl SqliteCache {
             )"
         ).execute(pool).await?;
 
+        // Queued operations that ran out of retries, kept until the user retries or discards them
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS sync_dead_letter (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                operation_type TEXT NOT NULL,
+                table_name TEXT NOT NULL,
+                record_id TEXT NOT NULL,
+                data TEXT NOT NULL,
+                attempts INTEGER NOT NULL,
+                last_error TEXT,
+                created_at TEXT NOT NULL,
+                failed_at TEXT NOT NULL
+            )"
+        ).execute(pool).await?;
+
+        // Columns added after the initial release
+        Self::add_column_if_missing(pool, "document_chunks", "embedding_model", "TEXT").await?;
+        Self::add_column_if_missing(pool, "document_chunks", "embedding_dim", "INTEGER").await?;
+        Self::add_column_if_missing(pool, "documents", "content_hash", "TEXT").await?;
+        Self::add_column_if_missing(pool, "mock_tests", "kind", "TEXT NOT NULL DEFAULT 'exam'").await?;
+        Self::add_column_if_missing(pool, "test_results", "percentage", "REAL").await?;
+
+        // Applied migrations
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS schema_version (
+                version INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                applied_at TEXT NOT NULL
+            )"
+        ).execute(pool).await?;
+
         // Create indexes
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_cases_user ON cases(user_id)").execute(pool).await?;
+        sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_tags_user_name ON tags(user_id, name)").execute(pool).await?;
+        sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_case_tags_pair ON case_tags(case_id, tag_id)").execute(pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_case_tags_tag ON case_tags(tag_id)").execute(pool).await?;
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_documents_user ON documents(user_id)").execute(pool).await?;
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_documents_case ON documents(case_id)").execute(pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_documents_hash ON documents(user_id, content_hash)").execute(pool).await?;
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_chunks_document ON document_chunks(document_id)").execute(pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_chunks_embedding_model ON document_chunks(embedding_model, embedding_dim)").execute(pool).await?;
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_flashcard_sets_user ON flashcard_sets(user_id)").execute(pool).await?;
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_flashcards_set ON flashcards(set_id)").execute(pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_review_sessions_user ON review_sessions(user_id)").execute(pool).await?;
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_mock_tests_user ON mock_tests(user_id)").execute(pool).await?;
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_test_results_user ON test_results(user_id)").execute(pool).await?;
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_study_plans_user ON study_plans(user_id)").execute(pool).await?;
+        sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_question_bank_contribution ON question_bank(contributed_by_user, question)").execute(pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_outlines_user ON outlines(user_id)").execute(pool).await?;
+        sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_sync_conflicts_record ON sync_conflicts(table_name, record_id)").execute(pool).await?;
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_sync_queue_table ON sync_queue(table_name, record_id)").execute(pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_tutor_messages_conversation ON tutor_messages(conversation_id)").execute(pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_annotations_document ON annotations(document_id)").execute(pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_case_citations_case ON case_citations(case_id)").execute(pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_irac_oppositions_case ON irac_oppositions(case_id)").execute(pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_document_topics_topic ON document_topics(topic)").execute(pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_nodes_label_key ON nodes(label_key)").execute(pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_edges_to_node ON edges(to_node)").execute(pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_ingest_jobs_status ON ingest_jobs(status)").execute(pool).await?;
+
+        self.create_cases_fts(pool).await?;
+        self.create_chunks_fts(pool).await?;
+        self.create_question_bank_fts(pool).await?;
+
+        Ok(())
+    }
+
+    /// Full-text index over cases, kept in sync with the `cases` table by triggers
+    async fn create_cases_fts(&self, pool: &Pool<Sqlite>) -> AppResult<()> {
+        let exists: Option<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'cases_fts'"
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        // unicode61 folds case and, with remove_diacritics, accents
+        sqlx::query(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS cases_fts USING fts5(
+                title, issue, rule, analysis, conclusion,
+                content=cases, content_rowid=rowid,
+                tokenize='unicode61 remove_diacritics 2'
+            )"
+        ).execute(pool).await?;
+
+        sqlx::query(
+            "CREATE TRIGGER IF NOT EXISTS cases_fts_insert AFTER INSERT ON cases BEGIN
+                INSERT INTO cases_fts(rowid, title, issue, rule, analysis, conclusion)
+                VALUES (new.rowid, new.title, new.issue, new.rule, new.analysis, new.conclusion);
+            END"
+        ).execute(pool).await?;
+
+        sqlx::query(
+            "CREATE TRIGGER IF NOT EXISTS cases_fts_delete AFTER DELETE ON cases BEGIN
+                INSERT INTO cases_fts(cases_fts, rowid, title, issue, rule, analysis, conclusion)
+                VALUES ('delete', old.rowid, old.title, old.issue, old.rule, old.analysis, old.conclusion);
+            END"
+        ).execute(pool).await?;
+
+        sqlx::query(
+            "CREATE TRIGGER IF NOT EXISTS cases_fts_update AFTER UPDATE ON cases BEGIN
+                INSERT INTO cases_fts(cases_fts, rowid, title, issue, rule, analysis, conclusion)
+                VALUES ('delete', old.rowid, old.title, old.issue, old.rule, old.analysis, old.conclusion);
+                INSERT INTO cases_fts(rowid, title, issue, rule, analysis, conclusion)
+                VALUES (new.rowid, new.title, new.issue, new.rule, new.analysis, new.conclusion);
+            END"
+        ).execute(pool).await?;
+
+        // Index cases that existed before the FTS table was added
+        if exists.is_none() {
+            sqlx::query("INSERT INTO cases_fts(cases_fts) VALUES ('rebuild')").execute(pool).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Full-text index over chunk text for keyword search, kept in sync by triggers
+    async fn create_chunks_fts(&self, pool: &Pool<Sqlite>) -> AppResult<()> {
+        let exists: Option<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'document_chunks_fts'"
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS document_chunks_fts USING fts5(
+                chunk_text,
+                content=document_chunks, content_rowid=rowid,
+                tokenize='unicode61 remove_diacritics 2'
+            )"
+        ).execute(pool).await?;
+
+        sqlx::query(
+            "CREATE TRIGGER IF NOT EXISTS document_chunks_fts_insert AFTER INSERT ON document_chunks BEGIN
+                INSERT INTO document_chunks_fts(rowid, chunk_text) VALUES (new.rowid, new.chunk_text);
+            END"
+        ).execute(pool).await?;
+
+        sqlx::query(
+            "CREATE TRIGGER IF NOT EXISTS document_chunks_fts_delete AFTER DELETE ON document_chunks BEGIN
+                INSERT INTO document_chunks_fts(document_chunks_fts, rowid, chunk_text)
+                VALUES ('delete', old.rowid, old.chunk_text);
+            END"
+        ).execute(pool).await?;
+
+        sqlx::query(
+            "CREATE TRIGGER IF NOT EXISTS document_chunks_fts_update AFTER UPDATE OF chunk_text ON document_chunks BEGIN
+                INSERT INTO document_chunks_fts(document_chunks_fts, rowid, chunk_text)
+                VALUES ('delete', old.rowid, old.chunk_text);
+                INSERT INTO document_chunks_fts(rowid, chunk_text) VALUES (new.rowid, new.chunk_text);
+            END"
+        ).execute(pool).await?;
+
+        // Index chunks that existed before the FTS table was added
+        if exists.is_none() {
+            sqlx::query("INSERT INTO document_chunks_fts(document_chunks_fts) VALUES ('rebuild')").execute(pool).await?;
+        }
 
         Ok(())
     }
 
+    /// Full-text index over the question bank, kept in sync by triggers
+    async fn create_question_bank_fts(&self, pool: &Pool<Sqlite>) -> AppResult<()> {
+        sqlx::query(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS question_bank_fts USING fts5(
+                question, explanation, topic,
+                content=question_bank, content_rowid=rowid,
+                tokenize='unicode61 remove_diacritics 2'
+            )"
+        ).execute(pool).await?;
+
+        sqlx::query(
+            "CREATE TRIGGER IF NOT EXISTS question_bank_fts_insert AFTER INSERT ON question_bank BEGIN
+                INSERT INTO question_bank_fts(rowid, question, explanation, topic)
+                VALUES (new.rowid, new.question, new.explanation, new.topic);
+            END"
+        ).execute(pool).await?;
+
+        sqlx::query(
+            "CREATE TRIGGER IF NOT EXISTS question_bank_fts_delete AFTER DELETE ON question_bank BEGIN
+                INSERT INTO question_bank_fts(question_bank_fts, rowid, question, explanation, topic)
+                VALUES ('delete', old.rowid, old.question, old.explanation, old.topic);
+            END"
+        ).execute(pool).await?;
+
+        sqlx::query(
+            "CREATE TRIGGER IF NOT EXISTS question_bank_fts_update AFTER UPDATE OF question, explanation, topic ON question_bank BEGIN
+                INSERT INTO question_bank_fts(question_bank_fts, rowid, question, explanation, topic)
+                VALUES ('delete', old.rowid, old.question, old.explanation, old.topic);
+                INSERT INTO question_bank_fts(rowid, question, explanation, topic)
+                VALUES (new.rowid, new.question, new.explanation, new.topic);
+            END"
+        ).execute(pool).await?;
+
+        Ok(())
+    }
+
+    /// Keyword search over a user's cases, best BM25 matches first
+    pub async fn search_cases_fulltext(
+        &self,
+        user_id: &str,
+        query: &str,
+        limit: usize,
+    ) -> AppResult<Vec<CaseSummary>> {
+        let match_query = match fts_match_query(query) {
+            Some(q) => q,
+            None => return Ok(Vec::new()),
+        };
+
+        let pool = self.get_pool().await?;
+        let rows = sqlx::query(
+            "SELECT c.id, c.title, c.case_name, c.created_at, c.updated_at, bm25(cases_fts) AS rank
+             FROM cases c
+             JOIN cases_fts ON c.rowid = cases_fts.rowid
+             WHERE cases_fts MATCH ? AND c.user_id = ? AND c.deleted_at IS NULL
+             ORDER BY rank
+             LIMIT ?"
+        )
+        .bind(&match_query)
+        .bind(user_id)
+        .bind(limit as i64)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| CaseSummary {
+                id: row.get("id"),
+                title: row.get("title"),
+                case_name: row.get("case_name"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+                rank: row.get("rank"),
+            })
+            .collect())
+    }
+
+    /// Add a column to an existing table (CREATE TABLE IF NOT EXISTS won't alter old databases)
+    async fn add_column_if_missing(
+        pool: &Pool<Sqlite>,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> AppResult<()> {
+        let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+            .bind(table)
+            .fetch_all(pool)
+            .await?;
+
+        if !columns.iter().any(|c| c == column) {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply pending migrations, each in its own transaction with its version row
+    async fn run_migrations(&self, pool: &Pool<Sqlite>, migrations: &[(&str, &str)]) -> AppResult<()> {
+        let current = Self::current_schema_version(pool).await?;
+
+        for (version, name, sql) in pending_migrations(current, migrations) {
+            let mut tx = pool.begin().await?;
+
+            sqlx::raw_sql(sql).execute(&mut *tx).await.map_err(|e| {
+                AppError::Database(format!("Migration {} ({}) failed: {}", version, name, e))
+            })?;
+
+            sqlx::query("INSERT INTO schema_version (version, name, applied_at) VALUES (?, ?, ?)")
+                .bind(version)
+                .bind(name)
+                .bind(chrono::Utc::now().to_rfc3339())
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn current_schema_version(pool: &Pool<Sqlite>) -> AppResult<i64> {
+        let version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM schema_version")
+            .fetch_one(pool)
+            .await?;
+        Ok(version.unwrap_or(0))
+    }
+
+    /// Latest applied schema migration (0 for a database with none)
+    pub async fn schema_version(&self) -> AppResult<i64> {
+        let pool = self.get_pool().await?;
+        Self::current_schema_version(&pool).await
+    }
+
+    /// Size of the database file plus its WAL file (0 for in-memory databases)
+    fn file_size(&self) -> u64 {
+        let wal_path = PathBuf::from(format!("{}-wal", self.db_path.display()));
+        [&self.db_path, &wal_path]
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|m| m.len())
+            .sum()
+    }
+
+    /// Report how much disk space the local database uses
+    pub async fn storage_report(&self) -> AppResult<StorageReport> {
+        let pool = self.get_pool().await?;
+
+        let row = sqlx::query(
+            "SELECT
+                (SELECT COUNT(*) FROM document_chunks),
+                (SELECT COUNT(*) FROM documents),
+                (SELECT COUNT(*) FROM cases),
+                (SELECT COUNT(*) FROM flashcards),
+                (SELECT COALESCE(SUM(length(embedding)), 0)
+                 FROM document_chunks WHERE embedding IS NOT NULL)"
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        let count = |i: usize| -> AppResult<u64> { Ok(row.try_get::<i64, _>(i)?.max(0) as u64) };
+
+        Ok(StorageReport {
+            db_size_bytes: self.file_size(),
+            total_chunks: count(0)?,
+            total_documents: count(1)?,
+            total_cases: count(2)?,
+            total_flashcards: count(3)?,
+            estimated_embedding_bytes: count(4)?,
+        })
+    }
+
+    /// Rebuild the database file to reclaim free pages, returning the bytes freed
+    pub async fn vacuum(&self) -> AppResult<u64> {
+        let pool = self.get_pool().await?;
+        let before = self.file_size();
+
+        sqlx::query("VACUUM").execute(&pool).await?;
+        // In WAL mode the rebuilt pages land in the WAL until it is checkpointed
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&pool).await?;
+
+        Ok(before.saturating_sub(self.file_size()))
+    }
+
     /// Get the connection pool
     pub async fn get_pool(&self) -> AppResult<Pool<Sqlite>> {
         let guard = self.pool.lock().await;
@@ -259,6 +1060,158 @@ impl SqliteCache {
     }
 }
 
+/// A cache over a fresh in-memory database with the schema and migrations applied
+#[cfg(test)]
+pub(crate) async fn test_cache() -> SqliteCache {
+    let cache = SqliteCache::with_options(PathBuf::from(IN_MEMORY_DB), SqliteCacheOptions::in_memory());
+    cache.initialize().await.unwrap();
+    cache
+}
+
+/// Pool over a fresh in-memory database with the schema and migrations applied
+#[cfg(test)]
+pub(crate) async fn test_pool() -> Pool<Sqlite> {
+    test_cache().await.get_pool().await.unwrap()
+}
+
+/// Page selection for list queries (pages are 1-based)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaginationOptions {
+    pub page: usize,
+    pub page_size: usize,
+}
+
+impl Default for PaginationOptions {
+    fn default() -> Self {
+        Self {
+            page: 1,
+            page_size: 50,
+        }
+    }
+}
+
+impl PaginationOptions {
+    /// Largest page a caller may request
+    pub const MAX_PAGE_SIZE: usize = 500;
+
+    /// Clamp page 0 and empty or oversized pages, rejecting pages whose
+    /// offset doesn't fit in SQLite's `OFFSET`
+    pub fn normalized(self) -> AppResult<Self> {
+        let options = Self {
+            page: self.page.max(1),
+            page_size: self.page_size.clamp(1, Self::MAX_PAGE_SIZE),
+        };
+
+        let last_row = (options.page - 1)
+            .checked_mul(options.page_size)
+            .and_then(|offset| offset.checked_add(options.page_size));
+        match last_row {
+            Some(row) if i64::try_from(row).is_ok() => Ok(options),
+            _ => Err(AppError::Validation(format!("Page {} is out of range", self.page))),
+        }
+    }
+
+    /// Number of rows to skip (SQL `OFFSET`)
+    pub fn offset(&self) -> usize {
+        self.page.saturating_sub(1).saturating_mul(self.page_size)
+    }
+
+    /// Inclusive row range for PostgREST `Range` requests
+    pub fn range(&self) -> (usize, usize) {
+        let offset = self.offset();
+        (offset, offset.saturating_add(self.page_size.saturating_sub(1)))
+    }
+}
+
+/// One page of a list query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PagedResult<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+    pub total_pages: usize,
+}
+
+impl<T> PagedResult<T> {
+    pub fn new(items: Vec<T>, total: usize, options: PaginationOptions) -> Self {
+        Self {
+            items,
+            total,
+            page: options.page,
+            page_size: options.page_size,
+            total_pages: total.div_ceil(options.page_size),
+        }
+    }
+}
+
+/// Total row count from a PostgREST `Content-Range` header ("0-49/1234")
+pub fn content_range_total(header: &str) -> Option<usize> {
+    header.rsplit('/').next()?.parse().ok()
+}
+
+/// Total row count of a PostgREST response requested with `exact_count()`
+pub fn response_total(response: &reqwest::Response) -> Option<usize> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(content_range_total)
+}
+
+/// Send a PostgREST request, treating a 4xx or 5xx status as an error
+///
+/// `execute` only fails on transport errors, so a rejected write (RLS, a bad
+/// column) otherwise looks like success. The error has the status and body.
+pub async fn execute_checked(builder: postgrest::Builder) -> Result<reqwest::Response, String> {
+    let response = builder.execute().await.map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    Err(format!("{}: {}", status, body))
+}
+
+/// Case search result without the IRAC text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseSummary {
+    pub id: String,
+    pub title: String,
+    pub case_name: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    /// BM25 rank (lower is a better match)
+    pub rank: f64,
+}
+
+/// Turn free text into an FTS5 MATCH expression of quoted terms, so user
+/// input can't inject FTS query syntax
+fn fts_match_query(query: &str) -> Option<String> {
+    fts_terms(query).map(|terms| terms.join(" "))
+}
+
+/// Like `fts_match_query`, but matching any of the terms; BM25 still ranks
+/// rows containing more (and rarer) terms first
+pub(crate) fn fts_match_any_query(query: &str) -> Option<String> {
+    fts_terms(query).map(|terms| terms.join(" OR "))
+}
+
+fn fts_terms(query: &str) -> Option<Vec<String>> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"", term))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms)
+    }
+}
+
 /// Hybrid storage manager - decides whether to use local or cloud storage
 #[derive(Clone)]
 pub struct HybridStorage {
@@ -281,6 +1234,12 @@ impl HybridStorage {
         }
     }
 
+    /// Use `sqlite` as the local cache, e.g. one over an in-memory database
+    pub fn with_sqlite(mut self, sqlite: SqliteCache) -> Self {
+        self.sqlite = sqlite;
+        self
+    }
+
     /// Initialize both storage layers
     pub async fn initialize(&self) -> AppResult<()> {
         // Always initialize SQLite
@@ -296,16 +1255,11 @@ impl HybridStorage {
         Ok(())
     }
 
-    /// Check if we're online
+    /// Check if we're online by sending Supabase a request
     pub async fn check_online(&self) -> bool {
-        if let Some(supabase) = &self.supabase {
-            // Try a simple query to check connectivity
-            match supabase.select("profiles").await {
-                Ok(_) => true,
-                Err(_) => false,
-            }
-        } else {
-            false
+        match &self.supabase {
+            Some(supabase) => supabase.ping().await,
+            None => false,
         }
     }
 
@@ -338,3 +1292,186 @@ pub struct SyncOperation {
     pub record_id: String,
     pub data: String,
 }
+
+// Tauri Commands
+
+/// Current local schema version, for diagnosing upgrade issues
+#[tauri::command]
+pub async fn get_schema_version(storage: State<'_, HybridStorage>) -> CommandResult<i64> {
+    storage.sqlite().schema_version().await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn get_storage_report(storage: State<'_, HybridStorage>) -> CommandResult<StorageReport> {
+    storage.sqlite().storage_report().await.map_err(CommandError::from)
+}
+
+/// Compact the local database, returning the bytes freed
+#[tauri::command]
+pub async fn vacuum_database(storage: State<'_, HybridStorage>) -> CommandResult<u64> {
+    storage.sqlite().vacuum().await.map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::fake_http_server;
+
+    #[tokio::test]
+    async fn test_upsert_asks_postgrest_to_merge_on_the_conflict_column() {
+        let (url, server) = fake_http_server(vec![(201, "[]".to_string())]).await;
+
+        let client = SupabaseClient::new(url, "key".to_string());
+        let body = r#"{"id":"1","title":"Offer"}"#;
+        let response = client.upsert("cases", body, "id").await.unwrap().execute().await.unwrap();
+        assert_eq!(response.status().as_u16(), 201);
+
+        let request = server.await.unwrap().remove(0).to_lowercase();
+        let request_line = request.lines().next().unwrap();
+        assert!(request_line.starts_with("post /cases?"), "{}", request_line);
+        assert!(request_line.contains("on_conflict=id"), "{}", request_line);
+        let prefer = request.lines().find(|line| line.starts_with("prefer:")).unwrap();
+        assert!(prefer.contains("resolution=merge-duplicates"), "{}", prefer);
+    }
+
+    #[tokio::test]
+    async fn test_execute_checked_rejects_error_statuses() {
+        // PostgREST refuses the write, e.g. through row-level security
+        let (url, server) = fake_http_server(vec![(403, "row-level security!".to_string())]).await;
+
+        let client = SupabaseClient::new(url, "key".to_string());
+        let error = execute_checked(client.upsert("cases", "{}", "id").await.unwrap()).await.unwrap_err();
+        assert!(error.contains("403"), "{}", error);
+        assert!(error.contains("row-level security!"), "{}", error);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ping_needs_a_response_from_supabase() {
+        // Any status means Supabase is reachable, even without a valid key
+        let (url, server) = fake_http_server(vec![(401, String::new())]).await;
+        let url = format!("{}/rest/v1", url);
+        assert!(SupabaseClient::new(url.clone(), "key".to_string()).ping().await);
+        server.await.unwrap();
+
+        // Nothing is listening any more
+        assert!(!SupabaseClient::new(url, "key".to_string()).ping().await);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_cache_applies_options() {
+        let options = SqliteCacheOptions {
+            busy_timeout_ms: 1_234,
+            cache_size_kb: 2_000,
+            ..SqliteCacheOptions::in_memory()
+        };
+        let cache = SqliteCache::with_options(PathBuf::from(IN_MEMORY_DB), options);
+        cache.initialize().await.unwrap();
+        let pool = cache.get_pool().await.unwrap();
+
+        let busy_timeout: i64 = sqlx::query_scalar("PRAGMA busy_timeout").fetch_one(&pool).await.unwrap();
+        let cache_size: i64 = sqlx::query_scalar("PRAGMA cache_size").fetch_one(&pool).await.unwrap();
+        assert_eq!(busy_timeout, 1_234);
+        assert_eq!(cache_size, -2_000);
+
+        // Schema was created on the in-memory database
+        let tables: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE name = 'cases'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(tables, 1);
+    }
+
+    #[test]
+    fn test_pending_migrations() {
+        let migrations = [("first", "SELECT 1"), ("second", "SELECT 2"), ("third", "SELECT 3")];
+
+        let pending = pending_migrations(1, &migrations);
+        assert_eq!(pending, vec![(2, "second", "SELECT 2"), (3, "third", "SELECT 3")]);
+        assert!(pending_migrations(3, &migrations).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_migrations_apply_once() {
+        let cache = test_cache().await;
+        assert_eq!(cache.schema_version().await.unwrap(), migrations().len() as i64);
+
+        // Re-running finds nothing pending
+        let pool = cache.get_pool().await.unwrap();
+        cache.run_migrations(&pool, &migrations()).await.unwrap();
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM schema_version")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, migrations().len() as i64);
+
+        // Columns added by migrations exist on a fresh database
+        let added: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pragma_table_info('documents') WHERE name IN ('source_url', 'text_hash')",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(added, 2);
+    }
+
+    #[tokio::test]
+    async fn test_storage_report_counts_and_embedding_size() {
+        let cache = test_cache().await;
+        let pool = cache.get_pool().await.unwrap();
+
+        sqlx::query("INSERT INTO documents (id, title, document_type, created_at, updated_at) VALUES ('d1', 'Doc', 'user_case', 'now', 'now')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        for (id, bytes) in [("c1", 384 * 2), ("c2", 384 * 4)] {
+            sqlx::query("INSERT INTO document_chunks (id, document_id, chunk_index, chunk_text, embedding, embedding_dim, created_at) VALUES (?, 'd1', 0, 'text', ?, 384, 'now')")
+                .bind(id)
+                .bind(vec![0u8; bytes])
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let report = cache.storage_report().await.unwrap();
+        assert_eq!(report.total_documents, 1);
+        assert_eq!(report.total_chunks, 2);
+        assert_eq!(report.total_cases, 0);
+        assert_eq!(report.estimated_embedding_bytes, 384 * 2 + 384 * 4);
+        assert_eq!(report.db_size_bytes, 0);
+    }
+
+    #[test]
+    fn test_pagination_defaults_and_normalization() {
+        let options = PaginationOptions::default();
+        assert_eq!((options.page, options.page_size), (1, 50));
+
+        let options = PaginationOptions { page: 0, page_size: 10_000 }.normalized().unwrap();
+        assert_eq!((options.page, options.page_size), (1, PaginationOptions::MAX_PAGE_SIZE));
+
+        let huge = PaginationOptions { page: usize::MAX, page_size: 50 };
+        assert!(matches!(huge.normalized(), Err(AppError::Validation(_))));
+        assert_eq!(huge.offset(), usize::MAX);
+    }
+
+    #[test]
+    fn test_pagination_offset_and_range() {
+        let options = PaginationOptions { page: 3, page_size: 20 };
+        assert_eq!(options.offset(), 40);
+        assert_eq!(options.range(), (40, 59));
+    }
+
+    #[test]
+    fn test_paged_result_total_pages() {
+        let options = PaginationOptions { page: 1, page_size: 50 };
+        assert_eq!(PagedResult::new(vec![0; 50], 101, options).total_pages, 3);
+        assert_eq!(PagedResult::<i32>::new(Vec::new(), 0, options).total_pages, 0);
+    }
+
+    #[test]
+    fn test_content_range_total() {
+        assert_eq!(content_range_total("0-49/1234"), Some(1234));
+        assert_eq!(content_range_total("*/0"), Some(0));
+        assert_eq!(content_range_total("0-49/*"), None);
+    }
+}
@@ -4,11 +4,36 @@
  */
 
 use crate::error::{AppError, AppResult};
+use crate::logging::LogLevel;
 use crate::validation::{sanitize_text, validate_not_empty};
+use docx_rs::{DocumentChild, ParagraphChild, RunChild};
 use lopdf::Document;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
+/// Target words per chunk
+pub const WORDS_PER_CHUNK: usize = 500;
+
+/// Words repeated from the end of one chunk at the start of the next
+pub const DEFAULT_OVERLAP_WORDS: usize = 100;
+
+/// Bounds applied to `ChunkingOptions::target_words`
+const MIN_TARGET_WORDS: usize = 20;
+const MAX_TARGET_WORDS: usize = 5000;
+
+/// Default heading pattern for numbered sections ("1. Formation of Contract", "2.1 Offer")
+pub const DEFAULT_SECTION_PATTERN: &str = r"^\d+[\.\d]*\s+[A-Z]";
+
+/// Heading pattern for markdown-style headings ("## Heading text"), as emitted for DOCX headings
+pub const MARKDOWN_SECTION_PATTERN: &str = r"^#{1,6}\s+\S";
+
+/// Longest line `heading_text` treats as a heading, in words
+const MAX_HEADING_WORDS: usize = 12;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocumentMetadata {
     pub user_id: Option<String>,
@@ -16,6 +41,66 @@ pub struct DocumentMetadata {
     pub title: String,
     pub document_type: String, // "user_case" | "knowledge_base"
     pub document_id: Option<String>,
+    /// Web page the document was fetched from
+    #[serde(default)]
+    pub source_url: Option<String>,
+}
+
+/// Where `semantic_chunk` may end a chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkStrategy {
+    /// Whole paragraphs where they fit, falling back to sentences for long paragraphs
+    #[default]
+    Paragraph,
+    /// Exactly `target_words` words, ignoring sentence and paragraph breaks
+    Fixed,
+    /// Whole sentences
+    Sentence,
+}
+
+/// How documents are split into chunks for embedding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkingOptions {
+    /// Most words in a chunk
+    pub target_words: usize,
+    /// Words from the end of a chunk repeated at the start of the next
+    pub overlap_words: usize,
+    pub strategy: ChunkStrategy,
+}
+
+impl Default for ChunkingOptions {
+    fn default() -> Self {
+        Self {
+            target_words: WORDS_PER_CHUNK,
+            overlap_words: DEFAULT_OVERLAP_WORDS,
+            strategy: ChunkStrategy::default(),
+        }
+    }
+}
+
+impl ChunkingOptions {
+    /// Clamp the target to a usable size and the overlap to at most half of it
+    pub fn normalized(self) -> Self {
+        let target_words = self.target_words.clamp(MIN_TARGET_WORDS, MAX_TARGET_WORDS);
+        Self {
+            target_words,
+            overlap_words: self.overlap_words.min(target_words / 2),
+            strategy: self.strategy,
+        }
+    }
+}
+
+/// A sentence, or a window of a sentence longer than the target, that
+/// `pack_units` keeps in one chunk
+struct ChunkUnit {
+    text: String,
+    words: usize,
+    /// Words in the paragraph this unit starts, or None if it doesn't start one
+    paragraph_words: Option<usize>,
+    /// Most recent heading at or before this unit
+    section: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +125,57 @@ pub struct DocumentChunk {
 pub struct ProcessedDocument {
     pub chunks: Vec<DocumentChunk>,
     pub total_chunks: usize,
+    /// `DocumentProcessor::text_hash` of the extracted text
+    pub text_hash: String,
+}
+
+/// Stage of ingestion an `IngestionProgress` update is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    /// Reading text from the file, counted in pages
+    Extracting,
+    /// Splitting the text, reported once with the number of chunks made
+    Chunking,
+    /// Counted in chunks embedded
+    Embedding,
+    /// Counted in chunks written; `current == total` once they are committed
+    Storing,
+}
+
+/// `current` of `total` units of an ingestion stage done
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct IngestionProgress {
+    pub stage: Stage,
+    pub current: usize,
+    pub total: usize,
+}
+
+/// Sending half of a channel of `IngestionProgress` updates
+pub type ProgressSender = tokio::sync::mpsc::Sender<IngestionProgress>;
+
+/// Send a progress update without waiting; updates are dropped while the
+/// receiver is behind, since a later one supersedes them
+pub fn report_progress(progress: Option<&ProgressSender>, stage: Stage, current: usize, total: usize) {
+    if let Some(sender) = progress {
+        let _ = sender.try_send(IngestionProgress { stage, current, total });
+    }
+}
+
+/// `AppError::OperationFailed` message for an ingestion stopped by `cancel_ingestion`
+pub const CANCELLED: &str = "cancelled";
+
+/// Fail with `CANCELLED` once `cancel` has been cancelled
+pub fn ensure_not_cancelled(cancel: Option<&CancellationToken>) -> AppResult<()> {
+    match cancel {
+        Some(token) if token.is_cancelled() => Err(AppError::OperationFailed(CANCELLED.to_string())),
+        _ => Ok(()),
+    }
+}
+
+/// Whether `error` is the one returned for a cancelled ingestion
+pub fn is_cancelled(error: &AppError) -> bool {
+    matches!(error, AppError::OperationFailed(msg) if msg == CANCELLED)
 }
 
 pub struct DocumentProcessor;
@@ -47,128 +183,462 @@ pub struct DocumentProcessor;
 impl DocumentProcessor {
     /// Extract text from PDF file
     pub fn extract_text_from_pdf(pdf_data: &[u8]) -> AppResult<String> {
+        let pages = Self::extract_pages_from_pdf(pdf_data)?;
+
+        let mut text = String::new();
+        for (_, page_text) in &pages {
+            text.push_str(page_text);
+            text.push('\n');
+        }
+
+        Ok(text)
+    }
+
+    /// Extract text from PDF file, keeping it split by page number
+    pub fn extract_pages_from_pdf(pdf_data: &[u8]) -> AppResult<Vec<(u32, String)>> {
+        Self::extract_pages_with_progress(pdf_data, None, None)
+    }
+
+    /// `extract_pages_from_pdf`, reporting each page read as `Stage::Extracting`
+    fn extract_pages_with_progress(
+        pdf_data: &[u8],
+        progress: Option<&ProgressSender>,
+        cancel: Option<&CancellationToken>,
+    ) -> AppResult<Vec<(u32, String)>> {
         let document = Document::load_mem(pdf_data)
             .map_err(|e| AppError::PdfExtraction(format!("Failed to load PDF: {}", e)))?;
 
-        let mut text = String::new();
+        let mut pages: Vec<(u32, String)> = Vec::new();
+        let page_ids = document.get_pages();
+        let total_pages = page_ids.len();
 
         // Iterate through all pages
-        let pages = document.get_pages();
-        for (page_num, _) in pages.iter() {
+        for (i, (page_num, _)) in page_ids.iter().enumerate() {
+            ensure_not_cancelled(cancel)?;
+            report_progress(progress, Stage::Extracting, i, total_pages);
             match document.extract_text(&[*page_num]) {
                 Ok(page_text) => {
-                    text.push_str(&page_text);
-                    text.push('\n');
+                    pages.push((*page_num, page_text));
                 }
                 Err(e) => {
-                    eprintln!("Warning: Failed to extract text from page {}: {}", page_num, e);
+                    crate::logging::log(
+                        LogLevel::Warn,
+                        "document",
+                        &format!("Failed to extract text from page {}: {}", page_num, e),
+                    );
                     // Continue with other pages even if one fails
                     continue;
                 }
             }
         }
+        report_progress(progress, Stage::Extracting, total_pages, total_pages);
 
-        if text.trim().is_empty() {
+        if pages.iter().all(|(_, text)| text.trim().is_empty()) {
             return Err(AppError::PdfExtraction(
                 "PDF contains no extractable text".to_string()
             ));
         }
 
+        Ok(pages)
+    }
+
+    /// Extract text from a Word document, one paragraph per line
+    ///
+    /// Paragraphs styled as headings are emitted as markdown headings
+    /// ("## Heading text") so `chunk_by_section` can detect them.
+    pub fn extract_text_from_docx(docx_data: &[u8]) -> AppResult<String> {
+        let docx = docx_rs::read_docx(docx_data)
+            .map_err(|e| AppError::DocumentProcessing(format!("Failed to read Word document: {:?}", e)))?;
+
+        let mut text = String::new();
+
+        for child in &docx.document.children {
+            let paragraph = match child {
+                DocumentChild::Paragraph(paragraph) => paragraph,
+                _ => continue,
+            };
+
+            let mut paragraph_text = String::new();
+            for paragraph_child in &paragraph.children {
+                let runs = match paragraph_child {
+                    ParagraphChild::Run(run) => vec![run.as_ref()],
+                    ParagraphChild::Hyperlink(link) => link
+                        .children
+                        .iter()
+                        .filter_map(|c| match c {
+                            ParagraphChild::Run(run) => Some(run.as_ref()),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => continue,
+                };
+
+                for run in runs {
+                    for run_child in &run.children {
+                        match run_child {
+                            RunChild::Text(t) => paragraph_text.push_str(&t.text),
+                            RunChild::Tab(_) => paragraph_text.push('\t'),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+
+            let paragraph_text = paragraph_text.trim();
+            if paragraph_text.is_empty() {
+                continue;
+            }
+
+            // Style IDs look like "Heading1", "Heading2", ...
+            let heading_level = paragraph
+                .property
+                .style
+                .as_ref()
+                .and_then(|style| {
+                    let val = style.val.to_lowercase();
+                    val.strip_prefix("heading")
+                        .and_then(|level| level.trim().parse::<usize>().ok())
+                });
+
+            match heading_level {
+                Some(level) => {
+                    text.push_str(&"#".repeat(level.clamp(1, 6)));
+                    text.push(' ');
+                    text.push_str(paragraph_text);
+                    text.push('\n');
+                }
+                None => {
+                    text.push_str(paragraph_text);
+                    text.push('\n');
+                }
+            }
+        }
+
+        if text.trim().is_empty() {
+            return Err(AppError::DocumentProcessing(
+                "Word document contains no extractable text".to_string()
+            ));
+        }
+
         Ok(text)
     }
 
     /// Clean and normalize text
     fn clean_text(text: &str) -> String {
-        // Remove excessive whitespace
+        // Remove excessive whitespace, keeping line breaks so paragraphs survive
         let cleaned = text
-            .split_whitespace()
-            .collect::<Vec<&str>>()
-            .join(" ");
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<&str>>().join(" "))
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<String>>()
+            .join("\n");
 
         // Sanitize text
         sanitize_text(&cleaned)
     }
 
-    /// Semantic chunking: Split text by paragraphs with overlap
-    pub fn semantic_chunk(text: &str, overlap_words: usize) -> AppResult<Vec<String>> {
+    /// Semantic chunking: split text into chunks of at most `target_words`
+    /// words, ending chunks where `strategy` allows, with overlap
+    pub fn semantic_chunk(text: &str, options: &ChunkingOptions) -> AppResult<Vec<String>> {
+        Ok(Self::semantic_chunk_sections(text, options)?
+            .into_iter()
+            .map(|(chunk, _)| chunk)
+            .collect())
+    }
+
+    /// `semantic_chunk`, pairing each chunk with the heading (see
+    /// `heading_text`) in effect where the chunk's new text begins
+    pub fn semantic_chunk_sections(text: &str, options: &ChunkingOptions) -> AppResult<Vec<(String, Option<String>)>> {
         validate_not_empty(text, "Text for chunking")?;
 
+        let options = options.clone().normalized();
         let cleaned = Self::clean_text(text);
 
-        // Split by paragraphs (double newlines or single newlines followed by capitals)
-        let paragraphs: Vec<&str> = cleaned
-            .split('\n')
-            .map(|p| p.trim())
-            .filter(|p| !p.is_empty())
-            .collect();
+        let chunks = match options.strategy {
+            ChunkStrategy::Fixed => {
+                let mut words: Vec<&str> = Vec::new();
+                let mut sections: Vec<Option<String>> = Vec::new();
+                let mut section = None;
+                for line in cleaned.lines() {
+                    if let Some(heading) = heading_text(line) {
+                        section = Some(heading);
+                    }
+                    for word in line.split_whitespace() {
+                        words.push(word);
+                        sections.push(section.clone());
+                    }
+                }
 
-        let mut chunks: Vec<String> = Vec::new();
-        let words_per_chunk = 500; // Target words per chunk
-
-        let mut current_chunk: Vec<String> = Vec::new();
-        let mut current_word_count = 0;
-
-        for paragraph in paragraphs {
-            let words: Vec<&str> = paragraph.split_whitespace().collect();
-            let word_count = words.len();
-
-            // If adding this paragraph exceeds the limit, save current chunk
-            if current_word_count + word_count > words_per_chunk && !current_chunk.is_empty() {
-                chunks.push(current_chunk.join("\n\n"));
-
-                // Start new chunk with overlap
-                let overlap_paragraphs = if current_chunk.len() > 1 {
-                    // Calculate how many paragraphs to keep for overlap
-                    let mut overlap_count = 0;
-                    let mut overlap_words = 0;
-                    
-                    for para in current_chunk.iter().rev() {
-                        let para_words = para.split_whitespace().count();
-                        if overlap_words + para_words <= overlap_words {
-                            overlap_count += 1;
-                            overlap_words += para_words;
-                        } else {
-                            break;
-                        }
+                let step = options.target_words - options.overlap_words;
+                let mut chunks = Vec::new();
+                let mut start = 0;
+                while start < words.len() {
+                    let end = (start + options.target_words).min(words.len());
+                    let first_new = if start == 0 { 0 } else { (start + options.overlap_words).min(end - 1) };
+                    chunks.push((words[start..end].join(" "), sections[first_new].clone()));
+                    if end == words.len() {
+                        break;
                     }
-                    
-                    let start_idx = current_chunk.len().saturating_sub(overlap_count.max(1));
-                    current_chunk[start_idx..].to_vec()
-                } else {
-                    Vec::new()
-                };
+                    start += step;
+                }
+                chunks
+            }
+            ChunkStrategy::Paragraph | ChunkStrategy::Sentence => {
+                Self::pack_units(Self::chunk_units(&cleaned, &options), &options)
+            }
+        };
+
+        // If text is too short for chunking, return as single chunk
+        if chunks.is_empty() && !cleaned.is_empty() {
+            return Ok(vec![(cleaned, None)]);
+        }
+
+        Ok(chunks)
+    }
+
+    /// Split cleaned text (one paragraph per line) into sentences, breaking
+    /// sentences longer than `target_words` into word windows
+    fn chunk_units(cleaned: &str, options: &ChunkingOptions) -> Vec<ChunkUnit> {
+        let mut units = Vec::new();
+        let mut section = None;
+
+        for paragraph in cleaned.split('\n').map(str::trim).filter(|p| !p.is_empty()) {
+            if let Some(heading) = heading_text(paragraph) {
+                section = Some(heading);
+            }
+            let paragraph_words = paragraph.split_whitespace().count();
+            let pieces = split_sentences(paragraph).into_iter().flat_map(|sentence| {
+                let words: Vec<&str> = sentence.split_whitespace().collect();
+                words
+                    .chunks(options.target_words)
+                    .map(|window| window.join(" "))
+                    .collect::<Vec<String>>()
+            });
+
+            for (i, text) in pieces.enumerate() {
+                units.push(ChunkUnit {
+                    words: text.split_whitespace().count(),
+                    text,
+                    paragraph_words: (i == 0).then_some(paragraph_words),
+                    section: section.clone(),
+                });
+            }
+        }
+
+        units
+    }
+
+    /// Greedily fill chunks up to `target_words`, starting each chunk after
+    /// the first with the trailing units of the previous one that fit in
+    /// `overlap_words`
+    ///
+    /// With the paragraph strategy a chunk already within 20% of the target
+    /// ends before a paragraph that wouldn't fit, rather than mid-paragraph.
+    /// Each chunk is paired with the section of its first unit not carried over.
+    fn pack_units(units: Vec<ChunkUnit>, options: &ChunkingOptions) -> Vec<(String, Option<String>)> {
+        let mut chunks: Vec<(String, Option<String>)> = Vec::new();
+        let mut current: Vec<ChunkUnit> = Vec::new();
+        let mut current_words = 0;
+        // Leading units of `current` repeated from the previous chunk
+        let mut carried = 0;
+
+        for unit in units {
+            let full = current_words + unit.words > options.target_words;
+            let paragraph_break = options.strategy == ChunkStrategy::Paragraph
+                && current_words * 5 >= options.target_words * 4
+                && unit
+                    .paragraph_words
+                    .is_some_and(|words| current_words + words > options.target_words);
+
+            if (full || paragraph_break) && current.len() > carried {
+                chunks.push((join_units(&current), current[carried].section.clone()));
+
+                let mut keep = 0;
+                let mut kept_words = 0;
+                for previous in current.iter().rev() {
+                    if kept_words + previous.words > options.overlap_words {
+                        break;
+                    }
+                    keep += 1;
+                    kept_words += previous.words;
+                }
+                current.drain(..current.len() - keep);
+                current_words = kept_words;
+                carried = keep;
+            }
 
-                current_chunk = overlap_paragraphs;
-                current_word_count = current_chunk
-                    .iter()
-                    .map(|p| p.split_whitespace().count())
-                    .sum();
+            // Drop overlap that would push this unit past the target
+            while current_words + unit.words > options.target_words && carried > 0 {
+                current_words -= current.remove(0).words;
+                carried -= 1;
             }
 
-            current_chunk.push(paragraph.to_string());
-            current_word_count += word_count;
+            current_words += unit.words;
+            current.push(unit);
         }
 
-        // Add last chunk
-        if !current_chunk.is_empty() {
-            chunks.push(current_chunk.join("\n\n"));
+        if current.len() > carried {
+            chunks.push((join_units(&current), current[carried].section.clone()));
         }
 
-        // If text is too short for chunking, return as single chunk
-        if chunks.is_empty() && !cleaned.is_empty() {
-            return Ok(vec![cleaned]);
+        chunks
+    }
+
+    /// Section-aware chunking: split pages along headings matched by `pattern`
+    ///
+    /// Each section is kept as one chunk unless it exceeds `target_words`
+    /// words, in which case it is split into consecutive chunks that all carry
+    /// the section heading. Returns an empty list when no heading matches so
+    /// callers can fall back to paragraph chunking.
+    pub fn chunk_by_section(
+        pages: Vec<(u32, String)>,
+        pattern: &str,
+        metadata: &DocumentMetadata,
+        options: &ChunkingOptions,
+    ) -> AppResult<Vec<DocumentChunk>> {
+        let heading_regex = Regex::new(pattern)
+            .map_err(|e| AppError::TextChunking(format!("Invalid section pattern: {}", e)))?;
+
+        // (heading, words) per section; text before the first heading has no heading
+        let mut sections: Vec<(Option<String>, Vec<String>)> = vec![(None, Vec::new())];
+
+        for (_page, page_text) in &pages {
+            for line in page_text.lines() {
+                let line = sanitize_text(line.trim());
+                if line.is_empty() {
+                    continue;
+                }
+
+                if heading_regex.is_match(&line) {
+                    let heading = line.trim_start_matches('#').trim().to_string();
+                    sections.push((Some(heading), Vec::new()));
+                }
+
+                if let Some((_, words)) = sections.last_mut() {
+                    words.extend(line.split_whitespace().map(|w| w.to_string()));
+                }
+            }
+        }
+
+        if sections.len() == 1 {
+            return Ok(Vec::new());
+        }
+
+        let document_id = metadata
+            .document_id
+            .clone()
+            .or_else(|| metadata.case_id.clone())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        let mut chunks: Vec<DocumentChunk> = Vec::new();
+        for (heading, words) in sections {
+            for window in words.chunks(options.target_words.clamp(MIN_TARGET_WORDS, MAX_TARGET_WORDS)) {
+                chunks.push(DocumentChunk {
+                    id: Uuid::new_v4().to_string(),
+                    text: window.join(" "),
+                    metadata: ChunkMetadata {
+                        document_id: document_id.clone(),
+                        chunk_index: chunks.len() as i32,
+                        user_id: metadata.user_id.clone(),
+                        case_id: metadata.case_id.clone(),
+                        document_type: metadata.document_type.clone(),
+                        source_title: metadata.title.clone(),
+                        section: heading.clone(),
+                    },
+                });
+            }
         }
 
         Ok(chunks)
     }
 
-    /// Process PDF and generate chunks
+    /// Hex-encoded SHA-256 of raw file bytes
+    pub fn content_hash(data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    /// SHA-256 of a chunk's text, used as a stable chunk identity
+    pub fn chunk_hash(text: &str) -> String {
+        Self::content_hash(text.as_bytes())
+    }
+
+    /// SHA-256 of extracted text with case and whitespace normalized, so a
+    /// re-saved or re-exported copy of the same material hashes the same
+    pub fn text_hash(text: &str) -> String {
+        let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        Self::content_hash(normalized.as_bytes())
+    }
+
+    /// Diff stored chunks, given as `(id, chunk_hash)` pairs, against a new chunk list
+    ///
+    /// Returns `(added, removed_ids)`: chunks in `new` with no matching text in
+    /// `old`, and ids of chunks in `old` with no matching text in `new`.
+    /// Repeated identical chunks are matched one-to-one.
+    pub fn diff_chunks(
+        old: &[(String, String)],
+        new: &[DocumentChunk],
+    ) -> (Vec<DocumentChunk>, Vec<String>) {
+        let mut new_counts: HashMap<String, usize> = HashMap::new();
+        for chunk in new {
+            *new_counts.entry(Self::chunk_hash(&chunk.text)).or_insert(0) += 1;
+        }
+
+        let mut old_counts: HashMap<String, usize> = HashMap::new();
+        let mut removed_ids = Vec::new();
+        for (id, hash) in old {
+            match new_counts.get_mut(hash) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    *old_counts.entry(hash.clone()).or_insert(0) += 1;
+                }
+                _ => removed_ids.push(id.clone()),
+            }
+        }
+
+        let mut added = Vec::new();
+        for chunk in new {
+            let hash = Self::chunk_hash(&chunk.text);
+            match old_counts.get_mut(&hash) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => added.push(chunk.clone()),
+            }
+        }
+
+        (added, removed_ids)
+    }
+
+    /// Process PDF and generate chunks, reporting extraction and chunking
+    /// progress on `progress_channel`
     pub fn process_pdf(
         pdf_data: &[u8],
         metadata: DocumentMetadata,
+        options: &ChunkingOptions,
+        progress_channel: Option<ProgressSender>,
+        cancel: Option<&CancellationToken>,
     ) -> AppResult<ProcessedDocument> {
-        // Extract text from PDF
-        let text = Self::extract_text_from_pdf(pdf_data)?;
+        let progress = progress_channel.as_ref();
+        ensure_not_cancelled(cancel)?;
+
+        // Extract text from PDF, stopping between pages if cancelled
+        let pages = Self::extract_pages_with_progress(pdf_data, progress, cancel)?;
+        ensure_not_cancelled(cancel)?;
+        let text = pages
+            .iter()
+            .map(|(_, page_text)| page_text.as_str())
+            .collect::<Vec<&str>>()
+            .join("\n");
+        let text_hash = Self::text_hash(&text);
+
+        // Prefer section-aware chunks when the PDF has numbered headings
+        let chunks = Self::chunk_by_section(pages, DEFAULT_SECTION_PATTERN, &metadata, options)?;
+        if !chunks.is_empty() {
+            report_progress(progress, Stage::Chunking, chunks.len(), chunks.len());
+            return Ok(ProcessedDocument {
+                total_chunks: chunks.len(),
+                chunks,
+                text_hash,
+            });
+        }
 
         if text.trim().is_empty() {
             return Err(AppError::DocumentProcessing(
@@ -176,8 +646,8 @@ impl DocumentProcessor {
             ));
         }
 
-        // Generate semantic chunks
-        let chunk_texts = Self::semantic_chunk(&text, 200)?;
+        // Generate semantic chunks, labelled with any headings the numbered pattern missed
+        let chunk_texts = Self::semantic_chunk_sections(&text, options)?;
 
         // Create chunk objects
         let document_id = metadata
@@ -189,7 +659,7 @@ impl DocumentProcessor {
         let chunks: Vec<DocumentChunk> = chunk_texts
             .into_iter()
             .enumerate()
-            .map(|(index, chunk_text)| {
+            .map(|(index, (chunk_text, section))| {
                 let chunk_id = Uuid::new_v4().to_string();
 
                 DocumentChunk {
@@ -202,33 +672,68 @@ impl DocumentProcessor {
                         case_id: metadata.case_id.clone(),
                         document_type: metadata.document_type.clone(),
                         source_title: metadata.title.clone(),
-                        section: None,
+                        section,
                     },
                 }
             })
             .collect();
+        report_progress(progress, Stage::Chunking, chunks.len(), chunks.len());
 
         Ok(ProcessedDocument {
             total_chunks: chunks.len(),
             chunks,
+            text_hash,
         })
     }
 
+    /// Process a Word document and generate chunks
+    pub fn process_docx(
+        docx_data: &[u8],
+        metadata: DocumentMetadata,
+        options: &ChunkingOptions,
+    ) -> AppResult<ProcessedDocument> {
+        // Extract text from DOCX (headings become "## Heading" lines)
+        let text = Self::extract_text_from_docx(docx_data)?;
+
+        // Prefer section-aware chunks when the document uses heading styles
+        let chunks = Self::chunk_by_section(vec![(1, text.clone())], MARKDOWN_SECTION_PATTERN, &metadata, options)?;
+        if !chunks.is_empty() {
+            return Ok(ProcessedDocument {
+                total_chunks: chunks.len(),
+                chunks,
+                text_hash: Self::text_hash(&text),
+            });
+        }
+
+        Self::process_text(&text, metadata, options)
+    }
+
     /// Process plain text and generate chunks
     pub fn process_text(
         text: &str,
         metadata: DocumentMetadata,
+        options: &ChunkingOptions,
     ) -> AppResult<ProcessedDocument> {
         validate_not_empty(text, "Text for processing")?;
 
+        // Prefer section-aware chunks when the text has numbered headings
+        let chunks = Self::chunk_by_section(vec![(1, text.to_string())], DEFAULT_SECTION_PATTERN, &metadata, options)?;
+        if !chunks.is_empty() {
+            return Ok(ProcessedDocument {
+                total_chunks: chunks.len(),
+                chunks,
+                text_hash: Self::text_hash(text),
+            });
+        }
+
         let cleaned = Self::clean_text(text);
 
         if cleaned.trim().is_empty() {
             return Err(AppError::DocumentProcessing("Text is empty".to_string()));
         }
 
-        // Generate semantic chunks
-        let chunk_texts = Self::semantic_chunk(&cleaned, 200)?;
+        // Generate semantic chunks, labelled with any headings the numbered pattern missed
+        let chunk_texts = Self::semantic_chunk_sections(&cleaned, options)?;
 
         // Create chunk objects
         let document_id = metadata
@@ -240,7 +745,7 @@ impl DocumentProcessor {
         let chunks: Vec<DocumentChunk> = chunk_texts
             .into_iter()
             .enumerate()
-            .map(|(index, chunk_text)| {
+            .map(|(index, (chunk_text, section))| {
                 let chunk_id = Uuid::new_v4().to_string();
 
                 DocumentChunk {
@@ -253,7 +758,7 @@ impl DocumentProcessor {
                         case_id: metadata.case_id.clone(),
                         document_type: metadata.document_type.clone(),
                         source_title: metadata.title.clone(),
-                        section: None,
+                        section,
                     },
                 }
             })
@@ -262,10 +767,113 @@ impl DocumentProcessor {
         Ok(ProcessedDocument {
             total_chunks: chunks.len(),
             chunks,
+            text_hash: Self::text_hash(text),
         })
     }
 }
 
+/// The heading a line of text introduces, if it is one
+///
+/// Recognises markdown headings ("## Damages"), numbered headings ("II.
+/// Damages", "3) Remedies", "Part III Damages") and short all-caps lines
+/// ("STATEMENT OF FACTS"). Lines over `MAX_HEADING_WORDS` words or ending in
+/// punctuation are taken to be body text, e.g. numbered list items.
+pub fn heading_text(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.starts_with('#') {
+        let heading = line.trim_start_matches('#').trim();
+        return (!heading.is_empty()).then(|| heading.to_string());
+    }
+
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if words.is_empty() || words.len() > MAX_HEADING_WORDS || line.ends_with(['.', ',', ';', ':']) {
+        return None;
+    }
+
+    let is_numeral = |word: &str| {
+        !word.is_empty()
+            && (word.chars().all(|c| c.is_ascii_digit() || c == '.')
+                || word.chars().all(|c| matches!(c, 'I' | 'V' | 'X' | 'L' | 'C')))
+    };
+    let numbered = match words.as_slice() {
+        [label, number, rest, ..] if ["part", "chapter"].contains(&label.to_lowercase().as_str()) => {
+            is_numeral(number.trim_end_matches(['.', ':'])) && rest.starts_with(|c: char| c.is_uppercase())
+        }
+        [number, rest, ..] => {
+            number.ends_with(['.', ')'])
+                && is_numeral(number.trim_end_matches(['.', ')']))
+                && rest.starts_with(|c: char| c.is_uppercase())
+        }
+        _ => false,
+    };
+
+    let letters: Vec<char> = line.chars().filter(|c| c.is_alphabetic()).collect();
+    let all_caps = letters.len() >= 4 && letters.iter().all(|c| c.is_uppercase());
+
+    (numbered || all_caps).then(|| line.to_string())
+}
+
+/// Whether `word` ends in '.', '!' or '?' (allowing closing quotes and brackets)
+fn ends_sentence(word: &str) -> bool {
+    word.trim_end_matches(['"', '\'', ')', ']', '\u{201d}', '\u{2019}'])
+        .ends_with(['.', '!', '?'])
+}
+
+/// Split a paragraph after words ending a sentence
+fn split_sentences(paragraph: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+
+    for word in paragraph.split_whitespace() {
+        current.push(word);
+        if ends_sentence(word) {
+            sentences.push(current.join(" "));
+            current.clear();
+        }
+    }
+
+    if !current.is_empty() {
+        sentences.push(current.join(" "));
+    }
+
+    sentences
+}
+
+/// Byte ranges of the sentences in `text`, split as `split_sentences` does
+/// and also at line breaks, so a heading is not joined to the next sentence
+pub fn sentence_spans(text: &str) -> Vec<(usize, usize)> {
+    let words: Vec<(usize, &str)> = text
+        .split_whitespace()
+        .map(|word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+        .collect();
+
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, &(offset, word)) in words.iter().enumerate() {
+        let sentence_start = *start.get_or_insert(offset);
+        let end = offset + word.len();
+        let next = words.get(i + 1).map_or(text.len(), |&(next, _)| next);
+
+        if ends_sentence(word) || text[end..next].contains('\n') || i + 1 == words.len() {
+            spans.push((sentence_start, end));
+            start = None;
+        }
+    }
+    spans
+}
+
+/// Join chunk units, keeping paragraph breaks between them
+fn join_units(units: &[ChunkUnit]) -> String {
+    let mut text = String::new();
+    for (i, unit) in units.iter().enumerate() {
+        if i > 0 {
+            text.push_str(if unit.paragraph_words.is_some() { "\n\n" } else { " " });
+        }
+        text.push_str(&unit.text);
+    }
+    text
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -277,13 +885,123 @@ mod tests {
         assert!(!cleaned.contains("  "));
     }
 
+    #[test]
+    fn test_report_progress_drops_updates_while_the_channel_is_full() {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+        report_progress(Some(&sender), Stage::Extracting, 0, 2);
+        report_progress(Some(&sender), Stage::Extracting, 1, 2);
+        report_progress(None, Stage::Chunking, 3, 3);
+
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            IngestionProgress { stage: Stage::Extracting, current: 0, total: 2 }
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_process_pdf_stops_when_cancelled() {
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let result = DocumentProcessor::process_pdf(b"%PDF-1.4", test_metadata(), &ChunkingOptions::default(), None, Some(&cancel));
+        assert!(is_cancelled(&result.unwrap_err()));
+        assert!(ensure_not_cancelled(Some(&CancellationToken::new())).is_ok());
+        assert!(ensure_not_cancelled(None).is_ok());
+    }
+
+    #[test]
+    fn test_sentence_spans() {
+        let text = "BACKGROUND\nThe claim was filed. Did it \"succeed?\" It did\n\n  Costs followed.  ";
+        let sentences: Vec<&str> = sentence_spans(text).into_iter().map(|(start, end)| &text[start..end]).collect();
+        assert_eq!(
+            sentences,
+            vec!["BACKGROUND", "The claim was filed.", "Did it \"succeed?\"", "It did", "Costs followed."]
+        );
+        assert!(sentence_spans(" \n ").is_empty());
+    }
+
     #[test]
     fn test_semantic_chunk() {
         let text = "This is a test paragraph.\n\nThis is another paragraph with more text to make it longer.";
-        let chunks = DocumentProcessor::semantic_chunk(text, 50).unwrap();
+        let chunks = DocumentProcessor::semantic_chunk(text, &ChunkingOptions::default()).unwrap();
         assert!(!chunks.is_empty());
     }
 
+    /// `paragraphs` paragraphs of five 12-word sentences each
+    fn long_text(paragraphs: usize, separator: &str) -> String {
+        let sentence = "The claimant must show that the defendant owed a duty of care.";
+        (0..paragraphs)
+            .map(|_| [sentence; 5].join(" "))
+            .collect::<Vec<String>>()
+            .join(separator)
+    }
+
+    fn word_counts(chunks: &[String]) -> Vec<usize> {
+        chunks.iter().map(|c| c.split_whitespace().count()).collect()
+    }
+
+    /// Every chunk but the last is within 20% of the target, and none is over it
+    fn assert_near_target(chunks: &[String], target: usize) {
+        let counts = word_counts(chunks);
+        assert!(counts.len() > 1, "expected several chunks, got {:?}", counts);
+        assert!(counts.iter().all(|&n| n <= target), "{:?}", counts);
+        assert!(
+            counts[..counts.len() - 1].iter().all(|&n| n * 5 >= target * 4),
+            "{:?}",
+            counts
+        );
+    }
+
+    #[test]
+    fn test_semantic_chunk_respects_target_for_each_strategy() {
+        for strategy in [ChunkStrategy::Paragraph, ChunkStrategy::Fixed, ChunkStrategy::Sentence] {
+            for target in [100, 250, 500] {
+                let options = ChunkingOptions { target_words: target, overlap_words: 20, strategy };
+                let chunks = DocumentProcessor::semantic_chunk(&long_text(40, "\n\n"), &options).unwrap();
+                assert_near_target(&chunks, target);
+            }
+        }
+    }
+
+    #[test]
+    fn test_semantic_chunk_splits_text_without_paragraph_breaks() {
+        // 2,400 words on one line, as from a PDF with no blank lines
+        let text = long_text(40, " ");
+        let options = ChunkingOptions { target_words: 300, overlap_words: 0, strategy: ChunkStrategy::Paragraph };
+
+        let chunks = DocumentProcessor::semantic_chunk(&text, &options).unwrap();
+
+        assert_near_target(&chunks, 300);
+        assert_eq!(word_counts(&chunks).iter().sum::<usize>(), 2400);
+        assert!(chunks.iter().all(|c| c.ends_with("duty of care.")));
+    }
+
+    #[test]
+    fn test_semantic_chunk_overlap() {
+        let options = ChunkingOptions { target_words: 100, overlap_words: 24, strategy: ChunkStrategy::Sentence };
+        let chunks = DocumentProcessor::semantic_chunk(&long_text(10, "\n"), &options).unwrap();
+
+        // Each chunk repeats the last two sentences of the one before
+        let tail: Vec<&str> = chunks[0].split_whitespace().rev().take(24).collect();
+        let head: Vec<&str> = chunks[1].split_whitespace().take(24).collect();
+        assert_eq!(tail.into_iter().rev().collect::<Vec<&str>>(), head);
+
+        let fixed = ChunkingOptions { strategy: ChunkStrategy::Fixed, ..options };
+        let chunks = DocumentProcessor::semantic_chunk(&long_text(10, "\n"), &fixed).unwrap();
+        assert_eq!(word_counts(&chunks), vec![100, 100, 100, 100, 100, 100, 100, 68]);
+    }
+
+    #[test]
+    fn test_chunking_options_normalized() {
+        let options = ChunkingOptions { target_words: 0, overlap_words: 500, strategy: ChunkStrategy::Fixed }.normalized();
+        assert_eq!(options.target_words, MIN_TARGET_WORDS);
+        assert_eq!(options.overlap_words, MIN_TARGET_WORDS / 2);
+
+        let options: ChunkingOptions = serde_json::from_str(r#"{"strategy": "sentence"}"#).unwrap();
+        assert_eq!(options, ChunkingOptions { strategy: ChunkStrategy::Sentence, ..ChunkingOptions::default() });
+    }
+
     #[test]
     fn test_process_text() {
         let metadata = DocumentMetadata {
@@ -292,15 +1010,175 @@ mod tests {
             title: "Test Case".to_string(),
             document_type: "user_case".to_string(),
             document_id: None,
+            source_url: None,
         };
 
         let text = "This is a test case with some content.";
-        let result = DocumentProcessor::process_text(text, metadata);
+        let result = DocumentProcessor::process_text(text, metadata, &ChunkingOptions::default());
         assert!(result.is_ok());
 
         let processed = result.unwrap();
         assert!(!processed.chunks.is_empty());
         assert_eq!(processed.total_chunks, processed.chunks.len());
     }
-}
 
+    fn test_metadata() -> DocumentMetadata {
+        DocumentMetadata {
+            user_id: None,
+            case_id: None,
+            title: "Contract Law Notes".to_string(),
+            document_type: "user_case".to_string(),
+            document_id: Some("doc123".to_string()),
+            source_url: None,
+        }
+    }
+
+    #[test]
+    fn test_chunk_by_section() {
+        let pages = vec![
+            (1, "Introduction to the course.\n1. Formation of Contract\nA contract needs offer and acceptance.".to_string()),
+            (2, "2.1 Offer\nAn offer must be definite.\n2.2 Acceptance\nAcceptance must mirror the offer.".to_string()),
+        ];
+
+        let chunks = DocumentProcessor::chunk_by_section(pages, DEFAULT_SECTION_PATTERN, &test_metadata(), &ChunkingOptions::default()).unwrap();
+        let sections: Vec<Option<&str>> = chunks.iter().map(|c| c.metadata.section.as_deref()).collect();
+
+        assert_eq!(
+            sections,
+            vec![None, Some("1. Formation of Contract"), Some("2.1 Offer"), Some("2.2 Acceptance")]
+        );
+        assert!(chunks[2].text.contains("An offer must be definite."));
+        assert!(chunks.iter().all(|c| c.metadata.document_id == "doc123"));
+    }
+
+    #[test]
+    fn test_chunk_by_section_splits_long_sections() {
+        let body = vec!["word"; WORDS_PER_CHUNK + 10].join(" ");
+        let pages = vec![(1, format!("1. Remedies\n{}", body))];
+
+        let chunks = DocumentProcessor::chunk_by_section(pages, DEFAULT_SECTION_PATTERN, &test_metadata(), &ChunkingOptions::default()).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.metadata.section.as_deref() == Some("1. Remedies")));
+        assert_eq!(chunks[1].metadata.chunk_index, 1);
+    }
+
+    #[test]
+    fn test_chunk_by_section_markdown_headings() {
+        let pages = vec![(1, "# Torts\nDuty of care.\n## Negligence\nBreach and causation.".to_string())];
+        let chunks = DocumentProcessor::chunk_by_section(pages, MARKDOWN_SECTION_PATTERN, &test_metadata(), &ChunkingOptions::default()).unwrap();
+        let sections: Vec<Option<&str>> = chunks.iter().map(|c| c.metadata.section.as_deref()).collect();
+        assert_eq!(sections, vec![Some("Torts"), Some("Negligence")]);
+    }
+
+    #[test]
+    fn test_semantic_chunk_carries_roman_numeral_headings() {
+        let text = format!(
+            "Brief for the Claimant\nI. Facts\n{}\nII. Liability\n{}\nIII. Damages\n{}",
+            long_text(2, "\n"),
+            long_text(2, "\n"),
+            long_text(2, "\n")
+        );
+
+        let options = ChunkingOptions { target_words: 60, overlap_words: 12, strategy: ChunkStrategy::Paragraph };
+        let processed = DocumentProcessor::process_text(&text, test_metadata(), &options).unwrap();
+        let mut sections: Vec<Option<&str>> = processed.chunks.iter().map(|c| c.metadata.section.as_deref()).collect();
+        assert!(sections.len() > 4);
+        sections.dedup();
+        assert_eq!(sections, vec![None, Some("I. Facts"), Some("II. Liability"), Some("III. Damages")]);
+        assert!(processed.chunks.iter().all(|c| c.metadata.section.is_some() || c.text.starts_with("Brief for")));
+
+        // Fixed windows take the heading in effect where their new text begins
+        let fixed = ChunkingOptions { strategy: ChunkStrategy::Fixed, ..options };
+        let chunks = DocumentProcessor::semantic_chunk_sections(&text, &fixed).unwrap();
+        assert_eq!(chunks[0].1, None);
+        assert_eq!(chunks.last().unwrap().1.as_deref(), Some("III. Damages"));
+    }
+
+    #[test]
+    fn test_heading_text() {
+        assert_eq!(heading_text("IV. Remedies").as_deref(), Some("IV. Remedies"));
+        assert_eq!(heading_text("Part III Damages").as_deref(), Some("Part III Damages"));
+        assert_eq!(heading_text("## Negligence").as_deref(), Some("Negligence"));
+        assert_eq!(heading_text("STATEMENT OF FACTS").as_deref(), Some("STATEMENT OF FACTS"));
+        assert_eq!(heading_text("I. The defendant breached the contract."), None);
+        assert_eq!(heading_text("I was not served with the claim"), None);
+        assert_eq!(heading_text("USA"), None);
+    }
+
+    #[test]
+    fn test_extract_text_from_docx_rejects_invalid_data() {
+        let result = DocumentProcessor::extract_text_from_docx(b"not a docx file");
+        assert!(matches!(result, Err(AppError::DocumentProcessing(_))));
+    }
+
+    fn chunk(id: &str, text: &str) -> DocumentChunk {
+        DocumentChunk {
+            id: id.to_string(),
+            text: text.to_string(),
+            metadata: ChunkMetadata {
+                document_id: "doc123".to_string(),
+                chunk_index: 0,
+                user_id: None,
+                case_id: None,
+                document_type: "user_case".to_string(),
+                source_title: "Statutes".to_string(),
+                section: None,
+            },
+        }
+    }
+
+    fn hashed(chunks: &[DocumentChunk]) -> Vec<(String, String)> {
+        chunks
+            .iter()
+            .map(|c| (c.id.clone(), DocumentProcessor::chunk_hash(&c.text)))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_chunks() {
+        let old = vec![
+            chunk("a", "Section 1"),
+            chunk("b", "Section 2"),
+            chunk("c", "Section 3"),
+        ];
+        let new = vec![
+            chunk("x", "Section 1"),
+            chunk("y", "Section 2 (amended)"),
+            chunk("z", "Section 3"),
+        ];
+
+        let (added, removed) = DocumentProcessor::diff_chunks(&hashed(&old), &new);
+
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].text, "Section 2 (amended)");
+        assert_eq!(removed, vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn test_diff_chunks_with_repeated_text() {
+        let old = vec![chunk("a", "Footer"), chunk("b", "Footer")];
+        let new = vec![chunk("x", "Footer")];
+
+        let (added, removed) = DocumentProcessor::diff_chunks(&hashed(&old), &new);
+
+        assert!(added.is_empty());
+        assert_eq!(removed, vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn test_chunk_by_section_without_headings() {
+        let pages = vec![(1, "Plain paragraph with no headings.\nAnother line.".to_string())];
+        let chunks = DocumentProcessor::chunk_by_section(pages, DEFAULT_SECTION_PATTERN, &test_metadata(), &ChunkingOptions::default()).unwrap();
+        assert!(chunks.is_empty());
+    }
+
+    #[test]
+    fn test_text_hash_ignores_case_and_whitespace() {
+        let original = DocumentProcessor::process_text("Offer and acceptance.\n\nConsideration must move from the promisee.", test_metadata(), &ChunkingOptions::default()).unwrap();
+        let resaved = DocumentProcessor::process_text("offer and  acceptance.\r\nConsideration must move\nfrom the promisee. ", test_metadata(), &ChunkingOptions::default()).unwrap();
+
+        assert_eq!(original.text_hash, resaved.text_hash);
+        assert_ne!(original.text_hash, DocumentProcessor::text_hash("Offer and acceptance."));
+    }
+}
@@ -3,6 +3,10 @@
  * Provides comprehensive error handling across all modules
  */
 
+use crate::document::CANCELLED;
+use crate::retry::CIRCUIT_OPEN;
+use reqwest::StatusCode;
+use serde::Serialize;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -19,7 +23,7 @@ pub enum AppError {
     
     // Network errors
     #[error("Network request failed: {0}")]
-    Network(#[from] reqwest::Error),
+    Network(String),
     
     #[error("API error: {0}")]
     Api(String),
@@ -34,6 +38,9 @@ pub enum AppError {
         message: String,
     },
     
+    #[error("Model refused the request: {0}")]
+    ModelRefusal(String),
+    
     #[error("Embedding generation failed: {0}")]
     Embedding(String),
     
@@ -64,6 +71,9 @@ pub enum AppError {
     #[error("Missing required field: {0}")]
     MissingField(String),
     
+    #[error("Invalid UUID: {0}")]
+    InvalidUuid(String),
+    
     // Authentication errors
     #[error("Authentication error: {0}")]
     Auth(String),
@@ -120,6 +130,9 @@ impl AppError {
             Self::Supabase(_) => {
                 "Unable to connect to the cloud service. Please check your internet connection.".to_string()
             }
+            Self::Network(msg) if msg.starts_with("Proxy authentication failed") => {
+                "Proxy authentication failed. Please check your proxy settings.".to_string()
+            }
             Self::Network(_) => {
                 "Network connection failed. Please check your internet connection.".to_string()
             }
@@ -129,6 +142,9 @@ impl AppError {
             Self::OpenRouter { message, .. } => {
                 format!("AI service error: {}", message)
             }
+            Self::ModelRefusal(_) => {
+                "The AI model declined to answer this request. Try rephrasing it or using a different model.".to_string()
+            }
             Self::Embedding(_) => {
                 "Failed to process document embedding. Please try again.".to_string()
             }
@@ -141,7 +157,7 @@ impl AppError {
             Self::TextChunking(_) => {
                 "Failed to process document text. Please try again.".to_string()
             }
-            Self::Validation(msg) | Self::InvalidInput(msg) => {
+            Self::Validation(msg) | Self::InvalidInput(msg) | Self::InvalidUuid(msg) => {
                 format!("Invalid input: {}", msg)
             }
             Self::MissingField(field) => {
@@ -181,13 +197,234 @@ impl AppError {
     }
 }
 
-/// Convert AppError to String for Tauri command results
-impl From<AppError> for String {
+impl AppError {
+    /// Stable snake_case code identifying the kind of error, for the
+    /// frontend to select a localised message
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            Self::Database(_) => "db.error",
+            Self::Supabase(_) => "db.supabase_error",
+            Self::Sqlx(_) => "db.query_failed",
+            Self::Network(msg) if msg.starts_with("Proxy authentication failed") => "network.proxy_auth_failed",
+            Self::Network(_) => "network.request_failed",
+            Self::Api(_) => "api.error",
+            Self::Llm(_) => "llm.error",
+            Self::OpenRouter { status: 429, .. } => "llm.rate_limited",
+            Self::OpenRouter { status: 401 | 403, .. } => "llm.unauthorized",
+            Self::OpenRouter { .. } => "llm.provider_error",
+            Self::ModelRefusal(_) => "llm.refused",
+            Self::Embedding(_) => "llm.embedding_failed",
+            Self::Rag(_) => "rag.error",
+            Self::DocumentProcessing(_) => "document.processing_failed",
+            Self::VectorSearch(_) => "rag.search_failed",
+            Self::PdfExtraction(_) => "document.pdf_extraction_failed",
+            Self::TextChunking(_) => "document.chunking_failed",
+            Self::Validation(_) => "validation.invalid",
+            Self::InvalidInput(_) => "validation.invalid_input",
+            Self::MissingField(_) => "validation.missing_field",
+            Self::InvalidUuid(_) => "validation.uuid_invalid",
+            Self::Auth(_) => "auth.failed",
+            Self::Unauthorized(_) => "auth.unauthorized",
+            Self::Sync(_) => "sync.failed",
+            Self::Offline => "sync.offline",
+            Self::SyncConflict(_) => "sync.conflict",
+            Self::FileSystem(_) => "fs.io_error",
+            Self::FileNotFound(_) => "fs.not_found",
+            Self::JsonSerialization(_) => "data.serialization_failed",
+            Self::Config(_) => "config.invalid",
+            Self::MissingEnv(_) => "config.missing_env",
+            Self::Internal(_) => "internal.error",
+            Self::NotFound(_) => "db.not_found",
+            Self::OperationFailed(msg) if msg == CIRCUIT_OPEN => "llm.circuit_open",
+            Self::OperationFailed(msg) if msg == CANCELLED => "operation.cancelled",
+            Self::OperationFailed(_) => "operation.failed",
+        }
+    }
+}
+
+/// Error returned by Tauri commands
+///
+/// `code` is stable and meant for lookups; `message` is the technical
+/// detail and `user_message` an English fallback for display.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct CommandError {
+    pub code: String,
+    pub message: String,
+    pub user_message: String,
+}
+
+impl From<AppError> for CommandError {
+    /// Every command error passes through here, so this is where they are logged
     fn from(error: AppError) -> Self {
-        error.to_string()
+        crate::logging::log_command_error(&error);
+
+        Self {
+            code: error.error_code().to_string(),
+            message: error.to_string(),
+            user_message: error.user_message(),
+        }
+    }
+}
+
+impl From<sqlx::Error> for CommandError {
+    fn from(error: sqlx::Error) -> Self {
+        AppError::from(error).into()
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(error: std::io::Error) -> Self {
+        AppError::from(error).into()
+    }
+}
+
+impl From<serde_json::Error> for CommandError {
+    fn from(error: serde_json::Error) -> Self {
+        AppError::from(error).into()
+    }
+}
+
+/// Convert reqwest errors, calling out proxy failures explicitly
+impl From<reqwest::Error> for AppError {
+    fn from(error: reqwest::Error) -> Self {
+        let detail = error.to_string();
+        // A 407 on the CONNECT tunnel has no response status, only hyper's error text
+        let proxy_auth_failed = error.status() == Some(StatusCode::PROXY_AUTHENTICATION_REQUIRED)
+            || detail.to_lowercase().contains("proxy authentication")
+            || detail.to_lowercase().contains("proxy authorization required");
+
+        if proxy_auth_failed {
+            Self::Network(format!(
+                "Proxy authentication failed. Check the credentials in http_proxy ({})",
+                detail
+            ))
+        } else {
+            Self::Network(detail)
+        }
     }
 }
 
 /// Result type alias for application operations
 pub type AppResult<T> = Result<T, AppError>;
 
+/// Result type returned by Tauri commands
+pub type CommandResult<T> = Result<T, CommandError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// One error of every variant. `variant_index` has no wildcard arm, so a
+    /// new variant fails to compile until it is added here.
+    fn every_variant() -> Vec<AppError> {
+        let msg = || "detail".to_string();
+        let errors = vec![
+            AppError::Database(msg()),
+            AppError::Supabase(msg()),
+            AppError::Sqlx(sqlx::Error::RowNotFound),
+            AppError::Network(msg()),
+            AppError::Api(msg()),
+            AppError::Llm(msg()),
+            AppError::OpenRouter { status: 500, message: msg() },
+            AppError::ModelRefusal(msg()),
+            AppError::Embedding(msg()),
+            AppError::Rag(msg()),
+            AppError::DocumentProcessing(msg()),
+            AppError::VectorSearch(msg()),
+            AppError::PdfExtraction(msg()),
+            AppError::TextChunking(msg()),
+            AppError::Validation(msg()),
+            AppError::InvalidInput(msg()),
+            AppError::MissingField(msg()),
+            AppError::InvalidUuid(msg()),
+            AppError::Auth(msg()),
+            AppError::Unauthorized(msg()),
+            AppError::Sync(msg()),
+            AppError::Offline,
+            AppError::SyncConflict(msg()),
+            AppError::FileSystem(std::io::Error::other("detail")),
+            AppError::FileNotFound(msg()),
+            AppError::JsonSerialization(serde_json::from_str::<()>("x").unwrap_err()),
+            AppError::Config(msg()),
+            AppError::MissingEnv(msg()),
+            AppError::Internal(msg()),
+            AppError::NotFound(msg()),
+            AppError::OperationFailed(msg()),
+        ];
+
+        for (i, error) in errors.iter().enumerate() {
+            assert_eq!(variant_index(error), i, "every_variant is out of order at {:?}", error);
+        }
+        errors
+    }
+
+    fn variant_index(error: &AppError) -> usize {
+        match error {
+            AppError::Database(_) => 0,
+            AppError::Supabase(_) => 1,
+            AppError::Sqlx(_) => 2,
+            AppError::Network(_) => 3,
+            AppError::Api(_) => 4,
+            AppError::Llm(_) => 5,
+            AppError::OpenRouter { .. } => 6,
+            AppError::ModelRefusal(_) => 7,
+            AppError::Embedding(_) => 8,
+            AppError::Rag(_) => 9,
+            AppError::DocumentProcessing(_) => 10,
+            AppError::VectorSearch(_) => 11,
+            AppError::PdfExtraction(_) => 12,
+            AppError::TextChunking(_) => 13,
+            AppError::Validation(_) => 14,
+            AppError::InvalidInput(_) => 15,
+            AppError::MissingField(_) => 16,
+            AppError::InvalidUuid(_) => 17,
+            AppError::Auth(_) => 18,
+            AppError::Unauthorized(_) => 19,
+            AppError::Sync(_) => 20,
+            AppError::Offline => 21,
+            AppError::SyncConflict(_) => 22,
+            AppError::FileSystem(_) => 23,
+            AppError::FileNotFound(_) => 24,
+            AppError::JsonSerialization(_) => 25,
+            AppError::Config(_) => 26,
+            AppError::MissingEnv(_) => 27,
+            AppError::Internal(_) => 28,
+            AppError::NotFound(_) => 29,
+            AppError::OperationFailed(_) => 30,
+        }
+    }
+
+    #[test]
+    fn test_every_variant_has_a_unique_code() {
+        let mut errors = every_variant();
+        // Variants with more than one code
+        errors.push(AppError::Network("Proxy authentication failed".to_string()));
+        errors.push(AppError::OpenRouter { status: 429, message: "slow down".to_string() });
+        errors.push(AppError::OpenRouter { status: 401, message: "bad key".to_string() });
+        errors.push(AppError::OperationFailed(CIRCUIT_OPEN.to_string()));
+        errors.push(AppError::OperationFailed(CANCELLED.to_string()));
+
+        let mut seen = HashSet::new();
+        for error in &errors {
+            let code = error.error_code();
+            assert!(!code.is_empty(), "{:?} has an empty code", error);
+            assert!(
+                code.chars().all(|c| c.is_ascii_lowercase() || c == '_' || c == '.'),
+                "{} is not snake_case",
+                code
+            );
+            assert!(seen.insert(code), "{} is used twice", code);
+        }
+    }
+
+    #[test]
+    fn test_command_error_carries_code_and_messages() {
+        let error = CommandError::from(AppError::Offline);
+
+        assert_eq!(error.code, "sync.offline");
+        assert_eq!(error.message, AppError::Offline.to_string());
+        assert_eq!(error.user_message, AppError::Offline.user_message());
+    }
+}
+
@@ -0,0 +1,477 @@
+/**
+ * Data Export Module
+ * Exports all of a user's data to JSON and imports it back
+ */
+
+use crate::db::HybridStorage;
+use crate::error::{AppError, AppResult, CommandError, CommandResult};
+use crate::validation::validate_uuid;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::sqlite::SqliteArguments;
+use sqlx::{Pool, Sqlite, Transaction};
+use std::collections::HashMap;
+use tauri::State;
+use uuid::Uuid;
+
+/// A table row as exported, keyed by column name
+pub type ExportRow = Map<String, Value>;
+
+/// Local bookkeeping columns that are not exported
+const SYNC_COLUMNS: &[&str] = &["synced", "dirty"];
+
+/// Rows of a table with a `user_id` column owned by the bound user
+const OWNED: &str = "user_id = ?";
+
+/// Flashcards in a set owned by the bound user
+const OWNED_CARDS: &str = "set_id IN (SELECT id FROM flashcard_sets WHERE user_id = ?)";
+
+/// Every row belonging to one user
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserDataExport {
+    pub exported_at: String,
+    pub schema_version: i64,
+    pub user_id: String,
+    pub cases: Vec<ExportRow>,
+    pub flashcard_sets: Vec<ExportRow>,
+    pub flashcards: Vec<ExportRow>,
+    pub mock_tests: Vec<ExportRow>,
+    pub test_results: Vec<ExportRow>,
+    pub study_plans: Vec<ExportRow>,
+}
+
+/// What to do when an imported row's id already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportConflict {
+    /// Keep the existing row
+    Skip,
+    /// Replace the existing row with the imported one
+    Overwrite,
+    /// Import the row under a new id
+    Rename,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct ImportSummary {
+    /// Rows inserted without a conflict
+    pub imported: usize,
+    pub overwritten: usize,
+    pub renamed: usize,
+    pub skipped: usize,
+}
+
+/// Build the export for `user_id`
+pub async fn collect_user_data(storage: &HybridStorage, user_id: &str) -> AppResult<UserDataExport> {
+    let user_id = &validate_uuid(user_id, "User ID")?;
+    let pool = storage.sqlite().get_pool().await?;
+
+    Ok(UserDataExport {
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        schema_version: storage.sqlite().schema_version().await?,
+        user_id: user_id.to_string(),
+        cases: export_rows(&pool, "cases", OWNED, user_id).await?,
+        flashcard_sets: export_rows(&pool, "flashcard_sets", OWNED, user_id).await?,
+        flashcards: export_rows(&pool, "flashcards", OWNED_CARDS, user_id).await?,
+        mock_tests: export_rows(&pool, "mock_tests", OWNED, user_id).await?,
+        test_results: export_rows(&pool, "test_results", OWNED, user_id).await?,
+        study_plans: export_rows(&pool, "study_plans", OWNED, user_id).await?,
+    })
+}
+
+/// Write all of a user's data to `output_path` as pretty-printed JSON
+pub async fn write_user_export(storage: &HybridStorage, user_id: &str, output_path: &str) -> AppResult<()> {
+    let export = collect_user_data(storage, user_id).await?;
+    let json = serde_json::to_string_pretty(&export)?;
+    tokio::fs::write(output_path, json).await?;
+    Ok(())
+}
+
+/// Import an export file for `user_id` in a single transaction
+///
+/// Rows are imported as `user_id`'s whoever exported them.
+pub async fn import_user_file(
+    storage: &HybridStorage,
+    user_id: &str,
+    path: &str,
+    conflict: ImportConflict,
+) -> AppResult<ImportSummary> {
+    let user_id = validate_uuid(user_id, "User ID")?;
+    let json = tokio::fs::read_to_string(path).await?;
+    let export: UserDataExport = serde_json::from_str(&json)?;
+
+    let current = storage.sqlite().schema_version().await?;
+    if export.schema_version > current {
+        return Err(AppError::Validation(format!(
+            "Export was created by a newer version of the app (schema {}, this app has {})",
+            export.schema_version, current
+        )));
+    }
+
+    let pool = storage.sqlite().get_pool().await?;
+    import_export(&pool, &user_id, export, conflict).await
+}
+
+/// Insert every row of `export` as `user_id`'s, rolling back if any insert fails
+async fn import_export(
+    pool: &Pool<Sqlite>,
+    user_id: &str,
+    export: UserDataExport,
+    conflict: ImportConflict,
+) -> AppResult<ImportSummary> {
+    let mut tx = pool.begin().await?;
+    let mut summary = ImportSummary::default();
+
+    // Parents first; children follow renamed parents through the id maps
+    let mut import = TableImport {
+        tx: &mut tx,
+        user_id,
+        conflict,
+        summary: &mut summary,
+    };
+    let no_parent = HashMap::new();
+    import.table("cases", OWNED, export.cases, None, &no_parent).await?;
+    let set_ids = import.table("flashcard_sets", OWNED, export.flashcard_sets, None, &no_parent).await?;
+    import.table("flashcards", OWNED_CARDS, export.flashcards, Some("set_id"), &set_ids).await?;
+    let test_ids = import.table("mock_tests", OWNED, export.mock_tests, None, &no_parent).await?;
+    import.table("test_results", OWNED, export.test_results, Some("test_id"), &test_ids).await?;
+    import.table("study_plans", OWNED, export.study_plans, None, &no_parent).await?;
+
+    tx.commit().await?;
+    Ok(summary)
+}
+
+/// Column names of `table`, excluding sync bookkeeping
+pub(crate) async fn table_columns(executor: impl sqlx::SqliteExecutor<'_>, table: &str) -> AppResult<Vec<String>> {
+    let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?) ORDER BY cid")
+        .bind(table)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(columns
+        .into_iter()
+        .filter(|c| !SYNC_COLUMNS.contains(&c.as_str()))
+        .collect())
+}
+
+/// Rows of `table` matching `filter` (bound to `user_id`) as JSON objects
+async fn export_rows(pool: &Pool<Sqlite>, table: &str, filter: &str, user_id: &str) -> AppResult<Vec<ExportRow>> {
+    let columns = table_columns(pool, table).await?;
+    let fields = columns
+        .iter()
+        .map(|c| format!("'{}', {}", c, c))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let rows: Vec<String> = sqlx::query_scalar(&format!(
+        "SELECT json_object({}) FROM {} WHERE {} ORDER BY rowid",
+        fields, table, filter
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| serde_json::from_str(row).map_err(AppError::from))
+        .collect()
+}
+
+/// The transaction and options shared by every table of an import
+struct TableImport<'a, 'c> {
+    tx: &'a mut Transaction<'c, Sqlite>,
+    user_id: &'a str,
+    conflict: ImportConflict,
+    summary: &'a mut ImportSummary,
+}
+
+impl TableImport<'_, '_> {
+    /// Import rows into `table`, returning a map of renamed ids
+    ///
+    /// `user_id` columns are set to the importing user, and every row must
+    /// end up matching `owner` (bound to `user_id`). An id already taken by
+    /// another user's row is imported under a new id whatever `conflict` says.
+    /// `parent_column` is rewritten through `parent_ids` so children stay
+    /// attached to a parent imported under a new id.
+    async fn table(
+        &mut self,
+        table: &str,
+        owner: &str,
+        rows: Vec<ExportRow>,
+        parent_column: Option<&str>,
+        parent_ids: &HashMap<String, String>,
+    ) -> AppResult<HashMap<String, String>> {
+        let columns = table_columns(&mut **self.tx, table).await?;
+        let mut renamed = HashMap::new();
+
+        for row in rows {
+            // Drop unknown columns from older or newer exports
+            let mut row: ExportRow = row.into_iter().filter(|(key, _)| columns.contains(key)).collect();
+            if row.contains_key("user_id") {
+                row.insert("user_id".to_string(), Value::String(self.user_id.to_string()));
+            }
+
+            if let Some(column) = parent_column {
+                let new_parent = row
+                    .get(column)
+                    .and_then(Value::as_str)
+                    .and_then(|id| parent_ids.get(id))
+                    .cloned();
+                if let Some(new_parent) = new_parent {
+                    row.insert(column.to_string(), Value::String(new_parent));
+                }
+            }
+
+            let id = row
+                .get("id")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| AppError::Validation(format!("Row in {} has no id", table)))?;
+
+            let exists: Option<i64> = sqlx::query_scalar(&format!("SELECT 1 FROM {} WHERE id = ?", table))
+                .bind(&id)
+                .fetch_optional(&mut **self.tx)
+                .await?;
+            let conflict = match exists {
+                Some(_) if !self.owns(table, owner, &id).await? => ImportConflict::Rename,
+                _ => self.conflict,
+            };
+
+            let upsert = if exists.is_none() {
+                self.summary.imported += 1;
+                false
+            } else {
+                match conflict {
+                    ImportConflict::Skip => {
+                        self.summary.skipped += 1;
+                        continue;
+                    }
+                    ImportConflict::Overwrite => {
+                        self.summary.overwritten += 1;
+                        true
+                    }
+                    ImportConflict::Rename => {
+                        let new_id = Uuid::new_v4().to_string();
+                        row.insert("id".to_string(), Value::String(new_id.clone()));
+                        renamed.insert(id, new_id);
+                        self.summary.renamed += 1;
+                        false
+                    }
+                }
+            };
+
+            insert_row(self.tx, table, &row, upsert).await?;
+
+            // A child pointing at a parent outside the export could attach to someone else's row
+            let id = row.get("id").and_then(Value::as_str).unwrap_or_default().to_string();
+            if !self.owns(table, owner, &id).await? {
+                return Err(AppError::Validation(format!(
+                    "Row {} in {} doesn't belong to the importing user",
+                    id, table
+                )));
+            }
+        }
+
+        Ok(renamed)
+    }
+
+    /// Whether the row `id` of `table` matches `owner` for the importing user
+    async fn owns(&mut self, table: &str, owner: &str, id: &str) -> AppResult<bool> {
+        let owned: Option<i64> = sqlx::query_scalar(&format!("SELECT 1 FROM {} WHERE id = ? AND {}", table, owner))
+            .bind(id)
+            .bind(self.user_id)
+            .fetch_optional(&mut **self.tx)
+            .await?;
+        Ok(owned.is_some())
+    }
+}
+
+/// Insert a row marked dirty so it syncs; `upsert` updates an existing id in place
+///
+/// Updates use `ON CONFLICT` rather than `INSERT OR REPLACE`, which would
+/// delete the old row and cascade to its children.
+async fn insert_row(tx: &mut Transaction<'_, Sqlite>, table: &str, row: &ExportRow, upsert: bool) -> AppResult<()> {
+    let columns: Vec<&String> = row.keys().collect();
+    let names = columns.iter().map(|c| c.as_str()).collect::<Vec<_>>().join(", ");
+    let placeholders = vec!["?"; columns.len()].join(", ");
+
+    let mut sql = format!(
+        "INSERT INTO {} ({}, synced, dirty) VALUES ({}, 0, 1)",
+        table, names, placeholders
+    );
+    if upsert {
+        let updates = columns
+            .iter()
+            .filter(|c| c.as_str() != "id")
+            .map(|c| format!("{} = excluded.{}", c, c))
+            .chain(["synced = 0".to_string(), "dirty = 1".to_string()])
+            .collect::<Vec<_>>()
+            .join(", ");
+        sql.push_str(&format!(" ON CONFLICT(id) DO UPDATE SET {}", updates));
+    }
+
+    let mut query = sqlx::query(&sql);
+    for column in &columns {
+        query = bind_json(query, &row[column.as_str()]);
+    }
+    query.execute(&mut **tx).await?;
+
+    Ok(())
+}
+
+/// Bind a JSON value as the SQLite value it is stored as
+pub(crate) fn bind_json<'q>(
+    query: sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>>,
+    value: &Value,
+) -> sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>> {
+    match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Bool(b) => query.bind(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => query.bind(i),
+            None => query.bind(n.as_f64()),
+        },
+        Value::String(s) => query.bind(s.clone()),
+        other => query.bind(other.to_string()),
+    }
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub async fn export_user_data(
+    storage: State<'_, HybridStorage>,
+    user_id: String,
+    output_path: String,
+) -> CommandResult<()> {
+    write_user_export(&storage, &user_id, &output_path).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn import_user_data(
+    storage: State<'_, HybridStorage>,
+    user_id: String,
+    path: String,
+    conflict: ImportConflict,
+) -> CommandResult<ImportSummary> {
+    import_user_file(&storage, &user_id, &path, conflict).await.map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    const USER: &str = "11111111-1111-4111-8111-111111111111";
+
+    async fn seeded_pool() -> Pool<Sqlite> {
+        let pool = test_pool().await;
+
+        sqlx::query("INSERT INTO flashcard_sets (id, user_id, title, created_at, updated_at, synced) VALUES ('set-1', ?, 'Torts', 'now', 'now', 1)")
+            .bind(USER)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO flashcards (id, set_id, front, back, created_at) VALUES ('card-1', 'set-1', 'Duty?', 'Donoghue v Stevenson', 'now')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        pool
+    }
+
+    async fn export_sets(pool: &Pool<Sqlite>) -> UserDataExport {
+        UserDataExport {
+            user_id: USER.to_string(),
+            flashcard_sets: export_rows(pool, "flashcard_sets", OWNED, USER).await.unwrap(),
+            flashcards: export_rows(pool, "flashcards", OWNED_CARDS, USER).await.unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_omits_sync_columns() {
+        let pool = seeded_pool().await;
+        let export = export_sets(&pool).await;
+
+        assert_eq!(export.flashcard_sets.len(), 1);
+        assert_eq!(export.flashcard_sets[0]["title"], "Torts");
+        assert!(!export.flashcard_sets[0].contains_key("synced"));
+        assert_eq!(export.flashcards[0]["set_id"], "set-1");
+    }
+
+    #[tokio::test]
+    async fn test_import_conflicts() {
+        let pool = seeded_pool().await;
+        let export = export_sets(&pool).await;
+
+        let summary = import_export(&pool, USER, export.clone(), ImportConflict::Skip).await.unwrap();
+        assert_eq!(summary, ImportSummary { skipped: 2, ..Default::default() });
+
+        let summary = import_export(&pool, USER, export, ImportConflict::Rename).await.unwrap();
+        assert_eq!(summary, ImportSummary { renamed: 2, ..Default::default() });
+
+        // The renamed card follows its renamed set
+        let orphans: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM flashcards WHERE set_id NOT IN (SELECT id FROM flashcard_sets)",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let sets: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM flashcard_sets").fetch_one(&pool).await.unwrap();
+        assert_eq!((orphans, sets), (0, 2));
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_keeps_children() {
+        let pool = seeded_pool().await;
+        let mut export = export_sets(&pool).await;
+        export.flashcard_sets[0].insert("title".to_string(), Value::String("Torts II".to_string()));
+        export.flashcards.clear();
+
+        let summary = import_export(&pool, USER, export, ImportConflict::Overwrite).await.unwrap();
+        assert_eq!(summary.overwritten, 1);
+
+        let (title, dirty): (String, i64) = sqlx::query_as("SELECT title, dirty FROM flashcard_sets WHERE id = 'set-1'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let cards: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM flashcards").fetch_one(&pool).await.unwrap();
+        assert_eq!((title.as_str(), dirty, cards), ("Torts II", 1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_imported_rows_belong_to_the_importing_user() {
+        const OTHER: &str = "22222222-2222-4222-8222-222222222222";
+        let pool = seeded_pool().await;
+        let export = export_sets(&pool).await;
+
+        // Another user's import of the same file can't overwrite the owner's rows
+        let summary = import_export(&pool, OTHER, export.clone(), ImportConflict::Overwrite).await.unwrap();
+        assert_eq!(summary, ImportSummary { renamed: 2, ..Default::default() });
+
+        let owners: Vec<(String, String)> = sqlx::query_as("SELECT id, user_id FROM flashcard_sets ORDER BY user_id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(owners[0], ("set-1".to_string(), USER.to_string()));
+        assert_eq!(owners[1].1, OTHER);
+        let cards: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM flashcards WHERE set_id = ?")
+            .bind(&owners[1].0)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(cards, 1);
+
+        // A card can't be slipped into a set the importer doesn't own
+        let mut cards_only = export;
+        cards_only.flashcard_sets.clear();
+        cards_only.flashcards[0].insert("id".to_string(), Value::String("card-2".to_string()));
+        let result = import_export(&pool, OTHER, cards_only, ImportConflict::Skip).await;
+        assert!(matches!(result, Err(AppError::Validation(_))));
+        let card: Option<i64> = sqlx::query_scalar("SELECT 1 FROM flashcards WHERE id = 'card-2'")
+            .fetch_optional(&pool)
+            .await
+            .unwrap();
+        assert!(card.is_none());
+    }
+}
@@ -3,11 +3,21 @@
  * Manages flashcard sets and individual flashcards with CRUD operations
  */
 
-use crate::db::HybridStorage;
-use crate::error::{AppError, AppResult};
-use crate::validation::{validate_flashcard_content, validate_not_empty, validate_uuid};
-use chrono::Utc;
+use crate::db::{execute_checked, response_total, HybridStorage, PagedResult, PaginationOptions};
+use crate::error::{AppError, AppResult, CommandError, CommandResult};
+use crate::llm::{ChatOptions, LLMService, Message};
+use crate::rag::RAGService;
+use crate::recycle_bin;
+use crate::state::AppState;
+use crate::sync::SyncManager;
+use crate::tokens;
+use crate::validation::{validate_flashcard_content, validate_not_empty, validate_positive_integer, validate_range, validate_uuid};
+use chrono::{DateTime, Duration, Utc};
+use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
+use sqlx::{Row, Sqlite, Transaction};
+use std::sync::Arc;
+use tauri::State;
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -43,18 +53,110 @@ pub struct CreateFlashcardRequest {
     pub back: String,
 }
 
+/// Result of generating a flashcard set from a document
+#[derive(Debug, Serialize, Clone)]
+pub struct GeneratedFlashcards {
+    pub set: FlashcardSet,
+    pub flashcards: Vec<Flashcard>,
+    /// Requested cards that the LLM didn't return or that failed validation
+    pub skipped: usize,
+}
+
+/// Which cards of a set a review session covers, and in what order
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewStrategy {
+    /// Every card, in the order it was added
+    AllCards,
+    /// Cards never reviewed or whose next review is due
+    DueOnly,
+    /// Every card, lowest ease factor first
+    WeakestFirst,
+    /// Every card, shuffled
+    Random,
+}
+
+impl ReviewStrategy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReviewStrategy::AllCards => "all_cards",
+            ReviewStrategy::DueOnly => "due_only",
+            ReviewStrategy::WeakestFirst => "weakest_first",
+            ReviewStrategy::Random => "random",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReviewSession {
+    pub id: String,
+    pub cards: Vec<Flashcard>,
+    pub total: usize,
+    pub created_at: String,
+}
+
+/// Where a review session stands after a card is reviewed
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReviewProgress {
+    pub reviewed: usize,
+    pub remaining: usize,
+    /// Sum of answer qualities as a fraction of the best possible (0.0-1.0)
+    pub session_score: f64,
+}
+
+/// Totals for a finished review session
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionSummary {
+    pub session_id: String,
+    pub total: usize,
+    pub reviewed: usize,
+    /// Cards answered with a quality of 3 or more
+    pub recalled: usize,
+    pub average_quality: f64,
+    pub session_score: f64,
+    pub duration_secs: i64,
+    pub finished_at: String,
+}
+
+/// A card's SM-2 scheduling state
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CardSchedule {
+    ease_factor: f64,
+    interval_days: i64,
+    repetitions: i64,
+}
+
+impl Default for CardSchedule {
+    fn default() -> Self {
+        Self {
+            ease_factor: 2.5,
+            interval_days: 0,
+            repetitions: 0,
+        }
+    }
+}
+
+/// A card together with its schedule, if it has ever been reviewed
+struct ScheduledCard {
+    card: Flashcard,
+    schedule: Option<CardSchedule>,
+    due_at: Option<String>,
+}
+
+#[derive(Clone)]
 pub struct FlashcardService {
     storage: HybridStorage,
+    sync: Arc<SyncManager>,
 }
 
 impl FlashcardService {
-    pub fn new(storage: HybridStorage) -> Self {
-        Self { storage }
+    pub fn new(storage: HybridStorage, sync: Arc<SyncManager>) -> Self {
+        Self { storage, sync }
     }
 
     /// Create a new flashcard set
-    pub async fn create_set(&self, request: CreateFlashcardSetRequest) -> AppResult<FlashcardSet> {
-        validate_uuid(&request.user_id, "User ID")?;
+    pub async fn create_set(&self, mut request: CreateFlashcardSetRequest) -> AppResult<FlashcardSet> {
+        request.user_id = validate_uuid(&request.user_id, "User ID")?;
         validate_not_empty(&request.title, "Set title")?;
 
         let set = FlashcardSet {
@@ -78,114 +180,114 @@ impl FlashcardService {
                     "updated_at": set.updated_at,
                 });
 
-                supabase
-                    .insert("flashcard_sets", &data.to_string())
-                    .await?
-                    .execute()
+                execute_checked(supabase.upsert("flashcard_sets", &data.to_string(), "id").await?)
                     .await
                     .map_err(|e| AppError::Supabase(format!("Failed to create set: {}", e)))?;
             }
         }
 
         // Save locally
-        self.storage.sqlite().execute(move |conn| {
-            conn.execute(
-                "INSERT INTO flashcard_sets 
-                 (id, user_id, title, description, created_at, updated_at, synced, dirty)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-                rusqlite::params![
-                    &set.id,
-                    &set.user_id,
-                    &set.title,
-                    &set.description,
-                    &set.created_at,
-                    &set.updated_at,
-                    if self.storage.is_online().await { 1 } else { 0 },
-                    if self.storage.is_online().await { 0 } else { 1 },
-                ],
-            )?;
-            Ok(())
-        }).await?;
+        let online = self.storage.is_online().await;
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query(
+            "INSERT INTO flashcard_sets
+             (id, user_id, title, description, created_at, updated_at, synced, dirty)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&set.id)
+        .bind(&set.user_id)
+        .bind(&set.title)
+        .bind(&set.description)
+        .bind(&set.created_at)
+        .bind(&set.updated_at)
+        .bind(online as i64)
+        .bind(!online as i64)
+        .execute(&pool)
+        .await?;
 
         Ok(set)
     }
 
-    /// Get all flashcard sets for a user
-    pub async fn get_sets(&self, user_id: &str) -> AppResult<Vec<FlashcardSet>> {
-        validate_uuid(user_id, "User ID")?;
+    /// Get a page of flashcard sets for a user, most recently updated first
+    pub async fn get_sets(
+        &self,
+        user_id: &str,
+        pagination: Option<PaginationOptions>,
+    ) -> AppResult<PagedResult<FlashcardSet>> {
+        let user_id = &validate_uuid(user_id, "User ID")?;
+        let pagination = pagination.unwrap_or_default().normalized()?;
 
         // Try Supabase first if online
         if self.storage.is_online().await {
             if let Some(supabase) = self.storage.supabase() {
+                let (from, to) = pagination.range();
                 let response = supabase
                     .select("flashcard_sets")
                     .await?
                     .eq("user_id", user_id)
+                    .is("deleted_at", "null")
+                    .order("updated_at.desc")
+                    .range(from, to)
+                    .exact_count()
                     .execute()
                     .await
                     .map_err(|e| AppError::Supabase(format!("Failed to fetch sets: {}", e)))?;
 
+                let total = response_total(&response);
                 let body = response.text().await?;
                 let sets: Vec<FlashcardSet> = serde_json::from_str(&body)?;
-                return Ok(sets);
+                let total = total.unwrap_or(pagination.offset() + sets.len());
+                return Ok(PagedResult::new(sets, total, pagination));
             }
         }
 
         // Fallback to local
-        let user_id = user_id.to_string();
-        self.storage.sqlite().execute(move |conn| {
-            let mut stmt = conn.prepare(
-                "SELECT id, user_id, title, description, created_at, updated_at
-                 FROM flashcard_sets
-                 WHERE user_id = ?1
-                 ORDER BY updated_at DESC"
-            )?;
-
-            let sets = stmt
-                .query_map([&user_id], |row| {
-                    Ok(FlashcardSet {
-                        id: row.get(0)?,
-                        user_id: row.get(1)?,
-                        title: row.get(2)?,
-                        description: row.get(3)?,
-                        created_at: row.get(4)?,
-                        updated_at: row.get(5)?,
-                    })
-                })?
-                .collect::<Result<Vec<_>, _>>()?;
-
-            Ok(sets)
-        }).await
-    }
+        let pool = self.storage.sqlite().get_pool().await?;
+        let total: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM flashcard_sets WHERE user_id = ? AND deleted_at IS NULL"
+        )
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await?;
 
-    /// Delete a flashcard set
-    pub async fn delete_set(&self, set_id: &str) -> AppResult<()> {
-        validate_uuid(set_id, "Set ID")?;
+        let rows = sqlx::query(
+            "SELECT id, user_id, title, description, created_at, updated_at
+             FROM flashcard_sets
+             WHERE user_id = ? AND deleted_at IS NULL
+             ORDER BY updated_at DESC
+             LIMIT ? OFFSET ?"
+        )
+        .bind(user_id)
+        .bind(pagination.page_size as i64)
+        .bind(pagination.offset() as i64)
+        .fetch_all(&pool)
+        .await?;
 
-        // Try Supabase if online
-        if self.storage.is_online().await {
-            if let Some(supabase) = self.storage.supabase() {
-                supabase
-                    .delete("flashcard_sets")
-                    .await?
-                    .eq("id", set_id)
-                    .execute()
-                    .await
-                    .map_err(|e| AppError::Supabase(format!("Failed to delete set: {}", e)))?;
-            }
-        }
+        let sets = rows
+            .iter()
+            .map(|row| FlashcardSet {
+                id: row.get("id"),
+                user_id: row.get("user_id"),
+                title: row.get("title"),
+                description: row.get("description"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+            })
+            .collect();
 
-        // Delete locally
-        let set_id = set_id.to_string();
-        self.storage.sqlite().execute(move |conn| {
-            conn.execute("DELETE FROM flashcard_sets WHERE id = ?1", [&set_id])?;
-            Ok(())
-        }).await
+        Ok(PagedResult::new(sets, total as usize, pagination))
+    }
+
+    /// Move a flashcard set to the recycle bin; its cards go when it is
+    /// permanently deleted
+    pub async fn delete_set(&self, set_id: &str) -> AppResult<()> {
+        let set_id = validate_uuid(set_id, "Set ID")?;
+        recycle_bin::move_to_bin(&self.storage, "flashcard_sets", &set_id).await
     }
 
     /// Add a flashcard to a set
-    pub async fn add_flashcard(&self, request: CreateFlashcardRequest) -> AppResult<Flashcard> {
-        validate_uuid(&request.set_id, "Set ID")?;
+    pub async fn add_flashcard(&self, mut request: CreateFlashcardRequest) -> AppResult<Flashcard> {
+        request.set_id = validate_uuid(&request.set_id, "Set ID")?;
         let front = validate_flashcard_content(&request.front, "Front")?;
         let back = validate_flashcard_content(&request.back, "Back")?;
 
@@ -208,39 +310,35 @@ impl FlashcardService {
                     "created_at": flashcard.created_at,
                 });
 
-                supabase
-                    .insert("flashcards", &data.to_string())
-                    .await?
-                    .execute()
+                execute_checked(supabase.upsert("flashcards", &data.to_string(), "id").await?)
                     .await
                     .map_err(|e| AppError::Supabase(format!("Failed to add flashcard: {}", e)))?;
             }
         }
 
         // Save locally
-        self.storage.sqlite().execute(move |conn| {
-            conn.execute(
-                "INSERT INTO flashcards (id, set_id, front, back, created_at, synced, dirty)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-                rusqlite::params![
-                    &flashcard.id,
-                    &flashcard.set_id,
-                    &flashcard.front,
-                    &flashcard.back,
-                    &flashcard.created_at,
-                    if self.storage.is_online().await { 1 } else { 0 },
-                    if self.storage.is_online().await { 0 } else { 1 },
-                ],
-            )?;
-            Ok(())
-        }).await?;
+        let online = self.storage.is_online().await;
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query(
+            "INSERT INTO flashcards (id, set_id, front, back, created_at, synced, dirty)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&flashcard.id)
+        .bind(&flashcard.set_id)
+        .bind(&flashcard.front)
+        .bind(&flashcard.back)
+        .bind(&flashcard.created_at)
+        .bind(online as i64)
+        .bind(!online as i64)
+        .execute(&pool)
+        .await?;
 
         Ok(flashcard)
     }
 
     /// Get all flashcards in a set
     pub async fn get_flashcards(&self, set_id: &str) -> AppResult<Vec<Flashcard>> {
-        validate_uuid(set_id, "Set ID")?;
+        let set_id = &validate_uuid(set_id, "Set ID")?;
 
         // Try Supabase first if online
         if self.storage.is_online().await {
@@ -249,6 +347,7 @@ impl FlashcardService {
                     .select("flashcards")
                     .await?
                     .eq("set_id", set_id)
+                    .is("deleted_at", "null")
                     .execute()
                     .await
                     .map_err(|e| AppError::Supabase(format!("Failed to fetch flashcards: {}", e)))?;
@@ -260,54 +359,732 @@ impl FlashcardService {
         }
 
         // Fallback to local
-        let set_id = set_id.to_string();
-        self.storage.sqlite().execute(move |conn| {
-            let mut stmt = conn.prepare(
-                "SELECT id, set_id, front, back, created_at
-                 FROM flashcards
-                 WHERE set_id = ?1
-                 ORDER BY created_at ASC"
-            )?;
-
-            let flashcards = stmt
-                .query_map([&set_id], |row| {
-                    Ok(Flashcard {
-                        id: row.get(0)?,
-                        set_id: row.get(1)?,
-                        front: row.get(2)?,
-                        back: row.get(3)?,
-                        created_at: row.get(4)?,
-                    })
-                })?
-                .collect::<Result<Vec<_>, _>>()?;
-
-            Ok(flashcards)
-        }).await
+        let pool = self.storage.sqlite().get_pool().await?;
+        let rows = sqlx::query(
+            "SELECT id, set_id, front, back, created_at
+             FROM flashcards
+             WHERE set_id = ?
+             ORDER BY created_at ASC"
+        )
+        .bind(set_id)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows.iter().map(flashcard_from_row).collect())
     }
 
     /// Delete a flashcard
     pub async fn delete_flashcard(&self, flashcard_id: &str) -> AppResult<()> {
-        validate_uuid(flashcard_id, "Flashcard ID")?;
+        let flashcard_id = &validate_uuid(flashcard_id, "Flashcard ID")?;
 
-        // Try Supabase if online
-        if self.storage.is_online().await {
-            if let Some(supabase) = self.storage.supabase() {
-                supabase
-                    .delete("flashcards")
-                    .await?
-                    .eq("id", flashcard_id)
-                    .execute()
-                    .await
-                    .map_err(|e| AppError::Supabase(format!("Failed to delete flashcard: {}", e)))?;
+        // Tombstone the remote row, or queue that until the next sync when offline
+        self.sync.delete_remote("flashcards", flashcard_id).await?;
+
+        // Delete locally
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query("DELETE FROM flashcards WHERE id = ?")
+            .bind(flashcard_id)
+            .execute(&pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Start reviewing a user's flashcard set, picking and ordering its cards by `strategy`
+    pub async fn start_review_session(
+        &self,
+        set_id: &str,
+        user_id: &str,
+        strategy: ReviewStrategy,
+    ) -> AppResult<ReviewSession> {
+        let set_id = validate_uuid(set_id, "Set ID")?;
+        let user_id = validate_uuid(user_id, "User ID")?;
+
+        let session_id = Uuid::new_v4().to_string();
+        let created_at = Utc::now().to_rfc3339();
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        let mut tx = pool.begin().await?;
+
+        let owned: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM flashcard_sets WHERE id = ? AND user_id = ? AND deleted_at IS NULL)"
+        )
+        .bind(&set_id)
+        .bind(&user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if !owned {
+            return Err(AppError::NotFound(format!("Flashcard set {} not found", set_id)));
+        }
+
+        let rows = sqlx::query(
+            "SELECT f.id, f.set_id, f.front, f.back, f.created_at,
+                    r.ease_factor, r.interval_days, r.repetitions, r.due_at
+             FROM flashcards f
+             LEFT JOIN flashcard_reviews r ON r.flashcard_id = f.id
+             WHERE f.set_id = ?
+             ORDER BY f.created_at ASC"
+        )
+        .bind(&set_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let scheduled = rows
+            .iter()
+            .map(|row| ScheduledCard {
+                card: flashcard_from_row(row),
+                schedule: row.get::<Option<f64>, _>("ease_factor").map(|ease_factor| CardSchedule {
+                    ease_factor,
+                    interval_days: row.get("interval_days"),
+                    repetitions: row.get("repetitions"),
+                }),
+                due_at: row.get("due_at"),
+            })
+            .collect();
+
+        let cards = order_cards(scheduled, strategy, Utc::now());
+        if cards.is_empty() {
+            return Err(AppError::Validation("There are no cards to review in this set".to_string()));
+        }
+
+        let card_ids: Vec<&str> = cards.iter().map(|c| c.id.as_str()).collect();
+        sqlx::query(
+            "INSERT INTO review_sessions (id, user_id, set_id, strategy, card_ids, created_at)
+             VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&session_id)
+        .bind(&user_id)
+        .bind(&set_id)
+        .bind(strategy.as_str())
+        .bind(serde_json::to_string(&card_ids)?)
+        .bind(&created_at)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(ReviewSession {
+            id: session_id,
+            total: cards.len(),
+            cards,
+            created_at,
+        })
+    }
+
+    /// Record how well a card was recalled (SM-2 quality, 0-5) and reschedule it
+    ///
+    /// Reviewing a card again in the same session replaces the earlier answer.
+    pub async fn record_card_review(&self, session_id: &str, flashcard_id: &str, quality: u8) -> AppResult<ReviewProgress> {
+        let session_id = validate_uuid(session_id, "Session ID")?;
+        let flashcard_id = validate_uuid(flashcard_id, "Flashcard ID")?;
+        validate_range(quality as i32, "Quality", 0, 5)?;
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        let mut tx = pool.begin().await?;
+
+        let (card_ids, finished_at) = session_cards(&mut tx, &session_id).await?;
+        if finished_at.is_some() {
+            return Err(AppError::Validation("This review session has already finished".to_string()));
+        }
+        if !card_ids.contains(&flashcard_id) {
+            return Err(AppError::Validation("This card is not part of the review session".to_string()));
+        }
+
+        let current = sqlx::query("SELECT ease_factor, interval_days, repetitions FROM flashcard_reviews WHERE flashcard_id = ?")
+            .bind(&flashcard_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|row| CardSchedule {
+                ease_factor: row.get("ease_factor"),
+                interval_days: row.get("interval_days"),
+                repetitions: row.get("repetitions"),
+            })
+            .unwrap_or_default();
+        let next = sm2(current, quality);
+
+        let now = Utc::now();
+        let due_at = (now + Duration::days(next.interval_days)).to_rfc3339();
+        let now = now.to_rfc3339();
+
+        sqlx::query(
+            "INSERT INTO flashcard_reviews (flashcard_id, ease_factor, interval_days, repetitions, due_at, reviewed_at)
+             VALUES (?, ?, ?, ?, ?, ?)
+             ON CONFLICT(flashcard_id) DO UPDATE SET
+                 ease_factor = excluded.ease_factor,
+                 interval_days = excluded.interval_days,
+                 repetitions = excluded.repetitions,
+                 due_at = excluded.due_at,
+                 reviewed_at = excluded.reviewed_at"
+        )
+        .bind(&flashcard_id)
+        .bind(next.ease_factor)
+        .bind(next.interval_days)
+        .bind(next.repetitions)
+        .bind(&due_at)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO review_session_cards (session_id, flashcard_id, quality, reviewed_at)
+             VALUES (?, ?, ?, ?)"
+        )
+        .bind(&session_id)
+        .bind(&flashcard_id)
+        .bind(quality as i64)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+
+        let qualities = session_qualities(&mut tx, &session_id).await?;
+        tx.commit().await?;
+
+        Ok(ReviewProgress {
+            reviewed: qualities.len(),
+            remaining: card_ids.len().saturating_sub(qualities.len()),
+            session_score: session_score(&qualities),
+        })
+    }
+
+    /// Close a review session and total up its answers
+    ///
+    /// Finishing an already finished session returns the same summary.
+    pub async fn finish_session(&self, session_id: &str) -> AppResult<SessionSummary> {
+        let session_id = validate_uuid(session_id, "Session ID")?;
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        let mut tx = pool.begin().await?;
+
+        let (card_ids, finished_at) = session_cards(&mut tx, &session_id).await?;
+        let finished_at = match finished_at {
+            Some(finished_at) => finished_at,
+            None => {
+                let now = Utc::now().to_rfc3339();
+                sqlx::query("UPDATE review_sessions SET finished_at = ? WHERE id = ?")
+                    .bind(&now)
+                    .bind(&session_id)
+                    .execute(&mut *tx)
+                    .await?;
+                now
             }
+        };
+
+        let created_at: String = sqlx::query_scalar("SELECT created_at FROM review_sessions WHERE id = ?")
+            .bind(&session_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let qualities = session_qualities(&mut tx, &session_id).await?;
+        tx.commit().await?;
+
+        let duration_secs = match (DateTime::parse_from_rfc3339(&created_at), DateTime::parse_from_rfc3339(&finished_at)) {
+            (Ok(start), Ok(end)) => (end - start).num_seconds().max(0),
+            _ => 0,
+        };
+
+        Ok(SessionSummary {
+            session_id,
+            total: card_ids.len(),
+            reviewed: qualities.len(),
+            recalled: qualities.iter().filter(|q| **q >= 3).count(),
+            average_quality: if qualities.is_empty() {
+                0.0
+            } else {
+                qualities.iter().map(|q| *q as f64).sum::<f64>() / qualities.len() as f64
+            },
+            session_score: session_score(&qualities),
+            duration_secs,
+            finished_at,
+        })
+    }
+
+    /// Generate a flashcard set from an indexed document using the LLM
+    ///
+    /// Cards that fail validation are dropped rather than failing the whole
+    /// set; the number of requested cards not created is reported as `skipped`.
+    pub async fn generate_from_document(
+        &self,
+        rag: &RAGService,
+        llm: &LLMService,
+        model: &str,
+        user_id: &str,
+        document_id: &str,
+        count: i32,
+    ) -> AppResult<GeneratedFlashcards> {
+        let user_id = &validate_uuid(user_id, "User ID")?;
+        validate_positive_integer(count, "Number of flashcards")?;
+
+        let title = rag.document_title(document_id).await?;
+        let chunks = rag.document_chunks(document_id).await?;
+        if chunks.is_empty() {
+            return Err(AppError::NotFound(format!("Document {} has no indexed chunks", document_id)));
         }
 
-        // Delete locally
-        let flashcard_id = flashcard_id.to_string();
-        self.storage.sqlite().execute(move |conn| {
-            conn.execute("DELETE FROM flashcards WHERE id = ?1", [&flashcard_id])?;
-            Ok(())
-        }).await
+        let system_prompt = "You are an expert legal educator creating study flashcards for law students.
+
+Guidelines:
+- Each card tests one legal concept, rule, element, or holding from the material
+- The front is a concise question or term; the back is an accurate, self-contained answer
+- Only use information found in the provided material
+- Format your response as JSON";
+
+        let render = |material: &str| {
+            format!(
+                "Create {} flashcards from the following material titled \"{}\":\n\n{}\n\nProvide your response as a JSON array:\n[\n  {{\"front\": \"Question or term\", \"back\": \"Answer or definition\"}}\n]",
+                count, title, material
+            )
+        };
+
+        let options = ChatOptions {
+            model: Some(model.to_string()),
+            temperature: Some(0.4),
+            max_tokens: Some(3000),
+            task: Some("flashcards".to_string()),
+            ..Default::default()
+        };
+
+        // Keep the document text within the model's context window
+        let material = chunks.iter().map(|c| c.text.as_str()).collect::<Vec<_>>().join("\n\n");
+        let template = render("");
+        let budget = tokens::prompt_budget(model, options.max_tokens.unwrap_or(0), &[system_prompt, &template]);
+        let material = tokens::truncate_to_tokens(&material, budget, model);
+
+        let messages = vec![
+            Message {
+                role: "system".to_string(),
+                content: system_prompt.to_string(),
+            },
+            Message {
+                role: "user".to_string(),
+                content: render(&material),
+            },
+        ];
+
+        let response = llm.chat(messages, options).await?;
+
+        let mut pairs = parse_flashcard_pairs(&response)?;
+        pairs.truncate(count as usize);
+        if pairs.is_empty() {
+            return Err(AppError::Llm("The model did not return any valid flashcards".to_string()));
+        }
+
+        let set = self
+            .create_set(CreateFlashcardSetRequest {
+                user_id: user_id.to_string(),
+                title: title.clone(),
+                description: Some(format!("Generated from {}", title)),
+            })
+            .await?;
+
+        let mut flashcards = Vec::with_capacity(pairs.len());
+        for (front, back) in pairs {
+            flashcards.push(
+                self.add_flashcard(CreateFlashcardRequest {
+                    set_id: set.id.clone(),
+                    front,
+                    back,
+                })
+                .await?,
+            );
+        }
+
+        Ok(GeneratedFlashcards {
+            skipped: count as usize - flashcards.len(),
+            set,
+            flashcards,
+        })
+    }
+}
+
+/// Card IDs of a review session in review order, and when it finished
+async fn session_cards(tx: &mut Transaction<'_, Sqlite>, session_id: &str) -> AppResult<(Vec<String>, Option<String>)> {
+    let (card_ids, finished_at): (String, Option<String>) =
+        sqlx::query_as("SELECT card_ids, finished_at FROM review_sessions WHERE id = ?")
+            .bind(session_id)
+            .fetch_optional(&mut **tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Review session {} not found", session_id)))?;
+
+    Ok((serde_json::from_str(&card_ids)?, finished_at))
+}
+
+/// Qualities of the cards reviewed so far in a session
+async fn session_qualities(tx: &mut Transaction<'_, Sqlite>, session_id: &str) -> AppResult<Vec<u8>> {
+    let qualities: Vec<i64> = sqlx::query_scalar("SELECT quality FROM review_session_cards WHERE session_id = ?")
+        .bind(session_id)
+        .fetch_all(&mut **tx)
+        .await?;
+    Ok(qualities.into_iter().map(|q| q as u8).collect())
+}
+
+fn flashcard_from_row(row: &sqlx::sqlite::SqliteRow) -> Flashcard {
+    Flashcard {
+        id: row.get("id"),
+        set_id: row.get("set_id"),
+        front: row.get("front"),
+        back: row.get("back"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Sum of `qualities` as a fraction of a perfect 5 on every card
+fn session_score(qualities: &[u8]) -> f64 {
+    if qualities.is_empty() {
+        return 0.0;
+    }
+    qualities.iter().map(|q| *q as f64).sum::<f64>() / (qualities.len() as f64 * 5.0)
+}
+
+/// Next SM-2 schedule after answering with `quality` (0-5)
+///
+/// A lapse (quality below 3) starts the card over at a one-day interval; the
+/// ease factor adjusts on every answer and never drops below 1.3.
+fn sm2(schedule: CardSchedule, quality: u8) -> CardSchedule {
+    let q = quality.min(5) as f64;
+    let ease_factor = (schedule.ease_factor + 0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)).max(1.3);
+
+    if quality < 3 {
+        return CardSchedule {
+            ease_factor,
+            interval_days: 1,
+            repetitions: 0,
+        };
     }
+
+    let interval_days = match schedule.repetitions {
+        0 => 1,
+        1 => 6,
+        _ => (schedule.interval_days as f64 * schedule.ease_factor).round() as i64,
+    };
+    CardSchedule {
+        ease_factor,
+        interval_days,
+        repetitions: schedule.repetitions + 1,
+    }
+}
+
+/// The cards a session with `strategy` reviews, in order
+fn order_cards(mut cards: Vec<ScheduledCard>, strategy: ReviewStrategy, now: DateTime<Utc>) -> Vec<Flashcard> {
+    match strategy {
+        ReviewStrategy::AllCards => {}
+        ReviewStrategy::DueOnly => cards.retain(|c| {
+            c.due_at
+                .as_deref()
+                .and_then(|due| DateTime::parse_from_rfc3339(due).ok())
+                .is_none_or(|due| due <= now)
+        }),
+        ReviewStrategy::WeakestFirst => cards.sort_by(|a, b| {
+            let ease = |c: &ScheduledCard| c.schedule.unwrap_or_default().ease_factor;
+            ease(a).total_cmp(&ease(b))
+        }),
+        ReviewStrategy::Random => cards.shuffle(&mut rand::thread_rng()),
+    }
+
+    cards.into_iter().map(|c| c.card).collect()
+}
+
+/// Extract valid front/back pairs from an LLM response
+///
+/// Accepts a bare JSON array or an object with a `flashcards` array, optionally
+/// inside a markdown code block. Entries that fail validation are skipped.
+fn parse_flashcard_pairs(response: &str) -> AppResult<Vec<(String, String)>> {
+    let value: serde_json::Value = serde_json::from_str(response)
+        .ok()
+        .or_else(|| {
+            regex::Regex::new(r"```(?:json)?\n([\s\S]*?)```")
+                .ok()
+                .and_then(|re| re.captures(response))
+                .and_then(|caps| caps.get(1))
+                .and_then(|m| serde_json::from_str(m.as_str()).ok())
+        })
+        .ok_or_else(|| AppError::Llm("Failed to parse LLM response as JSON".to_string()))?;
+
+    let cards = value
+        .as_array()
+        .or_else(|| value["flashcards"].as_array())
+        .ok_or_else(|| AppError::Llm("Invalid flashcards format".to_string()))?;
+
+    Ok(cards
+        .iter()
+        .filter_map(|card| {
+            let front = validate_flashcard_content(card["front"].as_str()?, "Front").ok()?;
+            let back = validate_flashcard_content(card["back"].as_str()?, "Back").ok()?;
+            Some((front, back))
+        })
+        .collect())
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub async fn get_flashcard_sets(
+    state: State<'_, AppState>,
+    user_id: String,
+    pagination: Option<PaginationOptions>,
+) -> CommandResult<PagedResult<FlashcardSet>> {
+    let service = state.flashcard_service().await?;
+    service.get_sets(&user_id, pagination).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn create_flashcard_set(
+    state: State<'_, AppState>,
+    user_id: String,
+    title: String,
+    description: Option<String>,
+) -> CommandResult<FlashcardSet> {
+    let service = state.flashcard_service().await?;
+    service
+        .create_set(CreateFlashcardSetRequest {
+            user_id,
+            title,
+            description,
+        })
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn delete_flashcard_set(state: State<'_, AppState>, set_id: String) -> CommandResult<()> {
+    let service = state.flashcard_service().await?;
+    service.delete_set(&set_id).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn add_flashcard(
+    state: State<'_, AppState>,
+    set_id: String,
+    front: String,
+    back: String,
+) -> CommandResult<Flashcard> {
+    let service = state.flashcard_service().await?;
+    service
+        .add_flashcard(CreateFlashcardRequest { set_id, front, back })
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn get_flashcards(state: State<'_, AppState>, set_id: String) -> CommandResult<Vec<Flashcard>> {
+    let service = state.flashcard_service().await?;
+    service.get_flashcards(&set_id).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn delete_flashcard(state: State<'_, AppState>, flashcard_id: String) -> CommandResult<()> {
+    let service = state.flashcard_service().await?;
+    service.delete_flashcard(&flashcard_id).await.map_err(CommandError::from)
 }
 
+#[tauri::command]
+pub async fn generate_flashcards_from_document(
+    state: State<'_, AppState>,
+    user_id: String,
+    document_id: String,
+    count: i32,
+) -> CommandResult<GeneratedFlashcards> {
+    let service = state.flashcard_service().await?;
+    let rag = state.rag_service().await?;
+    let llm = state.llm_service().await?;
+    let quiz_model = state.config.read().await.models.quiz_model.clone();
+
+    service
+        .generate_from_document(&rag, &llm, &quiz_model, &user_id, &document_id, count)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn start_review_session(
+    state: State<'_, AppState>,
+    set_id: String,
+    user_id: String,
+    strategy: ReviewStrategy,
+) -> CommandResult<ReviewSession> {
+    let service = state.flashcard_service().await?;
+    service
+        .start_review_session(&set_id, &user_id, strategy)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn record_card_review(
+    state: State<'_, AppState>,
+    session_id: String,
+    flashcard_id: String,
+    quality: u8,
+) -> CommandResult<ReviewProgress> {
+    let service = state.flashcard_service().await?;
+    service
+        .record_card_review(&session_id, &flashcard_id, quality)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn finish_review_session(state: State<'_, AppState>, session_id: String) -> CommandResult<SessionSummary> {
+    let service = state.flashcard_service().await?;
+    service.finish_session(&session_id).await.map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{test_cache, IN_MEMORY_DB};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_parse_flashcard_pairs_skips_invalid_entries() {
+        let response = r#"```json
+[
+  {"front": "What is consideration?", "back": "Something of value exchanged between parties"},
+  {"front": "", "back": "Missing front"},
+  {"front": "Offer"}
+]
+```"#;
+
+        let pairs = parse_flashcard_pairs(response).unwrap();
+
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].0, "What is consideration?");
+    }
+
+    #[test]
+    fn test_parse_flashcard_pairs_wrapped_object() {
+        let response = r#"{"flashcards": [{"front": "Mens rea", "back": "The guilty mind"}]}"#;
+        assert_eq!(parse_flashcard_pairs(response).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_flashcard_pairs_rejects_non_json() {
+        assert!(parse_flashcard_pairs("Here are your flashcards!").is_err());
+    }
+
+    fn scheduled(id: &str, ease_factor: Option<f64>, due_at: Option<&str>) -> ScheduledCard {
+        ScheduledCard {
+            card: Flashcard {
+                id: id.to_string(),
+                set_id: "set".to_string(),
+                front: "Front".to_string(),
+                back: "Back".to_string(),
+                created_at: "2024-03-01T00:00:00Z".to_string(),
+            },
+            schedule: ease_factor.map(|ease_factor| CardSchedule { ease_factor, ..Default::default() }),
+            due_at: due_at.map(str::to_string),
+        }
+    }
+
+    fn ids(cards: &[Flashcard]) -> Vec<&str> {
+        cards.iter().map(|c| c.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sm2_grows_the_interval_on_recall() {
+        let first = sm2(CardSchedule::default(), 5);
+        assert_eq!((first.interval_days, first.repetitions), (1, 1));
+        assert!((first.ease_factor - 2.6).abs() < 1e-9);
+
+        let second = sm2(first, 4);
+        assert_eq!((second.interval_days, second.repetitions), (6, 2));
+
+        let third = sm2(second, 4);
+        assert_eq!(third.interval_days, (6.0 * second.ease_factor).round() as i64);
+    }
+
+    #[test]
+    fn test_sm2_lapse_restarts_the_card() {
+        let learned = CardSchedule { ease_factor: 1.4, interval_days: 30, repetitions: 5 };
+        let lapsed = sm2(learned, 0);
+
+        assert_eq!((lapsed.interval_days, lapsed.repetitions), (1, 0));
+        assert_eq!(lapsed.ease_factor, 1.3);
+    }
+
+    #[test]
+    fn test_order_cards_by_strategy() {
+        let now = DateTime::parse_from_rfc3339("2024-03-10T00:00:00Z").unwrap().with_timezone(&Utc);
+        let cards = || {
+            vec![
+                scheduled("new", None, None),
+                scheduled("due", Some(2.0), Some("2024-03-09T00:00:00Z")),
+                scheduled("later", Some(1.5), Some("2024-03-20T00:00:00Z")),
+            ]
+        };
+
+        assert_eq!(ids(&order_cards(cards(), ReviewStrategy::AllCards, now)), vec!["new", "due", "later"]);
+        assert_eq!(ids(&order_cards(cards(), ReviewStrategy::DueOnly, now)), vec!["new", "due"]);
+        assert_eq!(ids(&order_cards(cards(), ReviewStrategy::WeakestFirst, now)), vec!["later", "due", "new"]);
+
+        let mut shuffled = ids(&order_cards(cards(), ReviewStrategy::Random, now)).into_iter().map(str::to_string).collect::<Vec<_>>();
+        shuffled.sort();
+        assert_eq!(shuffled, vec!["due", "later", "new"]);
+    }
+
+    #[test]
+    fn test_session_score() {
+        assert_eq!(session_score(&[]), 0.0);
+        assert_eq!(session_score(&[5, 5]), 1.0);
+        assert_eq!(session_score(&[5, 0]), 0.5);
+    }
+
+    #[test]
+    fn test_review_strategy_serde() {
+        let strategy: ReviewStrategy = serde_json::from_value(serde_json::json!("weakest_first")).unwrap();
+        assert_eq!(strategy, ReviewStrategy::WeakestFirst);
+        assert_eq!(serde_json::to_value(ReviewStrategy::DueOnly).unwrap(), "due_only");
+    }
+
+    #[tokio::test]
+    async fn test_review_sessions_are_persisted_and_reschedule_cards() {
+        let cache = test_cache().await;
+        let pool = cache.get_pool().await.unwrap();
+        let storage = HybridStorage::new(PathBuf::from(IN_MEMORY_DB), None, None).with_sqlite(cache);
+        let service = FlashcardService::new(storage.clone(), Arc::new(SyncManager::new(Arc::new(storage))));
+
+        let user_id = "11111111-1111-4111-8111-111111111111";
+        let set = service
+            .create_set(CreateFlashcardSetRequest {
+                user_id: user_id.to_string(),
+                title: "Contracts".to_string(),
+                description: None,
+            })
+            .await
+            .unwrap();
+        for front in ["Offer", "Acceptance"] {
+            let card = CreateFlashcardRequest {
+                set_id: set.id.clone(),
+                front: front.to_string(),
+                back: "A definition".to_string(),
+            };
+            service.add_flashcard(card).await.unwrap();
+        }
+
+        let session = service.start_review_session(&set.id, user_id, ReviewStrategy::AllCards).await.unwrap();
+        assert_eq!(session.total, 2);
+        let stored: String = sqlx::query_scalar("SELECT card_ids FROM review_sessions WHERE id = ?")
+            .bind(&session.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, serde_json::to_string(&ids(&session.cards)).unwrap());
+
+        let progress = service.record_card_review(&session.id, &session.cards[0].id, 4).await.unwrap();
+        assert_eq!((progress.reviewed, progress.remaining), (1, 1));
+        let (interval_days, repetitions): (i64, i64) =
+            sqlx::query_as("SELECT interval_days, repetitions FROM flashcard_reviews WHERE flashcard_id = ?")
+                .bind(&session.cards[0].id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!((interval_days, repetitions), (1, 1));
+
+        let summary = service.finish_session(&session.id).await.unwrap();
+        assert_eq!((summary.total, summary.reviewed, summary.recalled), (2, 1, 1));
+        assert_eq!(service.finish_session(&session.id).await.unwrap().finished_at, summary.finished_at);
+        assert!(matches!(
+            service.record_card_review(&session.id, &session.cards[1].id, 5).await,
+            Err(AppError::Validation(_))
+        ));
+
+        // Only cards never reviewed or now due are picked for the next session
+        let due = service.start_review_session(&set.id, user_id, ReviewStrategy::DueOnly).await.unwrap();
+        assert_eq!(ids(&due.cards), vec![session.cards[1].id.as_str()]);
+    }
+}
@@ -0,0 +1,577 @@
+/**
+ * Ingest Queue Module
+ * Queues files for ingestion and indexes them one at a time in a background worker
+ */
+
+use crate::db::HybridStorage;
+use crate::document::{is_cancelled, ChunkingOptions, DocumentMetadata};
+use crate::error::{AppError, AppResult, CommandResult};
+use crate::llm::LLMService;
+use crate::logging::LogLevel;
+use crate::rag::{self, IngestResult, RagState};
+use crate::state::AppState;
+use crate::validation::validate_uuid;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// Event emitted with the `IngestJob` whenever a job is queued or changes status
+const INGEST_JOB_EVENT: &str = "rag://ingest-job";
+
+/// How long the worker waits before reading the queue again after a database error
+const WORKER_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IngestJobStatus {
+    Pending,
+    Processing,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl IngestJobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Processing => "processing",
+            Self::Done => "done",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(status: &str) -> Option<Self> {
+        [Self::Pending, Self::Processing, Self::Done, Self::Failed, Self::Cancelled]
+            .into_iter()
+            .find(|s| s.as_str() == status)
+    }
+}
+
+/// A file queued for ingestion
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestJob {
+    pub id: String,
+    pub path: String,
+    pub title: String,
+    pub user_id: Option<String>,
+    /// Document the file is stored as; once done, the existing document if
+    /// the user had already indexed the same content
+    pub document_id: String,
+    pub status: IngestJobStatus,
+    /// Why the job failed
+    pub error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A job with the options it was queued with
+struct QueuedJob {
+    job: IngestJob,
+    metadata: DocumentMetadata,
+    chunking: Option<ChunkingOptions>,
+    allow_duplicate: bool,
+}
+
+/// Wakes the ingest worker when a job is queued
+#[derive(Clone, Default)]
+pub struct IngestQueue {
+    wake: Arc<Notify>,
+}
+
+impl IngestQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tell the worker a job is waiting; a worker that is busy picks it up next
+    fn notify(&self) {
+        self.wake.notify_one();
+    }
+}
+
+/// Check that `path` is a file `process_file` can ingest
+fn validate_ingest_path(path: &str) -> AppResult<()> {
+    let path = Path::new(path);
+    if !path.is_file() {
+        return Err(AppError::FileNotFound(path.display().to_string()));
+    }
+
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    if !extension.is_empty() && !rag::INGESTABLE_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(AppError::DocumentProcessing(format!(
+            "Unsupported file type '.{}'. Supported types: .{}",
+            extension,
+            rag::INGESTABLE_EXTENSIONS.join(", .")
+        )));
+    }
+    Ok(())
+}
+
+const JOB_COLUMNS: &str =
+    "id, path, document_id, metadata, chunking, allow_duplicate, status, error, created_at, updated_at";
+
+fn queued_job(row: &sqlx::sqlite::SqliteRow) -> AppResult<QueuedJob> {
+    let metadata: DocumentMetadata = serde_json::from_str(row.get("metadata"))?;
+    let chunking = row
+        .get::<Option<String>, _>("chunking")
+        .map(|json| serde_json::from_str(&json))
+        .transpose()?;
+    let status: String = row.get("status");
+
+    Ok(QueuedJob {
+        job: IngestJob {
+            id: row.get("id"),
+            path: row.get("path"),
+            title: metadata.title.clone(),
+            user_id: metadata.user_id.clone(),
+            document_id: row.get("document_id"),
+            status: IngestJobStatus::parse(&status)
+                .ok_or_else(|| AppError::Internal(format!("Unknown ingest job status '{}'", status)))?,
+            error: row.get("error"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        },
+        metadata,
+        chunking,
+        allow_duplicate: row.get::<i64, _>("allow_duplicate") != 0,
+    })
+}
+
+async fn get_job(pool: &Pool<Sqlite>, job_id: &str) -> AppResult<QueuedJob> {
+    let row = sqlx::query(&format!("SELECT {} FROM ingest_jobs WHERE id = ?", JOB_COLUMNS))
+        .bind(job_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Ingest job {} not found", job_id)))?;
+    queued_job(&row)
+}
+
+/// Queue a file, recording its document as 'pending' until the worker reaches it
+///
+/// The title defaults to the file name.
+pub async fn enqueue_job(
+    pool: &Pool<Sqlite>,
+    path: &str,
+    metadata: DocumentMetadata,
+    allow_duplicate: bool,
+    chunking: Option<ChunkingOptions>,
+) -> AppResult<IngestJob> {
+    let mut metadata = metadata;
+    if metadata.title.trim().is_empty() {
+        metadata.title = rag::file_title(path);
+    }
+    let document_id = Uuid::new_v4().to_string();
+    metadata.document_id = Some(document_id.clone());
+    let job_id = Uuid::new_v4().to_string();
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO documents (id, user_id, case_id, title, document_type, embedding_status, total_chunks, source_url, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, 'pending', 0, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)"
+    )
+    .bind(&document_id)
+    .bind(&metadata.user_id)
+    .bind(&metadata.case_id)
+    .bind(&metadata.title)
+    .bind(&metadata.document_type)
+    .bind(&metadata.source_url)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO ingest_jobs (id, path, document_id, metadata, chunking, allow_duplicate, status, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, 'pending', CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)"
+    )
+    .bind(&job_id)
+    .bind(path)
+    .bind(&document_id)
+    .bind(serde_json::to_string(&metadata)?)
+    .bind(chunking.as_ref().map(serde_json::to_string).transpose()?)
+    .bind(allow_duplicate)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(get_job(pool, &job_id).await?.job)
+}
+
+/// Mark the oldest pending job and its document as processing
+async fn claim_next_job(pool: &Pool<Sqlite>) -> AppResult<Option<QueuedJob>> {
+    let job_id: Option<String> =
+        sqlx::query_scalar("SELECT id FROM ingest_jobs WHERE status = 'pending' ORDER BY rowid LIMIT 1")
+            .fetch_optional(pool)
+            .await?;
+    let Some(job_id) = job_id else {
+        return Ok(None);
+    };
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE ingest_jobs SET status = 'processing', updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(&job_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // A document completed before the app last closed keeps its status
+    sqlx::query(
+        "UPDATE documents SET embedding_status = 'processing', updated_at = CURRENT_TIMESTAMP
+         WHERE id = (SELECT document_id FROM ingest_jobs WHERE id = ?) AND embedding_status = 'pending'"
+    )
+    .bind(&job_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    get_job(pool, &job_id).await.map(Some)
+}
+
+/// Record how a claimed job ended
+///
+/// When the content was already indexed the job points at the existing
+/// document and the placeholder is removed; when it failed the document is
+/// marked 'failed'.
+async fn finish_job(pool: &Pool<Sqlite>, job: &IngestJob, outcome: &AppResult<IngestResult>) -> AppResult<IngestJob> {
+    let mut tx = pool.begin().await?;
+
+    match outcome {
+        Ok(result) => {
+            if result.doc_id != job.document_id {
+                sqlx::query("DELETE FROM documents WHERE id = ? AND embedding_status IN ('pending', 'processing')")
+                    .bind(&job.document_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            sqlx::query(
+                "UPDATE ingest_jobs SET status = 'done', document_id = ?, error = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+            )
+            .bind(&result.doc_id)
+            .bind(&job.id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        // Stopped by `cancel_ingestion`; nothing of the document is kept
+        Err(e) if is_cancelled(e) => {
+            sqlx::query("DELETE FROM documents WHERE id = ? AND embedding_status IN ('pending', 'processing')")
+                .bind(&job.document_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE ingest_jobs SET status = 'cancelled', updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(&job.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        Err(e) => {
+            sqlx::query("UPDATE documents SET embedding_status = 'failed', updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(&job.document_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE ingest_jobs SET status = 'failed', error = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(e.to_string())
+                .bind(&job.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(get_job(pool, &job.id).await?.job)
+}
+
+/// Cancel a job that hasn't started, removing its placeholder document
+pub async fn cancel_job(pool: &Pool<Sqlite>, job_id: &str) -> AppResult<IngestJob> {
+    let job = get_job(pool, job_id).await?.job;
+    if job.status != IngestJobStatus::Pending {
+        return Err(AppError::Validation(format!(
+            "Only pending jobs can be cancelled; this job is {}",
+            job.status.as_str()
+        )));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE ingest_jobs SET status = 'cancelled', updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(job_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM documents WHERE id = ? AND embedding_status = 'pending'")
+        .bind(&job.document_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(get_job(pool, job_id).await?.job)
+}
+
+/// Return jobs left processing when the app closed to the queue
+async fn requeue_interrupted_jobs(pool: &Pool<Sqlite>) -> AppResult<u64> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "UPDATE documents SET embedding_status = 'pending', updated_at = CURRENT_TIMESTAMP
+         WHERE embedding_status = 'processing'
+           AND id IN (SELECT document_id FROM ingest_jobs WHERE status = 'processing')"
+    )
+    .execute(&mut *tx)
+    .await?;
+    let requeued = sqlx::query("UPDATE ingest_jobs SET status = 'pending', updated_at = CURRENT_TIMESTAMP WHERE status = 'processing'")
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    tx.commit().await?;
+    Ok(requeued)
+}
+
+/// All jobs, most recently queued first
+pub async fn list_jobs(pool: &Pool<Sqlite>) -> AppResult<Vec<IngestJob>> {
+    let rows = sqlx::query(&format!("SELECT {} FROM ingest_jobs ORDER BY rowid DESC", JOB_COLUMNS))
+        .fetch_all(pool)
+        .await?;
+    rows.iter().map(|row| queued_job(row).map(|q| q.job)).collect()
+}
+
+/// Work through queued jobs one at a time for the life of the app, emitting
+/// `rag://ingest-job` as each starts and ends
+///
+/// Jobs left processing when the app last closed are queued again first.
+pub fn spawn_ingest_worker(app: AppHandle, storage: HybridStorage, rag: RagState, llm: LLMService, queue: IngestQueue) {
+    tauri::async_runtime::spawn(async move {
+        let pool = match storage.sqlite().get_pool().await {
+            Ok(pool) => pool,
+            Err(e) => {
+                crate::logging::log(LogLevel::Error, "ingest", &format!("Ingest worker not started: {}", e));
+                return;
+            }
+        };
+
+        match requeue_interrupted_jobs(&pool).await {
+            Ok(0) => {}
+            Ok(requeued) => crate::logging::log(LogLevel::Info, "ingest", &format!("Requeued {} interrupted ingest jobs", requeued)),
+            Err(e) => crate::logging::log(LogLevel::Warn, "ingest", &format!("Failed to requeue interrupted jobs: {}", e)),
+        }
+
+        loop {
+            let queued = match claim_next_job(&pool).await {
+                Ok(Some(queued)) => queued,
+                Ok(None) => {
+                    queue.wake.notified().await;
+                    continue;
+                }
+                Err(e) => {
+                    crate::logging::log(LogLevel::Warn, "ingest", &format!("Failed to read the ingest queue: {}", e));
+                    tokio::time::sleep(WORKER_RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            let _ = app.emit(INGEST_JOB_EVENT, &queued.job);
+
+            // Registered like `ingest_document`, so `cancel_ingestion` can stop the job part way
+            let doc_id = queued.metadata.document_id.clone().unwrap_or_default();
+            let state = app.try_state::<AppState>().map(|state| state.inner().clone());
+            let cancel = match &state {
+                Some(state) => Some(state.start_ingestion(&doc_id).await),
+                None => None,
+            };
+
+            // Follow config reloads; the startup service only stands in without an AppState
+            let llm = match &state {
+                Some(state) => match state.configured_llm().await {
+                    Ok(llm) => llm,
+                    Err(e) => {
+                        crate::logging::log(LogLevel::Warn, "ingest", &format!("Using the startup LLM config: {}", e));
+                        llm.clone()
+                    }
+                },
+                None => llm.clone(),
+            };
+
+            let chunking = queued.chunking.clone().unwrap_or_else(|| llm.chunking_options().clone());
+            let outcome = rag::ingest_file(
+                &app,
+                &pool,
+                &rag,
+                &llm,
+                &queued.job.path,
+                &queued.metadata,
+                queued.allow_duplicate,
+                &chunking,
+                cancel.as_ref(),
+            )
+            .await;
+            if let Some(state) = &state {
+                state.finish_ingestion(&doc_id).await;
+            }
+            if let Err(e) = &outcome {
+                crate::logging::log(LogLevel::Warn, "ingest", &format!("Failed to ingest {}: {}", queued.job.path, e));
+            }
+
+            match finish_job(&pool, &queued.job, &outcome).await {
+                Ok(job) => {
+                    let _ = app.emit(INGEST_JOB_EVENT, &job);
+                }
+                Err(e) => crate::logging::log(
+                    LogLevel::Error,
+                    "ingest",
+                    &format!("Failed to record the outcome of ingest job {}: {}", queued.job.id, e),
+                ),
+            }
+        }
+    });
+}
+
+// Tauri Commands
+
+/// Queue a file for background ingestion, returning its job without waiting
+///
+/// `metadata` and `chunking` are as for `ingest_pdf`.
+#[tauri::command]
+pub async fn queue_document_ingest(
+    app: AppHandle,
+    storage: State<'_, HybridStorage>,
+    queue: State<'_, IngestQueue>,
+    path: String,
+    metadata: DocumentMetadata,
+    allow_duplicate: Option<bool>,
+    chunking: Option<ChunkingOptions>,
+) -> CommandResult<IngestJob> {
+    validate_ingest_path(&path)?;
+    let pool = storage.sqlite().get_pool().await?;
+
+    let job = enqueue_job(&pool, &path, metadata, allow_duplicate.unwrap_or(false), chunking).await?;
+    queue.notify();
+    let _ = app.emit(INGEST_JOB_EVENT, &job);
+
+    Ok(job)
+}
+
+#[tauri::command]
+pub async fn get_ingest_jobs(storage: State<'_, HybridStorage>) -> CommandResult<Vec<IngestJob>> {
+    let pool = storage.sqlite().get_pool().await?;
+    Ok(list_jobs(&pool).await?)
+}
+
+#[tauri::command]
+pub async fn cancel_ingest_job(
+    app: AppHandle,
+    storage: State<'_, HybridStorage>,
+    job_id: String,
+) -> CommandResult<IngestJob> {
+    let job_id = validate_uuid(&job_id, "Job ID")?;
+    let pool = storage.sqlite().get_pool().await?;
+
+    let job = cancel_job(&pool, &job_id).await?;
+    let _ = app.emit(INGEST_JOB_EVENT, &job);
+
+    Ok(job)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    fn metadata(title: &str) -> DocumentMetadata {
+        DocumentMetadata {
+            user_id: Some("user-a".to_string()),
+            case_id: None,
+            title: title.to_string(),
+            document_type: "user_case".to_string(),
+            document_id: None,
+            source_url: None,
+        }
+    }
+
+    async fn embedding_status(pool: &Pool<Sqlite>, doc_id: &str) -> Option<String> {
+        sqlx::query_scalar("SELECT embedding_status FROM documents WHERE id = ?")
+            .bind(doc_id)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_jobs_move_through_statuses_in_queue_order() {
+        let pool = test_pool().await;
+
+        let first = enqueue_job(&pool, "/cases/smith.pdf", metadata(""), false, None).await.unwrap();
+        let second = enqueue_job(&pool, "/cases/jones.txt", metadata("Jones"), false, None).await.unwrap();
+        assert_eq!(first.title, "smith.pdf");
+        assert_eq!(first.status, IngestJobStatus::Pending);
+        assert_eq!(embedding_status(&pool, &first.document_id).await.as_deref(), Some("pending"));
+
+        let claimed = claim_next_job(&pool).await.unwrap().unwrap();
+        assert_eq!(claimed.job.id, first.id);
+        assert_eq!(claimed.metadata.document_id.as_deref(), Some(first.document_id.as_str()));
+        assert_eq!(embedding_status(&pool, &first.document_id).await.as_deref(), Some("processing"));
+        assert!(cancel_job(&pool, &first.id).await.is_err());
+
+        let failed = finish_job(&pool, &claimed.job, &Err(AppError::DocumentProcessing("No content found in file".to_string())))
+            .await
+            .unwrap();
+        assert_eq!(failed.status, IngestJobStatus::Failed);
+        assert!(failed.error.unwrap().contains("No content"));
+        assert_eq!(embedding_status(&pool, &first.document_id).await.as_deref(), Some("failed"));
+
+        // Already indexed: the job points at the existing document and the placeholder goes
+        let claimed = claim_next_job(&pool).await.unwrap().unwrap();
+        assert_eq!(claimed.job.id, second.id);
+        let existing = IngestResult {
+            doc_id: "existing".to_string(),
+            message: String::new(),
+            total_chunks: 3,
+            already_ingested: true,
+        };
+        let done = finish_job(&pool, &claimed.job, &Ok(existing)).await.unwrap();
+        assert_eq!((done.status, done.document_id.as_str()), (IngestJobStatus::Done, "existing"));
+        assert_eq!(embedding_status(&pool, &second.document_id).await, None);
+
+        assert!(claim_next_job(&pool).await.unwrap().is_none());
+        let ids: Vec<String> = list_jobs(&pool).await.unwrap().into_iter().map(|j| j.id).collect();
+        assert_eq!(ids, vec![second.id, first.id]);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_and_requeue() {
+        let pool = test_pool().await;
+
+        let cancelled = enqueue_job(&pool, "/cases/a.pdf", metadata("A"), false, None).await.unwrap();
+        let interrupted = enqueue_job(&pool, "/cases/b.pdf", metadata("B"), true, Some(ChunkingOptions::default())).await.unwrap();
+
+        let job = cancel_job(&pool, &cancelled.id).await.unwrap();
+        assert_eq!(job.status, IngestJobStatus::Cancelled);
+        assert_eq!(embedding_status(&pool, &cancelled.document_id).await, None);
+        assert!(cancel_job(&pool, &cancelled.id).await.is_err());
+
+        // The app closed while the job was processing
+        let claimed = claim_next_job(&pool).await.unwrap().unwrap();
+        assert_eq!(claimed.job.id, interrupted.id);
+        assert!(claimed.allow_duplicate);
+        assert_eq!(claimed.chunking, Some(ChunkingOptions::default()));
+        assert_eq!(requeue_interrupted_jobs(&pool).await.unwrap(), 1);
+        assert_eq!(embedding_status(&pool, &interrupted.document_id).await.as_deref(), Some("pending"));
+        let claimed = claim_next_job(&pool).await.unwrap().unwrap();
+        assert_eq!(claimed.job.id, interrupted.id);
+
+        // Cancelled part way through by `cancel_ingestion`
+        let outcome = Err(AppError::OperationFailed(crate::document::CANCELLED.to_string()));
+        let job = finish_job(&pool, &claimed.job, &outcome).await.unwrap();
+        assert_eq!(job.status, IngestJobStatus::Cancelled);
+        assert_eq!(embedding_status(&pool, &interrupted.document_id).await, None);
+    }
+}
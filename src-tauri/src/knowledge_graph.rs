@@ -0,0 +1,492 @@
+/**
+ * Knowledge Graph Module
+ * Extracts legal concepts and the relationships between them from ingested documents
+ */
+
+use crate::db::HybridStorage;
+use crate::error::{AppError, AppResult, CommandError, CommandResult};
+use crate::llm::{parse_json_response, ChatOptions, LLMService, Message};
+use crate::logging::LogLevel;
+use crate::state::AppState;
+use crate::validation::{validate_not_empty, validate_uuid};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::HashMap;
+use tauri::State;
+use uuid::Uuid;
+
+/// Chunks sent to the model per extraction request
+const GRAPH_BATCH_SIZE: usize = 10;
+
+/// What a node in the graph stands for
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeType {
+    #[default]
+    Concept,
+    Case,
+    Statute,
+    Doctrine,
+}
+
+impl NodeType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            NodeType::Concept => "concept",
+            NodeType::Case => "case",
+            NodeType::Statute => "statute",
+            NodeType::Doctrine => "doctrine",
+        }
+    }
+
+    /// Unknown types are treated as plain concepts
+    fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "case" => NodeType::Case,
+            "statute" => NodeType::Statute,
+            "doctrine" => NodeType::Doctrine,
+            _ => NodeType::Concept,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GraphNode {
+    pub id: String,
+    pub label: String,
+    #[serde(rename = "type")]
+    pub node_type: NodeType,
+    /// Document the concept was first extracted from
+    pub document_id: String,
+}
+
+/// An edge, named `source`/`target` as D3's force layout expects
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GraphEdge {
+    pub id: String,
+    #[serde(rename = "source")]
+    pub from_node: String,
+    #[serde(rename = "target")]
+    pub to_node: String,
+    pub relation: String,
+    /// Number of chunks mentioning both concepts
+    pub weight: f64,
+}
+
+/// A user's graph, with concepts shared between documents merged into one node
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphExport {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Outcome of `build_graph_from_chunks`
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphStats {
+    pub nodes: usize,
+    pub edges: usize,
+    pub chunks: usize,
+    /// Batches the model's reply couldn't be used for; their chunks added nothing
+    pub failed_batches: usize,
+}
+
+/// A `(concept, relation, concept)` triple as returned by the model
+#[derive(Debug, Deserialize)]
+struct Triple {
+    subject: String,
+    #[serde(default)]
+    subject_type: String,
+    relation: String,
+    object: String,
+    #[serde(default)]
+    object_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TripleResponse {
+    triples: Vec<Triple>,
+}
+
+/// Nodes and edges extracted from one document, before they are stored
+#[derive(Debug, Default)]
+struct DocumentGraph {
+    nodes: Vec<(String, NodeType)>,
+    /// `(from, to, relation, weight)`, with nodes given by index
+    edges: Vec<(usize, usize, String, f64)>,
+}
+
+#[derive(Clone)]
+pub struct KnowledgeGraphService {
+    storage: HybridStorage,
+    llm_service: LLMService,
+}
+
+impl KnowledgeGraphService {
+    pub fn new(storage: HybridStorage, llm_service: LLMService) -> Self {
+        Self { storage, llm_service }
+    }
+
+    /// Extract concepts and relations from a document's chunks, replacing any
+    /// graph previously built for it
+    pub async fn build_graph_from_chunks(&self, document_id: &str) -> AppResult<GraphStats> {
+        validate_not_empty(document_id, "Document ID")?;
+        let pool = self.storage.sqlite().get_pool().await?;
+
+        let chunks: Vec<String> = sqlx::query_scalar(
+            "SELECT chunk_text FROM document_chunks WHERE document_id = ? ORDER BY chunk_index"
+        )
+        .bind(document_id)
+        .fetch_all(&pool)
+        .await?;
+        if chunks.is_empty() {
+            return Err(AppError::NotFound(format!("Document {} has no indexed chunks", document_id)));
+        }
+
+        let mut triples = Vec::new();
+        let mut failed_batches = 0;
+        let mut last_error = None;
+        for batch in chunks.chunks(GRAPH_BATCH_SIZE) {
+            match self.extract_triples(batch).await {
+                Ok(batch_triples) => triples.extend(batch_triples),
+                Err(e) => {
+                    crate::logging::log(
+                        LogLevel::Warn,
+                        "knowledge_graph",
+                        &format!("Concept extraction failed for a batch of {}: {}", document_id, e),
+                    );
+                    failed_batches += 1;
+                    last_error = Some(e);
+                }
+            }
+        }
+        if let Some(e) = last_error.filter(|_| triples.is_empty()) {
+            return Err(e);
+        }
+
+        let graph = assemble_graph(&triples, &chunks);
+        save_document_graph(&pool, document_id, &graph).await?;
+
+        Ok(GraphStats {
+            nodes: graph.nodes.len(),
+            edges: graph.edges.len(),
+            chunks: chunks.len(),
+            failed_batches,
+        })
+    }
+
+    /// The graph across the user's documents and the knowledge base
+    pub async fn get_graph(&self, user_id: &str) -> AppResult<GraphExport> {
+        let user_id = &validate_uuid(user_id, "User ID")?;
+        let pool = self.storage.sqlite().get_pool().await?;
+        graph_for_user(&pool, user_id).await
+    }
+
+    /// Ask the model for the concept triples stated in a batch of chunks
+    async fn extract_triples(&self, chunks: &[String]) -> AppResult<Vec<Triple>> {
+        let messages = vec![
+            Message {
+                role: "system".to_string(),
+                content: "You build knowledge graphs of legal concepts for law students. From the passages, extract relationships between legal concepts, cases, statutes and doctrines as triples.\n\
+                    Reply with JSON only: {\"triples\": [{\"subject\": \"...\", \"subject_type\": \"concept|case|statute|doctrine\", \"relation\": \"...\", \"object\": \"...\", \"object_type\": \"concept|case|statute|doctrine\"}]}\n\
+                    Use short canonical names (e.g. \"Duty of care\", \"Donoghue v Stevenson\") and short lowercase relations (e.g. \"establishes\", \"is an element of\", \"overrules\"). Reply with {\"triples\": []} if there are none.".to_string(),
+            },
+            Message {
+                role: "user".to_string(),
+                content: chunks.join("\n\n---\n\n"),
+            },
+        ];
+
+        let options = ChatOptions {
+            temperature: Some(0.0),
+            task: Some("knowledge_graph".to_string()),
+            ..Default::default()
+        };
+        let response = self.llm_service.chat(messages, options).await?;
+
+        parse_json_response::<TripleResponse>(&response)
+            .map(|r| r.triples)
+            .ok_or_else(|| AppError::Llm("Could not parse concept triples from the model response".to_string()))
+    }
+}
+
+/// Lowercased label with runs of whitespace collapsed, for matching
+fn label_key(label: &str) -> String {
+    label.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Nodes deduplicated by label and edges deduplicated by `(from, to, relation)`,
+/// each edge weighted by the number of chunks that mention both of its concepts
+fn assemble_graph(triples: &[Triple], chunks: &[String]) -> DocumentGraph {
+    let mut graph = DocumentGraph::default();
+    let mut node_index: HashMap<String, usize> = HashMap::new();
+    let mut edge_index: HashMap<(usize, usize, String), usize> = HashMap::new();
+
+    let mut node = |graph: &mut DocumentGraph, label: &str, node_type: &str| -> Option<usize> {
+        let key = label_key(label);
+        if key.is_empty() {
+            return None;
+        }
+        let node_type = NodeType::parse(node_type);
+        let index = *node_index.entry(key).or_insert_with(|| {
+            graph.nodes.push((label.split_whitespace().collect::<Vec<_>>().join(" "), node_type));
+            graph.nodes.len() - 1
+        });
+        // A specific type from a later mention beats the default
+        if graph.nodes[index].1 == NodeType::Concept {
+            graph.nodes[index].1 = node_type;
+        }
+        Some(index)
+    };
+
+    for triple in triples {
+        let relation = label_key(&triple.relation);
+        let from = node(&mut graph, &triple.subject, &triple.subject_type);
+        let to = node(&mut graph, &triple.object, &triple.object_type);
+        let (Some(from), Some(to)) = (from, to) else { continue };
+        if from == to || relation.is_empty() {
+            continue;
+        }
+        edge_index.entry((from, to, relation.clone())).or_insert_with(|| {
+            graph.edges.push((from, to, relation, 0.0));
+            graph.edges.len() - 1
+        });
+    }
+
+    let chunks: Vec<String> = chunks.iter().map(|c| label_key(c)).collect();
+    for edge in &mut graph.edges {
+        let from = label_key(&graph.nodes[edge.0].0);
+        let to = label_key(&graph.nodes[edge.1].0);
+        let together = chunks.iter().filter(|c| c.contains(&from) && c.contains(&to)).count();
+        // The model may paraphrase, so an extracted relation always counts once
+        edge.3 = together.max(1) as f64;
+    }
+
+    graph
+}
+
+/// Replace a document's stored nodes and edges with `graph`
+async fn save_document_graph(pool: &Pool<Sqlite>, document_id: &str, graph: &DocumentGraph) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    // Edges go with their nodes through the foreign key cascade
+    sqlx::query("DELETE FROM nodes WHERE document_id = ?")
+        .bind(document_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut node_ids = Vec::with_capacity(graph.nodes.len());
+    for (label, node_type) in &graph.nodes {
+        let id = Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO nodes (id, label, label_key, node_type, document_id, created_at)
+             VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)"
+        )
+        .bind(&id)
+        .bind(label)
+        .bind(label_key(label))
+        .bind(node_type.as_str())
+        .bind(document_id)
+        .execute(&mut *tx)
+        .await?;
+        node_ids.push(id);
+    }
+
+    for (from, to, relation, weight) in &graph.edges {
+        sqlx::query("INSERT INTO edges (id, from_node, to_node, relation, weight) VALUES (?, ?, ?, ?, ?)")
+            .bind(Uuid::new_v4().to_string())
+            .bind(&node_ids[*from])
+            .bind(&node_ids[*to])
+            .bind(relation)
+            .bind(weight)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Nodes and edges from the user's documents and the knowledge base. Nodes
+/// with the same label in different documents become one node, and their
+/// parallel edges one edge with the weights added up.
+async fn graph_for_user(pool: &Pool<Sqlite>, user_id: &str) -> AppResult<GraphExport> {
+    let visible = "d.user_id = ? OR d.document_type = 'knowledge_base'";
+
+    let node_rows = sqlx::query(&format!(
+        "SELECT n.id, n.label, n.label_key, n.node_type, n.document_id
+         FROM nodes n JOIN documents d ON d.id = n.document_id
+         WHERE {}
+         ORDER BY n.rowid",
+        visible
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let edge_rows = sqlx::query(&format!(
+        "SELECT e.id, e.from_node, e.to_node, e.relation, e.weight
+         FROM edges e
+         JOIN nodes n ON n.id = e.from_node
+         JOIN documents d ON d.id = n.document_id
+         WHERE {}
+         ORDER BY e.rowid",
+        visible
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut export = GraphExport::default();
+    let mut by_label: HashMap<String, String> = HashMap::new();
+    let mut merged_id: HashMap<String, String> = HashMap::new();
+    for row in &node_rows {
+        let id: String = row.get("id");
+        let key: String = row.get("label_key");
+        let canonical = by_label.entry(key).or_insert_with(|| {
+            export.nodes.push(GraphNode {
+                id: id.clone(),
+                label: row.get("label"),
+                node_type: NodeType::parse(row.get("node_type")),
+                document_id: row.get("document_id"),
+            });
+            id.clone()
+        });
+        merged_id.insert(id, canonical.clone());
+    }
+
+    let mut edge_index: HashMap<(String, String, String), usize> = HashMap::new();
+    for row in &edge_rows {
+        let (Some(from), Some(to)) = (
+            merged_id.get(row.get::<&str, _>("from_node")),
+            merged_id.get(row.get::<&str, _>("to_node")),
+        ) else {
+            continue;
+        };
+        let relation: String = row.get("relation");
+        let weight: f64 = row.get("weight");
+
+        match edge_index.get(&(from.clone(), to.clone(), relation.clone())) {
+            Some(&i) => export.edges[i].weight += weight,
+            None => {
+                edge_index.insert((from.clone(), to.clone(), relation.clone()), export.edges.len());
+                export.edges.push(GraphEdge {
+                    id: row.get("id"),
+                    from_node: from.clone(),
+                    to_node: to.clone(),
+                    relation,
+                    weight,
+                });
+            }
+        }
+    }
+
+    Ok(export)
+}
+
+// Tauri Commands
+
+/// Extract a document's concepts and relations into the knowledge graph
+#[tauri::command]
+pub async fn build_knowledge_graph(
+    state: State<'_, AppState>,
+    document_id: String,
+) -> CommandResult<GraphStats> {
+    let service = state.knowledge_graph_service().await?;
+    service.build_graph_from_chunks(&document_id).await.map_err(CommandError::from)
+}
+
+/// The user's knowledge graph as nodes and edges for D3
+#[tauri::command]
+pub async fn get_graph(
+    state: State<'_, AppState>,
+    user_id: String,
+) -> CommandResult<GraphExport> {
+    let service = state.knowledge_graph_service().await?;
+    service.get_graph(&user_id).await.map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    fn triple(subject: &str, relation: &str, object: &str, object_type: &str) -> Triple {
+        Triple {
+            subject: subject.to_string(),
+            subject_type: String::new(),
+            relation: relation.to_string(),
+            object: object.to_string(),
+            object_type: object_type.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_assemble_graph_dedupes_and_weights_by_cooccurrence() {
+        let triples = vec![
+            triple("Donoghue v Stevenson", "establishes", "Duty of care", "doctrine"),
+            triple("donoghue  v stevenson", "Establishes", "duty of care", "concept"),
+            triple("Duty of care", "is an element of", "Negligence", "concept"),
+            triple("Negligence", "relates to", "negligence", "concept"),
+            triple("", "establishes", "Negligence", "concept"),
+        ];
+        let chunks = vec![
+            "Donoghue v Stevenson established the modern duty of care.".to_string(),
+            "The duty of care in Donoghue v Stevenson rests on the neighbour principle.".to_string(),
+            "Breach is assessed separately.".to_string(),
+        ];
+
+        let graph = assemble_graph(&triples, &chunks);
+
+        let labels: Vec<&str> = graph.nodes.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, vec!["Donoghue v Stevenson", "Duty of care", "Negligence"]);
+        assert_eq!(graph.nodes[1].1, NodeType::Doctrine);
+        assert_eq!(
+            graph.edges,
+            vec![
+                (0, 1, "establishes".to_string(), 2.0),
+                (1, 2, "is an element of".to_string(), 1.0),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_graph_for_user_merges_shared_concepts() {
+        let pool = test_pool().await;
+
+        let user = Uuid::new_v4().to_string();
+        for (id, user_id, document_type) in [
+            ("mine", Some(user.as_str()), "user_case"),
+            ("kb", None, "knowledge_base"),
+            ("theirs", Some("someone-else"), "user_case"),
+        ] {
+            sqlx::query("INSERT INTO documents (id, user_id, document_type, title, created_at, updated_at) VALUES (?, ?, ?, ?, 'now', 'now')")
+                .bind(id)
+                .bind(user_id)
+                .bind(document_type)
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+
+            let triples = vec![triple("Duty of care", "is an element of", "Negligence", "concept")];
+            let graph = assemble_graph(&triples, &["duty of care and negligence".to_string()]);
+            save_document_graph(&pool, id, &graph).await.unwrap();
+        }
+        // Rebuilding replaces the document's graph rather than adding to it
+        let graph = assemble_graph(
+            &[triple("Duty of care", "is an element of", "Negligence", "concept")],
+            &["duty of care and negligence".to_string()],
+        );
+        save_document_graph(&pool, "mine", &graph).await.unwrap();
+
+        let export = graph_for_user(&pool, &user).await.unwrap();
+        let labels: Vec<&str> = export.nodes.iter().map(|n| n.label.as_str()).collect();
+        assert_eq!(labels, vec!["Duty of care", "Negligence"]);
+        assert_eq!(export.edges.len(), 1);
+        assert_eq!(export.edges[0].weight, 2.0);
+        assert_eq!(export.edges[0].from_node, export.nodes[0].id);
+
+        let json = serde_json::to_value(&export.edges[0]).unwrap();
+        assert!(json.get("source").is_some() && json.get("target").is_some());
+    }
+}
@@ -0,0 +1,273 @@
+/**
+ * Diagnostic Logging
+ * Rotating local log file that users can attach to bug reports
+ */
+
+use crate::error::{AppError, CommandResult};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::OnceLock;
+use tokio::sync::oneshot;
+
+/// Log file name inside the logs directory
+pub const LOG_FILE_NAME: &str = "firm_ai.log";
+
+/// Size at which the log file is rotated
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Files kept, including the current one
+const MAX_LOG_FILES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+        }
+    }
+}
+
+enum LogMessage {
+    Line(String),
+    /// Reply once every earlier line has been written
+    Flush(oneshot::Sender<()>),
+}
+
+/// Handle to a log file written by a background thread
+///
+/// Logging only queues the line, so it never blocks the caller on disk I/O.
+#[derive(Debug, Clone)]
+pub struct Logger {
+    sender: mpsc::Sender<LogMessage>,
+    dir: PathBuf,
+    max_files: usize,
+}
+
+impl Logger {
+    /// Log to `dir/firm_ai.log`, creating the directory if needed
+    pub fn new(dir: PathBuf) -> io::Result<Self> {
+        Self::with_limits(dir, MAX_LOG_BYTES, MAX_LOG_FILES)
+    }
+
+    fn with_limits(dir: PathBuf, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        let max_files = max_files.max(1);
+        let (sender, receiver) = mpsc::channel();
+        let mut writer = LogWriter {
+            dir: dir.clone(),
+            max_bytes,
+            max_files,
+        };
+        std::thread::Builder::new()
+            .name("firm-ai-log".to_string())
+            .spawn(move || writer.run(receiver))?;
+
+        Ok(Self { sender, dir, max_files })
+    }
+
+    /// Path of the file currently written to
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(LOG_FILE_NAME)
+    }
+
+    pub fn log(&self, level: LogLevel, module: &str, message: &str) {
+        let line = format!(
+            "[{}][{}][{}] {}",
+            chrono::Utc::now().to_rfc3339(),
+            level.as_str(),
+            module,
+            message.replace('\n', " ")
+        );
+        let _ = self.sender.send(LogMessage::Line(line));
+    }
+
+    /// Wait until every line logged so far is on disk
+    pub async fn flush(&self) {
+        let (reply, done) = oneshot::channel();
+        if self.sender.send(LogMessage::Flush(reply)).is_ok() {
+            let _ = done.await;
+        }
+    }
+
+    /// Write all kept log files, oldest first, into one file at `output_path`
+    pub async fn export(&self, output_path: &Path) -> io::Result<()> {
+        self.flush().await;
+
+        let mut output = File::create(output_path)?;
+        for index in (0..self.max_files).rev() {
+            match fs::read(log_file(&self.dir, index)) {
+                Ok(bytes) => output.write_all(&bytes)?,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        output.flush()
+    }
+}
+
+struct LogWriter {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl LogWriter {
+    fn run(&mut self, receiver: mpsc::Receiver<LogMessage>) {
+        for message in receiver {
+            match message {
+                LogMessage::Line(line) => {
+                    if let Err(e) = self.write_line(&line) {
+                        eprintln!("Failed to write log: {}", e);
+                    }
+                }
+                LogMessage::Flush(reply) => {
+                    let _ = reply.send(());
+                }
+            }
+        }
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let path = log_file(&self.dir, 0);
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 + 1 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(file, "{}", line)
+    }
+
+    /// Shift `firm_ai.log.N` to `.N+1`, dropping the oldest
+    fn rotate(&self) -> io::Result<()> {
+        let oldest = log_file(&self.dir, self.max_files - 1);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for index in (0..self.max_files - 1).rev() {
+            let from = log_file(&self.dir, index);
+            if from.exists() {
+                fs::rename(&from, log_file(&self.dir, index + 1))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `firm_ai.log` for index 0, `firm_ai.log.N` for older files
+fn log_file(dir: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => dir.join(LOG_FILE_NAME),
+        n => dir.join(format!("{}.{}", LOG_FILE_NAME, n)),
+    }
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Install the application-wide logger; later calls are ignored
+pub fn init(dir: PathBuf) -> io::Result<()> {
+    let logger = Logger::new(dir)?;
+    let _ = LOGGER.set(logger);
+    Ok(())
+}
+
+pub fn logger() -> Option<&'static Logger> {
+    LOGGER.get()
+}
+
+/// Log through the application-wide logger, or to stderr before it is
+/// installed (or when it couldn't be)
+pub fn log(level: LogLevel, module: &str, message: &str) {
+    match logger() {
+        Some(logger) => logger.log(level, module, message),
+        None => eprintln!("[{}] {}: {}", level.as_str(), module, message),
+    }
+}
+
+/// Record an error returned across the command boundary
+pub fn log_command_error(error: &AppError) {
+    // Debug output starts with the variant name, e.g. `NotFound("...")`
+    let debug = format!("{:?}", error);
+    let variant = debug.split(['(', ' ', '{']).next().unwrap_or_default();
+    log(LogLevel::Error, "command", &format!("{}: {}", variant, error));
+}
+
+// Tauri Commands
+
+/// Path of the current log file, or an empty string if logging is unavailable
+#[tauri::command]
+pub fn get_log_path() -> String {
+    logger()
+        .map(|l| l.path().to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Copy all kept logs into one file for attaching to a bug report
+#[tauri::command]
+pub async fn export_logs(output_path: String) -> CommandResult<()> {
+    let logger = logger().ok_or_else(|| AppError::Internal("Logging is not initialised".to_string()))?;
+    logger.export(Path::new(&output_path)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("firm-ai-logs-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_lines_are_formatted_and_flushed() {
+        let dir = temp_dir();
+        let logger = Logger::new(dir.clone()).unwrap();
+
+        logger.log(LogLevel::Error, "rag", "Not found: doc-1\nsecond line");
+        logger.flush().await;
+
+        let contents = fs::read_to_string(logger.path()).unwrap();
+        let line = contents.lines().next().unwrap();
+        assert!(line.starts_with('['));
+        assert!(line.ends_with("][ERROR][rag] Not found: doc-1 second line"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rotation_keeps_max_files_and_export_is_oldest_first() {
+        let dir = temp_dir();
+        let logger = Logger::with_limits(dir.clone(), 100, 3).unwrap();
+
+        for i in 0..10 {
+            logger.log(LogLevel::Info, "sync", &format!("entry {}", i));
+        }
+        logger.flush().await;
+
+        assert!(log_file(&dir, 2).exists());
+        assert!(!log_file(&dir, 3).exists());
+
+        let output = dir.join("export.log");
+        logger.export(&output).await.unwrap();
+        let exported = fs::read_to_string(&output).unwrap();
+        let entries: Vec<&str> = exported.lines().map(|l| l.rsplit(' ').next().unwrap()).collect();
+
+        // Older entries were rotated away; the rest are in order and end with the latest
+        assert!(entries.len() < 10);
+        assert!(entries.windows(2).all(|w| w[0].parse::<u32>().unwrap() < w[1].parse::<u32>().unwrap()));
+        assert_eq!(entries.last(), Some(&"9"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
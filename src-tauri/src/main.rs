@@ -3,14 +3,47 @@
 
 use tauri::Manager;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 mod error;
+mod logging;
 mod config;
 mod db;
+mod document;
+mod validation;
+mod ann;
+mod annotations;
 mod rag;
 mod llm;
+mod tokens;
+mod metrics;
+mod retry;
+mod tutor;
+mod state;
+mod sync;
+mod flashcards;
+mod mock_tests;
+mod study_plans;
+mod outlines;
+mod cases;
+mod precedents;
+mod citations;
+mod oppositions;
+mod topics;
+mod corpus;
+mod knowledge_graph;
+mod ingest_queue;
+mod export;
+mod recycle_bin;
+mod question_bank;
+mod notifications;
+mod profiles;
+#[cfg(test)]
+mod test_support;
 
 use config::AppConfig;
+use error::{CommandError, CommandResult};
+use logging::LogLevel;
 
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 #[tauri::command]
@@ -24,16 +57,16 @@ fn get_app_version() -> String {
 }
 
 #[tauri::command]
-async fn save_file(path: String, contents: String) -> Result<(), String> {
+async fn save_file(path: String, contents: String) -> CommandResult<()> {
     use std::fs;
-    fs::write(&path, contents).map_err(|e| e.to_string())?;
+    fs::write(&path, contents)?;
     Ok(())
 }
 
 #[tauri::command]
-async fn read_file(path: String) -> Result<String, String> {
+async fn read_file(path: String) -> CommandResult<String> {
     use std::fs;
-    fs::read_to_string(&path).map_err(|e| e.to_string())
+    fs::read_to_string(&path).map_err(CommandError::from)
 }
 
 fn main() {
@@ -45,6 +78,7 @@ fn main() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_http::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_sql::Builder::default().build())
         .invoke_handler(tauri::generate_handler![
             greet,
@@ -52,10 +86,124 @@ fn main() {
             save_file,
             read_file,
             rag::ingest_document,
+            rag::cancel_ingestion,
+            rag::ingest_pdf,
+            rag::ingest_url,
+            rag::ingest_directory,
+            rag::reingest_document,
+            rag::list_documents,
+            rag::find_duplicate_documents,
+            rag::delete_document,
+            rag::rebuild_rag_index,
+            rag::rag_status,
+            rag::reembed_all_documents,
+            rag::migrate_embeddings_to_f16,
+            rag::verify_rag_integrity,
             rag::query_context,
+            rag::find_related,
+            ingest_queue::queue_document_ingest,
+            ingest_queue::get_ingest_jobs,
+            ingest_queue::cancel_ingest_job,
             llm::llm_chat,
             llm::generate_irac,
+            llm::generate_opposing_arguments,
+            llm::score_legal_argument,
+            citations::get_case_citations,
+            oppositions::get_case_oppositions,
+            topics::get_documents_by_topic,
+            topics::get_document_topics,
+            topics::list_legal_topics,
+            corpus::export_rag_corpus,
+            corpus::import_rag_corpus,
+            knowledge_graph::build_knowledge_graph,
+            knowledge_graph::get_graph,
             llm::tutor_chat,
+            llm::set_llm_policy,
+            llm::list_available_models,
+            llm::estimate_llm_cost,
+            llm::get_api_key_status,
+            llm::get_circuit_breaker_status,
+            config::save_config,
+            config::reload_config,
+            llm::get_llm_metrics,
+            llm::reset_llm_metrics,
+            flashcards::create_flashcard_set,
+            flashcards::get_flashcard_sets,
+            flashcards::delete_flashcard_set,
+            flashcards::add_flashcard,
+            flashcards::get_flashcards,
+            flashcards::delete_flashcard,
+            flashcards::generate_flashcards_from_document,
+            flashcards::start_review_session,
+            flashcards::record_card_review,
+            flashcards::finish_review_session,
+            mock_tests::get_mock_tests,
+            mock_tests::generate_mock_test,
+            mock_tests::generate_quick_quiz,
+            mock_tests::submit_test_result,
+            mock_tests::get_readiness_score,
+            mock_tests::delete_mock_test,
+            mock_tests::compare_test_results,
+            mock_tests::export_test_results_to_csv,
+            cases::search_cases,
+            cases::create_case,
+            cases::get_cases,
+            cases::update_case,
+            cases::delete_case,
+            cases::create_tag,
+            cases::get_tags,
+            cases::delete_tag,
+            cases::add_tag_to_case,
+            cases::remove_tag_from_case,
+            precedents::recommend_precedents,
+            db::get_schema_version,
+            sync::get_sync_conflicts,
+            sync::resolve_conflict,
+            sync::resolve_sync_conflict,
+            sync::sync_now,
+            sync::get_sync_status,
+            sync::is_online,
+            sync::set_sync_interval,
+            sync::set_sync_user,
+            sync::get_sync_queue_status,
+            sync::get_dead_letter_operations,
+            sync::retry_dead_letter,
+            sync::discard_dead_letter,
+            recycle_bin::get_deleted_items,
+            recycle_bin::restore_deleted_item,
+            recycle_bin::permanently_delete_item,
+            question_bank::contribute_questions,
+            question_bank::search_question_bank,
+            question_bank::build_test_from_bank,
+            profiles::create_profile,
+            profiles::get_profile,
+            profiles::update_profile,
+            profiles::delete_profile,
+            profiles::list_profiles,
+            profiles::switch_user,
+            profiles::get_current_user,
+            db::get_storage_report,
+            db::vacuum_database,
+            logging::get_log_path,
+            logging::export_logs,
+            export::export_user_data,
+            export::import_user_data,
+            study_plans::create_study_plan,
+            study_plans::get_study_plans,
+            study_plans::get_study_plan,
+            study_plans::update_study_progress,
+            study_plans::delete_study_plan,
+            study_plans::generate_ai_study_plan,
+            study_plans::get_available_study_tasks,
+            study_plans::complete_study_task,
+            study_plans::export_study_plan_to_ical,
+            outlines::generate_outline,
+            outlines::get_outlines,
+            outlines::delete_outline,
+            annotations::add_annotation,
+            annotations::get_document_annotations,
+            annotations::update_annotation_note,
+            annotations::delete_annotation,
         ])
         .setup(|app| {
             // Set window title and configure window
@@ -66,31 +214,56 @@ fn main() {
                 let _ = window.open_devtools();
             }
 
-            // Load configuration from environment
-            let config = AppConfig::from_env();
-            
-            // Validate configuration
-            if let Err(e) = config.validate() {
-                eprintln!("Configuration warning: {}", e);
-                eprintln!("App will run with limited functionality");
-            }
-
-            // Get app data directory for SQLite
+            // Get app data directory for SQLite and the config file
             let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| {
                 PathBuf::from(".")
             });
             
             // Ensure directory exists
             if let Err(e) = std::fs::create_dir_all(&app_data_dir) {
-                eprintln!("Failed to create app data directory: {}", e);
+                logging::log(LogLevel::Error, "main", &format!("Failed to create app data directory: {}", e));
+            }
+
+            // Diagnostic log at app_data_dir/logs/firm_ai.log; until then, and
+            // without it, log lines go to stderr
+            if let Err(e) = logging::init(app_data_dir.join("logs")) {
+                logging::log(LogLevel::Error, "main", &format!("Failed to initialize logging: {}", e));
+            }
+
+            // Load saved configuration, falling back to the environment
+            let config_path = app_data_dir.join(config::CONFIG_FILE_NAME);
+            let config = if config_path.exists() {
+                AppConfig::load_from_file(&config_path).unwrap_or_else(|e| {
+                    logging::log(LogLevel::Warn, "config", &format!("Failed to load {:?}: {}", config_path, e));
+                    AppConfig::from_env()
+                })
+            } else {
+                AppConfig::from_env()
+            };
+            
+            // Validate configuration
+            if let Err(e) = config.validate() {
+                logging::log(
+                    LogLevel::Warn,
+                    "config",
+                    &format!("{}; the app will run with limited functionality", e),
+                );
             }
             
             let db_path = app_data_dir.join(&config.database_path);
             
-            println!("FIRM AI initialized successfully");
-            println!("Database path: {:?}", db_path);
-            println!("OpenRouter API key configured: {}", config.openrouter_api_key.is_some());
-            println!("Supabase configured: {}", config.supabase_url.is_some());
+            logging::log(LogLevel::Info, "main", &format!("FIRM AI {} starting", env!("CARGO_PKG_VERSION")));
+            logging::log(
+                LogLevel::Info,
+                "main",
+                &format!(
+                    "Database path: {:?}; OpenRouter API keys: {}; Supabase configured: {}; HTTP proxy configured: {}",
+                    db_path,
+                    config.api_keys().len(),
+                    config.supabase_url.is_some(),
+                    config.http_proxy.is_some()
+                ),
+            );
             
             // Initialize HybridStorage
             let storage = db::HybridStorage::new(
@@ -104,17 +277,72 @@ fn main() {
                 storage.initialize().await.expect("failed to initialize storage");
             });
             
-            app.manage(storage);
+            app.manage(storage.clone());
             
-            // Initialize RagState
+            // Initialize RagState; the embedding model loads on first use
             let rag_state = rag::RagState::new();
-            app.manage(rag_state);
+            app.manage(rag_state.clone());
+
+            // Start fetching the local model now so the first search doesn't wait for it
+            if config.models.embedding_provider != "openrouter" {
+                let rag = rag_state.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = rag.model().await {
+                        logging::log(LogLevel::Warn, "rag", &format!("Failed to load the embedding model: {}", e));
+                    }
+                });
+            }
+
+            // Build the chunk index in the background so the first search doesn't wait
+            let index = rag_state.index().clone();
+            let index_storage = storage.clone();
+            tauri::async_runtime::spawn(async move {
+                let result = match index_storage.sqlite().get_pool().await {
+                    Ok(pool) => index.rebuild(&pool).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    logging::log(LogLevel::Warn, "rag", &format!("Failed to build chunk index: {}", e));
+                }
+            });
 
             // Initialize LLMService
-            let llm_service = llm::LLMService::new(
-                config.openrouter_api_key.clone().unwrap_or_default()
+            // A bad proxy shouldn't stop the app opening. The saved config keeps it,
+            // so AI commands report the error until it is fixed and reloaded.
+            let llm_service = match llm::LLMService::from_config(&config) {
+                Ok(service) => service,
+                Err(e) => {
+                    logging::log(LogLevel::Error, "llm", &format!("Starting without the HTTP proxy: {}", e));
+                    llm::LLMService::from_config(&AppConfig { http_proxy: None, ..config.clone() })?
+                }
+            };
+            app.manage(llm_service.clone());
+
+            // Work through queued ingestion jobs in the background
+            let ingest_queue = ingest_queue::IngestQueue::new();
+            app.manage(ingest_queue.clone());
+            ingest_queue::spawn_ingest_worker(
+                app.handle().clone(),
+                storage.clone(),
+                rag_state.clone(),
+                llm_service.clone(),
+                ingest_queue,
             );
-            app.manage(llm_service);
+
+            // Shared state for the flashcard, mock test and study plan services
+            let sync_manager = sync::SyncManager::new(Arc::new(storage.clone()))
+                .with_app_handle(app.handle().clone())
+                .with_interval(config.sync_interval_seconds);
+            let app_state = state::AppState::new(config, config_path, storage.clone(), sync_manager, rag_state, llm_service);
+
+            // Sync and watch connectivity in the background for the life of the app
+            let background = tokio_util::sync::CancellationToken::new();
+            app_state.sync_manager.clone().start_periodic_sync(background.clone());
+            app_state.sync_manager.clone().start_connectivity_watcher(background);
+
+            // Announce study tasks due today
+            notifications::spawn_notification_scheduler(app.handle().clone(), storage.clone(), app_state.config.clone());
+            app.manage(app_state);
             
             Ok(())
         })
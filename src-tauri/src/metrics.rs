@@ -0,0 +1,171 @@
+/**
+ * LLM Metrics
+ * In-memory latency and error tracking for LLM calls
+ */
+
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Number of recent calls kept; aggregates are computed over this window
+const METRICS_CAPACITY: usize = 500;
+
+/// A single LLM call
+#[derive(Debug, Clone, Serialize)]
+pub struct LlmCallRecord {
+    pub timestamp: String,
+    pub task: String,
+    pub model: String,
+    /// "ok", "http <status>" for OpenRouter errors, or "error"
+    pub status: String,
+    pub latency_ms: u64,
+    /// Attempts repeated with another API key after a rate limit
+    pub retries: u32,
+}
+
+/// Rolling aggregates for one task over the recent calls
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TaskStats {
+    pub task: String,
+    pub calls: usize,
+    pub errors: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LlmMetricsSnapshot {
+    /// Most recent calls, newest last
+    pub recent: Vec<LlmCallRecord>,
+    pub tasks: Vec<TaskStats>,
+    /// Calls recorded since the last reset, including those no longer in `recent`
+    pub total_calls: u64,
+    pub total_errors: u64,
+}
+
+#[derive(Debug, Default)]
+struct MetricsInner {
+    recent: VecDeque<LlmCallRecord>,
+    total_calls: u64,
+    total_errors: u64,
+}
+
+/// Shared metrics store; clones record into the same buffer
+#[derive(Debug, Clone, Default)]
+pub struct LlmMetrics {
+    inner: Arc<Mutex<MetricsInner>>,
+}
+
+impl LlmMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, task: &str, model: &str, latency: Duration, status: &str, retries: u32) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+
+        if inner.recent.len() == METRICS_CAPACITY {
+            inner.recent.pop_front();
+        }
+        inner.recent.push_back(LlmCallRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            task: task.to_string(),
+            model: model.to_string(),
+            status: status.to_string(),
+            latency_ms: latency.as_millis() as u64,
+            retries,
+        });
+
+        inner.total_calls += 1;
+        if status != "ok" {
+            inner.total_errors += 1;
+        }
+    }
+
+    pub fn snapshot(&self) -> LlmMetricsSnapshot {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let recent: Vec<LlmCallRecord> = inner.recent.iter().cloned().collect();
+
+        LlmMetricsSnapshot {
+            tasks: task_stats(&recent),
+            recent,
+            total_calls: inner.total_calls,
+            total_errors: inner.total_errors,
+        }
+    }
+
+    pub fn reset(&self) {
+        *self.inner.lock().unwrap_or_else(|e| e.into_inner()) = MetricsInner::default();
+    }
+}
+
+/// Per-task call counts and latency percentiles, sorted by task name
+fn task_stats(records: &[LlmCallRecord]) -> Vec<TaskStats> {
+    let mut by_task: BTreeMap<&str, Vec<&LlmCallRecord>> = BTreeMap::new();
+    for record in records {
+        by_task.entry(record.task.as_str()).or_default().push(record);
+    }
+
+    by_task
+        .into_iter()
+        .map(|(task, records)| {
+            let mut latencies: Vec<u64> = records.iter().map(|r| r.latency_ms).collect();
+            latencies.sort_unstable();
+
+            TaskStats {
+                task: task.to_string(),
+                calls: records.len(),
+                errors: records.iter().filter(|r| r.status != "ok").count(),
+                p50_ms: percentile(&latencies, 50.0),
+                p95_ms: percentile(&latencies, 95.0),
+            }
+        })
+        .collect()
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], pct: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&values, 50.0), 50);
+        assert_eq!(percentile(&values, 95.0), 95);
+        assert_eq!(percentile(&[7], 95.0), 7);
+        assert_eq!(percentile(&[], 50.0), 0);
+    }
+
+    #[test]
+    fn test_metrics_shared_across_clones_and_bounded() {
+        let metrics = LlmMetrics::new();
+        let clone = metrics.clone();
+
+        for i in 0..METRICS_CAPACITY + 10 {
+            let status = if i % 10 == 0 { "http 429" } else { "ok" };
+            clone.record("irac", "google/gemini-2.0-flash-exp", Duration::from_millis(i as u64), status, 0);
+        }
+        metrics.record("tutor", "google/gemini-2.0-flash-exp", Duration::from_millis(5), "ok", 1);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.recent.len(), METRICS_CAPACITY);
+        assert_eq!(snapshot.total_calls, METRICS_CAPACITY as u64 + 11);
+        assert_eq!(snapshot.total_errors, 51);
+        assert_eq!(snapshot.tasks.len(), 2);
+        assert_eq!(snapshot.tasks[0].task, "irac");
+        assert_eq!(snapshot.tasks[1].calls, 1);
+
+        clone.reset();
+        assert_eq!(metrics.snapshot().total_calls, 0);
+    }
+}
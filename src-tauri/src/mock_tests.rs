@@ -3,15 +3,58 @@
  * Handles test generation, storage, and result tracking
  */
 
-use crate::db::HybridStorage;
-use crate::error::{AppError, AppResult};
+use crate::db::{execute_checked, response_total, HybridStorage, PagedResult, PaginationOptions};
+use crate::error::{AppError, AppResult, CommandError, CommandResult};
 use crate::llm::{LLMService, Message};
-use crate::rag::{RAGService, SearchOptions};
-use crate::validation::{validate_not_empty, validate_positive_integer, validate_score, validate_uuid};
-use chrono::Utc;
+use crate::logging::LogLevel;
+use crate::tokens;
+use crate::topics;
+use crate::recycle_bin;
+use crate::rag::{fenced_context, CitationSource, ContextChunk, RAGService, SearchMode, SearchOptions, SearchResult};
+use crate::state::AppState;
+use crate::validation::{validate_not_empty, validate_positive_integer, validate_quiz_question, validate_score, validate_uuid};
+use chrono::{DateTime, NaiveDate, NaiveTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::collections::{HashMap, HashSet};
+use tauri::State;
 use uuid::Uuid;
 
+/// `mock_tests.kind` for full multi-topic exams
+pub const EXAM_KIND: &str = "exam";
+/// `mock_tests.kind` for short single-topic practice quizzes
+pub const QUIZ_KIND: &str = "quiz";
+
+/// Question count bounds for quick quizzes
+const QUIZ_MIN_QUESTIONS: i32 = 5;
+const QUIZ_MAX_QUESTIONS: i32 = 10;
+
+/// Completion tokens allowed per exam topic request
+const EXAM_MAX_TOKENS: u32 = 4000;
+
+const EXAM_SYSTEM_PROMPT: &str = "You are an expert legal AI assistant specializing in creating comprehensive law school mock examinations.
+Your task is to create realistic exam questions that test deep understanding of legal principles.
+
+Guidelines:
+- Create challenging questions appropriate for law school level
+- Provide 4 multiple-choice options (A, B, C, D)
+- Ensure clear, unambiguous correct answers
+- Include detailed explanations that aid learning
+- Cover multiple legal principles and applications
+- Use realistic case scenarios
+- Format responses as JSON";
+
+/// Age in days at which a result counts half as much towards readiness
+const READINESS_HALF_LIFE_DAYS: f64 = 30.0;
+/// Overall score (percent) at which the estimated pass probability is 50%
+const PASS_MARK: f64 = 65.0;
+/// Steepness of the pass probability curve around `PASS_MARK`
+const PASS_CURVE_STEEPNESS: f64 = 0.15;
+/// Weakest topics listed in `ReadinessScore::recommended_focus`
+const FOCUS_TOPIC_COUNT: usize = 3;
+/// Header row of `export_results_to_csv`
+const RESULTS_CSV_HEADER: [&str; 7] = ["test_id", "test_title", "completed_at", "score", "total_questions", "pct_correct", "topic"];
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MockTest {
     pub id: String,
@@ -20,6 +63,19 @@ pub struct MockTest {
     pub description: Option<String>,
     pub questions: Vec<TestQuestion>,
     pub created_at: String,
+    /// "exam" or "quiz"
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    /// True when RAG context was trimmed to fit the model's context window
+    #[serde(default)]
+    pub truncated: bool,
+    /// Topics that came up short during generation (not stored)
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+fn default_kind() -> String {
+    EXAM_KIND.to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -29,6 +85,12 @@ pub struct TestQuestion {
     pub correct_answer: usize,
     pub explanation: String,
     pub topic: Option<String>,
+    /// Difficulty the question was generated at, when one was asked for
+    #[serde(default)]
+    pub difficulty: Option<String>,
+    /// Context chunks the explanation may cite as `[1]`, `[2]`, ...
+    #[serde(default)]
+    pub sources: Vec<CitationSource>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -38,6 +100,9 @@ pub struct TestResult {
     pub test_id: String,
     pub score: f64,
     pub total_questions: i32,
+    /// Score as a percentage of `total_questions`
+    #[serde(default)]
+    pub percentage: f64,
     pub answers: Vec<UserAnswer>,
     pub completed_at: String,
 }
@@ -46,6 +111,8 @@ pub struct TestResult {
 pub struct UserAnswer {
     pub question_index: usize,
     pub selected_answer: usize,
+    /// Computed server-side in `submit_result`; any client-provided value is ignored
+    #[serde(default)]
     pub is_correct: bool,
 }
 
@@ -55,6 +122,8 @@ pub struct GenerateMockTestRequest {
     pub topics: Vec<String>,
     pub num_questions: i32,
     pub include_rag_context: Option<bool>,
+    /// e.g. "easy" or "hard"; passed to the model and recorded on each question
+    pub difficulty: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -64,6 +133,53 @@ pub struct SubmitTestResultRequest {
     pub answers: Vec<UserAnswer>,
 }
 
+/// How ready a student is for the exam, from their stored test results
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReadinessScore {
+    /// Correct rate (percent) across all results, recent results weighted higher
+    pub overall_score: f64,
+    /// Correct rate (percent) per question topic, weighted the same way
+    pub by_topic: HashMap<String, f64>,
+    pub tests_taken: u32,
+    /// 0-1
+    pub estimated_pass_probability: f64,
+    /// Topics with the lowest correct rates, weakest first
+    pub recommended_focus: Vec<String>,
+}
+
+/// One attempt at a test, as compared by `compare_results`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttemptSummary {
+    pub result_id: String,
+    pub completed_at: String,
+    /// Percentage of questions answered correctly
+    pub score: f64,
+    /// Correct rate (percent) per question topic
+    pub by_topic: HashMap<String, f64>,
+}
+
+/// A user's attempts at one test, oldest first, with the trend across them
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResultComparison {
+    pub attempts: Vec<AttemptSummary>,
+    /// Least-squares slope of score against attempt number, in points per attempt
+    pub slope: f64,
+    pub improved: bool,
+    pub best_score: f64,
+    pub worst_score: f64,
+}
+
+/// A test result with the test it was taken on, for CSV export
+struct ExportedResult {
+    test_id: String,
+    test_title: String,
+    completed_at: String,
+    score: f64,
+    total_questions: i32,
+    questions: Vec<TestQuestion>,
+}
+
+#[derive(Clone)]
 pub struct MockTestService {
     storage: HybridStorage,
     llm_service: LLMService,
@@ -80,76 +196,132 @@ impl MockTestService {
     }
 
     /// Generate a mock test using LLM and RAG
-    pub async fn generate_test(&self, request: GenerateMockTestRequest) -> AppResult<MockTest> {
-        validate_uuid(&request.user_id, "User ID")?;
+    pub async fn generate_test(&self, mut request: GenerateMockTestRequest) -> AppResult<MockTest> {
+        request.user_id = validate_uuid(&request.user_id, "User ID")?;
         validate_positive_integer(request.num_questions, "Number of questions")?;
 
         if request.topics.is_empty() {
             return Err(AppError::Validation("At least one topic is required".to_string()));
         }
+        let difficulty = request
+            .difficulty
+            .as_deref()
+            .map(|d| d.trim().to_lowercase())
+            .filter(|d| !d.is_empty());
 
-        // Search for relevant context using RAG if enabled
-        let mut context_info = String::new();
-        if request.include_rag_context.unwrap_or(true) {
-            for topic in &request.topics {
-                let search_results = self
-                    .rag_service
-                    .search(
-                        topic,
-                        SearchOptions {
-                            limit: Some(2),
-                            user_id: Some(request.user_id.clone()),
-                            include_knowledge_base: Some(true),
-                            ..Default::default()
-                        },
-                    )
-                    .await
-                    .unwrap_or_default();
+        let counts = split_questions(request.num_questions as usize, request.topics.len());
+        let system_prompt = self.llm_service.system_prompt(EXAM_SYSTEM_PROMPT);
 
-                if !search_results.is_empty() {
-                    context_info.push_str(&format!(
-                        "\n\n{}:\n{}",
-                        topic,
-                        self.rag_service.format_context_for_llm(&search_results)
-                    ));
+        // Retrieve context for every topic up front so the most relevant
+        // chunks win the context budget, whichever topic they belong to
+        let mut contexts = vec![Vec::new(); request.topics.len()];
+        let mut truncated = false;
+        if request.include_rag_context.unwrap_or(true) {
+            for (index, topic) in request.topics.iter().enumerate() {
+                if counts[index] > 0 {
+                    contexts[index] = self.topic_context(&request.user_id, topic).await;
                 }
             }
+
+            let model = self.llm_service.default_model().to_string();
+            let budget = exam_context_budget(&model, &system_prompt, &request.topics, &counts, difficulty.as_deref());
+            (contexts, truncated) = allot_context(contexts, budget, &model);
         }
 
-        // Generate questions using LLM
-        let system_prompt = "You are an expert legal AI assistant specializing in creating comprehensive law school mock examinations.
-Your task is to create realistic exam questions that test deep understanding of legal principles across multiple topics.
+        // One request per topic; LLMService caps how many run at once
+        let mut tasks = tokio::task::JoinSet::new();
+        for (index, ((topic, count), context)) in request.topics.iter().zip(counts).zip(contexts).enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let service = self.clone();
+            let system_prompt = system_prompt.clone();
+            let topic = topic.clone();
+            let difficulty = difficulty.clone();
+            tasks.spawn(async move {
+                let result = service
+                    .generate_topic_questions(&system_prompt, &topic, difficulty.as_deref(), count, &context)
+                    .await;
+                (index, topic, count, result)
+            });
+        }
 
-Guidelines:
-- Create challenging questions appropriate for law school level
-- Provide 4 multiple-choice options (A, B, C, D)
-- Ensure clear, unambiguous correct answers
-- Include detailed explanations that aid learning
-- Cover multiple legal principles and applications
-- Use realistic case scenarios
-- Format responses as JSON";
+        let mut outcomes = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            outcomes.push(joined.map_err(|e| AppError::Internal(format!("Question generation task failed: {}", e)))?);
+        }
+        outcomes.sort_by_key(|(index, ..)| *index);
+        let (questions, warnings) =
+            merge_topic_questions(outcomes.into_iter().map(|(_, topic, count, result)| (topic, count, result)))?;
+
+        let test = MockTest {
+            id: Uuid::new_v4().to_string(),
+            user_id: request.user_id.clone(),
+            title: format!("Mock Law Exam: {}", request.topics.join(", ")),
+            description: Some(format!("Mock test covering: {}", request.topics.join(", "))),
+            questions,
+            created_at: Utc::now().to_rfc3339(),
+            kind: EXAM_KIND.to_string(),
+            truncated,
+            warnings,
+        };
 
-        let user_prompt = format!(
-            "Create a comprehensive mock law school exam with {} questions covering the following topics:
-{}{}
+        // Save test to storage
+        self.save_test(&test).await?;
 
-Provide your response as a JSON object with this structure:
-{{
-  \"title\": \"Descriptive exam title\",
-  \"questions\": [
-    {{
-      \"question\": \"The question text\",
-      \"options\": [\"Option A\", \"Option B\", \"Option C\", \"Option D\"],
-      \"correct_answer\": 0,
-      \"explanation\": \"Why this answer is correct\",
-      \"topic\": \"Contract Law\"
-    }}
-  ]
-}}",
-            request.num_questions,
-            request.topics.iter().enumerate().map(|(i, t)| format!("{}. {}", i + 1, t)).collect::<Vec<_>>().join("\n"),
-            context_info
-        );
+        Ok(test)
+    }
+
+    /// Search results to ground a topic's questions in, preferring documents
+    /// labelled with the topic when it is one of the fixed topics
+    async fn topic_context(&self, user_id: &str, topic: &str) -> Vec<SearchResult> {
+        // Vector mode, so scores are cosine similarities `allot_context` can compare across topics
+        let search = SearchOptions {
+            limit: Some(2),
+            mode: SearchMode::Vector,
+            user_id: Some(user_id.to_string()),
+            include_knowledge_base: Some(true),
+            diversify: Some(true),
+            ..Default::default()
+        };
+
+        // Questions can still be written without context, but say why there was none
+        let context = |result: AppResult<Vec<SearchResult>>| {
+            result.unwrap_or_else(|e| {
+                crate::logging::log(LogLevel::Warn, "mock_tests", &format!("No context for topic '{}': {}", topic, e));
+                Vec::new()
+            })
+        };
+
+        if let Some(tagged) = topics::canonical_topic(topic) {
+            let tagged = SearchOptions {
+                topic: Some(tagged.to_string()),
+                ..search.clone()
+            };
+            let results = context(self.rag_service.search(topic, tagged).await);
+            if !results.is_empty() {
+                return results;
+            }
+        }
+        context(self.rag_service.search(topic, search).await)
+    }
+
+    /// Generate `count` validated questions on a single exam topic, grounded
+    /// in `context` when there is any
+    async fn generate_topic_questions(
+        &self,
+        system_prompt: &str,
+        topic: &str,
+        difficulty: Option<&str>,
+        count: usize,
+        context: &[SearchResult],
+    ) -> AppResult<Vec<TestQuestion>> {
+        let context_info = if context.is_empty() {
+            String::new()
+        } else {
+            context_block(&self.rag_service.format_context_for_llm(context))
+        };
+        let sources = self.rag_service.citation_sources(context);
 
         let messages = vec![
             Message {
@@ -158,165 +330,222 @@ Provide your response as a JSON object with this structure:
             },
             Message {
                 role: "user".to_string(),
-                content: user_prompt,
+                content: exam_prompt(count, topic, difficulty, &context_info),
+            },
+        ];
+
+        let chat_options = crate::llm::ChatOptions {
+            temperature: Some(0.5),
+            max_tokens: Some(EXAM_MAX_TOKENS),
+            task: Some("mock_test".to_string()),
+            ..Default::default()
+        };
+        let response = self
+            .llm_service
+            .chat(messages, chat_options)
+            .await?;
+
+        let test_data = self.parse_json_response(&response)?;
+        let questions = self
+            .parse_questions(&test_data)?
+            .into_iter()
+            .filter(|q| validate_quiz_question(&q.question, &q.options, q.correct_answer).is_ok())
+            .take(count)
+            .map(|q| TestQuestion {
+                topic: Some(topic.to_string()),
+                difficulty: difficulty.map(str::to_string),
+                sources: sources.clone(),
+                ..q
+            })
+            .collect();
+
+        Ok(questions)
+    }
+
+    /// Generate a short single-topic quiz for daily practice
+    ///
+    /// Skips RAG and the full exam prompt so generation stays fast. Questions
+    /// that fail validation are dropped so every returned question is gradeable.
+    pub async fn generate_quick_quiz(
+        &self,
+        model: &str,
+        user_id: &str,
+        topic: &str,
+        num_questions: i32,
+    ) -> AppResult<MockTest> {
+        let user_id = &validate_uuid(user_id, "User ID")?;
+        validate_not_empty(topic, "Topic")?;
+        if !(QUIZ_MIN_QUESTIONS..=QUIZ_MAX_QUESTIONS).contains(&num_questions) {
+            return Err(AppError::Validation(format!(
+                "Quick quizzes must have between {} and {} questions",
+                QUIZ_MIN_QUESTIONS, QUIZ_MAX_QUESTIONS
+            )));
+        }
+
+        let messages = vec![
+            Message {
+                role: "system".to_string(),
+                content: self.llm_service.system_prompt(
+                    "You write short multiple-choice law school practice quizzes. Respond with JSON only.",
+                ),
+            },
+            Message {
+                role: "user".to_string(),
+                content: format!(
+                    "Write {} multiple-choice questions on {}. Each has 4 options, one correct answer, and a one-sentence explanation.
+JSON format: {{\"questions\": [{{\"question\": \"...\", \"options\": [\"...\", \"...\", \"...\", \"...\"], \"correct_answer\": 0, \"explanation\": \"...\"}}]}}",
+                    num_questions, topic
+                ),
             },
         ];
 
         let response = self
             .llm_service
             .chat(messages, crate::llm::ChatOptions {
+                model: Some(model.to_string()),
                 temperature: Some(0.5),
-                max_tokens: Some(4000),
-                model: None,
+                max_tokens: Some(1500),
+                task: Some("quick_quiz".to_string()),
+                ..Default::default()
             })
             .await?;
 
-        // Parse JSON response
-        let test_data: serde_json::Value = self.parse_json_response(&response)?;
-
-        let title = test_data["title"]
-            .as_str()
-            .unwrap_or("Mock Law Exam")
-            .to_string();
-
-        let questions: Vec<TestQuestion> = test_data["questions"]
-            .as_array()
-            .ok_or_else(|| AppError::Llm("Invalid questions format".to_string()))?
-            .iter()
+        let test_data = self.parse_json_response(&response)?;
+        let questions: Vec<TestQuestion> = self
+            .parse_questions(&test_data)?
+            .into_iter()
+            .filter(|q| validate_quiz_question(&q.question, &q.options, q.correct_answer).is_ok())
+            .take(num_questions as usize)
             .map(|q| TestQuestion {
-                question: q["question"].as_str().unwrap_or("").to_string(),
-                options: q["options"]
-                    .as_array()
-                    .unwrap_or(&Vec::new())
-                    .iter()
-                    .map(|o| o.as_str().unwrap_or("").to_string())
-                    .collect(),
-                correct_answer: q["correct_answer"].as_u64().unwrap_or(0) as usize,
-                explanation: q["explanation"].as_str().unwrap_or("").to_string(),
-                topic: q["topic"].as_str().map(|s| s.to_string()),
+                topic: Some(topic.to_string()),
+                ..q
             })
             .collect();
 
-        let test = MockTest {
+        if questions.is_empty() {
+            return Err(AppError::Llm("The model did not return any valid quiz questions".to_string()));
+        }
+
+        let quiz = MockTest {
             id: Uuid::new_v4().to_string(),
-            user_id: request.user_id.clone(),
-            title,
-            description: Some(format!("Mock test covering: {}", request.topics.join(", "))),
+            user_id: user_id.to_string(),
+            title: format!("Quick Quiz: {}", topic),
+            description: Some(format!("{}-question practice quiz on {}", questions.len(), topic)),
             questions,
             created_at: Utc::now().to_rfc3339(),
+            kind: QUIZ_KIND.to_string(),
+            truncated: false,
+            warnings: Vec::new(),
         };
 
-        // Save test to storage
-        self.save_test(&test).await?;
+        self.save_test(&quiz).await?;
 
-        Ok(test)
+        Ok(quiz)
     }
 
     /// Save a mock test to storage
     async fn save_test(&self, test: &MockTest) -> AppResult<()> {
-        let questions_json = serde_json::to_string(&test.questions)?;
-
-        // Try Supabase if online
-        if self.storage.is_online().await {
-            if let Some(supabase) = self.storage.supabase() {
-                let data = serde_json::json!({
-                    "id": test.id,
-                    "user_id": test.user_id,
-                    "title": test.title,
-                    "description": test.description,
-                    "questions": questions_json,
-                    "created_at": test.created_at,
-                });
-
-                supabase
-                    .insert("mock_tests", &data.to_string())
-                    .await?
-                    .execute()
-                    .await
-                    .map_err(|e| AppError::Supabase(format!("Failed to save test: {}", e)))?;
-            }
-        }
+        store_test(&self.storage, test).await
+    }
 
-        // Save locally
-        self.storage.sqlite().execute(move |conn| {
-            conn.execute(
-                "INSERT INTO mock_tests (id, user_id, title, description, questions, created_at, synced, dirty)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-                rusqlite::params![
-                    &test.id,
-                    &test.user_id,
-                    &test.title,
-                    &test.description,
-                    &questions_json,
-                    &test.created_at,
-                    if self.storage.is_online().await { 1 } else { 0 },
-                    if self.storage.is_online().await { 0 } else { 1 },
-                ],
-            )?;
-            Ok(())
-        }).await
-    }
-
-    /// Get all mock tests for a user
-    pub async fn get_tests(&self, user_id: &str) -> AppResult<Vec<MockTest>> {
-        validate_uuid(user_id, "User ID")?;
+    /// Get a page of mock tests for a user, newest first
+    pub async fn get_tests(
+        &self,
+        user_id: &str,
+        pagination: Option<PaginationOptions>,
+    ) -> AppResult<PagedResult<MockTest>> {
+        let user_id = &validate_uuid(user_id, "User ID")?;
+        let pagination = pagination.unwrap_or_default().normalized()?;
 
         // Try Supabase first if online
         if self.storage.is_online().await {
             if let Some(supabase) = self.storage.supabase() {
+                let (from, to) = pagination.range();
                 let response = supabase
                     .select("mock_tests")
                     .await?
                     .eq("user_id", user_id)
+                    .is("deleted_at", "null")
+                    .order("created_at.desc")
+                    .range(from, to)
+                    .exact_count()
                     .execute()
                     .await
                     .map_err(|e| AppError::Supabase(format!("Failed to fetch tests: {}", e)))?;
 
+                let total = response_total(&response);
                 let body = response.text().await?;
                 let tests: Vec<MockTest> = serde_json::from_str(&body)?;
-                return Ok(tests);
+                let total = total.unwrap_or(pagination.offset() + tests.len());
+                return Ok(PagedResult::new(tests, total, pagination));
             }
         }
 
         // Fallback to local
-        let user_id = user_id.to_string();
-        self.storage.sqlite().execute(move |conn| {
-            let mut stmt = conn.prepare(
-                "SELECT id, user_id, title, description, questions, created_at
-                 FROM mock_tests
-                 WHERE user_id = ?1
-                 ORDER BY created_at DESC"
-            )?;
-
-            let tests = stmt
-                .query_map([&user_id], |row| {
-                    let questions_json: String = row.get(4)?;
-                    let questions: Vec<TestQuestion> = serde_json::from_str(&questions_json).unwrap_or_default();
-
-                    Ok(MockTest {
-                        id: row.get(0)?,
-                        user_id: row.get(1)?,
-                        title: row.get(2)?,
-                        description: row.get(3)?,
-                        questions,
-                        created_at: row.get(5)?,
-                    })
-                })?
-                .collect::<Result<Vec<_>, _>>()?;
-
-            Ok(tests)
-        }).await
-    }
-
-    /// Submit test results
-    pub async fn submit_result(&self, request: SubmitTestResultRequest) -> AppResult<TestResult> {
-        validate_uuid(&request.user_id, "User ID")?;
-        validate_uuid(&request.test_id, "Test ID")?;
-
-        // Calculate score
-        let total_questions = request.answers.len() as i32;
-        let correct_answers = request.answers.iter().filter(|a| a.is_correct).count() as f64;
-        let score = correct_answers;
+        let pool = self.storage.sqlite().get_pool().await?;
+        let total: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM mock_tests WHERE user_id = ? AND deleted_at IS NULL"
+        )
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await?;
+
+        let rows = sqlx::query(
+            "SELECT id, user_id, title, description, questions, created_at, kind
+             FROM mock_tests
+             WHERE user_id = ? AND deleted_at IS NULL
+             ORDER BY created_at DESC
+             LIMIT ? OFFSET ?"
+        )
+        .bind(user_id)
+        .bind(pagination.page_size as i64)
+        .bind(pagination.offset() as i64)
+        .fetch_all(&pool)
+        .await?;
+
+        let tests = rows.iter().map(test_from_row).collect();
+        Ok(PagedResult::new(tests, total as usize, pagination))
+    }
+
+    /// Get a stored mock test by ID
+    pub async fn get_test(&self, test_id: &str) -> AppResult<MockTest> {
+        let test_id = &validate_uuid(test_id, "Test ID")?;
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        let row = sqlx::query(
+            "SELECT id, user_id, title, description, questions, created_at, kind
+             FROM mock_tests
+             WHERE id = ? AND deleted_at IS NULL"
+        )
+        .bind(test_id)
+        .fetch_optional(&pool)
+        .await?;
+
+        row.as_ref()
+            .map(test_from_row)
+            .ok_or_else(|| AppError::NotFound(format!("Mock test {} not found", test_id)))
+    }
+
+    /// Move a mock test to the recycle bin
+    pub async fn delete_test(&self, test_id: &str) -> AppResult<()> {
+        let test_id = validate_uuid(test_id, "Test ID")?;
+        recycle_bin::move_to_bin(&self.storage, "mock_tests", &test_id).await
+    }
+
+    /// Submit test results, grading the answers against the stored test
+    pub async fn submit_result(&self, mut request: SubmitTestResultRequest) -> AppResult<TestResult> {
+        request.user_id = validate_uuid(&request.user_id, "User ID")?;
+        request.test_id = validate_uuid(&request.test_id, "Test ID")?;
+
+        let test = self.get_test(&request.test_id).await?;
+        if test.user_id != request.user_id {
+            return Err(AppError::Validation("Test does not belong to this user".to_string()));
+        }
+
+        // Calculate score from the stored answer key, not client-reported correctness
+        let answers = grade_answers(&test.questions, &request.answers)?;
+        let total_questions = test.questions.len() as i32;
+        let score = answers.iter().filter(|a| a.is_correct).count() as f64;
 
         validate_score(score, total_questions)?;
 
@@ -326,15 +555,17 @@ Provide your response as a JSON object with this structure:
             test_id: request.test_id.clone(),
             score,
             total_questions,
-            answers: request.answers.clone(),
+            percentage: score_percentage(score, total_questions),
+            answers,
             completed_at: Utc::now().to_rfc3339(),
         };
 
         // Save result
         let answers_json = serde_json::to_string(&result.answers)?;
+        let online = self.storage.is_online().await;
 
         // Try Supabase if online
-        if self.storage.is_online().await {
+        if online {
             if let Some(supabase) = self.storage.supabase() {
                 let data = serde_json::json!({
                     "id": result.id,
@@ -342,42 +573,168 @@ Provide your response as a JSON object with this structure:
                     "test_id": result.test_id,
                     "score": result.score,
                     "total_questions": result.total_questions,
+                    "percentage": result.percentage,
                     "answers": answers_json,
                     "completed_at": result.completed_at,
                 });
 
-                supabase
-                    .insert("test_results", &data.to_string())
-                    .await?
-                    .execute()
+                execute_checked(supabase.upsert("test_results", &data.to_string(), "id").await?)
                     .await
                     .map_err(|e| AppError::Supabase(format!("Failed to save result: {}", e)))?;
             }
         }
 
         // Save locally
-        self.storage.sqlite().execute(move |conn| {
-            conn.execute(
-                "INSERT INTO test_results (id, user_id, test_id, score, total_questions, answers, completed_at, synced, dirty)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-                rusqlite::params![
-                    &result.id,
-                    &result.user_id,
-                    &result.test_id,
-                    result.score,
-                    result.total_questions,
-                    &answers_json,
-                    &result.completed_at,
-                    if self.storage.is_online().await { 1 } else { 0 },
-                    if self.storage.is_online().await { 0 } else { 1 },
-                ],
-            )?;
-            Ok(())
-        }).await?;
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query(
+            "INSERT INTO test_results (id, user_id, test_id, score, total_questions, percentage, answers, completed_at, synced, dirty)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&result.id)
+        .bind(&result.user_id)
+        .bind(&result.test_id)
+        .bind(result.score)
+        .bind(result.total_questions)
+        .bind(result.percentage)
+        .bind(&answers_json)
+        .bind(&result.completed_at)
+        .bind(online as i64)
+        .bind(!online as i64)
+        .execute(&pool)
+        .await?;
 
         Ok(result)
     }
 
+    /// Compute a readiness score from the user's local test results
+    pub async fn get_readiness_score(&self, user_id: &str) -> AppResult<ReadinessScore> {
+        let user_id = &validate_uuid(user_id, "User ID")?;
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        let rows = sqlx::query(
+            "SELECT r.id, r.user_id, r.test_id, r.score, r.total_questions, r.answers, r.completed_at, t.questions
+             FROM test_results r
+             LEFT JOIN mock_tests t ON t.id = r.test_id
+             WHERE r.user_id = ?"
+        )
+        .bind(user_id)
+        .fetch_all(&pool)
+        .await?;
+
+        let results: Vec<(TestResult, Vec<TestQuestion>)> = rows
+            .iter()
+            .map(|row| {
+                let questions_json: Option<String> = row.get("questions");
+                let questions: Vec<TestQuestion> = questions_json
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default();
+
+                (result_from_row(row), questions)
+            })
+            .collect();
+
+        Ok(readiness_score(&results, Utc::now()))
+    }
+
+    /// Compare a user's attempts at a test, oldest first
+    pub async fn compare_results(&self, user_id: &str, test_id: &str) -> AppResult<ResultComparison> {
+        let user_id = validate_uuid(user_id, "User ID")?;
+        let test_id = validate_uuid(test_id, "Test ID")?;
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        let questions_json: Option<String> = sqlx::query_scalar("SELECT questions FROM mock_tests WHERE id = ?")
+            .bind(&test_id)
+            .fetch_optional(&pool)
+            .await?;
+        let questions: Vec<TestQuestion> = questions_json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        let rows = sqlx::query(
+            "SELECT id, user_id, test_id, score, total_questions, answers, completed_at
+             FROM test_results
+             WHERE user_id = ? AND test_id = ?
+             ORDER BY completed_at ASC"
+        )
+        .bind(&user_id)
+        .bind(&test_id)
+        .fetch_all(&pool)
+        .await?;
+        let results: Vec<TestResult> = rows.iter().map(result_from_row).collect();
+
+        if results.is_empty() {
+            return Err(AppError::NotFound(format!("No results for mock test {}", test_id)));
+        }
+        Ok(compare_attempts(&results, &questions))
+    }
+
+    /// Export a user's results as CSV, one row per question of each result,
+    /// optionally only those completed on or after `from_date` (ISO 8601)
+    ///
+    /// Results of tests in the recycle bin are left out.
+    pub async fn export_results_to_csv(&self, user_id: &str, from_date: Option<&str>) -> AppResult<String> {
+        let user_id = validate_uuid(user_id, "User ID")?;
+        let from = from_date.map(export_lower_bound).transpose()?;
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        let rows = sqlx::query(
+            "SELECT r.test_id, t.title, r.completed_at, r.score, r.total_questions, t.questions
+             FROM test_results r
+             JOIN mock_tests t ON t.id = r.test_id
+             WHERE r.user_id = ?
+               AND t.deleted_at IS NULL
+               AND (? IS NULL OR r.completed_at >= ?)
+             ORDER BY r.completed_at ASC"
+        )
+        .bind(&user_id)
+        .bind(&from)
+        .bind(&from)
+        .fetch_all(&pool)
+        .await?;
+
+        let results: Vec<ExportedResult> = rows
+            .iter()
+            .map(|row| {
+                let questions_json: Option<String> = row.get("questions");
+                ExportedResult {
+                    test_id: row.get("test_id"),
+                    test_title: row.get("title"),
+                    completed_at: row.get("completed_at"),
+                    score: row.get::<Option<f64>, _>("score").unwrap_or(0.0),
+                    total_questions: row.get::<Option<i32>, _>("total_questions").unwrap_or(0),
+                    questions: questions_json
+                        .and_then(|json| serde_json::from_str(&json).ok())
+                        .unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        results_csv(&results)
+    }
+
+    /// Read the `questions` array of a generated test
+    fn parse_questions(&self, test_data: &serde_json::Value) -> AppResult<Vec<TestQuestion>> {
+        Ok(test_data["questions"]
+            .as_array()
+            .ok_or_else(|| AppError::Llm("Invalid questions format".to_string()))?
+            .iter()
+            .map(|q| TestQuestion {
+                question: q["question"].as_str().unwrap_or("").to_string(),
+                options: q["options"]
+                    .as_array()
+                    .unwrap_or(&Vec::new())
+                    .iter()
+                    .map(|o| o.as_str().unwrap_or("").to_string())
+                    .collect(),
+                correct_answer: q["correct_answer"].as_u64().unwrap_or(0) as usize,
+                explanation: q["explanation"].as_str().unwrap_or("").to_string(),
+                topic: q["topic"].as_str().map(|s| s.to_string()),
+                difficulty: None,
+                sources: Vec::new(),
+            })
+            .collect())
+    }
+
     /// Parse JSON response from LLM (handles markdown code blocks)
     fn parse_json_response(&self, response: &str) -> AppResult<serde_json::Value> {
         // Try direct parse
@@ -412,3 +769,868 @@ Provide your response as a JSON object with this structure:
     }
 }
 
+/// Grade submitted answers against a test's answer key
+///
+/// Rejects answers for question indices that don't exist and repeated
+/// answers to the same question.
+fn grade_answers(questions: &[TestQuestion], answers: &[UserAnswer]) -> AppResult<Vec<UserAnswer>> {
+    let mut seen = HashSet::new();
+
+    answers
+        .iter()
+        .map(|answer| {
+            let question = questions.get(answer.question_index).ok_or_else(|| {
+                AppError::Validation(format!(
+                    "Answer references question {} but the test has {} questions",
+                    answer.question_index,
+                    questions.len()
+                ))
+            })?;
+
+            if !seen.insert(answer.question_index) {
+                return Err(AppError::Validation(format!(
+                    "Question {} was answered more than once",
+                    answer.question_index
+                )));
+            }
+
+            Ok(UserAnswer {
+                question_index: answer.question_index,
+                selected_answer: answer.selected_answer,
+                is_correct: answer.selected_answer == question.correct_answer,
+            })
+        })
+        .collect()
+}
+
+/// User prompt asking for `count` questions on `topic`, with `context_info` appended
+fn exam_prompt(count: usize, topic: &str, difficulty: Option<&str>, context_info: &str) -> String {
+    let difficulty = difficulty.map(|d| format!(" at {} difficulty", d)).unwrap_or_default();
+    format!(
+        "Create {} mock law school exam questions{} on the topic: {}{}
+
+Provide your response as a JSON object with this structure:
+{{
+  \"questions\": [
+    {{
+      \"question\": \"The question text\",
+      \"options\": [\"Option A\", \"Option B\", \"Option C\", \"Option D\"],
+      \"correct_answer\": 0,
+      \"explanation\": \"Why this answer is correct\"
+    }}
+  ]
+}}",
+        count,
+        difficulty,
+        topic,
+        context_info
+    )
+}
+
+/// Formatted RAG context as it is appended to the exam prompt
+fn context_block(context: &str) -> String {
+    fenced_context("Relevant Legal Context", context)
+}
+
+/// Tokens of RAG context the topics of an exam may share
+///
+/// This is the model's context window less the completion tokens and the
+/// longest topic prompt, so any topic's request fits even if it gets all of it.
+fn exam_context_budget(
+    model: &str,
+    system_prompt: &str,
+    topics: &[String],
+    counts: &[usize],
+    difficulty: Option<&str>,
+) -> usize {
+    let longest_topic = topics.iter().max_by_key(|t| t.len()).map(String::as_str).unwrap_or("");
+    let max_count = counts.iter().copied().max().unwrap_or(0);
+    let template = exam_prompt(max_count, longest_topic, difficulty, &context_block(""));
+    tokens::prompt_budget(model, EXAM_MAX_TOKENS, &[system_prompt, &template])
+}
+
+/// Split `budget` tokens of context between topics by relevance
+///
+/// Results from every topic compete on score, so a strong match for the last
+/// topic beats a weak one for the first. Chunks are kept whole or dropped.
+/// Returns each topic's kept results, best first, and whether any were dropped.
+fn allot_context(contexts: Vec<Vec<SearchResult>>, budget: usize, model: &str) -> (Vec<Vec<SearchResult>>, bool) {
+    let topics = contexts.len();
+    let most_sources = contexts.iter().map(Vec::len).max().unwrap_or(0);
+    let candidates: Vec<(usize, SearchResult)> = contexts
+        .into_iter()
+        .enumerate()
+        .flat_map(|(topic, results)| results.into_iter().map(move |r| (topic, r)))
+        .collect();
+
+    // Each chunk is costed at the widest citation number it could get
+    let (kept, dropped) = tokens::select_within_budget(
+        candidates,
+        budget,
+        |(_, r)| r.score,
+        |(_, r)| tokens::token_estimate(&format!("{}\n\n", ContextChunk::from(r).cite(most_sources)), model),
+    );
+
+    let mut contexts = vec![Vec::new(); topics];
+    for (topic, result) in kept {
+        contexts[topic].push(result);
+    }
+    (contexts, dropped)
+}
+
+/// Split `total` questions across `topics` as evenly as possible, earlier topics first
+fn split_questions(total: usize, topics: usize) -> Vec<usize> {
+    if topics == 0 {
+        return Vec::new();
+    }
+    (0..topics)
+        .map(|i| total / topics + usize::from(i < total % topics))
+        .collect()
+}
+
+/// Questions whose word sets overlap at least this much are treated as duplicates
+const DUPLICATE_SIMILARITY: f64 = 0.9;
+
+fn question_words(question: &str) -> HashSet<String> {
+    question
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// Drop questions that are near-identical (by word overlap) to an earlier one
+fn dedupe_questions(questions: Vec<TestQuestion>) -> Vec<TestQuestion> {
+    let mut kept: Vec<(HashSet<String>, TestQuestion)> = Vec::new();
+
+    for question in questions {
+        let words = question_words(&question.question);
+        let duplicate = kept.iter().any(|(other, _)| {
+            let union = words.union(other).count();
+            union > 0 && words.intersection(other).count() as f64 / union as f64 >= DUPLICATE_SIMILARITY
+        });
+        if !duplicate {
+            kept.push((words, question));
+        }
+    }
+
+    kept.into_iter().map(|(_, q)| q).collect()
+}
+
+/// Combine each topic's questions, in topic order, noting shortfalls and
+/// duplicates as warnings
+///
+/// A topic that failed only adds a warning, unless the model refused: the
+/// whole test is then rejected rather than saved without that topic.
+fn merge_topic_questions(
+    outcomes: impl IntoIterator<Item = (String, usize, AppResult<Vec<TestQuestion>>)>,
+) -> AppResult<(Vec<TestQuestion>, Vec<String>)> {
+    let mut questions = Vec::new();
+    let mut warnings = Vec::new();
+    let mut first_error = None;
+    for (topic, count, result) in outcomes {
+        match result {
+            Ok(topic_questions) => {
+                if topic_questions.len() < count {
+                    warnings.push(format!(
+                        "{}: generated {} of {} questions",
+                        topic,
+                        topic_questions.len(),
+                        count
+                    ));
+                }
+                questions.extend(topic_questions);
+            }
+            Err(e @ AppError::ModelRefusal(_)) => return Err(e),
+            Err(e) => {
+                warnings.push(format!("{}: generation failed ({})", topic, e));
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    let before = questions.len();
+    let questions = dedupe_questions(questions);
+    if questions.len() < before {
+        warnings.push(format!("Removed {} duplicate questions", before - questions.len()));
+    }
+
+    if questions.is_empty() {
+        return Err(first_error.unwrap_or_else(|| {
+            AppError::Llm("The model did not return any valid exam questions".to_string())
+        }));
+    }
+
+    Ok((questions, warnings))
+}
+
+/// Save a mock test to Supabase when online and to the local cache
+pub(crate) async fn store_test(storage: &HybridStorage, test: &MockTest) -> AppResult<()> {
+    let questions_json = serde_json::to_string(&test.questions)?;
+    let online = storage.is_online().await;
+
+    // Try Supabase if online
+    if online {
+        if let Some(supabase) = storage.supabase() {
+            let data = serde_json::json!({
+                "id": test.id,
+                "user_id": test.user_id,
+                "title": test.title,
+                "description": test.description,
+                "questions": questions_json,
+                "created_at": test.created_at,
+                "kind": test.kind,
+            });
+
+            execute_checked(supabase.upsert("mock_tests", &data.to_string(), "id").await?)
+                .await
+                .map_err(|e| AppError::Supabase(format!("Failed to save test: {}", e)))?;
+        }
+    }
+
+    // Save locally
+    let pool = storage.sqlite().get_pool().await?;
+    sqlx::query(
+        "INSERT INTO mock_tests (id, user_id, title, description, questions, created_at, kind, synced, dirty)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&test.id)
+    .bind(&test.user_id)
+    .bind(&test.title)
+    .bind(&test.description)
+    .bind(&questions_json)
+    .bind(&test.created_at)
+    .bind(&test.kind)
+    .bind(online as i64)
+    .bind(!online as i64)
+    .execute(&pool)
+    .await?;
+    Ok(())
+}
+
+/// A `mock_tests` row; unreadable questions JSON gives a test without questions
+fn test_from_row(row: &sqlx::sqlite::SqliteRow) -> MockTest {
+    let questions_json: String = row.get("questions");
+    MockTest {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        title: row.get("title"),
+        description: row.get("description"),
+        questions: serde_json::from_str(&questions_json).unwrap_or_default(),
+        created_at: row.get("created_at"),
+        kind: row.get("kind"),
+        truncated: false,
+        warnings: Vec::new(),
+    }
+}
+
+/// A `test_results` row; a missing score or total counts as 0
+fn result_from_row(row: &sqlx::sqlite::SqliteRow) -> TestResult {
+    let score: f64 = row.get::<Option<f64>, _>("score").unwrap_or(0.0);
+    let total_questions: i32 = row.get::<Option<i32>, _>("total_questions").unwrap_or(0);
+    let answers_json: Option<String> = row.get("answers");
+
+    TestResult {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        test_id: row.get("test_id"),
+        score,
+        total_questions,
+        percentage: score_percentage(score, total_questions),
+        answers: answers_json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        completed_at: row.get("completed_at"),
+    }
+}
+
+/// Score as a percentage of the total, 0 for an empty test
+fn score_percentage(score: f64, total_questions: i32) -> f64 {
+    if total_questions <= 0 {
+        0.0
+    } else {
+        score / total_questions as f64 * 100.0
+    }
+}
+
+/// Weight of a result completed at `completed_at`, halving every
+/// `READINESS_HALF_LIFE_DAYS` so a result from today counts twice as much as
+/// one from a half-life ago
+fn recency_weight(completed_at: &str, now: DateTime<Utc>) -> f64 {
+    let age_days = DateTime::parse_from_rfc3339(completed_at)
+        .map(|t| (now - t.with_timezone(&Utc)).num_seconds() as f64 / 86_400.0)
+        .unwrap_or(0.0)
+        .max(0.0);
+    0.5_f64.powf(age_days / READINESS_HALF_LIFE_DAYS)
+}
+
+/// Logistic estimate of passing, 50% at `PASS_MARK`
+fn pass_probability(overall_score: f64) -> f64 {
+    1.0 / (1.0 + (-PASS_CURVE_STEEPNESS * (overall_score - PASS_MARK)).exp())
+}
+
+/// Readiness from graded results and the questions of the tests they were
+/// taken on; unanswered questions count as incorrect
+fn readiness_score(results: &[(TestResult, Vec<TestQuestion>)], now: DateTime<Utc>) -> ReadinessScore {
+    if results.is_empty() {
+        return ReadinessScore {
+            overall_score: 0.0,
+            by_topic: HashMap::new(),
+            tests_taken: 0,
+            estimated_pass_probability: 0.0,
+            recommended_focus: Vec::new(),
+        };
+    }
+
+    let mut weighted_score = 0.0;
+    let mut total_weight = 0.0;
+    // Topic -> (weighted correct answers, weighted questions)
+    let mut topics: HashMap<String, (f64, f64)> = HashMap::new();
+
+    for (result, questions) in results {
+        let weight = recency_weight(&result.completed_at, now);
+        weighted_score += weight * score_percentage(result.score, result.total_questions);
+        total_weight += weight;
+
+        let correct: HashSet<usize> = result
+            .answers
+            .iter()
+            .filter(|a| a.is_correct)
+            .map(|a| a.question_index)
+            .collect();
+        for (index, question) in questions.iter().enumerate() {
+            let Some(topic) = question.topic.as_deref().map(str::trim).filter(|t| !t.is_empty()) else {
+                continue;
+            };
+            let entry = topics.entry(topic.to_string()).or_default();
+            if correct.contains(&index) {
+                entry.0 += weight;
+            }
+            entry.1 += weight;
+        }
+    }
+
+    let overall_score = weighted_score / total_weight;
+    let by_topic: HashMap<String, f64> = topics
+        .into_iter()
+        .map(|(topic, (correct, total))| (topic, correct / total * 100.0))
+        .collect();
+
+    let mut weakest: Vec<(&String, &f64)> = by_topic.iter().collect();
+    weakest.sort_by(|a, b| a.1.total_cmp(b.1).then_with(|| a.0.cmp(b.0)));
+    let recommended_focus = weakest
+        .into_iter()
+        .take(FOCUS_TOPIC_COUNT)
+        .map(|(topic, _)| topic.clone())
+        .collect();
+
+    ReadinessScore {
+        overall_score,
+        by_topic,
+        tests_taken: results.len() as u32,
+        estimated_pass_probability: pass_probability(overall_score),
+        recommended_focus,
+    }
+}
+
+/// Correct rate (percent) per topic of `questions` in one result;
+/// unanswered questions count as incorrect
+fn topic_accuracy(result: &TestResult, questions: &[TestQuestion]) -> HashMap<String, f64> {
+    let correct: HashSet<usize> = result
+        .answers
+        .iter()
+        .filter(|a| a.is_correct)
+        .map(|a| a.question_index)
+        .collect();
+
+    // Topic -> (correct answers, questions)
+    let mut topics: HashMap<String, (u32, u32)> = HashMap::new();
+    for (index, question) in questions.iter().enumerate() {
+        let Some(topic) = question.topic.as_deref().map(str::trim).filter(|t| !t.is_empty()) else {
+            continue;
+        };
+        let entry = topics.entry(topic.to_string()).or_default();
+        if correct.contains(&index) {
+            entry.0 += 1;
+        }
+        entry.1 += 1;
+    }
+
+    topics
+        .into_iter()
+        .map(|(topic, (correct, total))| (topic, correct as f64 / total as f64 * 100.0))
+        .collect()
+}
+
+/// `from_date` as an RFC 3339 UTC timestamp comparable with `completed_at`;
+/// a bare date means the start of that day
+fn export_lower_bound(from_date: &str) -> AppResult<String> {
+    let from_date = from_date.trim();
+    let start = DateTime::parse_from_rfc3339(from_date)
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(from_date, "%Y-%m-%d")
+                .map(|d| d.and_time(NaiveTime::MIN).and_utc())
+        })
+        .map_err(|_| AppError::Validation(format!("From date must be an ISO 8601 date: {}", from_date)))?;
+    Ok(start.to_rfc3339())
+}
+
+/// CSV of `results`, one row per question with that question's topic; a
+/// result whose test has no stored questions still gets one row
+fn results_csv(results: &[ExportedResult]) -> AppResult<String> {
+    let csv_error = |e: csv::Error| AppError::Internal(format!("Failed to write CSV: {}", e));
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(RESULTS_CSV_HEADER).map_err(csv_error)?;
+
+    for result in results {
+        let completed_at = DateTime::parse_from_rfc3339(&result.completed_at)
+            .map(|t| t.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::Secs, true))
+            .unwrap_or_else(|_| result.completed_at.clone());
+        let score = result.score.to_string();
+        let total_questions = result.total_questions.to_string();
+        let pct_correct = format!("{:.2}", score_percentage(result.score, result.total_questions));
+
+        let topics: Vec<&str> = if result.questions.is_empty() {
+            vec![""]
+        } else {
+            result.questions.iter().map(|q| q.topic.as_deref().unwrap_or("")).collect()
+        };
+        for topic in topics {
+            writer
+                .write_record([
+                    result.test_id.as_str(),
+                    result.test_title.as_str(),
+                    completed_at.as_str(),
+                    score.as_str(),
+                    total_questions.as_str(),
+                    pct_correct.as_str(),
+                    topic,
+                ])
+                .map_err(csv_error)?;
+        }
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| AppError::Internal(format!("Failed to write CSV: {}", e)))?;
+    String::from_utf8(bytes).map_err(|e| AppError::Internal(format!("CSV is not UTF-8: {}", e)))
+}
+
+/// Least-squares slope of `scores` against their index, 0 for fewer than two
+fn trend_slope(scores: &[f64]) -> f64 {
+    if scores.len() < 2 {
+        return 0.0;
+    }
+
+    let n = scores.len() as f64;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = scores.iter().sum::<f64>() / n;
+    let (covariance, variance) = scores.iter().enumerate().fold((0.0, 0.0), |(cov, var), (i, y)| {
+        let dx = i as f64 - mean_x;
+        (cov + dx * (y - mean_y), var + dx * dx)
+    });
+    covariance / variance
+}
+
+/// Attempts in the order given (oldest first) with their trend; `results` must not be empty
+fn compare_attempts(results: &[TestResult], questions: &[TestQuestion]) -> ResultComparison {
+    let attempts: Vec<AttemptSummary> = results
+        .iter()
+        .map(|result| AttemptSummary {
+            result_id: result.id.clone(),
+            completed_at: result.completed_at.clone(),
+            score: score_percentage(result.score, result.total_questions),
+            by_topic: topic_accuracy(result, questions),
+        })
+        .collect();
+
+    let scores: Vec<f64> = attempts.iter().map(|a| a.score).collect();
+    let slope = trend_slope(&scores);
+
+    ResultComparison {
+        slope,
+        improved: slope > 0.0,
+        best_score: scores.iter().copied().fold(f64::MIN, f64::max),
+        worst_score: scores.iter().copied().fold(f64::MAX, f64::min),
+        attempts,
+    }
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub async fn get_mock_tests(
+    state: State<'_, AppState>,
+    user_id: String,
+    pagination: Option<PaginationOptions>,
+) -> CommandResult<PagedResult<MockTest>> {
+    let service = state.mock_test_service().await?;
+    service.get_tests(&user_id, pagination).await.map_err(CommandError::from)
+}
+
+/// Generate a full exam, one request per topic; topics that fail are listed in `warnings`
+#[tauri::command]
+pub async fn generate_mock_test(
+    state: State<'_, AppState>,
+    user_id: String,
+    topics: Vec<String>,
+    num_questions: i32,
+    include_rag_context: Option<bool>,
+    difficulty: Option<String>,
+) -> CommandResult<MockTest> {
+    let service = state.mock_test_service().await?;
+    service
+        .generate_test(GenerateMockTestRequest {
+            user_id,
+            topics,
+            num_questions,
+            include_rag_context,
+            difficulty,
+        })
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn generate_quick_quiz(
+    state: State<'_, AppState>,
+    user_id: String,
+    topic: String,
+    num_questions: Option<i32>,
+) -> CommandResult<MockTest> {
+    let service = state.mock_test_service().await?;
+    let quiz_model = state.config.read().await.models.quiz_model.clone();
+    service
+        .generate_quick_quiz(
+            &quiz_model,
+            &user_id,
+            &topic,
+            num_questions.unwrap_or(QUIZ_MIN_QUESTIONS),
+        )
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn submit_test_result(
+    state: State<'_, AppState>,
+    request: SubmitTestResultRequest,
+) -> CommandResult<TestResult> {
+    let service = state.mock_test_service().await?;
+    service.submit_result(request).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn delete_mock_test(state: State<'_, AppState>, test_id: String) -> CommandResult<()> {
+    let service = state.mock_test_service().await?;
+    service.delete_test(&test_id).await.map_err(CommandError::from)
+}
+
+/// Compare a user's attempts at a test to see whether they are improving
+#[tauri::command]
+pub async fn compare_test_results(
+    state: State<'_, AppState>,
+    user_id: String,
+    test_id: String,
+) -> CommandResult<ResultComparison> {
+    let service = state.mock_test_service().await?;
+    service.compare_results(&user_id, &test_id).await.map_err(CommandError::from)
+}
+
+/// Write a user's results to `output_path` as CSV for spreadsheets
+#[tauri::command]
+pub async fn export_test_results_to_csv(
+    state: State<'_, AppState>,
+    user_id: String,
+    from_date: Option<String>,
+    output_path: String,
+) -> CommandResult<()> {
+    let service = state.mock_test_service().await?;
+    let csv = service.export_results_to_csv(&user_id, from_date.as_deref()).await?;
+    tokio::fs::write(&output_path, csv).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_readiness_score(
+    state: State<'_, AppState>,
+    user_id: String,
+) -> CommandResult<ReadinessScore> {
+    let service = state.mock_test_service().await?;
+    service.get_readiness_score(&user_id).await.map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::ChunkMetadata;
+
+    fn question(correct_answer: usize) -> TestQuestion {
+        TestQuestion {
+            question: "Which element is required for a valid contract?".to_string(),
+            options: vec!["Offer".to_string(), "Tort".to_string(), "Mens rea".to_string()],
+            correct_answer,
+            explanation: "A contract requires offer and acceptance".to_string(),
+            topic: Some("Contract Law".to_string()),
+            difficulty: None,
+            sources: Vec::new(),
+        }
+    }
+
+    fn search_result(chunk_id: &str, score: f32, chars: usize) -> SearchResult {
+        SearchResult {
+            chunk_id: chunk_id.to_string(),
+            document_id: "doc".to_string(),
+            text: "law ".repeat(chars / 4),
+            score,
+            document_type: "knowledge_base".to_string(),
+            metadata: ChunkMetadata {
+                document_id: "doc".to_string(),
+                chunk_index: 0,
+                user_id: None,
+                case_id: None,
+                document_type: "knowledge_base".to_string(),
+                source_title: "Casebook".to_string(),
+                section: None,
+            },
+        }
+    }
+
+    fn answer(question_index: usize, selected_answer: usize, is_correct: bool) -> UserAnswer {
+        UserAnswer {
+            question_index,
+            selected_answer,
+            is_correct,
+        }
+    }
+
+    #[test]
+    fn test_grade_answers_ignores_client_is_correct() {
+        let questions = vec![question(0), question(2)];
+        let answers = vec![answer(0, 0, false), answer(1, 1, true)];
+
+        let graded = grade_answers(&questions, &answers).unwrap();
+
+        assert!(graded[0].is_correct);
+        assert!(!graded[1].is_correct);
+    }
+
+    #[test]
+    fn test_grade_answers_rejects_out_of_range_index() {
+        let questions = vec![question(0), question(1)];
+        let answers = vec![answer(0, 0, true), answer(2, 0, true)];
+
+        assert!(grade_answers(&questions, &answers).is_err());
+    }
+
+    #[test]
+    fn test_grade_answers_rejects_duplicate_index() {
+        let questions = vec![question(0), question(1)];
+        let answers = vec![answer(0, 0, true), answer(0, 0, true)];
+
+        assert!(grade_answers(&questions, &answers).is_err());
+    }
+
+    #[test]
+    fn test_split_questions() {
+        assert_eq!(split_questions(40, 5), vec![8, 8, 8, 8, 8]);
+        assert_eq!(split_questions(10, 3), vec![4, 3, 3]);
+        assert_eq!(split_questions(2, 3), vec![1, 1, 0]);
+        assert!(split_questions(5, 0).is_empty());
+    }
+
+    #[test]
+    fn test_dedupe_questions() {
+        let mut a = question(0);
+        a.question = "Which element of negligence requires a duty of care?".to_string();
+        let mut b = question(1);
+        b.question = "Which element of negligence requires a duty of care".to_string();
+        let mut c = question(2);
+        c.question = "What is consideration in contract law?".to_string();
+
+        let kept = dedupe_questions(vec![a, b, c]);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].correct_answer, 0);
+        assert_eq!(kept[1].correct_answer, 2);
+    }
+
+    #[test]
+    fn test_a_refused_topic_rejects_the_whole_test() {
+        let mut second = question(0);
+        second.question = "What does consideration require?".to_string();
+        let outcomes = || {
+            vec![
+                ("Contract Law".to_string(), 2, Ok(vec![question(0), second.clone()])),
+                ("Torts".to_string(), 1, Err(AppError::Llm("timed out".to_string()))),
+            ]
+        };
+
+        let (questions, warnings) = merge_topic_questions(outcomes()).unwrap();
+        assert_eq!(questions.len(), 2);
+        assert_eq!(warnings, vec!["Torts: generation failed (LLM service error: timed out)".to_string()]);
+
+        let mut refused = outcomes();
+        refused.push(("Criminal Law".to_string(), 1, Err(AppError::ModelRefusal("content_filter".to_string()))));
+        assert!(matches!(merge_topic_questions(refused), Err(AppError::ModelRefusal(_))));
+    }
+
+    #[test]
+    fn test_score_percentage() {
+        assert_eq!(score_percentage(3.0, 4), 75.0);
+        assert_eq!(score_percentage(0.0, 0), 0.0);
+    }
+
+    #[test]
+    fn test_readiness_score_weights_recent_results() {
+        let now = Utc::now();
+        let mut torts = question(0);
+        torts.topic = Some("Torts".to_string());
+        let questions = vec![question(0), question(0), torts.clone(), torts];
+        let result = |score: f64, answers: Vec<UserAnswer>, age_days: i64| TestResult {
+            id: Uuid::new_v4().to_string(),
+            user_id: "user".to_string(),
+            test_id: "test".to_string(),
+            score,
+            total_questions: 4,
+            percentage: 0.0,
+            answers,
+            completed_at: (now - chrono::Duration::days(age_days)).to_rfc3339(),
+        };
+
+        // Today: both contract questions right, torts wrong. A half-life ago: everything wrong
+        let results = vec![
+            (result(2.0, vec![answer(0, 0, true), answer(1, 0, true), answer(2, 1, false)], 0), questions.clone()),
+            (result(0.0, vec![answer(0, 1, false)], READINESS_HALF_LIFE_DAYS as i64), questions),
+        ];
+        let readiness = readiness_score(&results, now);
+
+        assert_eq!(readiness.tests_taken, 2);
+        assert!((readiness.overall_score - 100.0 / 3.0).abs() < 0.01);
+        assert!((readiness.by_topic["Contract Law"] - 200.0 / 3.0).abs() < 0.01);
+        assert_eq!(readiness.by_topic["Torts"], 0.0);
+        assert_eq!(readiness.recommended_focus, vec!["Torts", "Contract Law"]);
+        assert!(readiness.estimated_pass_probability < 0.5);
+
+        assert_eq!(pass_probability(PASS_MARK), 0.5);
+        assert!(pass_probability(90.0) > 0.95);
+        assert_eq!(readiness_score(&[], now).tests_taken, 0);
+    }
+
+    #[test]
+    fn test_compare_attempts_trend_and_topics() {
+        let mut torts = question(0);
+        torts.topic = Some("Torts".to_string());
+        let questions = vec![question(0), torts];
+        let result = |score: f64, answers: Vec<UserAnswer>| TestResult {
+            id: Uuid::new_v4().to_string(),
+            user_id: "user".to_string(),
+            test_id: "test".to_string(),
+            score,
+            total_questions: 2,
+            percentage: 0.0,
+            answers,
+            completed_at: "2024-03-01T00:00:00Z".to_string(),
+        };
+
+        let results = vec![
+            result(0.0, vec![answer(0, 1, false)]),
+            result(1.0, vec![answer(0, 0, true), answer(1, 1, false)]),
+            result(2.0, vec![answer(0, 0, true), answer(1, 0, true)]),
+        ];
+        let comparison = compare_attempts(&results, &questions);
+
+        let scores: Vec<f64> = comparison.attempts.iter().map(|a| a.score).collect();
+        assert_eq!(scores, vec![0.0, 50.0, 100.0]);
+        assert_eq!(comparison.slope, 50.0);
+        assert!(comparison.improved);
+        assert_eq!((comparison.best_score, comparison.worst_score), (100.0, 0.0));
+        assert_eq!(comparison.attempts[1].by_topic["Contract Law"], 100.0);
+        assert_eq!(comparison.attempts[1].by_topic["Torts"], 0.0);
+
+        let single = compare_attempts(&results[1..2], &questions);
+        assert_eq!(single.attempts.len(), 1);
+        assert_eq!(single.slope, 0.0);
+        assert!(!single.improved);
+    }
+
+    #[test]
+    fn test_results_csv_has_a_row_per_question() {
+        let mut torts = question(0);
+        torts.topic = None;
+        let results = vec![
+            ExportedResult {
+                test_id: "t1".to_string(),
+                test_title: "Contracts, midterm".to_string(),
+                completed_at: "2024-03-01T12:00:00.5+02:00".to_string(),
+                score: 1.0,
+                total_questions: 2,
+                questions: vec![question(0), torts],
+            },
+            ExportedResult {
+                test_id: "t2".to_string(),
+                test_title: "Torts".to_string(),
+                completed_at: "2024-03-02T09:00:00+00:00".to_string(),
+                score: 0.0,
+                total_questions: 0,
+                questions: Vec::new(),
+            },
+        ];
+
+        let csv = results_csv(&results).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "test_id,test_title,completed_at,score,total_questions,pct_correct,topic");
+        assert_eq!(lines[1], "t1,\"Contracts, midterm\",2024-03-01T10:00:00Z,1,2,50.00,Contract Law");
+        assert_eq!(lines[2], "t1,\"Contracts, midterm\",2024-03-01T10:00:00Z,1,2,50.00,");
+        assert_eq!(lines[3], "t2,Torts,2024-03-02T09:00:00Z,0,0,0.00,");
+        assert_eq!(lines.len(), 4);
+    }
+
+    #[test]
+    fn test_export_lower_bound_accepts_dates_and_timestamps() {
+        assert_eq!(export_lower_bound("2024-03-01").unwrap(), "2024-03-01T00:00:00+00:00");
+        assert_eq!(export_lower_bound("2024-03-01T12:00:00+02:00").unwrap(), "2024-03-01T10:00:00+00:00");
+        assert!(matches!(export_lower_bound("March 1st"), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_trend_slope() {
+        assert_eq!(trend_slope(&[]), 0.0);
+        assert_eq!(trend_slope(&[70.0]), 0.0);
+        assert_eq!(trend_slope(&[80.0, 70.0, 60.0]), -10.0);
+        assert_eq!(trend_slope(&[60.0, 60.0]), 0.0);
+    }
+
+    #[test]
+    fn test_allot_context_keeps_prompts_within_the_model_window() {
+        let model = "unknown/model"; // 8,192 token window
+        let topics = vec!["Torts".to_string(), "Contracts".to_string()];
+        let counts = vec![5, 5];
+        let contexts = vec![
+            vec![search_result("torts-weak", 0.4, 40_000), search_result("torts-ok", 0.6, 4_000)],
+            vec![search_result("contracts-best", 0.9, 6_000), search_result("contracts-big", 0.5, 12_000)],
+        ];
+
+        let budget = exam_context_budget(model, EXAM_SYSTEM_PROMPT, &topics, &counts, None);
+        let (contexts, truncated) = allot_context(contexts, budget, model);
+
+        assert!(truncated);
+        let kept: Vec<Vec<&str>> = contexts
+            .iter()
+            .map(|results| results.iter().map(|r| r.chunk_id.as_str()).collect())
+            .collect();
+        assert_eq!(kept, vec![vec!["torts-ok"], vec!["contracts-best"]]);
+
+        for ((topic, count), results) in topics.iter().zip(&counts).zip(&contexts) {
+            let context = results
+                .iter()
+                .enumerate()
+                .map(|(i, r)| ContextChunk::from(r).cite(i + 1))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            let prompt = exam_prompt(*count, topic, None, &context_block(&context));
+            let prompt_tokens = tokens::token_estimate(EXAM_SYSTEM_PROMPT, model) + tokens::token_estimate(&prompt, model);
+            assert!(prompt_tokens + EXAM_MAX_TOKENS as usize <= tokens::context_window(model));
+        }
+    }
+}
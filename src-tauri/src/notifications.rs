@@ -0,0 +1,186 @@
+/**
+ * Notifications Module
+ * Desktop notifications for study tasks due today
+ */
+
+use crate::config::AppConfig;
+use crate::db::HybridStorage;
+use crate::error::AppResult;
+use crate::logging::LogLevel;
+use chrono::{Local, Utc};
+use sqlx::{Pool, Row, Sqlite};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+use tokio::sync::RwLock;
+
+/// How often the scheduler checks for due tasks
+const NOTIFICATION_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// An incomplete study task due on the checked date
+#[derive(Debug, Clone, PartialEq)]
+struct DueTask {
+    task_id: String,
+    title: String,
+    plan_title: String,
+}
+
+/// Check for study tasks due today once a minute for the life of the app,
+/// notifying each one once `notification_time` has passed
+///
+/// `config` is read on every check, so a reloaded config applies without a restart.
+pub fn spawn_notification_scheduler(app: AppHandle, storage: HybridStorage, config: Arc<RwLock<AppConfig>>) {
+    tauri::async_runtime::spawn(async move {
+        let pool = match storage.sqlite().get_pool().await {
+            Ok(pool) => pool,
+            Err(e) => {
+                crate::logging::log(LogLevel::Error, "notifications", &format!("Notification scheduler not started: {}", e));
+                return;
+            }
+        };
+
+        let mut ticker = tokio::time::interval(NOTIFICATION_CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+
+            let (enabled, notify_from) = {
+                let config = config.read().await;
+                (config.enable_notifications, config.notification_time())
+            };
+            let Some(notify_from) = notify_from.filter(|_| enabled) else {
+                continue;
+            };
+
+            let now = Local::now();
+            if now.time() < notify_from {
+                continue;
+            }
+
+            let today = now.date_naive().format("%Y-%m-%d").to_string();
+            if let Err(e) = notify_due_tasks(&app, &pool, &today).await {
+                crate::logging::log(LogLevel::Warn, "notifications", &format!("Due task check failed: {}", e));
+            }
+        }
+    });
+}
+
+/// Show a notification for each task due on `date` that hasn't had one yet
+async fn notify_due_tasks(app: &AppHandle, pool: &Pool<Sqlite>, date: &str) -> AppResult<()> {
+    for task in due_tasks(pool, date).await? {
+        let shown = app
+            .notification()
+            .builder()
+            .title("Study task due today")
+            .body(format!("{} ({})", task.title, task.plan_title))
+            .show();
+
+        match shown {
+            Ok(()) => mark_sent(pool, &task.task_id, date).await?,
+            // Left unmarked so the next check tries again
+            Err(e) => crate::logging::log(
+                LogLevel::Warn,
+                "notifications",
+                &format!("Failed to show notification for task {}: {}", task.task_id, e),
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Incomplete tasks in study plans outside the recycle bin that are due on
+/// `date` (YYYY-MM-DD) and haven't been notified for it
+async fn due_tasks(pool: &Pool<Sqlite>, date: &str) -> AppResult<Vec<DueTask>> {
+    // Plans with unreadable task JSON are skipped rather than failing the whole query
+    let rows = sqlx::query(
+        "SELECT json_extract(t.value, '$.id') AS task_id,
+                json_extract(t.value, '$.title') AS title,
+                p.title AS plan_title
+         FROM study_plans p,
+              json_each(CASE WHEN json_valid(p.tasks) THEN p.tasks ELSE '[]' END) t
+         WHERE p.deleted_at IS NULL
+           AND substr(json_extract(t.value, '$.due_date'), 1, 10) = ?
+           AND json_extract(t.value, '$.completed') = 0
+           AND NOT EXISTS (
+               SELECT 1 FROM sent_notifications s
+               WHERE s.task_id = json_extract(t.value, '$.id') AND s.date = ?
+           )
+         ORDER BY p.title, title"
+    )
+    .bind(date)
+    .bind(date)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| DueTask {
+            task_id: row.get("task_id"),
+            title: row.get("title"),
+            plan_title: row.get("plan_title"),
+        })
+        .collect())
+}
+
+/// Record that `task_id` has been notified for `date`
+async fn mark_sent(pool: &Pool<Sqlite>, task_id: &str, date: &str) -> AppResult<()> {
+    sqlx::query("INSERT OR IGNORE INTO sent_notifications (task_id, date, sent_at) VALUES (?, ?, ?)")
+        .bind(task_id)
+        .bind(date)
+        .bind(Utc::now().to_rfc3339())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+    use serde_json::json;
+
+    async fn insert_plan(pool: &Pool<Sqlite>, id: &str, title: &str, tasks: &str, deleted: bool) {
+        sqlx::query(
+            "INSERT INTO study_plans (id, user_id, title, tasks, created_at, updated_at, deleted_at)
+             VALUES (?, 'user', ?, ?, 'now', 'now', ?)"
+        )
+        .bind(id)
+        .bind(title)
+        .bind(tasks)
+        .bind(deleted.then_some("now"))
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    fn task(id: &str, due_date: &str, completed: bool) -> serde_json::Value {
+        json!({ "id": id, "title": format!("Task {}", id), "completed": completed, "due_date": due_date })
+    }
+
+    #[tokio::test]
+    async fn test_due_tasks_are_incomplete_tasks_due_that_day_not_yet_notified() {
+        let pool = test_pool().await;
+
+        let tasks = json!([
+            task("due", "2026-03-02", false),
+            task("done", "2026-03-02", true),
+            task("tomorrow", "2026-03-03", false),
+            task("timestamp", "2026-03-02T17:00:00Z", false),
+        ]);
+        insert_plan(&pool, "plan", "Contracts", &tasks.to_string(), false).await;
+        insert_plan(&pool, "binned", "Torts", &json!([task("binned", "2026-03-02", false)]).to_string(), true).await;
+        insert_plan(&pool, "broken", "Crim", "not json", false).await;
+
+        let due = due_tasks(&pool, "2026-03-02").await.unwrap();
+        let ids: Vec<&str> = due.iter().map(|t| t.task_id.as_str()).collect();
+        assert_eq!(ids, vec!["due", "timestamp"]);
+        assert_eq!(due[0].plan_title, "Contracts");
+
+        mark_sent(&pool, "due", "2026-03-02").await.unwrap();
+        mark_sent(&pool, "due", "2026-03-02").await.unwrap();
+        let due = due_tasks(&pool, "2026-03-02").await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].task_id, "timestamp");
+    }
+}
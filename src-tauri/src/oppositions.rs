@@ -0,0 +1,137 @@
+/**
+ * Oppositions Module
+ * Stores the opposing side's arguments generated against a case's IRAC analysis
+ */
+
+use crate::db::HybridStorage;
+use crate::error::{AppResult, CommandResult};
+use crate::llm::IRACResult;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use tauri::State;
+use uuid::Uuid;
+
+/// The other side's case against an IRAC analysis, for moot court preparation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpposingArguments {
+    pub counter_issue: String,
+    pub counter_rule: String,
+    pub counter_analysis: String,
+    pub counter_conclusion: String,
+    /// Points where the original rule application could be challenged
+    #[serde(default)]
+    pub weaknesses: Vec<String>,
+}
+
+/// An analysis and the opposing arguments generated against it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CaseOpposition {
+    pub id: String,
+    pub case_id: String,
+    pub irac: IRACResult,
+    pub opposition: OpposingArguments,
+    pub created_at: String,
+}
+
+/// Record opposing arguments against `irac` for a case, returning the new row's id
+pub async fn save_opposition(
+    pool: &Pool<Sqlite>,
+    case_id: &str,
+    irac: &IRACResult,
+    opposition: &OpposingArguments,
+) -> AppResult<String> {
+    let id = Uuid::new_v4().to_string();
+
+    sqlx::query(
+        "INSERT INTO irac_oppositions
+         (id, case_id, irac, counter_issue, counter_rule, counter_analysis, counter_conclusion, weaknesses, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)"
+    )
+    .bind(&id)
+    .bind(case_id)
+    .bind(serde_json::to_string(irac)?)
+    .bind(&opposition.counter_issue)
+    .bind(&opposition.counter_rule)
+    .bind(&opposition.counter_analysis)
+    .bind(&opposition.counter_conclusion)
+    .bind(serde_json::to_string(&opposition.weaknesses)?)
+    .execute(pool)
+    .await?;
+
+    Ok(id)
+}
+
+/// Opposing arguments recorded for a case, newest first
+pub async fn case_oppositions(pool: &Pool<Sqlite>, case_id: &str) -> AppResult<Vec<CaseOpposition>> {
+    let rows = sqlx::query(
+        "SELECT id, case_id, irac, counter_issue, counter_rule, counter_analysis, counter_conclusion, weaknesses, created_at
+         FROM irac_oppositions
+         WHERE case_id = ?
+         ORDER BY created_at DESC, rowid DESC"
+    )
+    .bind(case_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| {
+            Ok(CaseOpposition {
+                id: row.get("id"),
+                case_id: row.get("case_id"),
+                irac: serde_json::from_str(row.get("irac"))?,
+                opposition: OpposingArguments {
+                    counter_issue: row.get("counter_issue"),
+                    counter_rule: row.get("counter_rule"),
+                    counter_analysis: row.get("counter_analysis"),
+                    counter_conclusion: row.get("counter_conclusion"),
+                    weaknesses: serde_json::from_str(row.get("weaknesses"))?,
+                },
+                created_at: row.get("created_at"),
+            })
+        })
+        .collect()
+}
+
+// Tauri Commands
+
+/// Analyses of a case alongside the opposing arguments generated against them
+#[tauri::command]
+pub async fn get_case_oppositions(
+    storage: State<'_, HybridStorage>,
+    case_id: String,
+) -> CommandResult<Vec<CaseOpposition>> {
+    let pool = storage.sqlite().get_pool().await?;
+    Ok(case_oppositions(&pool, &case_id).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    #[tokio::test]
+    async fn test_save_and_load_oppositions() {
+        let pool = test_pool().await;
+
+        let irac: IRACResult = serde_json::from_str(
+            r#"{"issue": "Was a duty owed?", "rule": "Neighbour principle", "analysis": "Proximity exists", "conclusion": "Yes"}"#,
+        )
+        .unwrap();
+        let opposition = OpposingArguments {
+            counter_issue: "Was the harm foreseeable?".to_string(),
+            counter_rule: "Caparo three-stage test".to_string(),
+            counter_analysis: "No assumption of responsibility".to_string(),
+            counter_conclusion: "No duty".to_string(),
+            weaknesses: vec!["Proximity is asserted, not shown".to_string()],
+        };
+
+        let id = save_opposition(&pool, "case-1", &irac, &opposition).await.unwrap();
+        save_opposition(&pool, "case-2", &irac, &opposition).await.unwrap();
+
+        let saved = case_oppositions(&pool, "case-1").await.unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].id, id);
+        assert_eq!(saved[0].irac.rule, "Neighbour principle");
+        assert_eq!(saved[0].opposition, opposition);
+    }
+}
@@ -0,0 +1,407 @@
+/**
+ * Outlines Module
+ * Generates topic outlines from a user's ingested cases and manages stored outlines
+ */
+
+use crate::db::HybridStorage;
+use crate::error::{AppError, AppResult, CommandError, CommandResult};
+use crate::llm::{ChatOptions, LLMService, Message};
+use crate::rag::{RAGService, SearchOptions, SearchResult};
+use crate::state::AppState;
+use crate::tokens;
+use crate::validation::{validate_not_empty, validate_uuid};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use tauri::State;
+use uuid::Uuid;
+
+/// Chunks retrieved per outline
+const OUTLINE_SEARCH_LIMIT: usize = 20;
+
+/// Completion sizes for the map (per-case notes) and reduce (outline) steps
+const MAP_MAX_TOKENS: u32 = 800;
+const REDUCE_MAX_TOKENS: u32 = 3000;
+
+/// Stop condensing notes after this many reduce rounds
+const MAX_REDUCE_ROUNDS: usize = 4;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Outline {
+    pub id: String,
+    pub user_id: String,
+    pub topic: String,
+    /// Markdown outline citing the supporting cases
+    pub content: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Clone)]
+pub struct OutlineService {
+    storage: HybridStorage,
+}
+
+impl OutlineService {
+    pub fn new(storage: HybridStorage) -> Self {
+        Self { storage }
+    }
+
+    /// Generate and save an outline of `topic` from the user's ingested cases
+    ///
+    /// Retrieved chunks are grouped by source case and summarized per case
+    /// (map), then the notes are combined into a single outline (reduce), so
+    /// long material is condensed rather than truncated.
+    pub async fn generate(
+        &self,
+        rag: &RAGService,
+        llm: &LLMService,
+        user_id: &str,
+        topic: &str,
+    ) -> AppResult<Outline> {
+        let user_id = &validate_uuid(user_id, "User ID")?;
+        validate_not_empty(topic, "Topic")?;
+
+        let results = rag
+            .search(
+                topic,
+                SearchOptions {
+                    limit: Some(OUTLINE_SEARCH_LIMIT),
+                    user_id: Some(user_id.to_string()),
+                    include_knowledge_base: Some(false),
+                    diversify: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        if results.is_empty() {
+            return Err(AppError::NotFound(format!(
+                "No ingested cases mention '{}'",
+                topic
+            )));
+        }
+
+        let notes = self.summarize_sources(llm, topic, group_by_source(results)).await?;
+        let content = self.reduce_to_outline(llm, topic, notes).await?;
+
+        let outline = Outline {
+            id: Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            topic: topic.to_string(),
+            content,
+            created_at: Utc::now().to_rfc3339(),
+            updated_at: Utc::now().to_rfc3339(),
+        };
+
+        self.save_outline(&outline).await?;
+
+        Ok(outline)
+    }
+
+    /// Map step: notes on the topic for each source case
+    async fn summarize_sources(
+        &self,
+        llm: &LLMService,
+        topic: &str,
+        sources: Vec<(String, Vec<String>)>,
+    ) -> AppResult<Vec<String>> {
+        let system_prompt = "You are a legal research assistant preparing notes for a law student's course outline.
+Extract only the holdings, rules, and reasoning that bear on the given topic, as concise bullet points.";
+
+        let model = llm.default_model().to_string();
+        let budget = tokens::prompt_budget(&model, MAP_MAX_TOKENS, &[system_prompt, topic]);
+        let mut notes = Vec::new();
+
+        for (title, texts) in sources {
+            let mut bullets = Vec::new();
+            for part in tokens::pack_to_budget(&texts, budget, &model) {
+                let prompt = format!(
+                    "Topic: {}\nCase: {}\n\nExcerpts:\n{}\n\nList the points from this case relevant to the topic.",
+                    topic, title, part
+                );
+                bullets.push(self.ask(llm, system_prompt, prompt, MAP_MAX_TOKENS).await?);
+            }
+            notes.push(format!("### {}\n{}", title, bullets.join("\n")));
+        }
+
+        Ok(notes)
+    }
+
+    /// Reduce step: condense notes until they fit, then write the outline
+    async fn reduce_to_outline(
+        &self,
+        llm: &LLMService,
+        topic: &str,
+        mut notes: Vec<String>,
+    ) -> AppResult<String> {
+        let system_prompt = "You are a legal research assistant building a law student's course outline.
+Write a hierarchical markdown outline of the topic using headings and nested bullets.
+Cite the supporting case in parentheses after each point, using the case names given in the notes.
+Only include points supported by the notes.";
+
+        let model = llm.default_model().to_string();
+        let budget = tokens::prompt_budget(&model, REDUCE_MAX_TOKENS, &[system_prompt, topic]);
+
+        for _ in 0..MAX_REDUCE_ROUNDS {
+            let batches = tokens::pack_to_budget(&notes, budget, &model);
+            if batches.len() <= 1 {
+                break;
+            }
+
+            let mut condensed = Vec::with_capacity(batches.len());
+            for batch in batches {
+                let prompt = format!(
+                    "Topic: {}\n\nNotes:\n{}\n\nMerge these notes into shorter notes, keeping each point's case name.",
+                    topic, batch
+                );
+                condensed.push(self.ask(llm, system_prompt, prompt, MAP_MAX_TOKENS).await?);
+            }
+            notes = condensed;
+        }
+
+        let notes = tokens::truncate_to_tokens(&notes.join("\n\n"), budget, &model);
+        let prompt = format!(
+            "Topic: {}\n\nNotes by case:\n{}\n\nWrite the outline for this topic.",
+            topic, notes
+        );
+
+        self.ask(llm, system_prompt, prompt, REDUCE_MAX_TOKENS).await
+    }
+
+    async fn ask(
+        &self,
+        llm: &LLMService,
+        system_prompt: &str,
+        prompt: String,
+        max_tokens: u32,
+    ) -> AppResult<String> {
+        let messages = vec![
+            Message {
+                role: "system".to_string(),
+                content: system_prompt.to_string(),
+            },
+            Message {
+                role: "user".to_string(),
+                content: prompt,
+            },
+        ];
+
+        llm.chat(
+            messages,
+            ChatOptions {
+                temperature: Some(0.3),
+                max_tokens: Some(max_tokens),
+                task: Some("outline".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Save an outline to storage
+    async fn save_outline(&self, outline: &Outline) -> AppResult<()> {
+        let online = self.storage.is_online().await;
+
+        // Try Supabase if online
+        if online {
+            if let Some(supabase) = self.storage.supabase() {
+                let data = serde_json::json!({
+                    "id": outline.id,
+                    "user_id": outline.user_id,
+                    "topic": outline.topic,
+                    "content": outline.content,
+                    "created_at": outline.created_at,
+                    "updated_at": outline.updated_at,
+                });
+
+                supabase
+                    .insert("outlines", &data.to_string())
+                    .await?
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to save outline: {}", e)))?;
+            }
+        }
+
+        // Save locally
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query(
+            "INSERT INTO outlines (id, user_id, topic, content, created_at, updated_at, synced, dirty)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&outline.id)
+        .bind(&outline.user_id)
+        .bind(&outline.topic)
+        .bind(&outline.content)
+        .bind(&outline.created_at)
+        .bind(&outline.updated_at)
+        .bind(online as i64)
+        .bind(!online as i64)
+        .execute(&pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Get all outlines for a user
+    pub async fn get_outlines(&self, user_id: &str) -> AppResult<Vec<Outline>> {
+        let user_id = &validate_uuid(user_id, "User ID")?;
+
+        // Try Supabase first if online
+        if self.storage.is_online().await {
+            if let Some(supabase) = self.storage.supabase() {
+                let response = supabase
+                    .select("outlines")
+                    .await?
+                    .eq("user_id", user_id)
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to fetch outlines: {}", e)))?;
+
+                let body = response.text().await?;
+                let outlines: Vec<Outline> = serde_json::from_str(&body)?;
+                return Ok(outlines);
+            }
+        }
+
+        // Fallback to local
+        let pool = self.storage.sqlite().get_pool().await?;
+        let rows = sqlx::query(
+            "SELECT id, user_id, topic, content, created_at, updated_at
+             FROM outlines
+             WHERE user_id = ?
+             ORDER BY updated_at DESC"
+        )
+        .bind(user_id)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| Outline {
+                id: row.get("id"),
+                user_id: row.get("user_id"),
+                topic: row.get("topic"),
+                content: row.get("content"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+            })
+            .collect())
+    }
+
+    /// Delete an outline
+    pub async fn delete_outline(&self, outline_id: &str) -> AppResult<()> {
+        let outline_id = &validate_uuid(outline_id, "Outline ID")?;
+
+        // Try Supabase if online
+        if self.storage.is_online().await {
+            if let Some(supabase) = self.storage.supabase() {
+                supabase
+                    .delete("outlines")
+                    .await?
+                    .eq("id", outline_id)
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Supabase(format!("Failed to delete outline: {}", e)))?;
+            }
+        }
+
+        // Delete locally
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query("DELETE FROM outlines WHERE id = ?")
+            .bind(outline_id)
+            .execute(&pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Group search results by source title, keeping the order of best match
+fn group_by_source(results: Vec<SearchResult>) -> Vec<(String, Vec<String>)> {
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+
+    for result in results {
+        let title = result.metadata.source_title;
+        match groups.iter_mut().find(|(t, _)| *t == title) {
+            Some((_, texts)) => texts.push(result.text),
+            None => groups.push((title, vec![result.text])),
+        }
+    }
+
+    groups
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub async fn generate_outline(
+    state: State<'_, AppState>,
+    user_id: String,
+    topic: String,
+) -> CommandResult<Outline> {
+    let service = state.outline_service().await?;
+    let rag = state.rag_service().await?;
+    let llm = state.llm_service().await?;
+
+    service
+        .generate(&rag, &llm, &user_id, &topic)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn get_outlines(
+    state: State<'_, AppState>,
+    user_id: String,
+) -> CommandResult<Vec<Outline>> {
+    let service = state.outline_service().await?;
+    service.get_outlines(&user_id).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn delete_outline(
+    state: State<'_, AppState>,
+    id: String,
+) -> CommandResult<()> {
+    let service = state.outline_service().await?;
+    service.delete_outline(&id).await.map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::ChunkMetadata;
+
+    fn result(title: &str, text: &str) -> SearchResult {
+        SearchResult {
+            chunk_id: Uuid::new_v4().to_string(),
+            document_id: "doc".to_string(),
+            text: text.to_string(),
+            score: 0.5,
+            document_type: "user_case".to_string(),
+            metadata: ChunkMetadata {
+                document_id: "doc".to_string(),
+                chunk_index: 0,
+                user_id: None,
+                case_id: None,
+                document_type: "user_case".to_string(),
+                source_title: title.to_string(),
+                section: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_group_by_source_keeps_rank_order() {
+        let groups = group_by_source(vec![
+            result("Carlill v Carbolic", "offer to the world"),
+            result("Hadley v Baxendale", "remoteness"),
+            result("Carlill v Carbolic", "acceptance by performance"),
+        ]);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, "Carlill v Carbolic");
+        assert_eq!(groups[0].1, vec!["offer to the world", "acceptance by performance"]);
+        assert_eq!(groups[1].0, "Hadley v Baxendale");
+    }
+}
@@ -0,0 +1,230 @@
+/**
+ * Precedents Module
+ * Suggests knowledge base precedents related to a case that aren't yet in the student's case library
+ */
+
+use crate::db::HybridStorage;
+use crate::error::{AppResult, CommandError, CommandResult};
+use crate::rag::{RAGService, SearchMode, SearchOptions, SearchResult};
+use crate::state::AppState;
+use crate::validation::{validate_not_empty, validate_uuid};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tauri::State;
+
+/// Default number of suggestions for `recommend_precedents`
+const DEFAULT_PRECEDENT_LIMIT: usize = 5;
+
+/// Most suggestions returned at once
+const MAX_PRECEDENT_LIMIT: usize = 20;
+
+/// Chunks retrieved per requested suggestion, since several chunks often
+/// come from the same source
+const CHUNKS_PER_SUGGESTION: usize = 4;
+
+/// Longest excerpt shown with a suggestion, in characters
+const MAX_EXCERPT_CHARS: usize = 300;
+
+/// A knowledge base source related to a case
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PrecedentSuggestion {
+    pub title: String,
+    /// Similarity of the best matching chunk, 0-1
+    pub relevance_score: f32,
+    /// Start of the best matching chunk
+    pub excerpt: String,
+    pub document_id: String,
+}
+
+#[derive(Clone)]
+pub struct PrecedentService {
+    storage: HybridStorage,
+    rag_service: RAGService,
+}
+
+impl PrecedentService {
+    pub fn new(storage: HybridStorage, rag_service: RAGService) -> Self {
+        Self { storage, rag_service }
+    }
+
+    /// Suggest knowledge base sources related to `case_text`, best match
+    /// first, leaving out any whose title matches a case or document already
+    /// in the user's library
+    pub async fn recommend_precedents(
+        &self,
+        case_text: &str,
+        user_id: &str,
+        limit: usize,
+    ) -> AppResult<Vec<PrecedentSuggestion>> {
+        validate_not_empty(case_text, "Case text")?;
+        let user_id = &validate_uuid(user_id, "User ID")?;
+        let limit = limit.clamp(1, MAX_PRECEDENT_LIMIT);
+
+        // Vector mode, so each score is a cosine similarity rather than a fused rank
+        let options = SearchOptions {
+            limit: Some(limit * CHUNKS_PER_SUGGESTION),
+            mode: SearchMode::Vector,
+            include_knowledge_base: Some(true),
+            document_type: Some("knowledge_base".to_string()),
+            ..Default::default()
+        };
+        let results = self.rag_service.search(case_text, options).await?;
+        let library = self.library_titles(user_id).await?;
+
+        Ok(group_precedents(results, &library, limit))
+    }
+
+    /// Normalized titles of the user's cases (title and case name) and documents
+    async fn library_titles(&self, user_id: &str) -> AppResult<HashSet<String>> {
+        let pool = self.storage.sqlite().get_pool().await?;
+
+        let titles: Vec<String> = sqlx::query_scalar(
+            "SELECT title FROM cases WHERE user_id = ?1 AND deleted_at IS NULL
+             UNION SELECT case_name FROM cases WHERE user_id = ?1 AND case_name IS NOT NULL AND deleted_at IS NULL
+             UNION SELECT title FROM documents WHERE user_id = ?1"
+        )
+        .bind(user_id)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(titles.iter().map(|t| normalize_title(t)).collect())
+    }
+}
+
+/// Lowercased title with runs of whitespace collapsed, for matching
+fn normalize_title(title: &str) -> String {
+    title.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// One suggestion per source title, scored by its best chunk, skipping
+/// titles in `library`
+fn group_precedents(
+    results: Vec<SearchResult>,
+    library: &HashSet<String>,
+    limit: usize,
+) -> Vec<PrecedentSuggestion> {
+    let mut by_title: HashMap<String, PrecedentSuggestion> = HashMap::new();
+
+    for result in results {
+        let title = result.metadata.source_title.trim().to_string();
+        let key = normalize_title(&title);
+        if key.is_empty() || library.contains(&key) {
+            continue;
+        }
+
+        let better = by_title
+            .get(&key)
+            .is_none_or(|existing| result.score > existing.relevance_score);
+        if better {
+            by_title.insert(
+                key,
+                PrecedentSuggestion {
+                    title,
+                    relevance_score: result.score,
+                    excerpt: excerpt(&result.text),
+                    document_id: result.document_id,
+                },
+            );
+        }
+    }
+
+    let mut suggestions: Vec<PrecedentSuggestion> = by_title.into_values().collect();
+    suggestions.sort_by(|a, b| {
+        b.relevance_score
+            .total_cmp(&a.relevance_score)
+            .then_with(|| a.title.cmp(&b.title))
+    });
+    suggestions.truncate(limit);
+    suggestions
+}
+
+/// First `MAX_EXCERPT_CHARS` characters of a chunk, cut at a word boundary
+fn excerpt(text: &str) -> String {
+    let text = text.trim();
+    if text.chars().count() <= MAX_EXCERPT_CHARS {
+        return text.to_string();
+    }
+
+    let cut: String = text.chars().take(MAX_EXCERPT_CHARS).collect();
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(i) => &cut[..i],
+        None => &cut,
+    };
+    format!("{}...", cut.trim_end())
+}
+
+// Tauri Commands
+
+/// Suggest knowledge base precedents related to a case the user hasn't added yet
+#[tauri::command]
+pub async fn recommend_precedents(
+    state: State<'_, AppState>,
+    case_text: String,
+    user_id: String,
+    limit: Option<usize>,
+) -> CommandResult<Vec<PrecedentSuggestion>> {
+    let service = state.precedent_service().await?;
+    service
+        .recommend_precedents(&case_text, &user_id, limit.unwrap_or(DEFAULT_PRECEDENT_LIMIT))
+        .await
+        .map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::ChunkMetadata;
+
+    fn result(document_id: &str, title: &str, text: &str, score: f32) -> SearchResult {
+        SearchResult {
+            chunk_id: format!("{}-{}", document_id, score),
+            document_id: document_id.to_string(),
+            text: text.to_string(),
+            score,
+            document_type: "knowledge_base".to_string(),
+            metadata: ChunkMetadata {
+                document_id: document_id.to_string(),
+                chunk_index: 0,
+                user_id: None,
+                case_id: None,
+                document_type: "knowledge_base".to_string(),
+                source_title: title.to_string(),
+                section: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_group_precedents() {
+        let results = vec![
+            result("donoghue", "Donoghue v Stevenson", "The neighbour principle.", 0.6),
+            result("caparo", "Caparo v Dickman", "The three-stage test.", 0.7),
+            result("donoghue", "Donoghue v Stevenson", "A duty of care is owed.", 0.9),
+            result("hedley", "Hedley Byrne v Heller", "Negligent misstatement.", 0.8),
+        ];
+        let library: HashSet<String> = [normalize_title("  hedley byrne   v HELLER")].into_iter().collect();
+
+        let suggestions = group_precedents(results, &library, 5);
+
+        let titles: Vec<&str> = suggestions.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, vec!["Donoghue v Stevenson", "Caparo v Dickman"]);
+        assert_eq!(suggestions[0].relevance_score, 0.9);
+        assert_eq!(suggestions[0].excerpt, "A duty of care is owed.");
+
+        let results = vec![
+            result("a", "A", "a", 0.5),
+            result("b", "B", "b", 0.4),
+        ];
+        assert_eq!(group_precedents(results, &HashSet::new(), 1).len(), 1);
+    }
+
+    #[test]
+    fn test_excerpt_cuts_at_word_boundary() {
+        let text = "negligence ".repeat(40);
+        let short = excerpt(&text);
+
+        assert!(short.ends_with("negligence..."));
+        assert!(short.chars().count() <= MAX_EXCERPT_CHARS + 3);
+        assert_eq!(excerpt("  Short passage. "), "Short passage.");
+    }
+}
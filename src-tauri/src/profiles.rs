@@ -0,0 +1,304 @@
+/**
+ * Profiles Module
+ * Local profiles for the people sharing this device; the current profile
+ * only prefills the frontend, and every data command still takes a user ID
+ */
+
+use crate::db::HybridStorage;
+use crate::error::{AppError, AppResult, CommandError, CommandResult};
+use crate::state::AppState;
+use crate::validation::{validate_email, validate_length, validate_url_http_or_https, validate_uuid};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use tauri::State;
+use uuid::Uuid;
+
+/// Longest display name accepted
+const MAX_DISPLAY_NAME_CHARS: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UserProfile {
+    pub id: String,
+    pub display_name: String,
+    pub email: Option<String>,
+    pub avatar_url: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateProfileRequest {
+    /// The signed-in Supabase user's ID, so the profile owns their synced data;
+    /// a new ID is generated when missing
+    pub id: Option<String>,
+    pub display_name: String,
+    pub email: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+/// New values for a profile's editable fields
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateProfileRequest {
+    pub id: String,
+    pub display_name: String,
+    pub email: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct ProfileService {
+    storage: HybridStorage,
+}
+
+impl ProfileService {
+    pub fn new(storage: HybridStorage) -> Self {
+        Self { storage }
+    }
+
+    pub async fn create_profile(&self, request: CreateProfileRequest) -> AppResult<UserProfile> {
+        let id = match request.id {
+            Some(id) => validate_uuid(&id, "Profile ID")?,
+            None => Uuid::new_v4().to_string(),
+        };
+        let profile = UserProfile {
+            id,
+            display_name: request.display_name.trim().to_string(),
+            email: request.email,
+            avatar_url: request.avatar_url,
+            created_at: Utc::now().to_rfc3339(),
+        };
+        let profile = validate_profile(profile)?;
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        insert_profile(&pool, &profile).await?;
+        Ok(profile)
+    }
+
+    pub async fn get_profile(&self, id: &str) -> AppResult<UserProfile> {
+        let id = validate_uuid(id, "Profile ID")?;
+        let pool = self.storage.sqlite().get_pool().await?;
+        fetch_profile(&pool, &id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Profile {} not found", id)))
+    }
+
+    pub async fn update_profile(&self, request: UpdateProfileRequest) -> AppResult<UserProfile> {
+        let mut profile = self.get_profile(&request.id).await?;
+        profile.display_name = request.display_name.trim().to_string();
+        profile.email = request.email;
+        profile.avatar_url = request.avatar_url;
+        let profile = validate_profile(profile)?;
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        update_profile_row(&pool, &profile).await?;
+        Ok(profile)
+    }
+
+    /// Delete a profile; data stored under its user ID is left alone
+    pub async fn delete_profile(&self, id: &str) -> AppResult<()> {
+        let id = validate_uuid(id, "Profile ID")?;
+        let pool = self.storage.sqlite().get_pool().await?;
+
+        let deleted = sqlx::query("DELETE FROM profiles WHERE id = ?")
+            .bind(&id)
+            .execute(&pool)
+            .await?
+            .rows_affected();
+        if deleted == 0 {
+            return Err(AppError::NotFound(format!("Profile {} not found", id)));
+        }
+        Ok(())
+    }
+
+    /// Every profile on this device, by display name
+    pub async fn list_profiles(&self) -> AppResult<Vec<UserProfile>> {
+        let pool = self.storage.sqlite().get_pool().await?;
+        fetch_profiles(&pool).await
+    }
+}
+
+/// `profile` with blank optional fields cleared, or an error naming the invalid field
+fn validate_profile(mut profile: UserProfile) -> AppResult<UserProfile> {
+    validate_length(&profile.display_name, "Display name", 1, MAX_DISPLAY_NAME_CHARS)?;
+
+    profile.email = profile.email.map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+    if let Some(email) = &profile.email {
+        validate_email(email)?;
+    }
+
+    profile.avatar_url = profile.avatar_url.map(|u| u.trim().to_string()).filter(|u| !u.is_empty());
+    if let Some(url) = &profile.avatar_url {
+        validate_url_http_or_https(url, "Avatar URL")?;
+    }
+
+    Ok(profile)
+}
+
+async fn insert_profile(pool: &Pool<Sqlite>, profile: &UserProfile) -> AppResult<()> {
+    let inserted = sqlx::query(
+        "INSERT OR IGNORE INTO profiles (id, display_name, email, avatar_url, created_at)
+         VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(&profile.id)
+    .bind(&profile.display_name)
+    .bind(&profile.email)
+    .bind(&profile.avatar_url)
+    .bind(&profile.created_at)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    if inserted == 0 {
+        return Err(AppError::Validation(format!("Profile {} already exists", profile.id)));
+    }
+    Ok(())
+}
+
+async fn update_profile_row(pool: &Pool<Sqlite>, profile: &UserProfile) -> AppResult<()> {
+    sqlx::query("UPDATE profiles SET display_name = ?, email = ?, avatar_url = ? WHERE id = ?")
+        .bind(&profile.display_name)
+        .bind(&profile.email)
+        .bind(&profile.avatar_url)
+        .bind(&profile.id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+async fn fetch_profile(pool: &Pool<Sqlite>, id: &str) -> AppResult<Option<UserProfile>> {
+    let row = sqlx::query("SELECT * FROM profiles WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.as_ref().map(profile_from_row))
+}
+
+async fn fetch_profiles(pool: &Pool<Sqlite>) -> AppResult<Vec<UserProfile>> {
+    let rows = sqlx::query("SELECT * FROM profiles ORDER BY display_name COLLATE NOCASE, created_at")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(profile_from_row).collect())
+}
+
+fn profile_from_row(row: &sqlx::sqlite::SqliteRow) -> UserProfile {
+    UserProfile {
+        id: row.get("id"),
+        display_name: row.get("display_name"),
+        email: row.get("email"),
+        avatar_url: row.get("avatar_url"),
+        created_at: row.get("created_at"),
+    }
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub async fn create_profile(state: State<'_, AppState>, request: CreateProfileRequest) -> CommandResult<UserProfile> {
+    let service = state.profile_service().await?;
+    service.create_profile(request).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn get_profile(state: State<'_, AppState>, id: String) -> CommandResult<UserProfile> {
+    let service = state.profile_service().await?;
+    service.get_profile(&id).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn update_profile(state: State<'_, AppState>, request: UpdateProfileRequest) -> CommandResult<UserProfile> {
+    let service = state.profile_service().await?;
+    service.update_profile(request).await.map_err(CommandError::from)
+}
+
+/// Delete a profile, signing it out first if it is the current one
+#[tauri::command]
+pub async fn delete_profile(state: State<'_, AppState>, id: String) -> CommandResult<()> {
+    let service = state.profile_service().await?;
+    service.delete_profile(&id).await?;
+
+    let mut current = state.current_user_id.lock().await;
+    if current.as_deref() == Some(id.to_ascii_lowercase().as_str()) {
+        *current = None;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_profiles(state: State<'_, AppState>) -> CommandResult<Vec<UserProfile>> {
+    let service = state.profile_service().await?;
+    service.list_profiles().await.map_err(CommandError::from)
+}
+
+/// Make `profile_id` the current profile, returning it
+#[tauri::command]
+pub async fn switch_user(state: State<'_, AppState>, profile_id: String) -> CommandResult<UserProfile> {
+    let service = state.profile_service().await?;
+    let profile = service.get_profile(&profile_id).await?;
+    *state.current_user_id.lock().await = Some(profile.id.clone());
+    Ok(profile)
+}
+
+/// The current profile, or `None` before `switch_user` or after it was deleted
+#[tauri::command]
+pub async fn get_current_user(state: State<'_, AppState>) -> CommandResult<Option<UserProfile>> {
+    let Some(id) = state.current_user_id.lock().await.clone() else {
+        return Ok(None);
+    };
+    let pool = state.storage.sqlite().get_pool().await?;
+    fetch_profile(&pool, &id).await.map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    fn profile(name: &str) -> UserProfile {
+        UserProfile {
+            id: Uuid::new_v4().to_string(),
+            display_name: name.to_string(),
+            email: None,
+            avatar_url: None,
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    #[test]
+    fn test_validate_profile_clears_blank_fields_and_rejects_bad_ones() {
+        let mut blank = profile("Ada");
+        blank.email = Some("  ".to_string());
+        blank.avatar_url = Some(String::new());
+        let blank = validate_profile(blank).unwrap();
+        assert_eq!(blank.email, None);
+        assert_eq!(blank.avatar_url, None);
+
+        let mut bad_email = profile("Ada");
+        bad_email.email = Some("ada at example".to_string());
+        assert!(validate_profile(bad_email).is_err());
+
+        let mut bad_url = profile("Ada");
+        bad_url.avatar_url = Some("ftp://example.com/a.png".to_string());
+        assert!(validate_profile(bad_url).is_err());
+
+        assert!(validate_profile(profile("")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_profiles_round_trip_and_list_by_name() {
+        let pool = test_pool().await;
+
+        let mut grace = profile("grace");
+        let ada = profile("Ada");
+        insert_profile(&pool, &grace).await.unwrap();
+        insert_profile(&pool, &ada).await.unwrap();
+        assert!(matches!(insert_profile(&pool, &ada).await, Err(AppError::Validation(_))));
+
+        grace.email = Some("grace@example.com".to_string());
+        update_profile_row(&pool, &grace).await.unwrap();
+        assert_eq!(fetch_profile(&pool, &grace.id).await.unwrap(), Some(grace.clone()));
+        assert_eq!(fetch_profile(&pool, &Uuid::new_v4().to_string()).await.unwrap(), None);
+
+        let names: Vec<String> = fetch_profiles(&pool).await.unwrap().into_iter().map(|p| p.display_name).collect();
+        assert_eq!(names, vec!["Ada", "grace"]);
+    }
+}
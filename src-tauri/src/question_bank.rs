@@ -0,0 +1,456 @@
+/**
+ * Question Bank Module
+ * Questions contributed from mock tests, searchable by keyword, topic and
+ * difficulty and reusable in new tests
+ */
+
+use crate::db::{fts_match_any_query, HybridStorage};
+use crate::error::{AppError, AppResult, CommandError, CommandResult};
+use crate::mock_tests::{store_test, MockTest, TestQuestion, EXAM_KIND};
+use crate::state::AppState;
+use crate::validation::validate_uuid;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::{HashMap, HashSet};
+use tauri::State;
+use uuid::Uuid;
+
+/// Default number of results for `search_question_bank`
+const DEFAULT_BANK_SEARCH_LIMIT: usize = 20;
+
+/// Most results returned by one search
+const MAX_BANK_SEARCH_LIMIT: usize = 100;
+
+/// Most questions in a test built from the bank
+const MAX_BANK_TEST_QUESTIONS: usize = 100;
+
+/// A question in the shared bank
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BankQuestion {
+    pub id: String,
+    pub contributed_by_user: String,
+    pub question: String,
+    pub options: Vec<String>,
+    pub correct_answer: usize,
+    pub explanation: String,
+    pub topic: Option<String>,
+    pub difficulty: Option<String>,
+    pub upvotes: i64,
+    pub created_at: String,
+}
+
+impl From<BankQuestion> for TestQuestion {
+    fn from(question: BankQuestion) -> Self {
+        TestQuestion {
+            question: question.question,
+            options: question.options,
+            correct_answer: question.correct_answer,
+            explanation: question.explanation,
+            topic: question.topic,
+            difficulty: question.difficulty,
+            sources: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct QuestionBankService {
+    storage: HybridStorage,
+}
+
+impl QuestionBankService {
+    pub fn new(storage: HybridStorage) -> Self {
+        Self { storage }
+    }
+
+    /// Copy the questions of one of the user's tests into the bank, returning
+    /// how many were added; questions the user already contributed are skipped
+    pub async fn contribute_questions(&self, test_id: &str, user_id: &str) -> AppResult<usize> {
+        let test_id = &validate_uuid(test_id, "Test ID")?;
+        let user_id = &validate_uuid(user_id, "User ID")?;
+        let pool = self.storage.sqlite().get_pool().await?;
+
+        let questions_json: Option<String> = sqlx::query_scalar(
+            "SELECT questions FROM mock_tests WHERE id = ? AND user_id = ? AND deleted_at IS NULL"
+        )
+        .bind(test_id)
+        .bind(user_id)
+        .fetch_optional(&pool)
+        .await?;
+        let questions_json = questions_json
+            .ok_or_else(|| AppError::NotFound(format!("Mock test {} not found", test_id)))?;
+        let questions: Vec<TestQuestion> = serde_json::from_str(&questions_json)?;
+
+        add_to_bank(&pool, user_id, &questions).await
+    }
+
+    /// Search the bank by keyword, best matches first, optionally limited to
+    /// a topic and difficulty; an empty query lists the most upvoted questions
+    pub async fn search_question_bank(
+        &self,
+        query: &str,
+        topic: Option<&str>,
+        difficulty: Option<&str>,
+        limit: usize,
+    ) -> AppResult<Vec<BankQuestion>> {
+        let pool = self.storage.sqlite().get_pool().await?;
+        search_bank(&pool, query, topic, difficulty, limit.clamp(1, MAX_BANK_SEARCH_LIMIT)).await
+    }
+
+    /// Create and save a mock test from bank questions, in the order given
+    pub async fn build_test_from_bank(&self, user_id: &str, question_ids: Vec<String>) -> AppResult<MockTest> {
+        let user_id = validate_uuid(user_id, "User ID")?;
+        let question_ids = unique_ids(question_ids)?;
+        if question_ids.is_empty() {
+            return Err(AppError::Validation("At least one question is required".to_string()));
+        }
+        if question_ids.len() > MAX_BANK_TEST_QUESTIONS {
+            return Err(AppError::Validation(format!(
+                "A test can have at most {} questions",
+                MAX_BANK_TEST_QUESTIONS
+            )));
+        }
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        let questions = bank_questions(&pool, &question_ids).await?;
+
+        let test = MockTest {
+            id: Uuid::new_v4().to_string(),
+            user_id,
+            title: "Question Bank Test".to_string(),
+            description: Some(format!("{} questions from the question bank", questions.len())),
+            questions: questions.into_iter().map(TestQuestion::from).collect(),
+            created_at: Utc::now().to_rfc3339(),
+            kind: EXAM_KIND.to_string(),
+            truncated: false,
+            warnings: Vec::new(),
+        };
+
+        store_test(&self.storage, &test).await?;
+        Ok(test)
+    }
+}
+
+/// Validated question IDs with repeats removed, keeping the first occurrence
+fn unique_ids(ids: Vec<String>) -> AppResult<Vec<String>> {
+    let mut seen = HashSet::new();
+    let mut unique = Vec::new();
+    for id in ids {
+        let id = validate_uuid(&id, "Question ID")?;
+        if seen.insert(id.clone()) {
+            unique.push(id);
+        }
+    }
+    Ok(unique)
+}
+
+/// Insert questions contributed by `user_id`, returning how many were new
+async fn add_to_bank(pool: &Pool<Sqlite>, user_id: &str, questions: &[TestQuestion]) -> AppResult<usize> {
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await?;
+    let mut added = 0;
+
+    for question in questions {
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO question_bank
+                (id, contributed_by_user, question, options, correct_answer, explanation, topic, difficulty, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(user_id)
+        .bind(&question.question)
+        .bind(serde_json::to_string(&question.options)?)
+        .bind(question.correct_answer as i64)
+        .bind(&question.explanation)
+        .bind(&question.topic)
+        .bind(&question.difficulty)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+        added += result.rows_affected() as usize;
+    }
+
+    tx.commit().await?;
+    Ok(added)
+}
+
+/// Bank questions matching any of the query's terms, ranked by BM25 then
+/// upvotes; topic and difficulty compare case-insensitively
+async fn search_bank(
+    pool: &Pool<Sqlite>,
+    query: &str,
+    topic: Option<&str>,
+    difficulty: Option<&str>,
+    limit: usize,
+) -> AppResult<Vec<BankQuestion>> {
+    const FILTERS: &str = "(? IS NULL OR lower(q.topic) = lower(?)) AND (? IS NULL OR lower(q.difficulty) = lower(?))";
+
+    let rows = match fts_match_any_query(query) {
+        Some(match_query) => {
+            sqlx::query(&format!(
+                "SELECT q.* FROM question_bank q
+                 JOIN question_bank_fts ON q.rowid = question_bank_fts.rowid
+                 WHERE question_bank_fts MATCH ? AND {}
+                 ORDER BY bm25(question_bank_fts), q.upvotes DESC
+                 LIMIT ?",
+                FILTERS
+            ))
+            .bind(match_query)
+            .bind(topic)
+            .bind(topic)
+            .bind(difficulty)
+            .bind(difficulty)
+            .bind(limit as i64)
+            .fetch_all(pool)
+            .await?
+        }
+        None => {
+            sqlx::query(&format!(
+                "SELECT q.* FROM question_bank q
+                 WHERE {}
+                 ORDER BY q.upvotes DESC, q.created_at DESC
+                 LIMIT ?",
+                FILTERS
+            ))
+            .bind(topic)
+            .bind(topic)
+            .bind(difficulty)
+            .bind(difficulty)
+            .bind(limit as i64)
+            .fetch_all(pool)
+            .await?
+        }
+    };
+
+    rows.iter().map(bank_question).collect()
+}
+
+/// The bank questions with the given IDs, in the same order; fails if any is missing
+async fn bank_questions(pool: &Pool<Sqlite>, ids: &[String]) -> AppResult<Vec<BankQuestion>> {
+    let sql = format!(
+        "SELECT * FROM question_bank WHERE id IN ({})",
+        vec!["?"; ids.len()].join(", ")
+    );
+    let mut query = sqlx::query(&sql);
+    for id in ids {
+        query = query.bind(id);
+    }
+
+    let mut found: HashMap<String, BankQuestion> = query
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| bank_question(row).map(|q| (q.id.clone(), q)))
+        .collect::<AppResult<_>>()?;
+
+    ids.iter()
+        .map(|id| {
+            found
+                .remove(id)
+                .ok_or_else(|| AppError::NotFound(format!("Bank question {} not found", id)))
+        })
+        .collect()
+}
+
+fn bank_question(row: &sqlx::sqlite::SqliteRow) -> AppResult<BankQuestion> {
+    let options: String = row.get("options");
+    let correct_answer: i64 = row.get("correct_answer");
+
+    Ok(BankQuestion {
+        id: row.get("id"),
+        contributed_by_user: row.get("contributed_by_user"),
+        question: row.get("question"),
+        options: serde_json::from_str(&options)?,
+        correct_answer: correct_answer as usize,
+        explanation: row.get::<Option<String>, _>("explanation").unwrap_or_default(),
+        topic: row.get("topic"),
+        difficulty: row.get("difficulty"),
+        upvotes: row.get("upvotes"),
+        created_at: row.get("created_at"),
+    })
+}
+
+// Tauri Commands
+
+/// Add the questions of one of the user's mock tests to the shared bank
+#[tauri::command]
+pub async fn contribute_questions(
+    state: State<'_, AppState>,
+    test_id: String,
+    user_id: String,
+) -> CommandResult<usize> {
+    let service = state.question_bank_service().await?;
+    service.contribute_questions(&test_id, &user_id).await.map_err(CommandError::from)
+}
+
+/// Search the shared question bank
+#[tauri::command]
+pub async fn search_question_bank(
+    state: State<'_, AppState>,
+    query: String,
+    topic: Option<String>,
+    difficulty: Option<String>,
+    limit: Option<usize>,
+) -> CommandResult<Vec<BankQuestion>> {
+    let service = state.question_bank_service().await?;
+    service
+        .search_question_bank(
+            &query,
+            topic.as_deref(),
+            difficulty.as_deref(),
+            limit.unwrap_or(DEFAULT_BANK_SEARCH_LIMIT),
+        )
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Create a mock test from chosen bank questions
+#[tauri::command]
+pub async fn build_test_from_bank(
+    state: State<'_, AppState>,
+    user_id: String,
+    question_ids: Vec<String>,
+) -> CommandResult<MockTest> {
+    let service = state.question_bank_service().await?;
+    service.build_test_from_bank(&user_id, question_ids).await.map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{test_cache, test_pool, IN_MEMORY_DB};
+    use std::path::PathBuf;
+
+    fn question(text: &str, topic: &str) -> TestQuestion {
+        TestQuestion {
+            question: text.to_string(),
+            options: vec!["Yes".to_string(), "No".to_string()],
+            correct_answer: 0,
+            explanation: format!("Because of the rule on {}", topic),
+            topic: Some(topic.to_string()),
+            difficulty: None,
+            sources: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_contributing_again_skips_questions_already_in_the_bank() {
+        let pool = test_pool().await;
+        let user = Uuid::new_v4().to_string();
+        let questions = vec![
+            question("Is consideration required for a binding contract?", "Contracts"),
+            question("Does a duty of care arise between neighbours?", "Torts"),
+        ];
+
+        assert_eq!(add_to_bank(&pool, &user, &questions).await.unwrap(), 2);
+        assert_eq!(add_to_bank(&pool, &user, &questions).await.unwrap(), 0);
+        // Another user contributing the same question adds their own copy
+        assert_eq!(add_to_bank(&pool, &Uuid::new_v4().to_string(), &questions[..1]).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_matches_keywords_and_filters_by_topic() {
+        let pool = test_pool().await;
+        let user = Uuid::new_v4().to_string();
+        add_to_bank(&pool, &user, &[
+            question("Is consideration required for a binding contract?", "Contracts"),
+            question("Does a duty of care arise between neighbours?", "Torts"),
+            question("Can a contract term exclude liability for negligence?", "Torts"),
+        ])
+        .await
+        .unwrap();
+
+        let found = search_bank(&pool, "contract", None, None, 10).await.unwrap();
+        assert_eq!(found.len(), 2);
+
+        let found = search_bank(&pool, "contract", Some("torts"), None, 10).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].question, "Can a contract term exclude liability for negligence?");
+        assert_eq!(found[0].options, vec!["Yes", "No"]);
+
+        assert_eq!(search_bank(&pool, "", Some("Torts"), None, 10).await.unwrap().len(), 2);
+        assert!(search_bank(&pool, "contract", Some("Evidence"), None, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_filters_by_difficulty() {
+        let pool = test_pool().await;
+        let hard = TestQuestion {
+            difficulty: Some("hard".to_string()),
+            ..question("Is a contract void for common mistake?", "Contracts")
+        };
+        add_to_bank(&pool, &Uuid::new_v4().to_string(), &[
+            hard,
+            question("Is a contract formed by an offer and acceptance?", "Contracts"),
+        ])
+        .await
+        .unwrap();
+
+        let found = search_bank(&pool, "contract", None, Some("Hard"), 10).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].difficulty.as_deref(), Some("hard"));
+        assert_eq!(search_bank(&pool, "contract", None, None, 10).await.unwrap().len(), 2);
+        assert!(search_bank(&pool, "", None, Some("easy"), 10).await.unwrap().is_empty());
+
+        // Tests built from the bank keep the difficulty
+        assert_eq!(TestQuestion::from(found[0].clone()).difficulty.as_deref(), Some("hard"));
+    }
+
+    #[tokio::test]
+    async fn test_bank_questions_keep_the_requested_order() {
+        let pool = test_pool().await;
+        add_to_bank(&pool, &Uuid::new_v4().to_string(), &[
+            question("First question about offers?", "Contracts"),
+            question("Second question about acceptance?", "Contracts"),
+        ])
+        .await
+        .unwrap();
+        let ids: Vec<String> = search_bank(&pool, "", None, None, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|q| q.id)
+            .collect();
+
+        let reversed: Vec<String> = ids.iter().rev().cloned().collect();
+        let questions = bank_questions(&pool, &reversed).await.unwrap();
+        assert_eq!(questions.iter().map(|q| q.id.clone()).collect::<Vec<_>>(), reversed);
+
+        let missing = vec![ids[0].clone(), Uuid::new_v4().to_string()];
+        assert!(matches!(bank_questions(&pool, &missing).await, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_built_tests_are_saved_with_the_bank_questions() {
+        let cache = test_cache().await;
+        let pool = cache.get_pool().await.unwrap();
+        let service = QuestionBankService::new(HybridStorage::new(PathBuf::from(IN_MEMORY_DB), None, None).with_sqlite(cache));
+
+        add_to_bank(&pool, &Uuid::new_v4().to_string(), &[question("Is silence acceptance?", "Contracts")])
+            .await
+            .unwrap();
+        let ids: Vec<String> = search_bank(&pool, "", None, None, 10).await.unwrap().into_iter().map(|q| q.id).collect();
+
+        let user = Uuid::new_v4().to_string();
+        let test = service.build_test_from_bank(&user, ids).await.unwrap();
+        let (user_id, questions, dirty): (String, String, i64) =
+            sqlx::query_as("SELECT user_id, questions, dirty FROM mock_tests WHERE id = ?")
+                .bind(&test.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(user_id, user);
+        assert_eq!(questions, serde_json::to_string(&test.questions).unwrap());
+        assert_eq!(test.questions[0].question, "Is silence acceptance?");
+        // Saved offline, so it waits for the next sync
+        assert_eq!(dirty, 1);
+    }
+
+    #[test]
+    fn test_unique_ids_drops_repeats_and_rejects_invalid_ids() {
+        let id = Uuid::new_v4().to_string();
+        assert_eq!(unique_ids(vec![id.clone(), id.clone()]).unwrap(), vec![id]);
+        assert!(unique_ids(vec!["not-a-uuid".to_string()]).is_err());
+    }
+}
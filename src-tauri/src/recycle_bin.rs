@@ -0,0 +1,374 @@
+/**
+ * Recycle Bin Module
+ * Soft-deleted cases, mock tests, flashcard sets and study plans, kept for
+ * `RECYCLE_BIN_DAYS` before they are removed for good
+ */
+
+use crate::db::HybridStorage;
+use crate::error::{AppError, AppResult, CommandError, CommandResult};
+use crate::logging::LogLevel;
+use crate::state::AppState;
+use crate::sync::SyncManager;
+use crate::validation::validate_uuid;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use tauri::State;
+
+/// Days a deleted item stays in the bin before `purge_expired` removes it
+pub const RECYCLE_BIN_DAYS: i64 = 30;
+
+/// Tables with a recycle bin, and whether each has an `updated_at` column
+const RECYCLABLE_TABLES: &[(&str, bool)] = &[
+    ("cases", true),
+    ("mock_tests", false),
+    ("flashcard_sets", true),
+    ("study_plans", true),
+];
+
+/// An item in the recycle bin
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeletedItem {
+    pub table: String,
+    pub id: String,
+    pub title: String,
+    pub deleted_at: String,
+}
+
+/// `table` as listed in `RECYCLABLE_TABLES` with whether it has `updated_at`;
+/// only names returned by this are interpolated into SQL
+fn recyclable_table(table: &str) -> AppResult<(&'static str, bool)> {
+    RECYCLABLE_TABLES
+        .iter()
+        .find(|(name, _)| *name == table)
+        .copied()
+        .ok_or_else(|| AppError::Validation(format!("No recycle bin for table: {}", table)))
+}
+
+/// Set clause marking a row changed so the change is pushed on the next sync;
+/// with `updated_at`, the time is bound to its one parameter
+fn touch_clause(has_updated_at: bool) -> &'static str {
+    if has_updated_at {
+        "dirty = 1, updated_at = ?"
+    } else {
+        "dirty = 1"
+    }
+}
+
+/// Move a row to the recycle bin
+///
+/// The row is only marked with `deleted_at`, which reaches Supabase with the
+/// next push so other devices bin it too.
+pub async fn move_to_bin(storage: &HybridStorage, table: &str, id: &str) -> AppResult<()> {
+    let (table, has_updated_at) = recyclable_table(table)?;
+    let now = Utc::now().to_rfc3339();
+
+    let sql = format!(
+        "UPDATE {} SET deleted_at = ?, {} WHERE id = ? AND deleted_at IS NULL",
+        table,
+        touch_clause(has_updated_at)
+    );
+    let mut query = sqlx::query(&sql).bind(&now);
+    if has_updated_at {
+        query = query.bind(&now);
+    }
+
+    let pool = storage.sqlite().get_pool().await?;
+    let updated = query.bind(id).execute(&pool).await?.rows_affected();
+
+    if updated == 0 {
+        return Err(AppError::NotFound(format!("No {} row to delete", table)));
+    }
+    Ok(())
+}
+
+/// Take one of `user_id`'s rows back out of the recycle bin
+pub async fn restore(storage: &HybridStorage, user_id: &str, table: &str, id: &str) -> AppResult<()> {
+    let (table, has_updated_at) = recyclable_table(table)?;
+    let now = Utc::now().to_rfc3339();
+
+    let sql = format!(
+        "UPDATE {} SET deleted_at = NULL, {} WHERE id = ? AND user_id = ? AND deleted_at IS NOT NULL",
+        table,
+        touch_clause(has_updated_at)
+    );
+    let mut query = sqlx::query(&sql);
+    if has_updated_at {
+        query = query.bind(&now);
+    }
+
+    let pool = storage.sqlite().get_pool().await?;
+    let restored = query.bind(id).bind(user_id).execute(&pool).await?.rows_affected();
+
+    if restored == 0 {
+        return Err(AppError::NotFound("Item is not in the recycle bin".to_string()));
+    }
+    Ok(())
+}
+
+/// A user's binned items, most recently deleted first, from one table or all of them
+pub async fn list_deleted(storage: &HybridStorage, user_id: &str, table: Option<&str>) -> AppResult<Vec<DeletedItem>> {
+    let tables: Vec<&'static str> = match table {
+        Some(table) => vec![recyclable_table(table)?.0],
+        None => RECYCLABLE_TABLES.iter().map(|(name, _)| *name).collect(),
+    };
+
+    let pool = storage.sqlite().get_pool().await?;
+    let mut items = Vec::new();
+    for table in tables {
+        let rows = sqlx::query(&format!(
+            "SELECT id, title, deleted_at FROM {} WHERE user_id = ? AND deleted_at IS NOT NULL",
+            table
+        ))
+        .bind(user_id)
+        .fetch_all(&pool)
+        .await?;
+
+        items.extend(rows.iter().map(|row| DeletedItem {
+            table: table.to_string(),
+            id: row.get("id"),
+            title: row.get("title"),
+            deleted_at: row.get("deleted_at"),
+        }));
+    }
+
+    items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    Ok(items)
+}
+
+/// Delete one of `user_id`'s binned rows for good; rows that reference it go with it
+///
+/// A row whose `deleted_at` hasn't been pushed yet has its tombstone sent, or
+/// queued, first so other devices still learn of the delete.
+pub async fn permanently_delete(
+    storage: &HybridStorage,
+    sync: &SyncManager,
+    user_id: &str,
+    table: &str,
+    id: &str,
+) -> AppResult<()> {
+    let (table, _) = recyclable_table(table)?;
+
+    let pool = storage.sqlite().get_pool().await?;
+    let dirty: Option<Option<i64>> = sqlx::query_scalar(&format!(
+        "SELECT dirty FROM {} WHERE id = ? AND user_id = ? AND deleted_at IS NOT NULL",
+        table
+    ))
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(&pool)
+    .await?;
+
+    match dirty {
+        None => return Err(AppError::NotFound("Item is not in the recycle bin".to_string())),
+        Some(Some(1)) => sync.delete_remote(table, id).await?,
+        Some(_) => {}
+    }
+
+    sqlx::query(&format!("DELETE FROM {} WHERE id = ? AND user_id = ?", table))
+        .bind(id)
+        .bind(user_id)
+        .execute(&pool)
+        .await?;
+    Ok(())
+}
+
+/// Permanently delete everything binned more than `RECYCLE_BIN_DAYS` ago,
+/// returning how many rows were removed
+///
+/// Tombstones not pushed yet are sent or queued first, as in
+/// `permanently_delete`; a row whose tombstone can't be queued is kept for
+/// the next purge.
+pub async fn purge_expired(storage: &HybridStorage, sync: &SyncManager) -> AppResult<usize> {
+    let cutoff = (Utc::now() - Duration::days(RECYCLE_BIN_DAYS)).to_rfc3339();
+
+    let pool = storage.sqlite().get_pool().await?;
+    let mut held: Vec<(&str, String)> = Vec::new();
+    for (table, _) in RECYCLABLE_TABLES {
+        let unpushed: Vec<String> = sqlx::query_scalar(&format!(
+            "SELECT id FROM {} WHERE deleted_at IS NOT NULL AND deleted_at < ? AND dirty = 1",
+            table
+        ))
+        .bind(&cutoff)
+        .fetch_all(&pool)
+        .await?;
+
+        for id in unpushed {
+            if let Err(e) = sync.delete_remote(table, &id).await {
+                crate::logging::log(LogLevel::Warn, "recycle_bin", &format!("Keeping {} {} in the bin: {}", table, id, e));
+                held.push((table, id));
+            }
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    let mut purged = 0;
+    for (table, _) in RECYCLABLE_TABLES {
+        let held: Vec<&String> = held.iter().filter(|(t, _)| t == table).map(|(_, id)| id).collect();
+        purged += sqlx::query(&format!(
+            "DELETE FROM {} WHERE deleted_at IS NOT NULL AND deleted_at < ?
+               AND id NOT IN (SELECT value FROM json_each(?))",
+            table
+        ))
+        .bind(&cutoff)
+        .bind(serde_json::to_string(&held)?)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    tx.commit().await?;
+    Ok(purged as usize)
+}
+
+// Tauri Commands
+
+/// List a user's deleted items, optionally from one table
+#[tauri::command]
+pub async fn get_deleted_items(
+    state: State<'_, AppState>,
+    user_id: String,
+    table: Option<String>,
+) -> CommandResult<Vec<DeletedItem>> {
+    let user_id = validate_uuid(&user_id, "User ID")?;
+    list_deleted(&state.storage, &user_id, table.as_deref())
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Put one of a user's deleted items back where it was
+#[tauri::command]
+pub async fn restore_deleted_item(
+    state: State<'_, AppState>,
+    user_id: String,
+    table: String,
+    id: String,
+) -> CommandResult<()> {
+    let user_id = validate_uuid(&user_id, "User ID")?;
+    let id = validate_uuid(&id, "Item ID")?;
+    restore(&state.storage, &user_id, &table, &id).await.map_err(CommandError::from)
+}
+
+/// Empty one of a user's items out of the recycle bin without waiting for it to expire
+#[tauri::command]
+pub async fn permanently_delete_item(
+    state: State<'_, AppState>,
+    user_id: String,
+    table: String,
+    id: String,
+) -> CommandResult<()> {
+    let user_id = validate_uuid(&user_id, "User ID")?;
+    let id = validate_uuid(&id, "Item ID")?;
+    permanently_delete(&state.storage, &state.sync_manager, &user_id, &table, &id)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{test_cache, IN_MEMORY_DB};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_only_recyclable_tables_are_accepted() {
+        assert_eq!(recyclable_table("mock_tests").unwrap(), ("mock_tests", false));
+        assert!(recyclable_table("flashcards").is_err());
+        assert!(recyclable_table("cases; DROP TABLE cases").is_err());
+    }
+
+    #[test]
+    fn test_touch_clause_only_sets_existing_columns() {
+        assert_eq!(touch_clause(true), "dirty = 1, updated_at = ?");
+        assert_eq!(touch_clause(false), "dirty = 1");
+    }
+
+    async fn binned_storage(supabase_url: Option<String>) -> HybridStorage {
+        let cache = test_cache().await;
+        let storage = HybridStorage::new(PathBuf::from(IN_MEMORY_DB), supabase_url, Some("key".to_string())).with_sqlite(cache);
+        let pool = storage.sqlite().get_pool().await.unwrap();
+        for id in ["kept", "gone", "expired"] {
+            sqlx::query("INSERT INTO cases (id, user_id, title, created_at, updated_at, synced, dirty) VALUES (?, 'user-1', ?, 'then', 'then', 1, 0)")
+                .bind(id)
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        storage
+    }
+
+    #[tokio::test]
+    async fn test_binned_rows_are_restored_deleted_and_purged() {
+        let storage = binned_storage(None).await;
+        let sync = SyncManager::new(Arc::new(storage.clone()));
+        let pool = storage.sqlite().get_pool().await.unwrap();
+
+        move_to_bin(&storage, "cases", "kept").await.unwrap();
+        move_to_bin(&storage, "cases", "gone").await.unwrap();
+        assert!(matches!(move_to_bin(&storage, "cases", "kept").await, Err(AppError::NotFound(_))));
+        let mut binned: Vec<String> = list_deleted(&storage, "user-1", None).await.unwrap().into_iter().map(|i| i.id).collect();
+        binned.sort();
+        assert_eq!(binned, vec!["gone", "kept"]);
+
+        // Only the owner can restore or delete a binned row
+        assert!(matches!(restore(&storage, "user-2", "cases", "kept").await, Err(AppError::NotFound(_))));
+        assert!(matches!(
+            permanently_delete(&storage, &sync, "user-2", "cases", "gone").await,
+            Err(AppError::NotFound(_))
+        ));
+
+        restore(&storage, "user-1", "cases", "kept").await.unwrap();
+        let (deleted_at, dirty): (Option<String>, i64) = sqlx::query_as("SELECT deleted_at, dirty FROM cases WHERE id = 'kept'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((deleted_at, dirty), (None, 1));
+
+        permanently_delete(&storage, &sync, "user-1", "cases", "gone").await.unwrap();
+        assert!(matches!(
+            permanently_delete(&storage, &sync, "user-1", "cases", "kept").await,
+            Err(AppError::NotFound(_))
+        ));
+
+        sqlx::query("UPDATE cases SET deleted_at = '2000-01-01T00:00:00+00:00' WHERE id = 'expired'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(purge_expired(&storage, &sync).await.unwrap(), 1);
+        let left: Vec<String> = sqlx::query_scalar("SELECT id FROM cases").fetch_all(&pool).await.unwrap();
+        assert_eq!(left, vec!["kept"]);
+    }
+
+    #[tokio::test]
+    async fn test_unpushed_deletes_are_queued_before_the_row_goes() {
+        // Offline with Supabase configured, so deletes are queued
+        let storage = binned_storage(Some("http://127.0.0.1:9".to_string())).await;
+        let sync = SyncManager::new(Arc::new(storage.clone()));
+        let pool = storage.sqlite().get_pool().await.unwrap();
+
+        move_to_bin(&storage, "cases", "gone").await.unwrap();
+        permanently_delete(&storage, &sync, "user-1", "cases", "gone").await.unwrap();
+
+        // Already pushed tombstones need nothing more
+        sqlx::query("UPDATE cases SET deleted_at = '2000-01-01T00:00:00+00:00', dirty = 0 WHERE id = 'kept'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        move_to_bin(&storage, "cases", "expired").await.unwrap();
+        sqlx::query("UPDATE cases SET deleted_at = '2000-01-01T00:00:00+00:00' WHERE id = 'expired'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(purge_expired(&storage, &sync).await.unwrap(), 2);
+
+        let queued: Vec<(String, String)> = sqlx::query_as("SELECT operation_type, record_id FROM sync_queue ORDER BY rowid")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            queued,
+            vec![("delete".to_string(), "gone".to_string()), ("delete".to_string(), "expired".to_string())]
+        );
+    }
+}
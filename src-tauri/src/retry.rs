@@ -0,0 +1,208 @@
+/**
+ * Retry and Failure Handling
+ * Circuit breaker that stops calling an unavailable upstream service until it recovers
+ */
+
+use crate::error::{AppError, AppResult};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Message of the error returned while the breaker is open
+pub const CIRCUIT_OPEN: &str = "circuit open";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Calls go through; `failures` counts consecutive upstream failures
+    Closed { failures: u32 },
+    /// Calls are rejected until the reset timeout after this instant
+    Open(Instant),
+    /// One probe call is in flight; others are rejected until it finishes
+    HalfOpen,
+}
+
+/// Breaker state as reported to the frontend
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerStatus {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// Shared circuit breaker; clones trip and reset together
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    state: Arc<Mutex<BreakerState>>,
+    failure_threshold: u32,
+    reset_timeout: Duration,
+}
+
+impl CircuitBreaker {
+    /// Open after `failure_threshold` consecutive failures and probe again after `reset_timeout`
+    pub fn new(failure_threshold: u32, reset_timeout: Duration) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(BreakerState::Closed { failures: 0 })),
+            failure_threshold: failure_threshold.max(1),
+            reset_timeout,
+        }
+    }
+
+    pub fn status(&self) -> BreakerStatus {
+        match *self.lock() {
+            BreakerState::Closed { .. } => BreakerStatus::Closed,
+            BreakerState::Open(_) => BreakerStatus::Open,
+            BreakerState::HalfOpen => BreakerStatus::HalfOpen,
+        }
+    }
+
+    /// Run `call` unless the breaker is open, recording whether the upstream failed
+    ///
+    /// Only errors for which `is_upstream_failure` is true count towards
+    /// tripping the breaker; a rejected request or a refusal means the
+    /// service is up.
+    pub async fn call<T, F, Fut>(&self, call: F) -> AppResult<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = AppResult<T>>,
+    {
+        self.acquire()?;
+
+        // Reopens the breaker if a probe is dropped before it finishes
+        let mut guard = ProbeGuard { breaker: self, finished: false };
+        let result = call().await;
+        guard.finished = true;
+
+        match &result {
+            Err(e) if is_upstream_failure(e) => self.record_failure(),
+            _ => self.record_success(),
+        }
+
+        result
+    }
+
+    /// Check whether a call may proceed, moving to half-open once the reset timeout has passed
+    fn acquire(&self) -> AppResult<()> {
+        let mut state = self.lock();
+
+        match *state {
+            BreakerState::Closed { .. } => Ok(()),
+            BreakerState::Open(since) if since.elapsed() >= self.reset_timeout => {
+                *state = BreakerState::HalfOpen;
+                Ok(())
+            }
+            BreakerState::Open(_) | BreakerState::HalfOpen => {
+                Err(AppError::OperationFailed(CIRCUIT_OPEN.to_string()))
+            }
+        }
+    }
+
+    fn record_success(&self) {
+        *self.lock() = BreakerState::Closed { failures: 0 };
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.lock();
+
+        *state = match *state {
+            BreakerState::Closed { failures } if failures + 1 < self.failure_threshold => {
+                BreakerState::Closed { failures: failures + 1 }
+            }
+            _ => BreakerState::Open(Instant::now()),
+        };
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+struct ProbeGuard<'a> {
+    breaker: &'a CircuitBreaker,
+    finished: bool,
+}
+
+impl Drop for ProbeGuard<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let mut state = self.breaker.lock();
+            if *state == BreakerState::HalfOpen {
+                *state = BreakerState::Open(Instant::now());
+            }
+        }
+    }
+}
+
+/// Whether an error means the upstream service is unavailable, rather than
+/// that this particular request was bad
+///
+/// A 429 doesn't count: it throttles one key, and the key ring moves on to the next.
+pub fn is_upstream_failure(error: &AppError) -> bool {
+    match error {
+        AppError::Network(_) => true,
+        AppError::OpenRouter { status, .. } => *status >= 500,
+        _ => false,
+    }
+}
+
+/// Whether `error` was returned by an open breaker without calling upstream
+pub fn is_circuit_open(error: &AppError) -> bool {
+    matches!(error, AppError::OperationFailed(msg) if msg == CIRCUIT_OPEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outage() -> AppResult<()> {
+        Err(AppError::OpenRouter {
+            status: 503,
+            message: "unavailable".to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_breaker_opens_and_recovers_after_probe() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(20));
+
+        assert!(breaker.call(|| async { outage() }).await.is_err());
+        assert_eq!(breaker.status(), BreakerStatus::Closed);
+        assert!(breaker.call(|| async { outage() }).await.is_err());
+        assert_eq!(breaker.status(), BreakerStatus::Open);
+
+        // Rejected without running the call
+        let mut called = false;
+        let result = breaker.call(|| async { called = true; Ok(()) }).await;
+        assert!(result.as_ref().is_err_and(is_circuit_open));
+        assert!(!called);
+
+        // A failed probe reopens; a successful one closes
+        tokio::time::sleep(Duration::from_millis(25)).await;
+        assert!(breaker.call(|| async { outage() }).await.is_err());
+        assert_eq!(breaker.status(), BreakerStatus::Open);
+
+        tokio::time::sleep(Duration::from_millis(25)).await;
+        breaker.call(|| async { Ok(()) }).await.unwrap();
+        assert_eq!(breaker.status(), BreakerStatus::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_do_not_trip_breaker() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+
+        for status in [400, 429] {
+            let result: AppResult<()> = breaker
+                .call(|| async {
+                    Err(AppError::OpenRouter {
+                        status,
+                        message: "rejected".to_string(),
+                    })
+                })
+                .await;
+
+            assert!(result.is_err());
+            assert_eq!(breaker.status(), BreakerStatus::Closed);
+        }
+    }
+}
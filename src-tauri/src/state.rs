@@ -3,28 +3,51 @@
  * Manages shared state across Tauri commands
  */
 
+use crate::annotations::AnnotationService;
+use crate::cases::CaseService;
 use crate::config::AppConfig;
 use crate::db::HybridStorage;
 use crate::error::{AppError, AppResult};
 use crate::flashcards::FlashcardService;
-use crate::llm::LLMService;
+use crate::knowledge_graph::KnowledgeGraphService;
+use crate::llm::{LLMService, LlmPolicy};
 use crate::mock_tests::MockTestService;
-use crate::rag::RAGService;
+use crate::outlines::OutlineService;
+use crate::precedents::PrecedentService;
+use crate::profiles::ProfileService;
+use crate::question_bank::QuestionBankService;
+use crate::rag::{RAGService, RagState};
+use crate::retry::CircuitBreaker;
 use crate::study_plans::StudyPlanService;
 use crate::sync::SyncManager;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock};
+use tokio_util::sync::CancellationToken;
 
 /// Application state accessible from all Tauri commands
 #[derive(Clone)]
 pub struct AppState {
-    /// Application configuration
-    pub config: Arc<AppConfig>,
+    /// Application configuration, replaced by `reload_config`
+    pub config: Arc<RwLock<AppConfig>>,
+    /// Where the configuration is saved
+    config_path: PathBuf,
     /// Hybrid storage (SQLite + Supabase)
     pub storage: Arc<HybridStorage>,
     /// Sync manager for background synchronization
     pub sync_manager: Arc<SyncManager>,
-    /// LLM service (lazily initialized)
+    /// Profile picked with `switch_user`, used only to prefill the frontend;
+    /// commands still take the user ID explicitly
+    pub current_user_id: Arc<Mutex<Option<String>>>,
+    /// Circuit breaker for OpenRouter, shared by every `LLMService` built from this state
+    pub circuit_breaker: CircuitBreaker,
+    /// Local embedding model
+    rag_state: RagState,
+    /// LLM service built at startup and managed as `LLMService` state;
+    /// services rebuilt after a reload share its metrics, policy and limiter
+    llm: LLMService,
+    /// LLM service for the current config (lazily initialized)
     llm_service: Arc<Mutex<Option<LLMService>>>,
     /// RAG service (lazily initialized)
     rag_service: Arc<Mutex<Option<RAGService>>>,
@@ -34,59 +57,151 @@ pub struct AppState {
     mock_test_service: Arc<Mutex<Option<MockTestService>>>,
     /// Study plan service
     study_plan_service: Arc<Mutex<Option<StudyPlanService>>>,
+    /// Outline service
+    outline_service: Arc<Mutex<Option<OutlineService>>>,
+    /// Annotation service
+    annotation_service: Arc<Mutex<Option<AnnotationService>>>,
+    /// Case service
+    case_service: Arc<Mutex<Option<CaseService>>>,
+    /// Precedent service
+    precedent_service: Arc<Mutex<Option<PrecedentService>>>,
+    /// Knowledge graph service
+    knowledge_graph_service: Arc<Mutex<Option<KnowledgeGraphService>>>,
+    /// Question bank service
+    question_bank_service: Arc<Mutex<Option<QuestionBankService>>>,
+    /// Profile service
+    profile_service: Arc<Mutex<Option<ProfileService>>>,
+    /// Cancellation tokens of running ingestions, by document ID
+    ingestions: Arc<Mutex<HashMap<String, CancellationToken>>>,
 }
 
 impl AppState {
     /// Create new app state
     pub fn new(
         config: AppConfig,
+        config_path: PathBuf,
         storage: HybridStorage,
         sync_manager: SyncManager,
+        rag_state: RagState,
+        llm: LLMService,
     ) -> Self {
         let storage = Arc::new(storage);
         let sync_manager = Arc::new(sync_manager);
 
         Self {
-            config: Arc::new(config),
+            config: Arc::new(RwLock::new(config)),
+            config_path,
             storage: storage.clone(),
             sync_manager,
+            current_user_id: Arc::new(Mutex::new(None)),
+            rag_state,
+            circuit_breaker: llm.circuit_breaker().clone(),
+            llm,
             llm_service: Arc::new(Mutex::new(None)),
             rag_service: Arc::new(Mutex::new(None)),
             flashcard_service: Arc::new(Mutex::new(None)),
             mock_test_service: Arc::new(Mutex::new(None)),
             study_plan_service: Arc::new(Mutex::new(None)),
+            outline_service: Arc::new(Mutex::new(None)),
+            annotation_service: Arc::new(Mutex::new(None)),
+            case_service: Arc::new(Mutex::new(None)),
+            precedent_service: Arc::new(Mutex::new(None)),
+            knowledge_graph_service: Arc::new(Mutex::new(None)),
+            question_bank_service: Arc::new(Mutex::new(None)),
+            profile_service: Arc::new(Mutex::new(None)),
+            ingestions: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    /// Get or create LLM service
+    /// Get the LLM service, which requires an API key
+    ///
+    /// Clones share the HTTP client and prompt policy, so `set_llm_policy`
+    /// applies to every service built from this state.
     pub async fn llm_service(&self) -> AppResult<LLMService> {
+        if self.config.read().await.api_keys().is_empty() {
+            return Err(AppError::Config("OpenRouter API key not configured".to_string()));
+        }
+
+        self.configured_llm().await
+    }
+
+    /// LLM service for the current config, whether or not an API key is set
+    ///
+    /// For embeddings and model listings, which work without a key.
+    pub async fn configured_llm(&self) -> AppResult<LLMService> {
         let mut service = self.llm_service.lock().await;
-        
+
         if service.is_none() {
-            let api_key = self
-                .config
-                .openrouter_api_key
-                .clone()
-                .ok_or_else(|| AppError::Config("OpenRouter API key not configured".to_string()))?;
-            
-            *service = Some(LLMService::new(api_key));
+            let config = self.config.read().await;
+            *service = Some(self.llm.reconfigured(&config)?);
         }
-        
+
         Ok(service.as_ref().unwrap().clone())
     }
 
+    /// Register a token that `cancel_ingestion` cancels while `doc_id` is being ingested
+    pub async fn start_ingestion(&self, doc_id: &str) -> CancellationToken {
+        let token = CancellationToken::new();
+        self.ingestions.lock().await.insert(doc_id.to_string(), token.clone());
+        token
+    }
+
+    /// Forget `doc_id`'s token once its ingestion has ended
+    pub async fn finish_ingestion(&self, doc_id: &str) {
+        self.ingestions.lock().await.remove(doc_id);
+    }
+
+    /// Cancel the ingestion of `doc_id`, returning false if none is running
+    pub async fn cancel_ingestion(&self, doc_id: &str) -> bool {
+        match self.ingestions.lock().await.get(doc_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Path of the saved configuration file
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    /// Re-read the config file and apply it without restarting
+    ///
+    /// Services that depend on the config are rebuilt on next use and the
+    /// periodic sync picks up the new interval. The managed `LLMService` is
+    /// only used for metrics and the prompt policy, which every service shares;
+    /// the policy is replaced by the one in the reloaded file.
+    pub async fn reload_config(&self) -> AppResult<()> {
+        let config = AppConfig::load_from_file(&self.config_path)?;
+        let sync_interval = config.sync_interval_seconds;
+        self.llm.set_policy(LlmPolicy::from_config(&config));
+
+        {
+            let mut current = self.config.write().await;
+            *current = config;
+        }
+
+        *self.llm_service.lock().await = None;
+        *self.rag_service.lock().await = None;
+        *self.mock_test_service.lock().await = None;
+        *self.precedent_service.lock().await = None;
+        *self.knowledge_graph_service.lock().await = None;
+
+        self.sync_manager.set_interval(sync_interval);
+
+        Ok(())
+    }
+
     /// Get or create RAG service
     pub async fn rag_service(&self) -> AppResult<RAGService> {
         let mut service = self.rag_service.lock().await;
         
         if service.is_none() {
-            let api_key = self
-                .config
-                .openrouter_api_key
-                .clone()
-                .ok_or_else(|| AppError::Config("OpenRouter API key not configured".to_string()))?;
-            
-            let rag = RAGService::with_storage(api_key, (*self.storage).clone());
+            // Local embeddings work without an API key, so don't go through llm_service()
+            let llm = self.configured_llm().await?;
+            let rag = RAGService::new((*self.storage).clone(), self.rag_state.clone(), llm);
             *service = Some(rag);
         }
         
@@ -98,7 +213,7 @@ impl AppState {
         let mut service = self.flashcard_service.lock().await;
         
         if service.is_none() {
-            *service = Some(FlashcardService::new((*self.storage).clone()));
+            *service = Some(FlashcardService::new((*self.storage).clone(), self.sync_manager.clone()));
         }
         
         Ok(service.as_ref().unwrap().clone())
@@ -128,91 +243,92 @@ impl AppState {
         Ok(service.as_ref().unwrap().clone())
     }
 
-    /// Check if online
-    pub async fn is_online(&self) -> bool {
-        self.storage.is_online().await
-    }
-
-    /// Get sync status
-    pub async fn sync_status(&self) -> AppResult<crate::sync::SyncStatus> {
-        self.sync_manager.get_status().await
-    }
-}
-
-// Make services cloneable by wrapping in Arc
-impl Clone for LLMService {
-    fn clone(&self) -> Self {
-        // Create a new instance with the same API key
-        LLMService::new(self.api_key.clone())
-    }
-}
-
-impl Clone for RAGService {
-    fn clone(&self) -> Self {
-        // Create a new instance with the same API key
-        RAGService::new(self.api_key.clone())
+    /// Get or create outline service
+    pub async fn outline_service(&self) -> AppResult<OutlineService> {
+        let mut service = self.outline_service.lock().await;
+        
+        if service.is_none() {
+            *service = Some(OutlineService::new((*self.storage).clone()));
+        }
+        
+        Ok(service.as_ref().unwrap().clone())
     }
-}
 
-impl Clone for FlashcardService {
-    fn clone(&self) -> Self {
-        FlashcardService::new(self.storage.clone())
+    /// Get or create annotation service
+    pub async fn annotation_service(&self) -> AppResult<AnnotationService> {
+        let mut service = self.annotation_service.lock().await;
+        
+        if service.is_none() {
+            *service = Some(AnnotationService::new((*self.storage).clone()));
+        }
+        
+        Ok(service.as_ref().unwrap().clone())
     }
-}
 
-impl Clone for MockTestService {
-    fn clone(&self) -> Self {
-        MockTestService::new(
-            self.storage.clone(),
-            self.llm_service.clone(),
-            self.rag_service.clone(),
-        )
+    /// Get or create case service
+    pub async fn case_service(&self) -> AppResult<CaseService> {
+        let mut service = self.case_service.lock().await;
+        
+        if service.is_none() {
+            *service = Some(CaseService::new((*self.storage).clone()));
+        }
+        
+        Ok(service.as_ref().unwrap().clone())
     }
-}
 
-impl Clone for StudyPlanService {
-    fn clone(&self) -> Self {
-        StudyPlanService::new(self.storage.clone())
+    /// Get or create precedent service
+    pub async fn precedent_service(&self) -> AppResult<PrecedentService> {
+        let mut service = self.precedent_service.lock().await;
+        
+        if service.is_none() {
+            let rag = self.rag_service().await?;
+            *service = Some(PrecedentService::new((*self.storage).clone(), rag));
+        }
+        
+        Ok(service.as_ref().unwrap().clone())
     }
-}
 
-// Add pub api_key field access for LLMService
-impl LLMService {
-    pub fn api_key(&self) -> &str {
-        &self.api_key
+    /// Get or create knowledge graph service
+    pub async fn knowledge_graph_service(&self) -> AppResult<KnowledgeGraphService> {
+        let mut service = self.knowledge_graph_service.lock().await;
+        
+        if service.is_none() {
+            let llm = self.llm_service().await?;
+            *service = Some(KnowledgeGraphService::new((*self.storage).clone(), llm));
+        }
+        
+        Ok(service.as_ref().unwrap().clone())
     }
-}
 
-// Add pub storage field access for services that need it
-impl FlashcardService {
-    pub fn storage(&self) -> &HybridStorage {
-        &self.storage
+    /// Get or create question bank service
+    pub async fn question_bank_service(&self) -> AppResult<QuestionBankService> {
+        let mut service = self.question_bank_service.lock().await;
+        
+        if service.is_none() {
+            *service = Some(QuestionBankService::new((*self.storage).clone()));
+        }
+        
+        Ok(service.as_ref().unwrap().clone())
     }
-}
 
-impl MockTestService {
-    pub fn storage(&self) -> &HybridStorage {
-        &self.storage
-    }
-    
-    pub fn llm_service(&self) -> &LLMService {
-        &self.llm_service
-    }
-    
-    pub fn rag_service(&self) -> &RAGService {
-        &self.rag_service
+    /// Get or create profile service
+    pub async fn profile_service(&self) -> AppResult<ProfileService> {
+        let mut service = self.profile_service.lock().await;
+        
+        if service.is_none() {
+            *service = Some(ProfileService::new((*self.storage).clone()));
+        }
+        
+        Ok(service.as_ref().unwrap().clone())
     }
-}
 
-impl StudyPlanService {
-    pub fn storage(&self) -> &HybridStorage {
-        &self.storage
+    /// Check if online
+    pub async fn is_online(&self) -> bool {
+        self.storage.is_online().await
     }
-}
 
-impl RAGService {
-    pub fn api_key(&self) -> &str {
-        &self.api_key
+    /// Get sync status
+    pub async fn sync_status(&self) -> AppResult<crate::sync::SyncStatus> {
+        self.sync_manager.get_status().await
     }
 }
-
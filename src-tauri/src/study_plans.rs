@@ -3,11 +3,18 @@
  * Manages study plans, tasks, and progress tracking
  */
 
-use crate::db::HybridStorage;
-use crate::error::{AppError, AppResult};
+use crate::db::{execute_checked, response_total, HybridStorage, PagedResult, PaginationOptions};
+use crate::error::{AppError, AppResult, CommandError, CommandResult};
+use crate::llm::{ChatOptions, LLMService, Message};
+use crate::logging::{self, LogLevel};
+use crate::recycle_bin;
+use crate::state::AppState;
 use crate::validation::{validate_not_empty, validate_percentage, validate_study_plan_dates, validate_uuid};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::collections::{HashMap, HashSet};
+use tauri::{AppHandle, Emitter, State};
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -31,6 +38,23 @@ pub struct StudyTask {
     pub description: Option<String>,
     pub completed: bool,
     pub due_date: Option<String>,
+    /// IDs of tasks in the same plan that must be done before this one
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Derived from `completed` and `depends_on` whenever the plan is saved
+    #[serde(default)]
+    pub status: TaskStatus,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    /// Waiting on a prerequisite
+    Locked,
+    #[default]
+    Available,
+    InProgress,
+    Done,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -40,15 +64,47 @@ pub struct CreateStudyPlanRequest {
     pub description: Option<String>,
     pub start_date: Option<String>,
     pub end_date: Option<String>,
+    #[serde(default)]
+    pub tasks: Vec<StudyTask>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateProgressRequest {
     pub plan_id: String,
-    pub progress: f64,
+    /// Used only when the plan has no tasks; otherwise progress is the share of completed tasks
+    pub progress: Option<f64>,
     pub tasks: Option<Vec<StudyTask>>,
 }
 
+/// Payload of the `plan-completed` event
+#[derive(Debug, Serialize, Clone)]
+pub struct PlanCompletedEvent {
+    pub plan_id: String,
+}
+
+/// Study plan as generated by the LLM, before validation
+#[derive(Debug, Deserialize)]
+struct GeneratedPlan {
+    #[serde(default)]
+    title: Option<String>,
+    tasks: Vec<GeneratedTask>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeneratedTask {
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    due_date: String,
+}
+
+/// Most tasks requested from the LLM for one plan
+const MAX_GENERATED_TASKS: usize = 90;
+
+/// Longest iCalendar content line in octets, excluding the CRLF (RFC 5545 3.1)
+const ICAL_LINE_LIMIT: usize = 75;
+
+#[derive(Clone)]
 pub struct StudyPlanService {
     storage: HybridStorage,
 }
@@ -59,8 +115,8 @@ impl StudyPlanService {
     }
 
     /// Create a new study plan
-    pub async fn create_plan(&self, request: CreateStudyPlanRequest) -> AppResult<StudyPlan> {
-        validate_uuid(&request.user_id, "User ID")?;
+    pub async fn create_plan(&self, mut request: CreateStudyPlanRequest) -> AppResult<StudyPlan> {
+        request.user_id = validate_uuid(&request.user_id, "User ID")?;
         validate_not_empty(&request.title, "Plan title")?;
 
         // Validate dates if provided
@@ -68,6 +124,10 @@ impl StudyPlanService {
             validate_study_plan_dates(start, end)?;
         }
 
+        let mut tasks = request.tasks;
+        validate_task_dependencies(&tasks)?;
+        update_task_statuses(&mut tasks);
+
         let plan = StudyPlan {
             id: Uuid::new_v4().to_string(),
             user_id: request.user_id.clone(),
@@ -76,11 +136,120 @@ impl StudyPlanService {
             start_date: request.start_date.clone(),
             end_date: request.end_date.clone(),
             progress: 0.0,
-            tasks: Vec::new(),
+            tasks,
+            created_at: Utc::now().to_rfc3339(),
+            updated_at: Utc::now().to_rfc3339(),
+        };
+
+        self.save_new_plan(plan).await
+    }
+
+    /// Have the LLM schedule study, review and mock test tasks from today until `exam_date`
+    ///
+    /// Tasks with a due date outside that range are dropped.
+    pub async fn generate_ai_plan(
+        &self,
+        user_id: &str,
+        exam_date: &str,
+        topics: Vec<String>,
+        hours_per_day: f64,
+        llm: &LLMService,
+    ) -> AppResult<StudyPlan> {
+        let user_id = &validate_uuid(user_id, "User ID")?;
+        let topics: Vec<String> = topics
+            .into_iter()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+        if topics.is_empty() {
+            return Err(AppError::MissingField("At least one exam topic is required".to_string()));
+        }
+        if !(hours_per_day > 0.0 && hours_per_day <= 24.0) {
+            return Err(AppError::Validation("Hours per day must be between 0 and 24".to_string()));
+        }
+
+        let today = chrono::Local::now().date_naive().format("%Y-%m-%d").to_string();
+        validate_study_plan_dates(&today, exam_date)?;
+
+        let system_prompt = llm.system_prompt(
+            "You are an expert law school study coach. You build realistic day-by-day revision schedules.
+Respond only with JSON.",
+        );
+        let user_prompt = format!(
+            "Create a study plan for an exam on {exam_date}. Today is {today}.
+The student can study {hours_per_day} hours per day.
+
+Topics:
+{topics}
+
+Guidelines:
+- Schedule tasks on dates from {today} to {exam_date} inclusive, in YYYY-MM-DD format
+- Cover every topic, giving harder and broader topics more time
+- Interleave spaced-repetition review sessions of earlier topics (about 1, 3 and 7 days after first study)
+- Schedule at least one full mock test, and a final review in the last days before the exam
+- Keep each day's tasks within {hours_per_day} hours and say how long each takes in its description
+- Use at most {max_tasks} tasks
+
+Provide your response as a JSON object with this structure:
+{{
+  \"title\": \"Short plan title\",
+  \"tasks\": [
+    {{
+      \"title\": \"Study: Offer and acceptance\",
+      \"description\": \"2h - read notes and brief two cases\",
+      \"due_date\": \"YYYY-MM-DD\"
+    }}
+  ]
+}}",
+            topics = topics.iter().map(|t| format!("- {}", t)).collect::<Vec<_>>().join("\n"),
+            max_tasks = MAX_GENERATED_TASKS,
+        );
+
+        let response = llm
+            .chat(
+                vec![
+                    Message {
+                        role: "system".to_string(),
+                        content: system_prompt,
+                    },
+                    Message {
+                        role: "user".to_string(),
+                        content: user_prompt,
+                    },
+                ],
+                ChatOptions {
+                    temperature: Some(0.4),
+                    max_tokens: Some(6000),
+                    task: Some("study_plan".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        let (title, tasks) = parse_generated_plan(&response, &today, exam_date)?;
+
+        let plan = StudyPlan {
+            id: Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            title: title.unwrap_or_else(|| format!("Exam plan until {}", exam_date)),
+            description: Some(format!(
+                "{} ({} hours per day)",
+                topics.join(", "),
+                hours_per_day
+            )),
+            start_date: Some(today),
+            end_date: Some(exam_date.to_string()),
+            progress: 0.0,
+            tasks,
             created_at: Utc::now().to_rfc3339(),
             updated_at: Utc::now().to_rfc3339(),
         };
 
+        self.save_new_plan(plan).await
+    }
+
+    /// Store a newly created plan in Supabase (when online) and locally
+    async fn save_new_plan(&self, plan: StudyPlan) -> AppResult<StudyPlan> {
         // Try to save to Supabase if online
         if self.storage.is_online().await {
             if let Some(supabase) = self.storage.supabase() {
@@ -98,10 +267,7 @@ impl StudyPlanService {
                     "updated_at": plan.updated_at,
                 });
 
-                supabase
-                    .insert("study_plans", &data.to_string())
-                    .await?
-                    .execute()
+                execute_checked(supabase.upsert("study_plans", &data.to_string(), "id").await?)
                     .await
                     .map_err(|e| AppError::Supabase(format!("Failed to create plan: {}", e)))?;
             }
@@ -109,90 +275,93 @@ impl StudyPlanService {
 
         // Save locally
         let tasks_json = serde_json::to_string(&plan.tasks)?;
-        self.storage.sqlite().execute(move |conn| {
-            conn.execute(
-                "INSERT INTO study_plans 
-                 (id, user_id, title, description, start_date, end_date, progress, tasks, created_at, updated_at, synced, dirty)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
-                rusqlite::params![
-                    &plan.id,
-                    &plan.user_id,
-                    &plan.title,
-                    &plan.description,
-                    &plan.start_date,
-                    &plan.end_date,
-                    plan.progress,
-                    &tasks_json,
-                    &plan.created_at,
-                    &plan.updated_at,
-                    if self.storage.is_online().await { 1 } else { 0 },
-                    if self.storage.is_online().await { 0 } else { 1 },
-                ],
-            )?;
-            Ok(())
-        }).await?;
+        let online = self.storage.is_online().await;
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query(
+            "INSERT INTO study_plans
+             (id, user_id, title, description, start_date, end_date, progress, tasks, created_at, updated_at, synced, dirty)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&plan.id)
+        .bind(&plan.user_id)
+        .bind(&plan.title)
+        .bind(&plan.description)
+        .bind(&plan.start_date)
+        .bind(&plan.end_date)
+        .bind(plan.progress)
+        .bind(&tasks_json)
+        .bind(&plan.created_at)
+        .bind(&plan.updated_at)
+        .bind(online as i64)
+        .bind(!online as i64)
+        .execute(&pool)
+        .await?;
 
         Ok(plan)
     }
 
-    /// Get all study plans for a user
-    pub async fn get_plans(&self, user_id: &str) -> AppResult<Vec<StudyPlan>> {
-        validate_uuid(user_id, "User ID")?;
+    /// Get a page of study plans for a user, most recently updated first
+    pub async fn get_plans(
+        &self,
+        user_id: &str,
+        pagination: Option<PaginationOptions>,
+    ) -> AppResult<PagedResult<StudyPlan>> {
+        let user_id = &validate_uuid(user_id, "User ID")?;
+        let pagination = pagination.unwrap_or_default().normalized()?;
 
         // Try Supabase first if online
         if self.storage.is_online().await {
             if let Some(supabase) = self.storage.supabase() {
+                let (from, to) = pagination.range();
                 let response = supabase
                     .select("study_plans")
                     .await?
                     .eq("user_id", user_id)
+                    .is("deleted_at", "null")
+                    .order("updated_at.desc")
+                    .range(from, to)
+                    .exact_count()
                     .execute()
                     .await
                     .map_err(|e| AppError::Supabase(format!("Failed to fetch plans: {}", e)))?;
 
+                let total = response_total(&response);
                 let body = response.text().await?;
                 let plans: Vec<StudyPlan> = serde_json::from_str(&body)?;
-                return Ok(plans);
+                let total = total.unwrap_or(pagination.offset() + plans.len());
+                return Ok(PagedResult::new(plans, total, pagination));
             }
         }
 
         // Fallback to local
-        let user_id = user_id.to_string();
-        self.storage.sqlite().execute(move |conn| {
-            let mut stmt = conn.prepare(
-                "SELECT id, user_id, title, description, start_date, end_date, progress, tasks, created_at, updated_at
-                 FROM study_plans
-                 WHERE user_id = ?1
-                 ORDER BY updated_at DESC"
-            )?;
-
-            let plans = stmt
-                .query_map([&user_id], |row| {
-                    let tasks_json: String = row.get(7)?;
-                    let tasks: Vec<StudyTask> = serde_json::from_str(&tasks_json).unwrap_or_default();
-
-                    Ok(StudyPlan {
-                        id: row.get(0)?,
-                        user_id: row.get(1)?,
-                        title: row.get(2)?,
-                        description: row.get(3)?,
-                        start_date: row.get(4)?,
-                        end_date: row.get(5)?,
-                        progress: row.get(6)?,
-                        tasks,
-                        created_at: row.get(8)?,
-                        updated_at: row.get(9)?,
-                    })
-                })?
-                .collect::<Result<Vec<_>, _>>()?;
-
-            Ok(plans)
-        }).await
+        let pool = self.storage.sqlite().get_pool().await?;
+        let total: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM study_plans WHERE user_id = ? AND deleted_at IS NULL"
+        )
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await?;
+
+        let rows = sqlx::query(
+            "SELECT id, user_id, title, description, start_date, end_date, progress, tasks, created_at, updated_at
+             FROM study_plans
+             WHERE user_id = ? AND deleted_at IS NULL
+             ORDER BY updated_at DESC
+             LIMIT ? OFFSET ?"
+        )
+        .bind(user_id)
+        .bind(pagination.page_size as i64)
+        .bind(pagination.offset() as i64)
+        .fetch_all(&pool)
+        .await?;
+
+        let plans = rows.iter().map(plan_from_row).collect();
+        Ok(PagedResult::new(plans, total as usize, pagination))
     }
 
     /// Get a specific study plan
     pub async fn get_plan(&self, plan_id: &str) -> AppResult<StudyPlan> {
-        validate_uuid(plan_id, "Plan ID")?;
+        let plan_id = &validate_uuid(plan_id, "Plan ID")?;
 
         // Try Supabase first if online
         if self.storage.is_online().await {
@@ -201,6 +370,7 @@ impl StudyPlanService {
                     .select("study_plans")
                     .await?
                     .eq("id", plan_id)
+                    .is("deleted_at", "null")
                     .execute()
                     .await
                     .map_err(|e| AppError::Supabase(format!("Failed to fetch plan: {}", e)))?;
@@ -212,49 +382,40 @@ impl StudyPlanService {
         }
 
         // Fallback to local
-        let plan_id = plan_id.to_string();
-        self.storage.sqlite().execute(move |conn| {
-            let mut stmt = conn.prepare(
-                "SELECT id, user_id, title, description, start_date, end_date, progress, tasks, created_at, updated_at
-                 FROM study_plans
-                 WHERE id = ?1"
-            )?;
-
-            let plan = stmt.query_row([&plan_id], |row| {
-                let tasks_json: String = row.get(7)?;
-                let tasks: Vec<StudyTask> = serde_json::from_str(&tasks_json).unwrap_or_default();
-
-                Ok(StudyPlan {
-                    id: row.get(0)?,
-                    user_id: row.get(1)?,
-                    title: row.get(2)?,
-                    description: row.get(3)?,
-                    start_date: row.get(4)?,
-                    end_date: row.get(5)?,
-                    progress: row.get(6)?,
-                    tasks,
-                    created_at: row.get(8)?,
-                    updated_at: row.get(9)?,
-                })
-            }).map_err(|_| AppError::NotFound("Plan not found".to_string()))?;
-
-            Ok(plan)
-        }).await
+        let pool = self.storage.sqlite().get_pool().await?;
+        let row = sqlx::query(
+            "SELECT id, user_id, title, description, start_date, end_date, progress, tasks, created_at, updated_at
+             FROM study_plans
+             WHERE id = ? AND deleted_at IS NULL"
+        )
+        .bind(plan_id)
+        .fetch_optional(&pool)
+        .await?;
+
+        row.as_ref()
+            .map(plan_from_row)
+            .ok_or_else(|| AppError::NotFound("Plan not found".to_string()))
     }
 
-    /// Update study plan progress
-    pub async fn update_progress(&self, request: UpdateProgressRequest) -> AppResult<StudyPlan> {
-        validate_uuid(&request.plan_id, "Plan ID")?;
-        validate_percentage(request.progress, "Progress")?;
+    /// Update a plan's tasks, recomputing task statuses and progress
+    pub async fn update_progress(&self, mut request: UpdateProgressRequest) -> AppResult<StudyPlan> {
+        request.plan_id = validate_uuid(&request.plan_id, "Plan ID")?;
+        if let Some(progress) = request.progress {
+            validate_percentage(progress, "Progress")?;
+        }
 
         // Get existing plan
         let mut plan = self.get_plan(&request.plan_id).await?;
 
         // Update fields
-        plan.progress = request.progress;
         if let Some(tasks) = request.tasks {
+            validate_task_dependencies(&tasks)?;
             plan.tasks = tasks;
         }
+        update_task_statuses(&mut plan.tasks);
+        if let Some(progress) = task_progress(&plan.tasks).or(request.progress) {
+            plan.progress = progress;
+        }
         plan.updated_at = Utc::now().to_rfc3339();
 
         // Try to update in Supabase if online
@@ -279,48 +440,603 @@ impl StudyPlanService {
 
         // Update locally
         let tasks_json = serde_json::to_string(&plan.tasks)?;
-        self.storage.sqlite().execute(move |conn| {
-            conn.execute(
-                "UPDATE study_plans 
-                 SET progress = ?1, tasks = ?2, updated_at = ?3, dirty = ?4
-                 WHERE id = ?5",
-                rusqlite::params![
-                    plan.progress,
-                    &tasks_json,
-                    &plan.updated_at,
-                    if self.storage.is_online().await { 0 } else { 1 },
-                    &plan.id,
-                ],
-            )?;
-            Ok(())
-        }).await?;
+        let online = self.storage.is_online().await;
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query(
+            "UPDATE study_plans
+             SET progress = ?, tasks = ?, updated_at = ?, dirty = ?
+             WHERE id = ?"
+        )
+        .bind(plan.progress)
+        .bind(&tasks_json)
+        .bind(&plan.updated_at)
+        .bind(!online as i64)
+        .bind(&plan.id)
+        .execute(&pool)
+        .await?;
 
         Ok(plan)
     }
 
-    /// Delete a study plan
+    /// Mark one task of a plan completed and recompute the plan's progress
+    ///
+    /// A task waiting on unfinished prerequisites can't be completed.
+    pub async fn complete_task(&self, plan_id: &str, task_id: &str) -> AppResult<StudyPlan> {
+        let mut plan = self.get_plan(plan_id).await?;
+        update_task_statuses(&mut plan.tasks);
+
+        let task = plan
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == task_id)
+            .ok_or_else(|| AppError::NotFound(format!("Task {} not found in plan", task_id)))?;
+        if task.status == TaskStatus::Locked {
+            return Err(AppError::Validation(format!(
+                "Task '{}' is locked until its prerequisites are done",
+                task.title
+            )));
+        }
+        task.completed = true;
+
+        self.update_progress(UpdateProgressRequest {
+            plan_id: plan.id,
+            progress: None,
+            tasks: Some(plan.tasks),
+        })
+        .await
+    }
+
+    /// Tasks of a plan whose prerequisites are all done and which haven't been started
+    pub async fn get_available_tasks(&self, plan_id: &str) -> AppResult<Vec<StudyTask>> {
+        let mut plan = self.get_plan(plan_id).await?;
+        update_task_statuses(&mut plan.tasks);
+
+        Ok(plan
+            .tasks
+            .into_iter()
+            .filter(|t| t.status == TaskStatus::Available)
+            .collect())
+    }
+
+    /// Render a plan as an iCalendar (.ics) document with one all-day event per dated task
+    pub async fn export_to_ical(&self, plan_id: &str) -> AppResult<String> {
+        let plan = self.get_plan(plan_id).await?;
+        Ok(plan_to_ical(&plan))
+    }
+
+    /// Move a study plan to the recycle bin
     pub async fn delete_plan(&self, plan_id: &str) -> AppResult<()> {
-        validate_uuid(plan_id, "Plan ID")?;
+        let plan_id = validate_uuid(plan_id, "Plan ID")?;
+        recycle_bin::move_to_bin(&self.storage, "study_plans", &plan_id).await
+    }
+}
 
-        // Try Supabase if online
-        if self.storage.is_online().await {
-            if let Some(supabase) = self.storage.supabase() {
-                supabase
-                    .delete("study_plans")
-                    .await?
-                    .eq("id", plan_id)
-                    .execute()
-                    .await
-                    .map_err(|e| AppError::Supabase(format!("Failed to delete plan: {}", e)))?;
+fn plan_from_row(row: &sqlx::sqlite::SqliteRow) -> StudyPlan {
+    let tasks_json: Option<String> = row.get("tasks");
+    StudyPlan {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        title: row.get("title"),
+        description: row.get("description"),
+        start_date: row.get("start_date"),
+        end_date: row.get("end_date"),
+        progress: row.get("progress"),
+        tasks: tasks_json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// Percentage of tasks completed, or None for a plan without tasks
+fn task_progress(tasks: &[StudyTask]) -> Option<f64> {
+    if tasks.is_empty() {
+        return None;
+    }
+    let completed = tasks.iter().filter(|t| t.completed).count();
+    Some(completed as f64 / tasks.len() as f64 * 100.0)
+}
+
+/// Check that every dependency names a task in `tasks` and that no task depends on itself,
+/// directly or through other tasks
+fn validate_task_dependencies(tasks: &[StudyTask]) -> AppResult<()> {
+    let by_id: HashMap<&str, &StudyTask> = tasks.iter().map(|t| (t.id.as_str(), t)).collect();
+
+    for task in tasks {
+        if let Some(missing) = task.depends_on.iter().find(|id| !by_id.contains_key(id.as_str())) {
+            return Err(AppError::Validation(format!(
+                "Task '{}' depends on unknown task {}",
+                task.title, missing
+            )));
+        }
+    }
+
+    // Depth-first search; reaching a task still on the stack means a cycle
+    fn has_cycle<'a>(
+        id: &'a str,
+        by_id: &HashMap<&'a str, &'a StudyTask>,
+        on_stack: &mut HashSet<&'a str>,
+        finished: &mut HashSet<&'a str>,
+    ) -> bool {
+        if finished.contains(id) {
+            return false;
+        }
+        if !on_stack.insert(id) {
+            return true;
+        }
+        for dependency in &by_id[id].depends_on {
+            if has_cycle(dependency, by_id, on_stack, finished) {
+                return true;
             }
         }
+        on_stack.remove(id);
+        finished.insert(id);
+        false
+    }
+
+    let mut on_stack = HashSet::new();
+    let mut finished = HashSet::new();
+    for task in tasks {
+        if has_cycle(&task.id, &by_id, &mut on_stack, &mut finished) {
+            return Err(AppError::Validation("circular dependency detected".to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Recompute each task's status: completed tasks are `Done`, tasks with a
+/// prerequisite that isn't done are `Locked`, and the rest are `Available`
+/// unless already `InProgress`. `completed` is the source of truth, so an
+/// unchecked task leaves `Done` even if that's still its stored status
+fn update_task_statuses(tasks: &mut [StudyTask]) {
+    let done: HashSet<String> = tasks
+        .iter()
+        .filter(|t| t.completed)
+        .map(|t| t.id.clone())
+        .collect();
+
+    for task in tasks.iter_mut() {
+        task.status = if task.completed {
+            TaskStatus::Done
+        } else if !task.depends_on.iter().all(|id| done.contains(id)) {
+            TaskStatus::Locked
+        } else if task.status == TaskStatus::InProgress {
+            TaskStatus::InProgress
+        } else {
+            TaskStatus::Available
+        };
+    }
+}
+
+/// iCalendar document with an all-day `VEVENT` for each task that has a due date
+///
+/// Tasks whose due date isn't a date (YYYY-MM-DD, optionally followed by a
+/// time) are left out.
+fn plan_to_ical(plan: &StudyPlan) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//FIRM AI//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_ical_text(&plan.title)),
+        "BEGIN:VTIMEZONE".to_string(),
+        "TZID:UTC".to_string(),
+        "BEGIN:STANDARD".to_string(),
+        "DTSTART:19700101T000000".to_string(),
+        "TZOFFSETFROM:+0000".to_string(),
+        "TZOFFSETTO:+0000".to_string(),
+        "TZNAME:UTC".to_string(),
+        "END:STANDARD".to_string(),
+        "END:VTIMEZONE".to_string(),
+    ];
+
+    for task in &plan.tasks {
+        let due = task
+            .due_date
+            .as_deref()
+            .and_then(|d| d.get(..10))
+            .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+        let Some(due) = due else {
+            continue;
+        };
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}@firm-ai", task.id));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", due.format("%Y%m%d")));
+        lines.push(format!("DTEND;VALUE=DATE:{}", due.succ_opt().unwrap_or(due).format("%Y%m%d")));
+        lines.push(format!("SUMMARY:{}", escape_ical_text(&task.title)));
+        if let Some(description) = task.description.as_deref().filter(|d| !d.trim().is_empty()) {
+            lines.push(format!("DESCRIPTION:{}", escape_ical_text(description)));
+        }
+        if task.completed {
+            lines.push("STATUS:CONFIRMED".to_string());
+        }
+        lines.push("TRANSP:TRANSPARENT".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold_ical_line(line)).collect()
+}
+
+/// Escape a TEXT property value (RFC 5545 3.3.11)
+fn escape_ical_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace(['\n', '\r'], "\\n")
+}
+
+/// Fold a content line into CRLF-terminated lines of at most `ICAL_LINE_LIMIT`
+/// octets, continuation lines starting with a space, without splitting a character
+fn fold_ical_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > ICAL_LINE_LIMIT {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Tasks from an LLM study plan response, sorted by due date
+///
+/// Tasks due before `start_date` or after `exam_date` are dropped; an
+/// unparseable response or one with no usable tasks is an error.
+fn parse_generated_plan(
+    response: &str,
+    start_date: &str,
+    exam_date: &str,
+) -> AppResult<(Option<String>, Vec<StudyTask>)> {
+    // Models often wrap JSON in a code fence or add a sentence around it
+    let json = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => response,
+    };
+    let plan: GeneratedPlan = serde_json::from_str(json)
+        .map_err(|e| AppError::Llm(format!("Failed to parse study plan: {}", e)))?;
+
+    let mut tasks: Vec<StudyTask> = Vec::new();
+    for task in plan.tasks {
+        let due_date = task.due_date.trim().to_string();
+        let in_range = validate_study_plan_dates(start_date, &due_date)
+            .and_then(|_| validate_study_plan_dates(&due_date, exam_date));
+        if let Err(e) = in_range {
+            logging::log(
+                LogLevel::Warn,
+                "study_plans",
+                &format!("Dropped generated task '{}' due {}: {}", task.title, due_date, e),
+            );
+            continue;
+        }
+        if task.title.trim().is_empty() {
+            continue;
+        }
+
+        tasks.push(StudyTask {
+            id: Uuid::new_v4().to_string(),
+            title: task.title.trim().to_string(),
+            description: task.description.filter(|d| !d.trim().is_empty()),
+            completed: false,
+            due_date: Some(due_date),
+            depends_on: Vec::new(),
+            status: TaskStatus::Available,
+        });
+    }
+
+    if tasks.is_empty() {
+        return Err(AppError::Llm("Generated study plan has no tasks between today and the exam".to_string()));
+    }
+
+    tasks.sort_by(|a, b| a.due_date.cmp(&b.due_date));
+    let title = plan.title.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    Ok((title, tasks))
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub async fn get_study_plans(
+    state: State<'_, AppState>,
+    user_id: String,
+    pagination: Option<PaginationOptions>,
+) -> CommandResult<PagedResult<StudyPlan>> {
+    let service = state.study_plan_service().await?;
+    service.get_plans(&user_id, pagination).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn create_study_plan(
+    state: State<'_, AppState>,
+    user_id: String,
+    title: String,
+    description: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> CommandResult<StudyPlan> {
+    let service = state.study_plan_service().await?;
+    service
+        .create_plan(CreateStudyPlanRequest {
+            user_id,
+            title,
+            description,
+            start_date,
+            end_date,
+            tasks: Vec::new(),
+        })
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn get_study_plan(state: State<'_, AppState>, plan_id: String) -> CommandResult<StudyPlan> {
+    let service = state.study_plan_service().await?;
+    service.get_plan(&plan_id).await.map_err(CommandError::from)
+}
 
-        // Delete locally
-        let plan_id = plan_id.to_string();
-        self.storage.sqlite().execute(move |conn| {
-            conn.execute("DELETE FROM study_plans WHERE id = ?1", [&plan_id])?;
-            Ok(())
-        }).await
+/// Replace a plan's tasks; `progress` is only used for plans without tasks
+#[tauri::command]
+pub async fn update_study_progress(
+    state: State<'_, AppState>,
+    plan_id: String,
+    progress: Option<f64>,
+    tasks: Option<Vec<StudyTask>>,
+) -> CommandResult<StudyPlan> {
+    let service = state.study_plan_service().await?;
+    service
+        .update_progress(UpdateProgressRequest { plan_id, progress, tasks })
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Generate and save a study plan covering `topics` up to `exam_date` (YYYY-MM-DD)
+#[tauri::command]
+pub async fn generate_ai_study_plan(
+    state: State<'_, AppState>,
+    user_id: String,
+    exam_date: String,
+    topics: Vec<String>,
+    hours_per_day: f64,
+) -> CommandResult<StudyPlan> {
+    let service = state.study_plan_service().await?;
+    let llm = state.llm_service().await?;
+    service
+        .generate_ai_plan(&user_id, &exam_date, topics, hours_per_day, &llm)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Mark a task completed, emitting `plan-completed` once every task in the plan is done
+#[tauri::command]
+pub async fn complete_study_task(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    plan_id: String,
+    task_id: String,
+) -> CommandResult<StudyPlan> {
+    let service = state.study_plan_service().await?;
+    let plan = service.complete_task(&plan_id, &task_id).await?;
+
+    if plan.progress >= 100.0 {
+        let _ = app.emit(
+            "plan-completed",
+            PlanCompletedEvent {
+                plan_id: plan.id.clone(),
+            },
+        );
     }
+
+    Ok(plan)
+}
+
+/// Tasks in a plan that can be started now
+#[tauri::command]
+pub async fn get_available_study_tasks(
+    state: State<'_, AppState>,
+    plan_id: String,
+) -> CommandResult<Vec<StudyTask>> {
+    let service = state.study_plan_service().await?;
+    service.get_available_tasks(&plan_id).await.map_err(CommandError::from)
 }
 
+/// Write a plan's dated tasks to `output_path` as an iCalendar file
+#[tauri::command]
+pub async fn export_study_plan_to_ical(
+    state: State<'_, AppState>,
+    plan_id: String,
+    output_path: String,
+) -> CommandResult<()> {
+    let service = state.study_plan_service().await?;
+    let ics = service.export_to_ical(&plan_id).await?;
+    tokio::fs::write(&output_path, ics).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_study_plan(state: State<'_, AppState>, plan_id: String) -> CommandResult<()> {
+    let service = state.study_plan_service().await?;
+    service.delete_plan(&plan_id).await.map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_generated_plan_keeps_tasks_in_range() {
+        let response = r#"Here is your plan:
+```json
+{
+  "title": "Contracts exam plan",
+  "tasks": [
+    {"title": "Review: Offer", "description": "1h", "due_date": "2026-03-04"},
+    {"title": "Study: Offer", "description": "2h", "due_date": "2026-03-02"},
+    {"title": "Too early", "due_date": "2026-02-28"},
+    {"title": "After the exam", "due_date": "2026-04-01"},
+    {"title": "Bad date", "due_date": "next Tuesday"},
+    {"title": "Mock test", "description": "", "due_date": "2026-03-20"}
+  ]
+}
+```"#;
+
+        let (title, tasks) = parse_generated_plan(response, "2026-03-01", "2026-03-21").unwrap();
+        assert_eq!(title.as_deref(), Some("Contracts exam plan"));
+
+        let titles: Vec<&str> = tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["Study: Offer", "Review: Offer", "Mock test"]);
+        assert!(tasks.iter().all(|t| !t.completed && t.due_date.is_some()));
+        assert_eq!(tasks[2].description, None);
+    }
+
+    #[test]
+    fn test_parse_generated_plan_rejects_unusable_response() {
+        assert!(matches!(parse_generated_plan("I can't do that", "2026-03-01", "2026-03-21"), Err(AppError::Llm(_))));
+
+        let none_in_range = r#"{"tasks": [{"title": "Late", "due_date": "2027-01-01"}]}"#;
+        assert!(matches!(parse_generated_plan(none_in_range, "2026-03-01", "2026-03-21"), Err(AppError::Llm(_))));
+    }
+
+    fn task(id: &str, depends_on: &[&str]) -> StudyTask {
+        StudyTask {
+            id: id.to_string(),
+            title: id.to_string(),
+            description: None,
+            completed: false,
+            due_date: None,
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            status: TaskStatus::default(),
+        }
+    }
+
+    #[test]
+    fn test_task_dependencies_reject_cycles_and_unknown_tasks() {
+        let chain = vec![task("a", &[]), task("b", &["a"]), task("c", &["a", "b"])];
+        assert!(validate_task_dependencies(&chain).is_ok());
+
+        let cycle = vec![task("a", &["c"]), task("b", &["a"]), task("c", &["b"])];
+        assert!(matches!(
+            validate_task_dependencies(&cycle),
+            Err(AppError::Validation(msg)) if msg == "circular dependency detected"
+        ));
+        assert!(validate_task_dependencies(&[task("a", &["a"])]).is_err());
+        assert!(validate_task_dependencies(&[task("a", &["missing"])]).is_err());
+    }
+
+    #[test]
+    fn test_task_statuses_follow_prerequisites() {
+        let mut tasks = vec![task("a", &[]), task("b", &["a"]), task("c", &["b"])];
+        update_task_statuses(&mut tasks);
+        let statuses: Vec<TaskStatus> = tasks.iter().map(|t| t.status).collect();
+        assert_eq!(statuses, vec![TaskStatus::Available, TaskStatus::Locked, TaskStatus::Locked]);
+
+        tasks[0].completed = true;
+        tasks[1].status = TaskStatus::InProgress;
+        update_task_statuses(&mut tasks);
+        let statuses: Vec<TaskStatus> = tasks.iter().map(|t| t.status).collect();
+        assert_eq!(statuses, vec![TaskStatus::Done, TaskStatus::InProgress, TaskStatus::Locked]);
+
+        tasks[1].completed = true;
+        update_task_statuses(&mut tasks);
+        assert_eq!(tasks[1].status, TaskStatus::Done);
+        assert_eq!(tasks[2].status, TaskStatus::Available);
+    }
+
+    #[test]
+    fn test_unchecking_a_done_task_reopens_it() {
+        let mut tasks = vec![task("a", &[]), task("b", &["a"])];
+        tasks[0].completed = true;
+        tasks[1].completed = true;
+        update_task_statuses(&mut tasks);
+        assert_eq!(tasks[0].status, TaskStatus::Done);
+
+        // The stored status is still Done when the task comes back unchecked
+        tasks[0].completed = false;
+        update_task_statuses(&mut tasks);
+        assert!(!tasks[0].completed);
+        assert_eq!(tasks[0].status, TaskStatus::Available);
+        assert_eq!(tasks[1].status, TaskStatus::Done);
+
+        tasks[1].completed = false;
+        update_task_statuses(&mut tasks);
+        assert_eq!(tasks[1].status, TaskStatus::Locked);
+        assert_eq!(task_progress(&tasks), Some(0.0));
+    }
+
+    #[test]
+    fn test_plan_to_ical_has_one_event_per_dated_task() {
+        let mut dated = task("a", &[]);
+        dated.title = "Study: Offer, acceptance; consideration".to_string();
+        dated.description = Some("2h\nread notes and brief two cases with a description long enough to need folding".to_string());
+        dated.due_date = Some("2026-03-02".to_string());
+        let mut timestamped = task("b", &[]);
+        timestamped.due_date = Some("2026-03-31T09:00:00Z".to_string());
+        let mut bad_date = task("c", &[]);
+        bad_date.due_date = Some("next Tuesday".to_string());
+
+        let plan = StudyPlan {
+            id: "plan".to_string(),
+            user_id: "user".to_string(),
+            title: "Contracts".to_string(),
+            description: None,
+            start_date: None,
+            end_date: None,
+            progress: 0.0,
+            tasks: vec![dated, timestamped, bad_date, task("d", &[])],
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+
+        let ics = plan_to_ical(&plan);
+        assert!(ics.lines().all(|line| line.len() <= ICAL_LINE_LIMIT + 1));
+
+        let mut parser = ical::IcalParser::new(ics.as_bytes());
+        let calendar = parser.next().unwrap().unwrap();
+        assert!(parser.next().is_none());
+
+        let prodid = calendar.properties.iter().find(|p| p.name == "PRODID").unwrap();
+        assert_eq!(prodid.value.as_deref(), Some("-//FIRM AI//EN"));
+        assert_eq!(calendar.timezones.len(), 1);
+        assert_eq!(calendar.events.len(), 2);
+
+        let property = |event: &ical::parser::ical::component::IcalEvent, name: &str| {
+            event
+                .properties
+                .iter()
+                .find(|p| p.name == name)
+                .and_then(|p| p.value.clone())
+        };
+        let first = &calendar.events[0];
+        assert_eq!(property(first, "SUMMARY").as_deref(), Some("Study: Offer\\, acceptance\\; consideration"));
+        assert_eq!(property(first, "DTSTART").as_deref(), Some("20260302"));
+        assert_eq!(property(first, "DTEND").as_deref(), Some("20260303"));
+        assert!(property(first, "DESCRIPTION").unwrap().ends_with("need folding"));
+        assert_eq!(property(&calendar.events[1], "DTEND").as_deref(), Some("20260401"));
+    }
+
+    #[test]
+    fn test_task_progress_is_share_of_completed_tasks() {
+        assert_eq!(task_progress(&[]), None);
+
+        let mut tasks = vec![task("a", &[]), task("b", &[]), task("c", &[]), task("d", &[])];
+        assert_eq!(task_progress(&tasks), Some(0.0));
+
+        tasks[0].completed = true;
+        tasks[2].completed = true;
+        update_task_statuses(&mut tasks);
+        assert_eq!(task_progress(&tasks), Some(50.0));
+
+        tasks.iter_mut().for_each(|t| t.completed = true);
+        assert_eq!(task_progress(&tasks), Some(100.0));
+    }
+}
@@ -3,13 +3,61 @@
  * Handles background synchronization between local SQLite and Supabase
  */
 
-use crate::db::{HybridStorage, SyncOperation};
-use crate::error::{AppError, AppResult};
-use chrono::Utc;
+use crate::db::{execute_checked, HybridStorage, SyncOperation};
+use crate::error::{AppError, AppResult, CommandError, CommandResult};
+use crate::export::{bind_json, table_columns};
+use crate::logging::LogLevel;
+use crate::state::AppState;
+use chrono::{DateTime, Utc};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite, Transaction};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::{watch, Mutex};
 use tokio::time::{interval, Duration};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+/// Local tables whose dirty rows are pushed to Supabase, parents before
+/// the tables that reference them
+const SYNCED_TABLES: &[&str] = &[
+    "cases",
+    "tags",
+    "case_tags",
+    "flashcard_sets",
+    "flashcards",
+    "mock_tests",
+    "test_results",
+    "study_plans",
+];
+
+/// Parent IDs per request when pulling a table whose rows are owned through a parent
+const PULL_PARENT_BATCH: usize = 100;
+
+/// Rows per pull request; Supabase returns at most 1000 by default
+const PULL_PAGE: usize = 1000;
+
+/// Dirty records pushed per request; their IDs also go in the URL of the
+/// select that checks them against Supabase
+const PUSH_BATCH: usize = 100;
+
+/// Queued operations move to the dead-letter table after this many failed attempts
+const MAX_SYNC_ATTEMPTS: i64 = 5;
+
+/// Upper bound on the delay before retrying a failed operation
+const MAX_RETRY_DELAY_SECS: i64 = 3600;
+
+/// Periodic sync interval used until `with_interval` or `set_interval` changes it
+const DEFAULT_SYNC_INTERVAL_SECS: u64 = 300;
+
+/// Seconds between connectivity checks, before jitter
+const CONNECTIVITY_CHECK_SECS: u64 = 30;
+
+/// Most seconds a connectivity check is moved earlier or later
+const CONNECTIVITY_JITTER_SECS: u64 = 5;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SyncStatus {
@@ -19,11 +67,112 @@ pub struct SyncStatus {
     pub is_online: bool,
 }
 
+/// Emitted with no payload when a sync begins
+pub const SYNC_STARTED_EVENT: &str = "sync://started";
+/// Emitted with a `SyncProgressEvent` as records are pushed and pulled
+pub const SYNC_PROGRESS_EVENT: &str = "sync://progress";
+/// Emitted with the `SyncReport` of a sync that ran to the end
+pub const SYNC_COMPLETED_EVENT: &str = "sync://completed";
+/// Emitted with a `SyncErrorEvent` when a sync stops early
+pub const SYNC_ERROR_EVENT: &str = "sync://error";
+
+/// Payload of `sync://progress`: `done` of the `total` items of `table` handled so far
+///
+/// `table` is `sync_queue` while queued operations are replayed.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SyncProgressEvent {
+    pub table: String,
+    pub done: usize,
+    pub total: usize,
+}
+
+/// What one sync did, returned by `sync_now` and sent with `sync://completed`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SyncReport {
+    /// Queued operations replayed and dirty records upserted
+    pub pushed: usize,
+    /// Remote changes written locally
+    pub pulled: usize,
+    /// Operations and records that failed; they are retried on the next sync
+    pub failed: usize,
+    pub timestamp: String,
+}
+
+/// Payload of `sync://error`
+#[derive(Debug, Serialize, Clone)]
+pub struct SyncErrorEvent {
+    pub message: String,
+}
+
+/// Payload of the `connectivity-changed` event
+#[derive(Debug, Serialize, Clone)]
+pub struct ConnectivityChangedEvent {
+    pub online: bool,
+}
+
+/// A queued operation as shown in the sync debugging view
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncQueueItem {
+    pub id: i64,
+    pub table_name: String,
+    pub record_id: String,
+    pub attempts: i64,
+    /// UTC time before which the operation won't be retried
+    pub next_retry_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+/// A queued operation that ran out of retries, waiting for the user to retry or discard it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeadLetterOperation {
+    pub id: i64,
+    pub operation_type: String,
+    pub table_name: String,
+    pub record_id: String,
+    pub data: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub failed_at: String,
+}
+
+/// A record modified both locally and in Supabase since the last sync
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncConflict {
+    pub id: String,
+    pub table_name: String,
+    pub record_id: String,
+    pub local_data: serde_json::Value,
+    pub remote_data: serde_json::Value,
+    pub detected_at: String,
+}
+
+/// How to settle a `SyncConflict`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// Keep the local version and push it to Supabase
+    UseLocal,
+    /// Replace the local version with Supabase's
+    UseRemote,
+    /// Write the given record to both sides
+    Merge(serde_json::Value),
+}
+
+/// Where sync events go, by event name
+type EventSink = Arc<dyn Fn(&str, serde_json::Value) + Send + Sync>;
+
 #[derive(Clone)]
 pub struct SyncManager {
     storage: Arc<HybridStorage>,
     is_syncing: Arc<Mutex<bool>>,
     last_sync: Arc<Mutex<Option<String>>>,
+    /// Receives sync events for the frontend; events are dropped without one
+    events: Option<EventSink>,
+    /// Seconds between periodic syncs; the running loop restarts its ticker on change
+    interval_secs: Arc<watch::Sender<u64>>,
+    /// User whose rows are pulled from Supabase; nothing is pulled without one
+    user_id: Arc<Mutex<Option<String>>>,
 }
 
 impl SyncManager {
@@ -32,41 +181,130 @@ impl SyncManager {
             storage,
             is_syncing: Arc::new(Mutex::new(false)),
             last_sync: Arc::new(Mutex::new(None)),
+            events: None,
+            interval_secs: Arc::new(watch::Sender::new(DEFAULT_SYNC_INTERVAL_SECS)),
+            user_id: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Sync every `secs` seconds
+    pub fn with_interval(self, secs: u64) -> Self {
+        self.set_interval(secs);
+        self
+    }
+
+    /// Change the periodic sync interval, restarting the ticker if the loop is running
+    pub fn set_interval(&self, secs: u64) {
+        self.interval_secs.send_if_modified(|current| {
+            let changed = *current != secs;
+            *current = secs;
+            changed
+        });
+    }
+
+    /// Pull rows belonging to `user_id`; after a switch of user every table is pulled from the start
+    pub async fn set_user(&self, user_id: Option<String>) -> AppResult<()> {
+        let mut current = self.user_id.lock().await;
+        if *current != user_id {
+            let pool = self.storage.sqlite().get_pool().await?;
+            sqlx::query("DELETE FROM sync_watermarks").execute(&pool).await?;
+            *current = user_id;
+        }
+        Ok(())
+    }
+
+    /// Emit sync events through `handle`
+    pub fn with_app_handle(mut self, handle: AppHandle) -> Self {
+        self.events = Some(Arc::new(move |event, payload| {
+            let _ = handle.emit(event, payload);
+        }));
+        self
+    }
+
+    fn emit<S: Serialize>(&self, event: &str, payload: S) {
+        if let (Some(events), Ok(payload)) = (&self.events, serde_json::to_value(payload)) {
+            events(event, payload);
         }
     }
 
-    /// Start periodic background sync
-    pub async fn start_periodic_sync(self: Arc<Self>) {
+    /// Start periodic background sync, running until `cancel` is cancelled
+    ///
+    /// The first sync starts straight away; after that one runs every interval.
+    pub fn start_periodic_sync(self: Arc<Self>, cancel: CancellationToken) -> JoinHandle<()> {
         let sync_manager = self.clone();
         
-        tokio::spawn(async move {
-            let mut ticker = interval(Duration::from_secs(300)); // Sync every 5 minutes
+        tauri::async_runtime::spawn(async move {
+            let mut interval_rx = sync_manager.interval_secs.subscribe();
+            let mut ticker = interval(sync_interval(*interval_rx.borrow_and_update()));
 
             loop {
-                ticker.tick().await;
-                
-                // Check if online
-                let is_online = sync_manager.storage.is_online().await;
-                if !is_online {
-                    // Try to reconnect
-                    let new_status = sync_manager.storage.check_online().await;
-                    sync_manager.storage.set_online(new_status).await;
-                    
-                    if !new_status {
-                        continue; // Still offline, skip this sync
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = ticker.tick() => {}
+                    changed = interval_rx.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        // Restart the ticker; its first tick fires immediately
+                        ticker = interval(sync_interval(*interval_rx.borrow_and_update()));
+                        continue;
                     }
                 }
 
+                // Empty the recycle bin of anything past its retention period
+                if let Err(e) = crate::recycle_bin::purge_expired(&sync_manager.storage, &sync_manager).await {
+                    crate::logging::log(LogLevel::Warn, "sync", &format!("Recycle bin purge failed: {}", e));
+                }
+                
+                // Kept current by the connectivity watcher
+                if !sync_manager.storage.is_online().await {
+                    continue; // Still offline, skip this sync
+                }
+
                 // Perform sync
                 if let Err(e) = sync_manager.sync_now().await {
-                    eprintln!("Background sync error: {}", e);
+                    crate::logging::log(LogLevel::Warn, "sync", &format!("Background sync failed: {}", e));
                 }
             }
-        });
+        })
+    }
+
+    /// Check connectivity every `CONNECTIVITY_CHECK_SECS` (with jitter) until
+    /// `cancel` is cancelled, keeping the storage's online flag current and
+    /// emitting `connectivity-changed` when it flips
+    ///
+    /// Coming back online syncs straight away instead of waiting for the next periodic sync.
+    pub fn start_connectivity_watcher(self: Arc<Self>, cancel: CancellationToken) -> JoinHandle<()> {
+        let sync_manager = self.clone();
+
+        tauri::async_runtime::spawn(async move {
+            let mut was_online = sync_manager.storage.is_online().await;
+
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = tokio::time::sleep(connectivity_check_delay()) => {}
+                }
+
+                let is_online = sync_manager.storage.check_online().await;
+                sync_manager.storage.set_online(is_online).await;
+                if is_online == was_online {
+                    continue;
+                }
+                was_online = is_online;
+                sync_manager.emit("connectivity-changed", ConnectivityChangedEvent { online: is_online });
+
+                if is_online {
+                    if let Err(e) = sync_manager.sync_now().await {
+                        crate::logging::log(LogLevel::Warn, "sync", &format!("Sync after reconnecting failed: {}", e));
+                    }
+                }
+            }
+        })
     }
 
     /// Manually trigger sync
-    pub async fn sync_now(&self) -> AppResult<()> {
+    pub async fn sync_now(&self) -> AppResult<SyncReport> {
         // Check if already syncing
         {
             let mut is_syncing = self.is_syncing.lock().await;
@@ -93,69 +331,106 @@ impl SyncManager {
         result
     }
 
-    /// Perform actual sync operations
-    async fn perform_sync(&self) -> AppResult<()> {
+    /// Perform actual sync operations, reporting progress to the frontend
+    async fn perform_sync(&self) -> AppResult<SyncReport> {
+        self.emit(SYNC_STARTED_EVENT, ());
+
+        let mut report = SyncReport::default();
+        match self.sync_records(&mut report).await {
+            Ok(()) => {
+                report.timestamp = Utc::now().to_rfc3339();
+                self.emit(SYNC_COMPLETED_EVENT, report.clone());
+                Ok(report)
+            }
+            Err(e) => {
+                self.emit(SYNC_ERROR_EVENT, SyncErrorEvent { message: e.to_string() });
+                Err(e)
+            }
+        }
+    }
+
+    /// Push queued operations and dirty records, then pull remote changes,
+    /// counting the work in `report` as it goes
+    async fn sync_records(&self, report: &mut SyncReport) -> AppResult<()> {
         // Check if online
         if !self.storage.is_online().await {
             return Err(AppError::Offline);
         }
 
         // Process sync queue
-        self.process_sync_queue().await?;
+        self.process_sync_queue(report).await?;
 
         // Sync dirty records
-        self.sync_dirty_records().await?;
+        self.sync_dirty_records(report).await?;
 
-        Ok(())
+        // Pull last so remote rows never overwrite unsent edits
+        self.pull_remote_records(report).await
     }
 
     /// Process queued operations
-    async fn process_sync_queue(&self) -> AppResult<()> {
+    async fn process_sync_queue(&self, report: &mut SyncReport) -> AppResult<()> {
         let operations = self.get_queued_operations().await?;
+        let total = operations.len();
 
-        for operation in operations {
+        for (done, operation) in operations.into_iter().enumerate() {
             match self.execute_sync_operation(&operation).await {
                 Ok(_) => {
                     // Remove from queue on success
                     self.remove_from_queue(operation.id).await?;
+                    report.pushed += 1;
                 }
                 Err(e) => {
-                    eprintln!("Failed to sync operation {}: {}", operation.id, e);
-                    // Increment attempt counter
-                    self.increment_sync_attempts(operation.id).await?;
+                    crate::logging::log(
+                        LogLevel::Warn,
+                        "sync",
+                        &format!("Failed to sync operation {}: {}", operation.id, e),
+                    );
+                    // Increment attempt counter and back off before the next try
+                    self.record_sync_failure(&operation, &e.to_string()).await?;
+                    report.failed += 1;
                 }
             }
+            self.emit_progress("sync_queue", done + 1, total);
         }
 
         Ok(())
     }
 
+    fn emit_progress(&self, table: &str, done: usize, total: usize) {
+        self.emit(
+            SYNC_PROGRESS_EVENT,
+            SyncProgressEvent {
+                table: table.to_string(),
+                done,
+                total,
+            },
+        );
+    }
+
     /// Get queued sync operations
     async fn get_queued_operations(&self) -> AppResult<Vec<QueuedOperation>> {
-        self.storage.sqlite().execute(|conn| {
-            let mut stmt = conn.prepare(
-                "SELECT id, operation_type, table_name, record_id, data, attempts
-                 FROM sync_queue
-                 WHERE attempts < 5
-                 ORDER BY created_at ASC
-                 LIMIT 50"
-            )?;
-
-            let operations = stmt
-                .query_map([], |row| {
-                    Ok(QueuedOperation {
-                        id: row.get(0)?,
-                        operation_type: row.get(1)?,
-                        table_name: row.get(2)?,
-                        record_id: row.get(3)?,
-                        data: row.get(4)?,
-                        attempts: row.get(5)?,
-                    })
-                })?
-                .collect::<Result<Vec<_>, _>>()?;
-
-            Ok(operations)
-        }).await
+        let pool = self.storage.sqlite().get_pool().await?;
+        let rows = sqlx::query(
+            "SELECT id, operation_type, table_name, record_id, data, attempts
+             FROM sync_queue
+             WHERE next_retry_at IS NULL OR next_retry_at <= datetime('now')
+             ORDER BY created_at ASC
+             LIMIT 50"
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| QueuedOperation {
+                id: row.get("id"),
+                operation_type: row.get("operation_type"),
+                table_name: row.get("table_name"),
+                record_id: row.get("record_id"),
+                data: row.get("data"),
+                attempts: row.get("attempts"),
+            })
+            .collect())
     }
 
     /// Execute a single sync operation
@@ -167,30 +442,21 @@ impl SyncManager {
 
         match operation.operation_type.as_str() {
             "insert" => {
-                supabase
-                    .insert(&operation.table_name, &operation.data)
-                    .await?
-                    .execute()
+                execute_checked(supabase.upsert(&operation.table_name, &operation.data, "id").await?)
                     .await
                     .map_err(|e| AppError::Sync(format!("Insert failed: {}", e)))?;
             }
             "update" => {
-                supabase
+                let update = supabase
                     .update(&operation.table_name, &operation.data)
                     .await?
-                    .eq("id", &operation.record_id)
-                    .execute()
+                    .eq("id", &operation.record_id);
+                execute_checked(update)
                     .await
                     .map_err(|e| AppError::Sync(format!("Update failed: {}", e)))?;
             }
             "delete" => {
-                supabase
-                    .delete(&operation.table_name)
-                    .await?
-                    .eq("id", &operation.record_id)
-                    .execute()
-                    .await
-                    .map_err(|e| AppError::Sync(format!("Delete failed: {}", e)))?;
+                self.mark_deleted(&operation.table_name, &operation.record_id, &operation.data).await?;
             }
             _ => {
                 return Err(AppError::Sync(format!(
@@ -203,121 +469,582 @@ impl SyncManager {
         Ok(())
     }
 
-    /// Sync dirty records (records modified locally but not synced)
-    async fn sync_dirty_records(&self) -> AppResult<()> {
-        let tables = vec![
-            "cases",
-            "flashcard_sets",
-            "flashcards",
-            "mock_tests",
-            "test_results",
-            "study_plans",
-        ];
+    /// Delete a record from Supabase, or queue the delete for the next sync
+    /// when offline or Supabase can't be reached
+    ///
+    /// Call before removing the local row. Nothing is queued when Supabase
+    /// isn't configured, since such installs never sync.
+    pub async fn delete_remote(&self, table_name: &str, record_id: &str) -> AppResult<()> {
+        let table = synced_table(table_name)?;
+        if self.storage.supabase().is_none() {
+            return Ok(());
+        }
 
-        for table in tables {
-            self.sync_dirty_table(table).await?;
+        let operation = tombstone_operation(table, record_id);
+        if self.storage.is_online().await {
+            match self.mark_deleted(table, record_id, &operation.data).await {
+                Ok(()) => return Ok(()),
+                Err(e) => crate::logging::log(
+                    LogLevel::Warn,
+                    "sync",
+                    &format!("Queueing delete of {} {}: {}", table, record_id, e),
+                ),
+            }
         }
 
-        Ok(())
+        self.queue_operation(operation).await
     }
 
-    /// Sync dirty records from a specific table
-    async fn sync_dirty_table(&self, table_name: &str) -> AppResult<()> {
+    /// Set the `deleted_at` tombstone of a Supabase row from `tombstone_operation`'s data
+    ///
+    /// Rows are kept rather than deleted so pulls on other devices see the delete.
+    async fn mark_deleted(&self, table_name: &str, record_id: &str, tombstone: &str) -> AppResult<()> {
         let supabase = self
             .storage
             .supabase()
             .ok_or_else(|| AppError::Internal("Supabase not configured".to_string()))?;
 
-        // Get dirty records
-        let table = table_name.to_string();
-        let dirty_records = self.storage.sqlite().execute(move |conn| {
-            let query = format!("SELECT * FROM {} WHERE dirty = 1 LIMIT 20", table);
-            let mut stmt = conn.prepare(&query)?;
-            
-            let column_count = stmt.column_count();
-            let mut records: Vec<serde_json::Value> = Vec::new();
+        execute_checked(supabase.update(table_name, tombstone).await?.eq("id", record_id))
+            .await
+            .map_err(|e| AppError::Sync(format!("Delete failed: {}", e)))?;
+        Ok(())
+    }
 
-            let rows = stmt.query_map([], |row| {
-                let mut record = serde_json::Map::new();
-                
-                for i in 0..column_count {
-                    let column_name = stmt.column_name(i)?.to_string();
-                    
-                    // Skip internal sync columns
-                    if column_name == "synced" || column_name == "dirty" {
-                        continue;
-                    }
+    /// Push dirty records (modified locally but not synced), parent tables
+    /// first so no row reaches Supabase before the row it references
+    async fn sync_dirty_records(&self, report: &mut SyncReport) -> AppResult<()> {
+        for table in SYNCED_TABLES {
+            self.sync_dirty_table(table, report).await?;
+        }
+        Ok(())
+    }
+
+    /// Pull the current user's rows changed in Supabase, parent tables first
+    ///
+    /// Nothing is pulled until `set_user` names the user.
+    async fn pull_remote_records(&self, report: &mut SyncReport) -> AppResult<()> {
+        let Some(user_id) = self.user_id.lock().await.clone() else {
+            return Ok(());
+        };
 
-                    // Try to get value as different types
-                    let value: serde_json::Value = if let Ok(s) = row.get::<_, String>(i) {
-                        serde_json::Value::String(s)
-                    } else if let Ok(n) = row.get::<_, i64>(i) {
-                        serde_json::Value::Number(n.into())
-                    } else if let Ok(f) = row.get::<_, f64>(i) {
-                        serde_json::json!(f)
-                    } else if let Ok(b) = row.get::<_, bool>(i) {
-                        serde_json::Value::Bool(b)
-                    } else {
-                        serde_json::Value::Null
-                    };
-
-                    record.insert(column_name, value);
+        // A table that can't be pulled doesn't hold back the ones after it
+        for table in SYNCED_TABLES {
+            match self.pull_remote_changes(table, &user_id).await {
+                Ok(pulled) => {
+                    self.emit_progress(table, pulled, pulled);
+                    report.pulled += pulled;
                 }
+                Err(e) => crate::logging::log(LogLevel::Warn, "sync", &format!("Failed to pull {}: {}", table, e)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sync dirty records from a specific table, `PUSH_BATCH` at a time
+    ///
+    /// A batch that fails to push is counted in `report.failed` and stays
+    /// dirty for the next sync; the rest of the table waits with it.
+    async fn sync_dirty_table(&self, table_name: &str, report: &mut SyncReport) -> AppResult<()> {
+        let table = synced_table(table_name)?;
+        if self.storage.supabase().is_none() {
+            return Err(AppError::Internal("Supabase not configured".to_string()));
+        }
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        let total: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM {} WHERE dirty = 1
+               AND id NOT IN (SELECT record_id FROM sync_conflicts WHERE table_name = ?)",
+            table
+        ))
+        .bind(table)
+        .fetch_one(&pool)
+        .await?;
+        let total = total as usize;
 
-                Ok(serde_json::Value::Object(record))
-            })?;
+        let mut done = 0;
+        while done < total {
+            let batch = self.dirty_batch(table).await?;
+            let batch_len = batch.len();
+            if batch_len == 0 {
+                break;
+            }
 
-            for row_result in rows {
-                if let Ok(record) = row_result {
-                    records.push(record);
+            match self.push_batch(table, batch).await {
+                Ok(pushed) => report.pushed += pushed,
+                Err(e) => {
+                    crate::logging::log(
+                        LogLevel::Warn,
+                        "sync",
+                        &format!("Failed to push {} {} records: {}", batch_len, table, e),
+                    );
+                    report.failed += batch_len;
+                    break;
                 }
             }
+            done += batch_len;
+            self.emit_progress(table, done.min(total), total);
+        }
 
-            Ok(records)
-        }).await?;
+        Ok(())
+    }
 
-        // Upload to Supabase (using upsert to handle both insert and update)
-        for record in dirty_records {
-            let record_id = record["id"].as_str().unwrap_or("").to_string();
-            let data = serde_json::to_string(&record)?;
+    /// Up to `PUSH_BATCH` dirty records of `table` as JSON, skipping any held
+    /// back by an unresolved conflict
+    async fn dirty_batch(&self, table: &'static str) -> AppResult<Vec<serde_json::Value>> {
+        // Select the table's columns by name, leaving out the internal sync columns
+        let pool = self.storage.sqlite().get_pool().await?;
+        let columns = table_columns(&pool, table).await?;
 
-            // Try upsert (insert or update)
-            supabase
-                .insert(table_name, &data)
-                .await?
-                .execute()
-                .await
-                .map_err(|e| AppError::Sync(format!("Upsert failed: {}", e)))?;
+        let fields = columns
+            .iter()
+            .map(|c| format!("'{}', {}", c, c))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let rows: Vec<String> = sqlx::query_scalar(&format!(
+            "SELECT json_object({}) FROM {} WHERE dirty = 1
+               AND id NOT IN (SELECT record_id FROM sync_conflicts WHERE table_name = ?)
+             LIMIT ?",
+            fields, table
+        ))
+        .bind(table)
+        .bind(PUSH_BATCH as i64)
+        .fetch_all(&pool)
+        .await?;
 
-            // Mark as synced locally
-            let table_name_clone = table_name.to_string();
-            self.storage.sqlite().execute(move |conn| {
-                let query = format!("UPDATE {} SET synced = 1, dirty = 0 WHERE id = ?1", table_name_clone);
-                conn.execute(&query, [&record_id])?;
-                Ok(())
-            }).await?;
+        rows.iter()
+            .map(|row| serde_json::from_str(row).map_err(AppError::from))
+            .collect()
+    }
+
+    /// Upsert a batch of dirty records in one request, except those whose
+    /// Supabase version is newer, conflicting or deleted, returning how many
+    /// were pushed
+    async fn push_batch(&self, table: &'static str, records: Vec<serde_json::Value>) -> AppResult<usize> {
+        let supabase = self
+            .storage
+            .supabase()
+            .ok_or_else(|| AppError::Internal("Supabase not configured".to_string()))?;
+
+        // Don't overwrite remote versions edited after the local ones
+        let ids: Vec<String> = records.iter().map(|r| r["id"].as_str().unwrap_or("").to_string()).collect();
+        let remotes = self.fetch_remote_records(table, &ids).await?;
+
+        let mut to_push = Vec::new();
+        let mut keep_remote = Vec::new();
+        let mut dropped = Vec::new();
+        for (record, record_id) in records.into_iter().zip(ids) {
+            let remote = remotes.get(&record_id);
+            match (push_decision(&record, remote), remote) {
+                (PushDecision::KeepRemote, Some(remote)) => {
+                    keep_remote.push(remote.as_object().cloned().unwrap_or_default());
+                }
+                (PushDecision::Conflict, Some(remote)) => {
+                    self.record_conflict(table, &record_id, &record, remote).await?;
+                }
+                (PushDecision::Drop, _) => dropped.push(record_id),
+                _ => to_push.push(record),
+            }
+        }
+
+        if !keep_remote.is_empty() || !dropped.is_empty() {
+            let pool = self.storage.sqlite().get_pool().await?;
+            let mut tx = pool.begin().await?;
+            for remote in &keep_remote {
+                write_synced_record(&mut tx, table, remote).await?;
+            }
+            for record_id in &dropped {
+                sqlx::query(&format!("DELETE FROM {} WHERE id = ?", table))
+                    .bind(record_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await?;
+        }
+
+        if to_push.is_empty() {
+            return Ok(0);
+        }
+
+        execute_checked(supabase.upsert(table, &serde_json::to_string(&to_push)?, "id").await?)
+            .await
+            .map_err(|e| AppError::Sync(format!("Upsert failed: {}", e)))?;
+
+        // Mark the whole batch as synced locally
+        let pushed: Vec<String> = to_push.iter().map(|r| r["id"].as_str().unwrap_or("").to_string()).collect();
+        let count = pushed.len();
+        let placeholders = vec!["?"; count].join(", ");
+        let query = format!("UPDATE {} SET synced = 1, dirty = 0 WHERE id IN ({})", table, placeholders);
+        let mut update = sqlx::query(&query);
+        for id in &pushed {
+            update = update.bind(id);
+        }
+        let pool = self.storage.sqlite().get_pool().await?;
+        update.execute(&pool).await?;
+
+        Ok(count)
+    }
+
+    /// Supabase versions of the records in `record_ids` that exist there, by id
+    async fn fetch_remote_records(
+        &self,
+        table_name: &str,
+        record_ids: &[String],
+    ) -> AppResult<HashMap<String, serde_json::Value>> {
+        let supabase = self
+            .storage
+            .supabase()
+            .ok_or_else(|| AppError::Internal("Supabase not configured".to_string()))?;
+
+        let select = supabase.select(table_name).await?.in_("id", record_ids);
+        let response = execute_checked(select)
+            .await
+            .map_err(|e| AppError::Sync(format!("Failed to fetch remote records: {}", e)))?;
+
+        let body = response.text().await?;
+        let records: Vec<serde_json::Value> = serde_json::from_str(&body)?;
+        Ok(records
+            .into_iter()
+            .filter_map(|record| Some((record["id"].as_str()?.to_string(), record)))
+            .collect())
+    }
+
+    /// Store both versions of a conflicting record
+    async fn record_conflict(
+        &self,
+        table_name: &str,
+        record_id: &str,
+        local: &serde_json::Value,
+        remote: &serde_json::Value,
+    ) -> AppResult<()> {
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query(
+            "INSERT INTO sync_conflicts (id, table_name, record_id, local_data, remote_data, detected_at)
+             VALUES (?, ?, ?, ?, ?, ?)
+             ON CONFLICT(table_name, record_id) DO UPDATE SET
+                local_data = excluded.local_data,
+                remote_data = excluded.remote_data,
+                detected_at = excluded.detected_at"
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(table_name)
+        .bind(record_id)
+        .bind(serde_json::to_string(local)?)
+        .bind(serde_json::to_string(remote)?)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Unresolved sync conflicts, oldest first
+    pub async fn get_conflicts(&self) -> AppResult<Vec<SyncConflict>> {
+        let pool = self.storage.sqlite().get_pool().await?;
+        let rows = sqlx::query(
+            "SELECT id, table_name, record_id, local_data, remote_data, detected_at
+             FROM sync_conflicts
+             ORDER BY detected_at ASC"
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows.iter().map(conflict_from_row).collect())
+    }
+
+    /// Settle a conflict, writing the chosen version to both sides
+    pub async fn resolve_conflict(&self, conflict_id: &str, resolution: ConflictResolution) -> AppResult<()> {
+        let conflict = self
+            .get_conflicts()
+            .await?
+            .into_iter()
+            .find(|c| c.id == conflict_id)
+            .ok_or_else(|| AppError::NotFound(format!("Sync conflict {}", conflict_id)))?;
+
+        let table_name = synced_table(&conflict.table_name)?;
+
+        let resolved = match resolution {
+            ConflictResolution::UseLocal => conflict.local_data,
+            ConflictResolution::UseRemote => conflict.remote_data,
+            ConflictResolution::Merge(record) => record,
+        };
+        let mut record = resolved
+            .as_object()
+            .cloned()
+            .ok_or_else(|| AppError::Validation("Resolved record must be a JSON object".to_string()))?;
+        record.insert("id".to_string(), serde_json::Value::String(conflict.record_id.clone()));
+
+        // Push to Supabase first so a failure leaves the conflict in place
+        if !self.storage.is_online().await {
+            return Err(AppError::Offline);
         }
+        let supabase = self
+            .storage
+            .supabase()
+            .ok_or_else(|| AppError::Internal("Supabase not configured".to_string()))?;
+        let update = supabase
+            .update(table_name, &serde_json::to_string(&record)?)
+            .await?
+            .eq("id", &conflict.record_id);
+        execute_checked(update)
+            .await
+            .map_err(|e| AppError::Sync(format!("Update failed: {}", e)))?;
 
+        // Write locally, mark synced and clear the conflict
+        let pool = self.storage.sqlite().get_pool().await?;
+        let mut tx = pool.begin().await?;
+        write_synced_record(&mut tx, table_name, &record).await?;
+        sqlx::query("DELETE FROM sync_conflicts WHERE id = ?")
+            .bind(&conflict.id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
         Ok(())
     }
 
+    /// Pull `user_id`'s rows changed in Supabase since the table's watermark,
+    /// returning how many were applied
+    ///
+    /// Changes are found by `updated_at`, or `created_at` for tables without
+    /// one, and by `deleted_at` tombstones, whose rows are deleted locally.
+    /// Other rows still dirty locally (pending a push or held by a conflict)
+    /// are left alone.
+    ///
+    /// Rows are pulled `PULL_PAGE` at a time, oldest change first, and the
+    /// watermark advances with each page written. When the user's parent rows
+    /// take more than one request, it only advances once every request is done,
+    /// since their pages interleave in time.
+    async fn pull_remote_changes(&self, table_name: &str, user_id: &str) -> AppResult<usize> {
+        let table = synced_table(table_name)?;
+        let owner = ownership(table);
+        let supabase = self
+            .storage
+            .supabase()
+            .ok_or_else(|| AppError::Internal("Supabase not configured".to_string()))?;
+
+        let pool = self.storage.sqlite().get_pool().await?;
+        let watermark: Option<String> =
+            sqlx::query_scalar("SELECT last_pulled_at FROM sync_watermarks WHERE table_name = ?")
+                .bind(table)
+                .fetch_optional(&pool)
+                .await?;
+
+        let changed_column = change_column(&table_columns(&pool, table).await?);
+
+        // Tables without a `user_id` are filtered by the user's parent rows
+        let owner_ids: Vec<String> = match owner.parent {
+            None => vec![user_id.to_string()],
+            Some(parent) => {
+                sqlx::query_scalar(&format!("SELECT id FROM {} WHERE user_id = ?", parent))
+                    .bind(user_id)
+                    .fetch_all(&pool)
+                    .await?
+            }
+        };
+
+        let batches: Vec<&[String]> = owner_ids.chunks(PULL_PARENT_BATCH).collect();
+        let advance_per_page = batches.len() == 1;
+
+        let mut applied = 0;
+        let mut newest_overall: Option<String> = None;
+        for ids in batches {
+            let mut offset = 0;
+            loop {
+                let mut query = supabase
+                    .select(table)
+                    .await?
+                    .in_(owner.column, ids)
+                    .order(format!("{}.asc,id.asc", changed_column))
+                    .range(offset, offset + PULL_PAGE - 1);
+                query = match &watermark {
+                    // Rows changed or deleted since the last pull
+                    Some(watermark) => query.or(format!(
+                        "{}.gt.\"{}\",deleted_at.gt.\"{}\"",
+                        changed_column, watermark, watermark
+                    )),
+                    None => query.is("deleted_at", "null"),
+                };
+                let response = query
+                    .execute()
+                    .await
+                    .map_err(|e| AppError::Sync(format!("Failed to pull {}: {}", table_name, e)))?;
+                if !response.status().is_success() {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(AppError::Sync(format!("Failed to pull {} ({}): {}", table_name, status, body)));
+                }
+
+                let page: Vec<serde_json::Value> = serde_json::from_str(&response.text().await?)?;
+                let newest = newest_change(&page, changed_column);
+                newest_overall = [newest_overall.take(), newest.clone()]
+                    .into_iter()
+                    .flatten()
+                    .max_by_key(|s| DateTime::parse_from_rfc3339(s).ok());
+                let newest = if advance_per_page { newest } else { None };
+                applied += apply_pulled_records(&pool, table, &page, newest.as_deref()).await?;
+
+                if page.len() < PULL_PAGE {
+                    break;
+                }
+                offset += PULL_PAGE;
+            }
+        }
+
+        if !advance_per_page && newest_overall.is_some() {
+            apply_pulled_records(&pool, table, &[], newest_overall.as_deref()).await?;
+        }
+        Ok(applied)
+    }
+
     /// Remove operation from sync queue
     async fn remove_from_queue(&self, operation_id: i64) -> AppResult<()> {
-        self.storage.sqlite().execute(move |conn| {
-            conn.execute("DELETE FROM sync_queue WHERE id = ?1", [operation_id])?;
-            Ok(())
-        }).await
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query("DELETE FROM sync_queue WHERE id = ?")
+            .bind(operation_id)
+            .execute(&pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Increment sync attempt counter, moving the operation to the dead-letter
+    /// table once it is out of retries
+    async fn record_sync_failure(&self, operation: &QueuedOperation, error: &str) -> AppResult<()> {
+        let attempts = operation.attempts as i64 + 1;
+        let pool = self.storage.sqlite().get_pool().await?;
+
+        if is_exhausted(attempts) {
+            crate::logging::log(
+                LogLevel::Warn,
+                "sync",
+                &format!(
+                    "Giving up on {} of {} {} after {} attempts: {}",
+                    operation.operation_type, operation.table_name, operation.record_id, attempts, error
+                ),
+            );
+
+            let mut tx = pool.begin().await?;
+            sqlx::query(
+                "INSERT INTO sync_dead_letter (operation_type, table_name, record_id, data, attempts, last_error, created_at, failed_at)
+                 SELECT operation_type, table_name, record_id, data, ?, ?, created_at, datetime('now')
+                 FROM sync_queue WHERE id = ?"
+            )
+            .bind(attempts)
+            .bind(error)
+            .bind(operation.id)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("DELETE FROM sync_queue WHERE id = ?")
+                .bind(operation.id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            return Ok(());
+        }
+
+        sqlx::query(
+            "UPDATE sync_queue
+             SET attempts = attempts + 1,
+                 next_retry_at = datetime('now', '+' || ? || ' seconds'),
+                 last_error = ?
+             WHERE id = ?"
+        )
+        .bind(retry_delay_secs(attempts))
+        .bind(error)
+        .bind(operation.id)
+        .execute(&pool)
+        .await?;
+        Ok(())
+    }
+
+    /// All queued operations with their retry state, for debugging
+    pub async fn get_queue_status(&self) -> AppResult<Vec<SyncQueueItem>> {
+        let pool = self.storage.sqlite().get_pool().await?;
+        let rows = sqlx::query(
+            "SELECT id, table_name, record_id, attempts, next_retry_at, last_error
+             FROM sync_queue
+             ORDER BY created_at ASC"
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| SyncQueueItem {
+                id: row.get("id"),
+                table_name: row.get("table_name"),
+                record_id: row.get("record_id"),
+                attempts: row.get("attempts"),
+                next_retry_at: row.get("next_retry_at"),
+                last_error: row.get("last_error"),
+            })
+            .collect())
+    }
+
+    /// Operations that ran out of retries, most recent failure first
+    pub async fn get_dead_letter_operations(&self) -> AppResult<Vec<DeadLetterOperation>> {
+        let pool = self.storage.sqlite().get_pool().await?;
+        let rows = sqlx::query(
+            "SELECT id, operation_type, table_name, record_id, data, attempts, last_error, created_at, failed_at
+             FROM sync_dead_letter
+             ORDER BY failed_at DESC, id DESC"
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| DeadLetterOperation {
+                id: row.get("id"),
+                operation_type: row.get("operation_type"),
+                table_name: row.get("table_name"),
+                record_id: row.get("record_id"),
+                data: row.get("data"),
+                attempts: row.get("attempts"),
+                last_error: row.get("last_error"),
+                created_at: row.get("created_at"),
+                failed_at: row.get("failed_at"),
+            })
+            .collect())
+    }
+
+    /// Put a dead-lettered operation back on the queue with a fresh set of retries
+    pub async fn retry_dead_letter(&self, id: i64) -> AppResult<()> {
+        let pool = self.storage.sqlite().get_pool().await?;
+        let mut tx = pool.begin().await?;
+        let requeued = sqlx::query(
+            "INSERT INTO sync_queue (operation_type, table_name, record_id, data, created_at, attempts)
+             SELECT operation_type, table_name, record_id, data, created_at, 0
+             FROM sync_dead_letter WHERE id = ?"
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        sqlx::query("DELETE FROM sync_dead_letter WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        if requeued == 0 {
+            return Err(AppError::NotFound(format!("Dead-letter operation {} not found", id)));
+        }
+        Ok(())
     }
 
-    /// Increment sync attempt counter
-    async fn increment_sync_attempts(&self, operation_id: i64) -> AppResult<()> {
-        self.storage.sqlite().execute(move |conn| {
-            conn.execute(
-                "UPDATE sync_queue SET attempts = attempts + 1 WHERE id = ?1",
-                [operation_id],
-            )?;
-            Ok(())
-        }).await
+    /// Drop a dead-lettered operation; its change is never sent to Supabase
+    pub async fn discard_dead_letter(&self, id: i64) -> AppResult<()> {
+        let pool = self.storage.sqlite().get_pool().await?;
+        let discarded = sqlx::query("DELETE FROM sync_dead_letter WHERE id = ?")
+            .bind(id)
+            .execute(&pool)
+            .await?
+            .rows_affected();
+
+        if discarded == 0 {
+            return Err(AppError::NotFound(format!("Dead-letter operation {} not found", id)));
+        }
+        Ok(())
     }
 
     /// Get current sync status
@@ -326,35 +1053,307 @@ impl SyncManager {
         let last_sync = self.last_sync.lock().await.clone();
         let is_online = self.storage.is_online().await;
 
-        let pending_operations = self.storage.sqlite().execute(|conn| {
-            let mut stmt = conn.prepare("SELECT COUNT(*) FROM sync_queue WHERE attempts < 5")?;
-            let count: i64 = stmt.query_row([], |row| row.get(0))?;
-            Ok(count as usize)
-        }).await?;
+        let pool = self.storage.sqlite().get_pool().await?;
+        let pending_operations: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sync_queue")
+            .fetch_one(&pool)
+            .await?;
 
         Ok(SyncStatus {
             is_syncing,
             last_sync,
-            pending_operations,
+            pending_operations: pending_operations as usize,
             is_online,
         })
     }
 
     /// Add operation to sync queue
     pub async fn queue_operation(&self, operation: SyncOperation) -> AppResult<()> {
-        self.storage.sqlite().execute(move |conn| {
-            conn.execute(
-                "INSERT INTO sync_queue (operation_type, table_name, record_id, data, created_at, attempts)
-                 VALUES (?1, ?2, ?3, ?4, datetime('now'), 0)",
-                rusqlite::params![
-                    &operation.operation_type,
-                    &operation.table_name,
-                    &operation.record_id,
-                    &operation.data,
-                ],
-            )?;
-            Ok(())
-        }).await
+        let pool = self.storage.sqlite().get_pool().await?;
+        sqlx::query(
+            "INSERT INTO sync_queue (operation_type, table_name, record_id, data, created_at, attempts)
+             VALUES (?, ?, ?, ?, datetime('now'), 0)"
+        )
+        .bind(&operation.operation_type)
+        .bind(&operation.table_name)
+        .bind(&operation.record_id)
+        .bind(&operation.data)
+        .execute(&pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Ticker period for an interval in seconds, never zero
+fn sync_interval(secs: u64) -> Duration {
+    Duration::from_secs(secs.max(1))
+}
+
+/// Time until the next connectivity check, `CONNECTIVITY_CHECK_SECS` give or
+/// take up to `CONNECTIVITY_JITTER_SECS`, so devices don't all check in step
+fn connectivity_check_delay() -> Duration {
+    let jitter_ms = CONNECTIVITY_JITTER_SECS * 1000;
+    let offset_ms = rand::thread_rng().gen_range(0..=2 * jitter_ms);
+    Duration::from_millis(CONNECTIVITY_CHECK_SECS * 1000 - jitter_ms + offset_ms)
+}
+
+/// Whether an operation that has failed `attempts` times is out of retries
+fn is_exhausted(attempts: i64) -> bool {
+    attempts >= MAX_SYNC_ATTEMPTS
+}
+
+/// Seconds to wait before retrying an operation that has failed `attempts` times
+fn retry_delay_secs(attempts: i64) -> i64 {
+    2_i64
+        .checked_pow(attempts.clamp(0, 32) as u32)
+        .unwrap_or(MAX_RETRY_DELAY_SECS)
+        .min(MAX_RETRY_DELAY_SECS)
+}
+
+/// Write records pulled from Supabase into `table` and advance its watermark
+/// to `newest` in one transaction, returning how many were applied
+async fn apply_pulled_records(
+    pool: &Pool<Sqlite>,
+    table: &str,
+    records: &[serde_json::Value],
+    newest: Option<&str>,
+) -> AppResult<usize> {
+    let mut tx = pool.begin().await?;
+    let mut applied = 0;
+    // Tables with a recycle bin keep remote deletes as binned rows
+    let has_bin = table_columns(&mut *tx, table)
+        .await?
+        .iter()
+        .any(|c| c == "deleted_at");
+
+    for record in records {
+        let Some(fields) = record.as_object() else {
+            continue;
+        };
+        let record_id = record["id"].as_str().unwrap_or("");
+
+        // Deleted on another device; the delete wins over local edits
+        if is_tombstone(record) && !has_bin {
+            sqlx::query(&format!("DELETE FROM {} WHERE id = ?", table))
+                .bind(record_id)
+                .execute(&mut *tx)
+                .await?;
+            applied += 1;
+            continue;
+        }
+
+        let dirty: Option<i64> = sqlx::query_scalar(&format!("SELECT 1 FROM {} WHERE id = ? AND dirty = 1", table))
+            .bind(record_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if dirty.is_some() {
+            continue;
+        }
+
+        write_synced_record(&mut tx, table, fields).await?;
+        applied += 1;
+    }
+
+    if let Some(newest) = newest {
+        sqlx::query(
+            "INSERT INTO sync_watermarks (table_name, last_pulled_at) VALUES (?, ?)
+             ON CONFLICT(table_name) DO UPDATE SET last_pulled_at = excluded.last_pulled_at"
+        )
+        .bind(table)
+        .bind(newest)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(applied)
+}
+
+/// Latest change or tombstone time among `records`
+fn newest_change(records: &[serde_json::Value], changed_column: &str) -> Option<String> {
+    records
+        .iter()
+        .flat_map(|r| [r[changed_column].as_str(), r["deleted_at"].as_str()])
+        .flatten()
+        .max_by_key(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(str::to_string)
+}
+
+/// What to do with a dirty local record given its remote version
+#[derive(Debug, PartialEq)]
+enum PushDecision {
+    /// Upsert the local version
+    Push,
+    /// The remote version is newer; replace the local one with it
+    KeepRemote,
+    /// The versions differ and can't be ordered; hold both for the user
+    Conflict,
+    /// The record was deleted on another device; remove the local one
+    Drop,
+}
+
+/// Last writer wins by `updated_at`; when either side has no timestamp,
+/// differing versions become a conflict rather than being overwritten.
+/// Records with their own `deleted_at` column are binned and restored like
+/// any other edit, so only tables without one drop on a remote delete.
+fn push_decision(local: &serde_json::Value, remote: Option<&serde_json::Value>) -> PushDecision {
+    let Some(remote) = remote else {
+        return PushDecision::Push;
+    };
+    if is_tombstone(remote) && local.get("deleted_at").is_none() {
+        return PushDecision::Drop;
+    }
+
+    if updated_at(local).is_some() && updated_at(remote).is_some() {
+        if remote_is_newer(local, remote) {
+            PushDecision::KeepRemote
+        } else {
+            PushDecision::Push
+        }
+    } else if same_record(local, remote) {
+        PushDecision::Push
+    } else {
+        PushDecision::Conflict
+    }
+}
+
+/// Whether a Supabase record has been deleted
+fn is_tombstone(record: &serde_json::Value) -> bool {
+    !record["deleted_at"].is_null()
+}
+
+/// Queued delete that sets the Supabase row's `deleted_at` to now
+fn tombstone_operation(table: &str, record_id: &str) -> SyncOperation {
+    SyncOperation {
+        operation_type: "delete".to_string(),
+        table_name: table.to_string(),
+        record_id: record_id.to_string(),
+        data: serde_json::json!({ "deleted_at": Utc::now().to_rfc3339() }).to_string(),
+    }
+}
+
+/// Whether every local field that Supabase also has holds the same value,
+/// compared as SQLite stores them (so `true` equals 1)
+fn same_record(local: &serde_json::Value, remote: &serde_json::Value) -> bool {
+    let (Some(local), Some(remote)) = (local.as_object(), remote.as_object()) else {
+        return local == remote;
+    };
+
+    local.iter().all(|(column, value)| {
+        remote
+            .get(column)
+            .is_none_or(|remote_value| stored_value(remote_value) == stored_value(value))
+    })
+}
+
+/// `value` as SQLite stores it when bound with `bind_json`
+fn stored_value(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Bool(b) => serde_json::Value::from(*b as i64),
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => serde_json::Value::String(value.to_string()),
+        other => other.clone(),
+    }
+}
+
+/// Whether `remote` was updated after `local`, by their `updated_at` timestamps
+///
+/// A record without `updated_at` on either side is never considered newer;
+/// `push_decision` compares such records by content instead.
+fn remote_is_newer(local: &serde_json::Value, remote: &serde_json::Value) -> bool {
+    match (updated_at(local), updated_at(remote)) {
+        (Some(local), Some(remote)) => remote > local,
+        _ => false,
+    }
+}
+
+/// A record's `updated_at`, if it has a parseable RFC 3339 timestamp
+fn updated_at(record: &serde_json::Value) -> Option<DateTime<chrono::FixedOffset>> {
+    record["updated_at"]
+        .as_str()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+}
+
+/// How rows of a synced table are tied to the user they're pulled for
+#[derive(Debug, Clone, Copy)]
+struct Ownership {
+    /// Column the pull filters on
+    column: &'static str,
+    /// Table `column` references, for tables without their own `user_id`
+    parent: Option<&'static str>,
+}
+
+fn ownership(table: &str) -> Ownership {
+    match table {
+        "case_tags" => Ownership { column: "case_id", parent: Some("cases") },
+        "flashcards" => Ownership { column: "set_id", parent: Some("flashcard_sets") },
+        _ => Ownership { column: "user_id", parent: None },
+    }
+}
+
+/// Timestamp column that shows a row changed: `updated_at`, or
+/// `created_at` for tables whose rows are never edited in place
+fn change_column(columns: &[String]) -> &'static str {
+    if columns.iter().any(|column| column == "updated_at") {
+        "updated_at"
+    } else {
+        "created_at"
+    }
+}
+
+/// `table_name` as listed in `SYNCED_TABLES`; only names returned by this
+/// are interpolated into SQL
+fn synced_table(table_name: &str) -> AppResult<&'static str> {
+    SYNCED_TABLES
+        .iter()
+        .copied()
+        .find(|table| *table == table_name)
+        .ok_or_else(|| AppError::Validation(format!("unknown table: {}", table_name)))
+}
+
+/// Insert or update a local row from a Supabase record and mark it synced
+///
+/// Fields without a matching local column, and the sync columns, are ignored.
+async fn write_synced_record(
+    tx: &mut Transaction<'_, Sqlite>,
+    table_name: &str,
+    record: &serde_json::Map<String, serde_json::Value>,
+) -> AppResult<()> {
+    let columns = table_columns(&mut **tx, table_name).await?;
+    let fields: Vec<(&String, &serde_json::Value)> =
+        record.iter().filter(|(column, _)| columns.contains(column)).collect();
+
+    let names = fields.iter().map(|(column, _)| column.as_str()).collect::<Vec<_>>().join(", ");
+    let placeholders = vec!["?"; fields.len()].join(", ");
+    let updates = fields
+        .iter()
+        .filter(|(column, _)| column.as_str() != "id")
+        .map(|(column, _)| format!("{0} = excluded.{0}", column))
+        .chain(["synced = 1".to_string(), "dirty = 0".to_string()])
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let sql = format!(
+        "INSERT INTO {} ({}, synced, dirty) VALUES ({}, 1, 0)
+         ON CONFLICT(id) DO UPDATE SET {}",
+        table_name, names, placeholders, updates
+    );
+    let mut query = sqlx::query(&sql);
+    for (_, value) in &fields {
+        query = bind_json(query, value);
+    }
+    query.execute(&mut **tx).await?;
+    Ok(())
+}
+
+fn conflict_from_row(row: &sqlx::sqlite::SqliteRow) -> SyncConflict {
+    let local_data: String = row.get("local_data");
+    let remote_data: String = row.get("remote_data");
+    SyncConflict {
+        id: row.get("id"),
+        table_name: row.get("table_name"),
+        record_id: row.get("record_id"),
+        local_data: serde_json::from_str(&local_data).unwrap_or(serde_json::Value::Null),
+        remote_data: serde_json::from_str(&remote_data).unwrap_or(serde_json::Value::Null),
+        detected_at: row.get("detected_at"),
     }
 }
 
@@ -368,3 +1367,782 @@ struct QueuedOperation {
     attempts: i32,
 }
 
+
+// Tauri Commands
+
+/// Sync now rather than waiting for the next periodic sync
+#[tauri::command]
+pub async fn sync_now(state: State<'_, AppState>) -> CommandResult<SyncReport> {
+    state.sync_manager.sync_now().await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn get_sync_status(state: State<'_, AppState>) -> CommandResult<SyncStatus> {
+    state.sync_status().await.map_err(CommandError::from)
+}
+
+/// Whether Supabase answered the last connectivity check
+#[tauri::command]
+pub async fn is_online(state: State<'_, AppState>) -> CommandResult<bool> {
+    Ok(state.is_online().await)
+}
+
+/// Sync every `seconds` seconds, starting a new interval now; `save_config`
+/// keeps the setting across restarts
+#[tauri::command]
+pub async fn set_sync_interval(state: State<'_, AppState>, seconds: u64) -> CommandResult<()> {
+    if seconds == 0 {
+        return Err(AppError::Validation("Sync interval must be at least one second".to_string()).into());
+    }
+
+    state.config.write().await.sync_interval_seconds = seconds;
+    state.sync_manager.set_interval(seconds);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_sync_conflicts(state: State<'_, AppState>) -> CommandResult<Vec<SyncConflict>> {
+    state.sync_manager.get_conflicts().await.map_err(CommandError::from)
+}
+
+/// Pull the rows of `user_id` (the signed-in user) on each sync; `None` stops pulling
+#[tauri::command]
+pub async fn set_sync_user(state: State<'_, AppState>, user_id: Option<String>) -> CommandResult<()> {
+    state.sync_manager.set_user(user_id).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn get_sync_queue_status(state: State<'_, AppState>) -> CommandResult<Vec<SyncQueueItem>> {
+    state.sync_manager.get_queue_status().await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn get_dead_letter_operations(state: State<'_, AppState>) -> CommandResult<Vec<DeadLetterOperation>> {
+    state.sync_manager.get_dead_letter_operations().await.map_err(CommandError::from)
+}
+
+/// Queue a dead-lettered operation again, e.g. after fixing what made it fail
+#[tauri::command]
+pub async fn retry_dead_letter(state: State<'_, AppState>, id: i64) -> CommandResult<()> {
+    state.sync_manager.retry_dead_letter(id).await.map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn discard_dead_letter(state: State<'_, AppState>, id: i64) -> CommandResult<()> {
+    state.sync_manager.discard_dead_letter(id).await.map_err(CommandError::from)
+}
+
+/// Which version of a conflicting record `resolve_sync_conflict` keeps
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum KeepVersion {
+    Local,
+    Remote,
+}
+
+/// Settle a conflict by keeping one side as-is; use `resolve_conflict` to merge
+#[tauri::command]
+pub async fn resolve_sync_conflict(
+    state: State<'_, AppState>,
+    id: String,
+    keep: KeepVersion,
+) -> CommandResult<()> {
+    let resolution = match keep {
+        KeepVersion::Local => ConflictResolution::UseLocal,
+        KeepVersion::Remote => ConflictResolution::UseRemote,
+    };
+    state
+        .sync_manager
+        .resolve_conflict(&id, resolution)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn resolve_conflict(
+    state: State<'_, AppState>,
+    conflict_id: String,
+    resolution: ConflictResolution,
+) -> CommandResult<()> {
+    state
+        .sync_manager
+        .resolve_conflict(&conflict_id, resolution)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{test_cache, IN_MEMORY_DB};
+    use serde_json::json;
+    use crate::test_support::fake_http_server;
+    use std::path::PathBuf;
+
+    /// Storage over a fresh in-memory database, online against `url` when given
+    async fn test_storage(url: Option<String>) -> Arc<HybridStorage> {
+        let sqlite = test_cache().await;
+        let storage = HybridStorage::new(PathBuf::from(IN_MEMORY_DB), url, Some("key".to_string())).with_sqlite(sqlite);
+        storage.set_online(true).await;
+        Arc::new(storage)
+    }
+
+    /// A fake PostgREST server that answers each request with the next of
+    /// `responses` as a JSON body, returning the requests it received
+    async fn fake_supabase(responses: Vec<&'static str>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        fake_http_server(responses.into_iter().map(|body| (200, body.to_string())).collect()).await
+    }
+
+    async fn insert_flashcard(pool: &Pool<Sqlite>, id: &str, front: &str) {
+        sqlx::query("INSERT OR IGNORE INTO flashcard_sets (id, user_id, title, created_at, updated_at) VALUES ('set-1', 'user-1', 'Torts', 'now', 'now')")
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO flashcards (id, set_id, front, back, created_at, synced, dirty) VALUES (?, 'set-1', ?, 'Back', '2024-03-01T10:00:00Z', 0, 1)")
+            .bind(id)
+            .bind(front)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_periodic_sync_stops_when_cancelled() {
+        let storage = Arc::new(HybridStorage::new(PathBuf::from(IN_MEMORY_DB), None, None));
+        let manager = Arc::new(SyncManager::new(storage).with_interval(3600));
+        let cancel = CancellationToken::new();
+
+        let handle = manager.start_periodic_sync(cancel.clone());
+        cancel.cancel();
+
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .expect("periodic sync should stop when cancelled")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_connectivity_checks_are_jittered_around_thirty_seconds() {
+        for _ in 0..100 {
+            let delay = connectivity_check_delay();
+            assert!(delay >= Duration::from_secs(25) && delay <= Duration::from_secs(35), "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_an_hour() {
+        assert_eq!(retry_delay_secs(1), 2);
+        assert_eq!(retry_delay_secs(4), 16);
+        assert_eq!(retry_delay_secs(11), 2048);
+        assert_eq!(retry_delay_secs(12), MAX_RETRY_DELAY_SECS);
+        assert_eq!(retry_delay_secs(100), MAX_RETRY_DELAY_SECS);
+    }
+
+    #[test]
+    fn test_operations_dead_letter_on_the_last_attempt() {
+        assert!(!is_exhausted(MAX_SYNC_ATTEMPTS - 1));
+        assert!(is_exhausted(MAX_SYNC_ATTEMPTS));
+    }
+
+    #[test]
+    fn test_only_synced_tables_are_accepted() {
+        assert_eq!(synced_table("cases").unwrap(), "cases");
+        assert!(matches!(synced_table("users; DROP TABLE cases"), Err(AppError::Validation(_))));
+        assert!(matches!(synced_table("sync_conflicts"), Err(AppError::Validation(_))));
+        assert!(matches!(synced_table("Cases"), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_parents_are_pushed_before_their_children() {
+        // (child, parent) for each foreign key between synced tables
+        let foreign_keys = [
+            ("case_tags", "cases"),
+            ("case_tags", "tags"),
+            ("flashcards", "flashcard_sets"),
+            ("test_results", "mock_tests"),
+        ];
+        let position = |table: &str| SYNCED_TABLES.iter().position(|t| *t == table).unwrap();
+
+        for (child, parent) in foreign_keys {
+            assert!(position(parent) < position(child), "{} is pushed before {}", child, parent);
+        }
+    }
+
+    #[test]
+    fn test_pulls_filter_children_by_parents_pulled_earlier() {
+        for (i, table) in SYNCED_TABLES.iter().enumerate() {
+            let owner = ownership(table);
+            match owner.parent {
+                None => assert_eq!(owner.column, "user_id"),
+                Some(parent) => {
+                    let parent_index = SYNCED_TABLES.iter().position(|t| *t == parent);
+                    assert!(parent_index.is_some_and(|p| p < i), "{} is pulled before {}", table, parent);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_change_column_falls_back_to_created_at() {
+        let columns = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(change_column(&columns(&["id", "created_at", "updated_at"])), "updated_at");
+        assert_eq!(change_column(&columns(&["id", "set_id", "created_at"])), "created_at");
+    }
+
+    #[test]
+    fn test_remote_is_newer() {
+        let local = json!({"id": "1", "updated_at": "2024-03-01T10:00:00+00:00"});
+        let newer = json!({"id": "1", "updated_at": "2024-03-01T11:00:00Z"});
+        let older = json!({"id": "1", "updated_at": "2024-03-01T09:00:00Z"});
+
+        assert!(remote_is_newer(&local, &newer));
+        assert!(!remote_is_newer(&local, &older));
+        assert!(!remote_is_newer(&json!({"id": "1"}), &newer));
+    }
+
+    #[tokio::test]
+    async fn test_pushing_a_table_reports_progress_over_its_unconflicted_dirty_records() {
+        let (url, server) = fake_supabase(vec!["[]", "[]"]).await;
+        let storage = test_storage(Some(url)).await;
+        let pool = storage.sqlite().get_pool().await.unwrap();
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let manager = SyncManager {
+            events: Some(Arc::new(move |event: &str, payload| sink.lock().unwrap().push((event.to_string(), payload)))),
+            ..SyncManager::new(storage)
+        };
+
+        insert_case(&pool, "first", "First", "2024-03-01T10:00:00Z").await;
+        insert_case(&pool, "second", "Second", "2024-03-01T10:00:00Z").await;
+        insert_case(&pool, "held", "Held back", "2024-03-01T10:00:00Z").await;
+        manager.record_conflict("cases", "held", &json!({}), &json!({})).await.unwrap();
+
+        let mut report = SyncReport::default();
+        manager.sync_dirty_table("cases", &mut report).await.unwrap();
+        assert_eq!((report.pushed, report.failed), (2, 0));
+        server.await.unwrap();
+
+        assert_eq!(case_state(&pool, "held").await, ("Held back".to_string(), 0, 1));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![(SYNC_PROGRESS_EVENT.to_string(), json!({"table": "cases", "done": 2, "total": 2}))]
+        );
+    }
+
+    async fn insert_case(pool: &Pool<Sqlite>, id: &str, title: &str, updated_at: &str) {
+        sqlx::query("INSERT INTO cases (id, user_id, title, created_at, updated_at, synced, dirty) VALUES (?, 'user-1', ?, '2024-03-01T09:00:00Z', ?, 0, 1)")
+            .bind(id)
+            .bind(title)
+            .bind(updated_at)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn case_state(pool: &Pool<Sqlite>, id: &str) -> (String, i64, i64) {
+        sqlx::query_as("SELECT title, synced, dirty FROM cases WHERE id = ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pulled_records_are_written_synced_except_unsent_edits() {
+        let storage = test_storage(None).await;
+        let pool = storage.sqlite().get_pool().await.unwrap();
+        insert_case(&pool, "edited", "Unsent edit", "2024-03-01T12:00:00Z").await;
+
+        let pulled = json!([
+            {"id": "edited", "user_id": "user-1", "title": "Remote", "created_at": "2024-03-01T09:00:00Z",
+             "updated_at": "2024-03-01T11:00:00Z", "deleted_at": null},
+            {"id": "new", "user_id": "user-1", "title": "Pulled", "created_at": "2024-03-01T09:00:00Z",
+             "updated_at": "2024-03-01T11:00:00Z", "deleted_at": null, "remote_only": "ignored"},
+        ]);
+        let records = pulled.as_array().unwrap();
+        assert_eq!(apply_pulled_records(&pool, "cases", records, None).await.unwrap(), 1);
+
+        assert_eq!(case_state(&pool, "edited").await, ("Unsent edit".to_string(), 0, 1));
+        assert_eq!(case_state(&pool, "new").await, ("Pulled".to_string(), 1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_pulled_tombstones_delete_or_bin_the_local_row() {
+        let storage = test_storage(None).await;
+        let pool = storage.sqlite().get_pool().await.unwrap();
+        insert_flashcard(&pool, "card-1", "Front").await;
+        insert_case(&pool, "case-1", "Donoghue", "2024-03-01T10:00:00Z").await;
+        sqlx::query("UPDATE cases SET synced = 1, dirty = 0").execute(&pool).await.unwrap();
+
+        // Flashcards have no recycle bin, so the delete wins over the local edit
+        let card = json!([{"id": "card-1", "set_id": "set-1", "front": "Front", "back": "Back",
+            "created_at": "2024-03-01T10:00:00Z", "deleted_at": "2024-03-01T11:00:00Z"}]);
+        assert_eq!(apply_pulled_records(&pool, "flashcards", card.as_array().unwrap(), None).await.unwrap(), 1);
+        let cards: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM flashcards").fetch_one(&pool).await.unwrap();
+        assert_eq!(cards, 0);
+
+        // Cases are moved to the recycle bin instead
+        let case = json!([{"id": "case-1", "user_id": "user-1", "title": "Donoghue", "created_at": "2024-03-01T09:00:00Z",
+            "updated_at": "2024-03-01T10:00:00Z", "deleted_at": "2024-03-01T11:00:00Z"}]);
+        assert_eq!(apply_pulled_records(&pool, "cases", case.as_array().unwrap(), None).await.unwrap(), 1);
+        let deleted_at: Option<String> = sqlx::query_scalar("SELECT deleted_at FROM cases WHERE id = 'case-1'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(deleted_at.as_deref(), Some("2024-03-01T11:00:00Z"));
+    }
+
+    #[tokio::test]
+    async fn test_newer_side_wins_when_pushing_dirty_records() {
+        // Supabase has an older version of `desktop` and a newer one of `laptop`
+        let remote = r#"[
+            {"id": "desktop", "user_id": "user-1", "title": "Stale", "created_at": "2024-03-01T09:00:00Z",
+             "updated_at": "2024-03-01T10:00:00Z", "deleted_at": null},
+            {"id": "laptop", "user_id": "user-1", "title": "Desktop edit", "created_at": "2024-03-01T09:00:00Z",
+             "updated_at": "2024-03-01T11:00:00Z", "deleted_at": null}
+        ]"#;
+        let (url, server) = fake_supabase(vec![remote, "[]"]).await;
+        let storage = test_storage(Some(url)).await;
+        let pool = storage.sqlite().get_pool().await.unwrap();
+        let manager = SyncManager::new(storage);
+
+        insert_case(&pool, "desktop", "Desktop edit", "2024-03-01T11:00:00Z").await;
+        insert_case(&pool, "laptop", "Stale laptop edit", "2024-03-01T10:00:00Z").await;
+
+        let mut report = SyncReport::default();
+        manager.sync_dirty_table("cases", &mut report).await.unwrap();
+        assert_eq!((report.pushed, report.failed), (1, 0));
+
+        // Only the newer local edit is upserted
+        let requests = server.await.unwrap();
+        assert!(requests[1].starts_with("POST /cases?"), "{}", requests[1]);
+        assert!(requests[1].contains(r#""id":"desktop""#), "{}", requests[1]);
+        assert!(!requests[1].contains(r#""id":"laptop""#), "{}", requests[1]);
+
+        assert_eq!(case_state(&pool, "desktop").await, ("Desktop edit".to_string(), 1, 0));
+        assert_eq!(case_state(&pool, "laptop").await, ("Desktop edit".to_string(), 1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_dirty_records_deleted_remotely_are_dropped_not_pushed() {
+        let remote = r#"[{"id": "card-1", "set_id": "set-1", "front": "Front", "back": "Back",
+            "created_at": "2024-03-01T10:00:00Z", "deleted_at": "2024-03-01T11:00:00Z"}]"#;
+        let (url, server) = fake_supabase(vec![remote]).await;
+        let storage = test_storage(Some(url)).await;
+        let pool = storage.sqlite().get_pool().await.unwrap();
+        let manager = SyncManager::new(storage);
+        insert_flashcard(&pool, "card-1", "Offline edit").await;
+
+        let mut report = SyncReport::default();
+        manager.sync_dirty_table("flashcards", &mut report).await.unwrap();
+        assert_eq!((report.pushed, report.failed), (0, 0));
+
+        // Only the lookup reached Supabase; nothing was upserted
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("GET /flashcards?"), "{}", requests[0]);
+        let cards: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM flashcards").fetch_one(&pool).await.unwrap();
+        assert_eq!(cards, 0);
+    }
+
+    #[tokio::test]
+    async fn test_dirty_records_are_pushed_in_batches_parents_first() {
+        let (url, server) = fake_supabase(vec!["[]"; 6]).await;
+        let storage = test_storage(Some(url)).await;
+        let pool = storage.sqlite().get_pool().await.unwrap();
+        let manager = SyncManager::new(storage);
+        for i in 0..=PUSH_BATCH {
+            insert_flashcard(&pool, &format!("card-{:03}", i), "Front").await;
+        }
+        sqlx::query("UPDATE flashcard_sets SET dirty = 1").execute(&pool).await.unwrap();
+
+        let mut report = SyncReport::default();
+        manager.sync_dirty_records(&mut report).await.unwrap();
+        assert_eq!((report.pushed, report.failed), (PUSH_BATCH + 2, 0));
+
+        // Each batch is checked against Supabase, then upserted in one request
+        let requests = server.await.unwrap();
+        let lines: Vec<&str> = requests.iter().map(|r| r.split('?').next().unwrap()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "GET /flashcard_sets",
+                "POST /flashcard_sets",
+                "GET /flashcards",
+                "POST /flashcards",
+                "GET /flashcards",
+                "POST /flashcards",
+            ]
+        );
+        assert_eq!(requests[3].matches(r#""front""#).count(), PUSH_BATCH);
+        assert!(requests[5].contains(&format!(r#""id":"card-{:03}""#, PUSH_BATCH)), "{}", requests[5]);
+
+        let dirty: i64 = sqlx::query_scalar(
+            "SELECT (SELECT COUNT(*) FROM flashcard_sets WHERE dirty = 1) + (SELECT COUNT(*) FROM flashcards WHERE dirty = 1)"
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(dirty, 0);
+    }
+
+    #[tokio::test]
+    async fn test_deletes_made_offline_are_sent_once_back_online() {
+        let (url, server) = fake_supabase(vec!["[]"]).await;
+        let storage = test_storage(Some(url)).await;
+        let manager = SyncManager::new(storage.clone());
+
+        storage.set_online(false).await;
+        manager.delete_remote("flashcards", "card-1").await.unwrap();
+        assert_eq!(manager.get_queue_status().await.unwrap().len(), 1);
+
+        storage.set_online(true).await;
+        let mut report = SyncReport::default();
+        manager.process_sync_queue(&mut report).await.unwrap();
+        assert_eq!((report.pushed, report.failed), (1, 0));
+        assert!(manager.get_queue_status().await.unwrap().is_empty());
+
+        // The row is tombstoned rather than deleted, so other devices pull the delete
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("PATCH /flashcards?id=eq.card-1 "), "{}", requests[0]);
+        assert!(requests[0].contains(r#""deleted_at":"#), "{}", requests[0]);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_deletes_are_queued() {
+        let (url, server) = fake_http_server(vec![(500, "{}".to_string())]).await;
+        let manager = SyncManager::new(test_storage(Some(url)).await);
+
+        manager.delete_remote("flashcards", "card-1").await.unwrap();
+        server.await.unwrap();
+        let queue = manager.get_queue_status().await.unwrap();
+        assert_eq!((queue.len(), queue[0].record_id.as_str()), (1, "card-1"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_sync_operations_stay_queued_with_the_error() {
+        let rejection = r#"{"message":"violates row-level security"}"#.to_string();
+        let (url, server) = fake_http_server(vec![(403, rejection)]).await;
+        let manager = SyncManager::new(test_storage(Some(url)).await);
+        let operation = SyncOperation {
+            operation_type: "update".to_string(),
+            table_name: "cases".to_string(),
+            record_id: "case-1".to_string(),
+            data: "{}".to_string(),
+        };
+        manager.queue_operation(operation).await.unwrap();
+
+        let mut report = SyncReport::default();
+        manager.process_sync_queue(&mut report).await.unwrap();
+        server.await.unwrap();
+        assert_eq!((report.pushed, report.failed), (0, 1));
+
+        // Backing off towards the dead-letter table instead of being dropped
+        let queue = manager.get_queue_status().await.unwrap();
+        assert_eq!((queue[0].record_id.as_str(), queue[0].attempts), ("case-1", 1));
+        let error = queue[0].last_error.as_deref().unwrap();
+        assert!(error.contains("403") && error.contains("row-level security"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_remote_fetches_report_the_status() {
+        let rejection = r#"{"message":"JWT expired"}"#.to_string();
+        let (url, server) = fake_http_server(vec![(401, rejection)]).await;
+        let manager = SyncManager::new(test_storage(Some(url)).await);
+
+        let error = manager.fetch_remote_records("cases", &["case-1".to_string()]).await.unwrap_err();
+        server.await.unwrap();
+        let message = error.to_string();
+        assert!(message.contains("401") && message.contains("JWT expired"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_sync_operations_move_to_dead_letter() {
+        let manager = SyncManager::new(test_storage(None).await);
+        for record_id in ["stuck", "retrying"] {
+            let operation = SyncOperation {
+                operation_type: "update".to_string(),
+                table_name: "cases".to_string(),
+                record_id: record_id.to_string(),
+                data: "{}".to_string(),
+            };
+            manager.queue_operation(operation).await.unwrap();
+        }
+
+        for mut operation in manager.get_queued_operations().await.unwrap() {
+            if operation.record_id == "stuck" {
+                operation.attempts = MAX_SYNC_ATTEMPTS as i32 - 1;
+            }
+            manager.record_sync_failure(&operation, "timeout").await.unwrap();
+        }
+
+        // The other operation waits out its backoff before the next try
+        let queue = manager.get_queue_status().await.unwrap();
+        assert_eq!(queue.len(), 1);
+        assert_eq!((queue[0].record_id.as_str(), queue[0].attempts), ("retrying", 1));
+        assert_eq!(queue[0].last_error.as_deref(), Some("timeout"));
+        assert!(queue[0].next_retry_at.is_some());
+        assert!(manager.get_queued_operations().await.unwrap().is_empty());
+
+        let dead = manager.get_dead_letter_operations().await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!((dead[0].record_id.as_str(), dead[0].attempts), ("stuck", MAX_SYNC_ATTEMPTS));
+        assert_eq!(dead[0].last_error.as_deref(), Some("timeout"));
+
+        // Retrying queues it again straight away, with a fresh set of attempts
+        manager.retry_dead_letter(dead[0].id).await.unwrap();
+        assert!(manager.get_dead_letter_operations().await.unwrap().is_empty());
+        assert!(matches!(manager.discard_dead_letter(dead[0].id).await, Err(AppError::NotFound(_))));
+        let requeued = manager.get_queued_operations().await.unwrap();
+        assert_eq!((requeued[0].record_id.as_str(), requeued[0].attempts), ("stuck", 0));
+    }
+
+    #[tokio::test]
+    async fn test_conflicts_are_recorded_and_resolved() {
+        let (url, server) = fake_supabase(vec!["[]"]).await;
+        let storage = test_storage(Some(url)).await;
+        let pool = storage.sqlite().get_pool().await.unwrap();
+        let manager = SyncManager::new(storage);
+
+        insert_flashcard(&pool, "card-1", "Local").await;
+        let local = json!({"id": "card-1", "set_id": "set-1", "front": "Local", "back": "Back",
+            "created_at": "2024-03-01T10:00:00Z"});
+        let remote = json!({"id": "card-1", "set_id": "set-1", "front": "Remote", "back": "Back",
+            "created_at": "2024-03-01T10:00:00Z", "remote_only": true});
+        manager.record_conflict("flashcards", "card-1", &local, &remote).await.unwrap();
+        // Detecting it again replaces the stored versions instead of adding a second conflict
+        manager.record_conflict("flashcards", "card-1", &local, &remote).await.unwrap();
+
+        let conflicts = manager.get_conflicts().await.unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!((conflicts[0].table_name.as_str(), conflicts[0].record_id.as_str()), ("flashcards", "card-1"));
+        assert_eq!(conflicts[0].remote_data, remote);
+
+        manager.resolve_conflict(&conflicts[0].id, ConflictResolution::UseRemote).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("PATCH /flashcards?id=eq.card-1 "), "{}", requests[0]);
+        assert!(requests[0].contains(r#""front":"Remote""#), "{}", requests[0]);
+
+        let (front, synced, dirty): (String, i64, i64) =
+            sqlx::query_as("SELECT front, synced, dirty FROM flashcards WHERE id = 'card-1'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!((front.as_str(), synced, dirty), ("Remote", 1, 0));
+        assert!(manager.get_conflicts().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_resolutions_leave_the_conflict_in_place() {
+        let (url, server) = fake_http_server(vec![(400, "{}".to_string())]).await;
+        let storage = test_storage(Some(url)).await;
+        let pool = storage.sqlite().get_pool().await.unwrap();
+        let manager = SyncManager::new(storage);
+
+        insert_flashcard(&pool, "card-1", "Local").await;
+        let local = json!({"id": "card-1", "front": "Local"});
+        let remote = json!({"id": "card-1", "front": "Remote"});
+        manager.record_conflict("flashcards", "card-1", &local, &remote).await.unwrap();
+        let conflict_id = manager.get_conflicts().await.unwrap()[0].id.clone();
+
+        let resolved = manager.resolve_conflict(&conflict_id, ConflictResolution::UseRemote).await;
+        server.await.unwrap();
+        assert!(matches!(resolved, Err(AppError::Sync(_))), "{:?}", resolved);
+
+        let (front, dirty): (String, i64) =
+            sqlx::query_as("SELECT front, dirty FROM flashcards WHERE id = 'card-1'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!((front.as_str(), dirty), ("Local", 1));
+        assert_eq!(manager.get_conflicts().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pulls_continue_from_the_newest_change_pulled() {
+        let first = r#"[{"id": "set-1", "user_id": "user-1", "title": "Torts", "description": null,
+            "created_at": "2024-03-01T09:00:00Z", "updated_at": "2024-03-01T10:00:00Z", "deleted_at": null}]"#;
+        let (url, server) = fake_supabase(vec![first, "[]"]).await;
+        let storage = test_storage(Some(url)).await;
+        let pool = storage.sqlite().get_pool().await.unwrap();
+        let manager = SyncManager::new(storage);
+
+        assert_eq!(manager.pull_remote_changes("flashcard_sets", "user-1").await.unwrap(), 1);
+        let watermark: String = sqlx::query_scalar("SELECT last_pulled_at FROM sync_watermarks WHERE table_name = 'flashcard_sets'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(watermark, "2024-03-01T10:00:00Z");
+        let title: String = sqlx::query_scalar("SELECT title FROM flashcard_sets WHERE id = 'set-1'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(title, "Torts");
+
+        // Nothing new; the watermark stays put
+        assert_eq!(manager.pull_remote_changes("flashcard_sets", "user-1").await.unwrap(), 0);
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /flashcard_sets?"), "{}", requests[0]);
+        assert!(requests[0].lines().next().unwrap().contains("deleted_at=is.null"), "{}", requests[0]);
+        assert!(requests[1].lines().next().unwrap().contains("or="), "{}", requests[1]);
+
+        // Switching user pulls every table from the start again
+        manager.set_user(Some("user-2".to_string())).await.unwrap();
+        let watermarks: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sync_watermarks").fetch_one(&pool).await.unwrap();
+        assert_eq!(watermarks, 0);
+    }
+
+    #[tokio::test]
+    async fn test_pulls_page_through_changes_oldest_first() {
+        let rows: Vec<serde_json::Value> = (0..=PULL_PAGE)
+            .map(|i| {
+                let updated_at = (DateTime::parse_from_rfc3339("2024-03-01T00:00:00Z").unwrap()
+                    + chrono::Duration::seconds(i as i64))
+                .to_rfc3339();
+                json!({"id": format!("set-{:04}", i), "user_id": "user-1", "title": "Torts", "description": null,
+                    "created_at": "2024-03-01T00:00:00Z", "updated_at": updated_at, "deleted_at": null})
+            })
+            .collect();
+        let (first, second) = rows.split_at(PULL_PAGE);
+        let (url, server) = fake_http_server(vec![
+            (200, json!(first).to_string()),
+            (200, json!(second).to_string()),
+        ])
+        .await;
+        let storage = test_storage(Some(url)).await;
+        let pool = storage.sqlite().get_pool().await.unwrap();
+        let manager = SyncManager::new(storage);
+
+        assert_eq!(manager.pull_remote_changes("flashcard_sets", "user-1").await.unwrap(), PULL_PAGE + 1);
+        let watermark: String = sqlx::query_scalar("SELECT last_pulled_at FROM sync_watermarks WHERE table_name = 'flashcard_sets'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(watermark, second[0]["updated_at"].as_str().unwrap());
+
+        let requests = server.await.unwrap();
+        for (request, range) in requests.iter().zip(["0-999", "1000-1999"]) {
+            let request = request.to_lowercase();
+            assert!(request.lines().next().unwrap().contains("order=updated_at.asc%2cid.asc"), "{}", request);
+            assert!(request.contains(&format!("range: {}", range)), "{}", request);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_a_table_that_fails_to_pull_is_skipped() {
+        let missing = (404, r#"{"message": "relation \"tags\" does not exist"}"#.to_string());
+        let empty = (200, "[]".to_string());
+        let mut responses = vec![missing.clone(), empty.clone(), missing];
+        responses.extend(std::iter::repeat_n(empty, 4));
+        let (url, server) = fake_http_server(responses).await;
+        let manager = SyncManager::new(test_storage(Some(url)).await);
+        manager.set_user(Some("user-1".to_string())).await.unwrap();
+
+        assert!(matches!(manager.pull_remote_changes("tags", "user-1").await, Err(AppError::Sync(_))));
+        let mut report = SyncReport::default();
+        manager.pull_remote_records(&mut report).await.unwrap();
+        assert_eq!(report.pulled, 0);
+
+        // Tables without local parent rows make no request
+        let requests = server.await.unwrap();
+        let tables: Vec<&str> = requests.iter().map(|r| r.split('?').next().unwrap()).collect();
+        assert_eq!(
+            tables,
+            vec![
+                "GET /tags",
+                "GET /cases",
+                "GET /tags",
+                "GET /flashcard_sets",
+                "GET /mock_tests",
+                "GET /test_results",
+                "GET /study_plans",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_newer_local_edit_is_pushed() {
+        let local = json!({"id": "1", "title": "Desktop edit", "updated_at": "2024-03-01T11:00:00Z"});
+        let remote = json!({"id": "1", "title": "Stale", "updated_at": "2024-03-01T10:00:00Z"});
+
+        assert_eq!(push_decision(&local, Some(&remote)), PushDecision::Push);
+        assert_eq!(push_decision(&local, None), PushDecision::Push);
+    }
+
+    #[test]
+    fn test_newer_remote_edit_is_kept() {
+        // A laptop coming online with an edit older than the one already synced
+        let local = json!({"id": "1", "title": "Stale laptop edit", "updated_at": "2024-03-01T10:00:00Z"});
+        let remote = json!({"id": "1", "title": "Desktop edit", "updated_at": "2024-03-01T11:00:00+00:00"});
+
+        assert_eq!(push_decision(&local, Some(&remote)), PushDecision::KeepRemote);
+    }
+
+    #[test]
+    fn test_untimestamped_records_conflict_only_when_they_differ() {
+        let local = json!({"case_id": "c1", "tag_id": "t1", "primary": 1});
+        let same = json!({"case_id": "c1", "tag_id": "t1", "primary": true, "remote_only": "x"});
+        let different = json!({"case_id": "c1", "tag_id": "t1", "primary": false});
+
+        assert_eq!(push_decision(&local, Some(&same)), PushDecision::Push);
+        assert_eq!(push_decision(&local, Some(&different)), PushDecision::Conflict);
+
+        let timestamped = json!({"case_id": "c1", "tag_id": "t1", "primary": false, "updated_at": "2024-03-01T11:00:00Z"});
+        assert_eq!(push_decision(&local, Some(&timestamped)), PushDecision::Conflict);
+    }
+
+    #[test]
+    fn test_remote_tombstone_drops_the_local_record() {
+        let local = json!({"id": "1", "title": "Offline edit", "updated_at": "2024-03-01T12:00:00Z"});
+        let deleted = json!({"id": "1", "title": "Old", "updated_at": "2024-03-01T10:00:00Z", "deleted_at": "2024-03-01T11:00:00Z"});
+        let live = json!({"id": "1", "title": "Old", "updated_at": "2024-03-01T10:00:00Z", "deleted_at": null});
+
+        assert_eq!(push_decision(&local, Some(&deleted)), PushDecision::Drop);
+        assert_eq!(push_decision(&local, Some(&live)), PushDecision::Push);
+
+        // A restore from the recycle bin is a newer edit, not a delete to drop
+        let restored = json!({"id": "1", "title": "Offline edit", "updated_at": "2024-03-01T12:00:00Z", "deleted_at": null});
+        assert_eq!(push_decision(&restored, Some(&deleted)), PushDecision::Push);
+    }
+
+    #[test]
+    fn test_offline_delete_is_queued_as_a_tombstone() {
+        let operation = tombstone_operation("flashcard_sets", "set-1");
+        assert_eq!(operation.operation_type, "delete");
+        assert_eq!((operation.table_name.as_str(), operation.record_id.as_str()), ("flashcard_sets", "set-1"));
+
+        // The queue replays this as the update that marks the Supabase row deleted
+        let data: serde_json::Value = serde_json::from_str(&operation.data).unwrap();
+        assert!(is_tombstone(&data));
+        assert!(updated_at(&json!({"updated_at": data["deleted_at"]})).is_some());
+    }
+
+    #[test]
+    fn test_sync_report_payload() {
+        let report = SyncReport { pushed: 3, pulled: 2, failed: 1, timestamp: "2024-03-01T12:00:00Z".to_string() };
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            json!({"pushed": 3, "pulled": 2, "failed": 1, "timestamp": "2024-03-01T12:00:00Z"})
+        );
+
+        let progress = SyncProgressEvent { table: "cases".to_string(), done: 1, total: 4 };
+        assert_eq!(serde_json::to_value(&progress).unwrap(), json!({"table": "cases", "done": 1, "total": 4}));
+    }
+
+    #[test]
+    fn test_keep_version_serde() {
+        let keep: KeepVersion = serde_json::from_value(json!("remote")).unwrap();
+        assert!(matches!(keep, KeepVersion::Remote));
+    }
+
+    #[test]
+    fn test_conflict_resolution_serde() {
+        let merge: ConflictResolution = serde_json::from_value(json!({"merge": {"title": "Merged"}})).unwrap();
+        assert!(matches!(merge, ConflictResolution::Merge(ref v) if v["title"] == "Merged"));
+
+        let local: ConflictResolution = serde_json::from_value(json!("use_local")).unwrap();
+        assert!(matches!(local, ConflictResolution::UseLocal));
+    }
+}
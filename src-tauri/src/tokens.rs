@@ -0,0 +1,276 @@
+/**
+ * Token Estimation
+ * Approximate token counts and context-window trimming for LLM prompts
+ */
+
+/// Tokens reserved for chat formatting and estimation error
+const PROMPT_OVERHEAD_TOKENS: usize = 64;
+
+/// Average characters per token for a model family
+fn chars_per_token(model: &str) -> f64 {
+    let model = model.to_lowercase();
+    if model.contains("claude") || model.contains("mistral") {
+        3.5
+    } else if model.contains("llama") {
+        3.8
+    } else {
+        // OpenAI and Gemini tokenizers average roughly four characters per token
+        4.0
+    }
+}
+
+/// Estimate the number of tokens `text` occupies for `model`
+///
+/// Uses a characters-per-token heuristic per model family, which is close
+/// enough for budgeting without shipping each provider's tokenizer.
+pub fn token_estimate(text: &str, model: &str) -> usize {
+    let chars = text.chars().count();
+    (chars as f64 / chars_per_token(model)).ceil() as usize
+}
+
+/// Context window size in tokens for `model`
+pub fn context_window(model: &str) -> usize {
+    let model = model.to_lowercase();
+    if model.contains("gemini-1.5") || model.contains("gemini-2") {
+        1_048_576
+    } else if model.contains("claude") {
+        200_000
+    } else if model.contains("gpt-4o") || model.contains("gpt-4-turbo") || model.contains("gpt-4.1") {
+        128_000
+    } else if model.contains("llama-3.1") || model.contains("llama-3.2") || model.contains("llama-3.3") {
+        131_072
+    } else if model.contains("gpt-3.5") {
+        16_385
+    } else if model.contains("gemini") || model.contains("mistral") {
+        32_768
+    } else {
+        8_192
+    }
+}
+
+/// Tokens available for variable prompt content once the fixed prompt text
+/// and the completion (`max_tokens`) are accounted for
+pub fn prompt_budget(model: &str, max_tokens: u32, fixed: &[&str]) -> usize {
+    let fixed_tokens: usize = fixed.iter().map(|text| token_estimate(text, model)).sum();
+    context_window(model)
+        .saturating_sub(max_tokens as usize)
+        .saturating_sub(fixed_tokens)
+        .saturating_sub(PROMPT_OVERHEAD_TOKENS)
+}
+
+/// Cut `text` so that it fits within `max_tokens`, preferring a word boundary
+pub fn truncate_to_tokens(text: &str, max_tokens: usize, model: &str) -> String {
+    if token_estimate(text, model) <= max_tokens {
+        return text.to_string();
+    }
+
+    let max_chars = (max_tokens as f64 * chars_per_token(model)).floor() as usize;
+    let cut: String = text.chars().take(max_chars).collect();
+
+    match cut.rfind(char::is_whitespace) {
+        Some(pos) if pos > cut.len() / 2 => cut[..pos].trim_end().to_string(),
+        _ => cut,
+    }
+}
+
+/// Group `items` into batches of at most `max_tokens` each, joined by blank lines
+///
+/// Used for map-reduce prompting: each batch is processed separately instead
+/// of truncating. Items larger than the budget are split across batches.
+pub fn pack_to_budget(items: &[String], max_tokens: usize, model: &str) -> Vec<String> {
+    let max_tokens = max_tokens.max(1);
+    let mut batches = Vec::new();
+    let mut current = String::new();
+
+    for item in items {
+        let mut rest = item.as_str();
+        while !rest.is_empty() {
+            let candidate = if current.is_empty() {
+                rest.to_string()
+            } else {
+                format!("{}\n\n{}", current, rest)
+            };
+
+            if token_estimate(&candidate, model) <= max_tokens {
+                current = candidate;
+                break;
+            }
+
+            if !current.is_empty() {
+                batches.push(std::mem::take(&mut current));
+                continue;
+            }
+
+            // A single item larger than the budget: emit a budget-sized piece
+            let piece = truncate_to_tokens(rest, max_tokens, model);
+            let piece = if piece.is_empty() {
+                rest.chars().take(1).collect()
+            } else {
+                piece
+            };
+            rest = rest[piece.len()..].trim_start();
+            batches.push(piece);
+        }
+    }
+
+    if !current.is_empty() {
+        batches.push(current);
+    }
+
+    batches
+}
+
+/// Prompt content after fitting it into a token budget
+#[derive(Debug, Clone, PartialEq)]
+pub struct FittedPrompt {
+    pub primary: String,
+    pub context: Vec<String>,
+    pub truncated: bool,
+}
+
+/// Fit `primary` text plus supporting `context` into `budget` tokens
+///
+/// Context entries are assumed to be ordered most relevant first and are
+/// dropped from the end before the primary text is cut.
+pub fn fit_to_budget(primary: &str, mut context: Vec<String>, budget: usize, model: &str) -> FittedPrompt {
+    let primary_tokens = token_estimate(primary, model);
+    let mut context_tokens: usize = context.iter().map(|c| token_estimate(c, model)).sum();
+    let mut truncated = false;
+
+    while primary_tokens + context_tokens > budget {
+        match context.pop() {
+            Some(dropped) => {
+                context_tokens -= token_estimate(&dropped, model);
+                truncated = true;
+            }
+            None => break,
+        }
+    }
+
+    let primary = if primary_tokens > budget {
+        truncated = true;
+        truncate_to_tokens(primary, budget, model)
+    } else {
+        primary.to_string()
+    };
+
+    FittedPrompt {
+        primary,
+        context,
+        truncated,
+    }
+}
+
+/// Keep whole items, highest `score` first, while their `cost` in tokens fits `budget`
+///
+/// Items are never cut: one that doesn't fit is left out, and smaller
+/// lower-scoring items may still use the space it would have taken. Returns
+/// the kept items in score order and whether any were left out.
+pub fn select_within_budget<T>(
+    mut items: Vec<T>,
+    budget: usize,
+    score: impl Fn(&T) -> f32,
+    cost: impl Fn(&T) -> usize,
+) -> (Vec<T>, bool) {
+    items.sort_by(|a, b| score(b).total_cmp(&score(a)));
+
+    let mut used = 0;
+    let mut dropped = false;
+    let mut kept = Vec::with_capacity(items.len());
+    for item in items {
+        let tokens = cost(&item);
+        if used + tokens <= budget {
+            used += tokens;
+            kept.push(item);
+        } else {
+            dropped = true;
+        }
+    }
+
+    (kept, dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_estimate_by_family() {
+        let text = "a".repeat(400);
+        assert_eq!(token_estimate(&text, "openai/gpt-4o"), 100);
+        assert_eq!(token_estimate(&text, "anthropic/claude-3.5-sonnet"), 115);
+        assert_eq!(token_estimate("", "google/gemini-2.0-flash-exp"), 0);
+    }
+
+    #[test]
+    fn test_context_window() {
+        assert_eq!(context_window("google/gemini-2.0-flash-exp"), 1_048_576);
+        assert_eq!(context_window("anthropic/claude-3.5-sonnet"), 200_000);
+        assert_eq!(context_window("meta-llama/llama-3.1-70b-instruct"), 131_072);
+        assert_eq!(context_window("unknown/model"), 8_192);
+    }
+
+    #[test]
+    fn test_pack_to_budget_groups_items() {
+        let items = vec!["a".repeat(40), "b".repeat(40), "c".repeat(40)]; // 10 tokens each
+        let batches = pack_to_budget(&items, 25, "openai/gpt-4o");
+
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0], format!("{}\n\n{}", "a".repeat(40), "b".repeat(40)));
+        assert_eq!(batches[1], "c".repeat(40));
+    }
+
+    #[test]
+    fn test_pack_to_budget_splits_oversized_items() {
+        let items = vec!["word ".repeat(100)]; // 125 tokens
+        let batches = pack_to_budget(&items, 50, "openai/gpt-4o");
+
+        assert!(batches.len() >= 3);
+        assert!(batches.iter().all(|b| token_estimate(b, "openai/gpt-4o") <= 50));
+        assert_eq!(batches.join(" ").split_whitespace().count(), 100);
+    }
+
+    #[test]
+    fn test_fit_to_budget_unchanged_when_it_fits() {
+        let fitted = fit_to_budget("short case", vec!["context".to_string()], 100, "openai/gpt-4o");
+        assert!(!fitted.truncated);
+        assert_eq!(fitted.primary, "short case");
+        assert_eq!(fitted.context.len(), 1);
+    }
+
+    #[test]
+    fn test_fit_to_budget_drops_context_first() {
+        let case_text = "word ".repeat(40); // 50 tokens
+        let context = vec!["x".repeat(80), "y".repeat(80)]; // 20 tokens each
+
+        let fitted = fit_to_budget(&case_text, context, 75, "openai/gpt-4o");
+
+        assert!(fitted.truncated);
+        assert_eq!(fitted.primary, case_text);
+        assert_eq!(fitted.context, vec!["x".repeat(80)]);
+    }
+
+    #[test]
+    fn test_fit_to_budget_truncates_primary_last() {
+        let case_text = "word ".repeat(200);
+        let fitted = fit_to_budget(&case_text, vec!["context".to_string()], 50, "openai/gpt-4o");
+
+        assert!(fitted.truncated);
+        assert!(fitted.context.is_empty());
+        assert!(token_estimate(&fitted.primary, "openai/gpt-4o") <= 50);
+        assert!(fitted.primary.ends_with("word"));
+    }
+
+    #[test]
+    fn test_select_within_budget_keeps_best_whole_items() {
+        let model = "openai/gpt-4o";
+        // (score, text): 25, 100 and 10 tokens
+        let items = vec![(0.5, "a".repeat(100)), (0.9, "b".repeat(400)), (0.7, "c".repeat(40))];
+
+        let (kept, dropped) = select_within_budget(items, 40, |(score, _)| *score, |(_, text)| token_estimate(text, model));
+
+        assert!(dropped);
+        let kept: Vec<f32> = kept.iter().map(|(score, _)| *score).collect();
+        assert_eq!(kept, vec![0.7, 0.5]);
+    }
+}
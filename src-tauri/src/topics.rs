@@ -0,0 +1,289 @@
+/**
+ * Topics Module
+ * Labels ingested documents with legal topics from a fixed taxonomy
+ */
+
+use crate::db::HybridStorage;
+use crate::document::DocumentChunk;
+use crate::error::{AppError, AppResult, CommandResult};
+use crate::llm::{ChatOptions, LLMService, Message};
+use crate::logging::LogLevel;
+use crate::rag::{document_summary, DocumentSummary};
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+use tauri::{AppHandle, Emitter, State};
+
+/// Topics a document can be labelled with
+pub const LEGAL_TOPICS: &[&str] = &[
+    "Administrative Law",
+    "Civil Procedure",
+    "Company Law",
+    "Constitutional Law",
+    "Contracts",
+    "Criminal Law",
+    "Employment Law",
+    "Equity and Trusts",
+    "Evidence",
+    "Family Law",
+    "Intellectual Property",
+    "International Law",
+    "Property",
+    "Tax Law",
+    "Torts",
+];
+
+/// Most topics recorded per document
+pub const MAX_DOCUMENT_TOPICS: usize = 3;
+
+/// Leading chunks of a document sent to the model
+const TOPIC_SAMPLE_CHUNKS: usize = 5;
+
+/// Characters of each sampled chunk sent to the model
+const TOPIC_SAMPLE_CHARS: usize = 1500;
+
+/// Payload of the `rag://document-topics` event emitted when tagging finishes
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentTopicsEvent {
+    pub doc_id: String,
+    pub topics: Vec<String>,
+    /// Set when tagging failed; the document stays untagged
+    pub error: Option<String>,
+}
+
+/// The taxonomy spelling of `topic`, matched case-insensitively
+pub fn canonical_topic(topic: &str) -> Option<&'static str> {
+    let topic = topic.trim();
+    LEGAL_TOPICS.iter().copied().find(|t| t.eq_ignore_ascii_case(topic))
+}
+
+/// Taxonomy topics named in a model response, in order and without duplicates
+///
+/// Accepts a JSON array of names, optionally in a code block, or a comma or
+/// line separated list. Names outside the taxonomy are dropped.
+fn parse_topics(response: &str) -> Vec<String> {
+    let names: Vec<String> = match (response.find('['), response.rfind(']')) {
+        (Some(start), Some(end)) if start < end => {
+            serde_json::from_str(&response[start..=end]).unwrap_or_default()
+        }
+        _ => Vec::new(),
+    };
+    let names = if names.is_empty() {
+        response
+            .split([',', '\n'])
+            .map(|name| name.trim_matches(|c: char| c.is_whitespace() || matches!(c, '-' | '*' | '"' | '`' | '.')).to_string())
+            .collect()
+    } else {
+        names
+    };
+
+    let mut topics: Vec<String> = Vec::new();
+    for topic in names.iter().filter_map(|name| canonical_topic(name)) {
+        if !topics.iter().any(|t| t == topic) {
+            topics.push(topic.to_string());
+        }
+    }
+    topics.truncate(MAX_DOCUMENT_TOPICS);
+    topics
+}
+
+/// Ask the model for up to `MAX_DOCUMENT_TOPICS` topics covering the leading chunks of a document
+pub async fn classify_topics(llm: &LLMService, chunks: &[String]) -> AppResult<Vec<String>> {
+    let sample = chunks
+        .iter()
+        .take(TOPIC_SAMPLE_CHUNKS)
+        .map(|chunk| chunk.chars().take(TOPIC_SAMPLE_CHARS).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    if sample.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let messages = vec![
+        Message {
+            role: "system".to_string(),
+            content: format!(
+                "You label law study materials with legal topics. Choose at most {} topics from this list, most relevant first: {}.\nReply with a JSON array of topic names exactly as listed, e.g. [\"Torts\", \"Evidence\"]. Reply with [] if none apply.",
+                MAX_DOCUMENT_TOPICS,
+                LEGAL_TOPICS.join(", ")
+            ),
+        },
+        Message {
+            role: "user".to_string(),
+            content: format!("Label this document:\n\n{}", sample),
+        },
+    ];
+
+    let options = ChatOptions {
+        temperature: Some(0.0),
+        max_tokens: Some(100),
+        task: Some("topics".to_string()),
+        ..Default::default()
+    };
+    let response = llm.chat(messages, options).await?;
+
+    Ok(parse_topics(&response))
+}
+
+/// Replace the topics recorded for a document
+pub async fn save_document_topics(pool: &Pool<Sqlite>, doc_id: &str, topics: &[String]) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM document_topics WHERE document_id = ?")
+        .bind(doc_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for topic in topics {
+        sqlx::query("INSERT INTO document_topics (document_id, topic, created_at) VALUES (?, ?, CURRENT_TIMESTAMP)")
+            .bind(doc_id)
+            .bind(topic)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Topics recorded for a document, in the order the model ranked them
+pub async fn document_topics(pool: &Pool<Sqlite>, doc_id: &str) -> AppResult<Vec<String>> {
+    Ok(sqlx::query_scalar("SELECT topic FROM document_topics WHERE document_id = ? ORDER BY rowid")
+        .bind(doc_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+/// Knowledge base documents and the user's own documents labelled with `topic`, newest first
+pub async fn documents_by_topic(
+    pool: &Pool<Sqlite>,
+    topic: &str,
+    user_id: Option<&str>,
+) -> AppResult<Vec<DocumentSummary>> {
+    let topic = canonical_topic(topic)
+        .ok_or_else(|| AppError::Validation(format!("Unknown topic '{}'", topic)))?;
+
+    let rows = sqlx::query(
+        "SELECT d.id, d.title, d.content_hash, d.embedding_status, d.created_at,
+                (SELECT COUNT(*) FROM document_chunks c WHERE c.document_id = d.id) AS total_chunks
+         FROM documents d
+         JOIN document_topics t ON t.document_id = d.id
+         WHERE t.topic = ? AND (d.document_type = 'knowledge_base' OR d.user_id IS ?)
+         ORDER BY d.created_at DESC"
+    )
+    .bind(topic)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(document_summary).collect())
+}
+
+/// Label a newly ingested document in the background, emitting
+/// `rag://document-topics` when done so ingestion isn't held up by the LLM
+pub fn spawn_topic_tagging(
+    app: &AppHandle,
+    pool: &Pool<Sqlite>,
+    llm: &LLMService,
+    doc_id: &str,
+    chunks: &[DocumentChunk],
+) {
+    let (app, pool, llm, doc_id) = (app.clone(), pool.clone(), llm.clone(), doc_id.to_string());
+    let sample: Vec<String> = chunks.iter().take(TOPIC_SAMPLE_CHUNKS).map(|c| c.text.clone()).collect();
+
+    tauri::async_runtime::spawn(async move {
+        let tagged = async {
+            let topics = classify_topics(&llm, &sample).await?;
+            save_document_topics(&pool, &doc_id, &topics).await?;
+            Ok::<_, AppError>(topics)
+        };
+
+        let event = match tagged.await {
+            Ok(topics) => DocumentTopicsEvent { doc_id, topics, error: None },
+            Err(e) => {
+                crate::logging::log(LogLevel::Warn, "rag", &format!("Failed to tag topics for {}: {}", doc_id, e));
+                DocumentTopicsEvent { doc_id, topics: Vec::new(), error: Some(e.to_string()) }
+            }
+        };
+        let _ = app.emit("rag://document-topics", event);
+    });
+}
+
+// Tauri Commands
+
+/// Documents labelled with a topic from `LEGAL_TOPICS`
+#[tauri::command]
+pub async fn get_documents_by_topic(
+    storage: State<'_, HybridStorage>,
+    topic: String,
+    user_id: Option<String>,
+) -> CommandResult<Vec<DocumentSummary>> {
+    let pool = storage.sqlite().get_pool().await?;
+    Ok(documents_by_topic(&pool, &topic, user_id.as_deref()).await?)
+}
+
+#[tauri::command]
+pub async fn get_document_topics(
+    storage: State<'_, HybridStorage>,
+    doc_id: String,
+) -> CommandResult<Vec<String>> {
+    let pool = storage.sqlite().get_pool().await?;
+    Ok(document_topics(&pool, &doc_id).await?)
+}
+
+/// The topics documents can be labelled with
+#[tauri::command]
+pub fn list_legal_topics() -> Vec<String> {
+    LEGAL_TOPICS.iter().map(|t| t.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    #[test]
+    fn test_parse_topics() {
+        assert_eq!(parse_topics("```json\n[\"torts\", \"Evidence\"]\n```"), vec!["Torts", "Evidence"]);
+        assert_eq!(parse_topics("- Contracts\n- Astrology\n- contracts"), vec!["Contracts"]);
+        assert_eq!(
+            parse_topics(r#"["Torts", "Evidence", "Contracts", "Property"]"#),
+            vec!["Torts", "Evidence", "Contracts"]
+        );
+        assert!(parse_topics("[]").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_documents_by_topic() {
+        let pool = test_pool().await;
+
+        for (id, user_id, document_type) in [
+            ("kb", None, "knowledge_base"),
+            ("mine", Some("user-a"), "user_case"),
+            ("theirs", Some("user-b"), "user_case"),
+        ] {
+            sqlx::query(
+                "INSERT INTO documents (id, user_id, title, document_type, created_at, updated_at) VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)"
+            )
+            .bind(id)
+            .bind(user_id)
+            .bind(id)
+            .bind(document_type)
+            .execute(&pool)
+            .await
+            .unwrap();
+            save_document_topics(&pool, id, &["Torts".to_string()]).await.unwrap();
+        }
+        save_document_topics(&pool, "kb", &["Evidence".to_string(), "Torts".to_string()]).await.unwrap();
+
+        let mut ids: Vec<String> = documents_by_topic(&pool, "torts", Some("user-a"))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|d| d.doc_id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["kb", "mine"]);
+        assert_eq!(document_topics(&pool, "kb").await.unwrap(), vec!["Evidence", "Torts"]);
+        assert!(documents_by_topic(&pool, "Astrology", None).await.is_err());
+    }
+}
@@ -0,0 +1,190 @@
+/**
+ * Tutor Conversations
+ * Persists tutor chat history and the teaching mode chosen for each conversation
+ */
+
+use crate::db::HybridStorage;
+use crate::error::{AppError, AppResult};
+use crate::llm::Message;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use uuid::Uuid;
+
+/// How the tutor responds to the student
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TutorMode {
+    /// Explain concepts directly
+    #[default]
+    Explain,
+    /// Guide the student with questions, revealing answers only after they try
+    Socratic,
+}
+
+impl TutorMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TutorMode::Explain => "explain",
+            TutorMode::Socratic => "socratic",
+        }
+    }
+
+    pub fn parse(value: &str) -> AppResult<Self> {
+        match value {
+            "explain" => Ok(TutorMode::Explain),
+            "socratic" => Ok(TutorMode::Socratic),
+            other => Err(AppError::Validation(format!("Unknown tutor mode '{}'", other))),
+        }
+    }
+}
+
+/// A stored tutor conversation
+#[derive(Debug, Clone, Serialize)]
+pub struct Conversation {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub mode: TutorMode,
+}
+
+/// Start a new conversation
+pub async fn create_conversation(
+    storage: &HybridStorage,
+    user_id: Option<&str>,
+    mode: TutorMode,
+) -> AppResult<Conversation> {
+    let pool = storage.sqlite().get_pool().await?;
+    let conversation = Conversation {
+        id: Uuid::new_v4().to_string(),
+        user_id: user_id.map(str::to_string),
+        mode,
+    };
+
+    sqlx::query(
+        "INSERT INTO tutor_conversations (id, user_id, mode, created_at, updated_at)
+         VALUES (?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)"
+    )
+    .bind(&conversation.id)
+    .bind(&conversation.user_id)
+    .bind(mode.as_str())
+    .execute(&pool)
+    .await?;
+
+    Ok(conversation)
+}
+
+/// Load a conversation by ID
+///
+/// Another user's conversation is reported as not found, so its history
+/// can't be continued under someone else's ID.
+pub async fn load_conversation(storage: &HybridStorage, id: &str, user_id: Option<&str>) -> AppResult<Conversation> {
+    let pool = storage.sqlite().get_pool().await?;
+
+    let row = sqlx::query("SELECT id, user_id, mode FROM tutor_conversations WHERE id = ? AND user_id IS ?")
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Conversation {} not found", id)))?;
+
+    Ok(Conversation {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        mode: TutorMode::parse(row.get::<String, _>("mode").as_str())?,
+    })
+}
+
+/// Change the mode used for the rest of a conversation
+pub async fn set_mode(storage: &HybridStorage, id: &str, mode: TutorMode) -> AppResult<()> {
+    let pool = storage.sqlite().get_pool().await?;
+
+    sqlx::query("UPDATE tutor_conversations SET mode = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(mode.as_str())
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Messages of a conversation in the order they were sent
+pub async fn conversation_messages(storage: &HybridStorage, id: &str) -> AppResult<Vec<Message>> {
+    let pool = storage.sqlite().get_pool().await?;
+
+    let rows = sqlx::query("SELECT role, content FROM tutor_messages WHERE conversation_id = ? ORDER BY id")
+        .bind(id)
+        .fetch_all(&pool)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Message {
+            role: row.get("role"),
+            content: row.get("content"),
+        })
+        .collect())
+}
+
+/// Append messages to a conversation
+pub async fn append_messages(storage: &HybridStorage, id: &str, messages: &[Message]) -> AppResult<()> {
+    let pool = storage.sqlite().get_pool().await?;
+    let mut tx = pool.begin().await?;
+
+    for message in messages {
+        sqlx::query(
+            "INSERT INTO tutor_messages (conversation_id, role, content, created_at)
+             VALUES (?, ?, ?, CURRENT_TIMESTAMP)"
+        )
+        .bind(id)
+        .bind(&message.role)
+        .bind(&message.content)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query("UPDATE tutor_conversations SET updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{test_cache, IN_MEMORY_DB};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_tutor_mode_round_trip() {
+        for mode in [TutorMode::Explain, TutorMode::Socratic] {
+            assert_eq!(TutorMode::parse(mode.as_str()).unwrap(), mode);
+        }
+        assert!(TutorMode::parse("lecture").is_err());
+    }
+
+    #[test]
+    fn test_tutor_mode_serde() {
+        let mode: TutorMode = serde_json::from_str("\"socratic\"").unwrap();
+        assert_eq!(mode, TutorMode::Socratic);
+        assert_eq!(serde_json::to_string(&TutorMode::Explain).unwrap(), "\"explain\"");
+    }
+
+    #[tokio::test]
+    async fn test_conversations_only_load_for_their_user() {
+        let cache = test_cache().await;
+        let storage = HybridStorage::new(PathBuf::from(IN_MEMORY_DB), None, None).with_sqlite(cache);
+
+        let mine = create_conversation(&storage, Some("user-1"), TutorMode::Socratic).await.unwrap();
+        let loaded = load_conversation(&storage, &mine.id, Some("user-1")).await.unwrap();
+        assert_eq!(loaded.mode, TutorMode::Socratic);
+
+        assert!(matches!(load_conversation(&storage, &mine.id, Some("user-2")).await, Err(AppError::NotFound(_))));
+        assert!(matches!(load_conversation(&storage, &mine.id, None).await, Err(AppError::NotFound(_))));
+
+        let anonymous = create_conversation(&storage, None, TutorMode::Explain).await.unwrap();
+        assert!(load_conversation(&storage, &anonymous.id, None).await.is_ok());
+        assert!(load_conversation(&storage, &anonymous.id, Some("user-1")).await.is_err());
+    }
+}
@@ -5,6 +5,7 @@
 
 use crate::error::{AppError, AppResult};
 use regex::Regex;
+use url::{Host, Url};
 
 /// Validate that a string is not empty
 pub fn validate_not_empty(value: &str, field_name: &str) -> AppResult<()> {
@@ -29,19 +30,19 @@ pub fn validate_length(value: &str, field_name: &str, min: usize, max: usize) ->
     Ok(())
 }
 
-/// Validate UUID format
-pub fn validate_uuid(value: &str, field_name: &str) -> AppResult<()> {
+/// Validate UUID format in either case, returning the lowercase form stored in the database
+pub fn validate_uuid(value: &str, field_name: &str) -> AppResult<String> {
     let uuid_regex = Regex::new(
-        r"^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$"
+        r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$"
     ).map_err(|e| AppError::Internal(format!("Regex error: {}", e)))?;
 
     if !uuid_regex.is_match(value) {
-        return Err(AppError::Validation(format!(
+        return Err(AppError::InvalidUuid(format!(
             "{} must be a valid UUID",
             field_name
         )));
     }
-    Ok(())
+    Ok(value.to_ascii_lowercase())
 }
 
 /// Validate email format
@@ -91,7 +92,61 @@ pub fn validate_percentage(value: f64, field_name: &str) -> AppResult<()> {
     Ok(())
 }
 
+/// Parse an absolute `https` URL with a host, returning it for callers that
+/// need its components
+///
+/// Debug builds also accept `http`.
+pub fn validate_url(value: &str, field_name: &str) -> AppResult<Url> {
+    parse_url(value, field_name, cfg!(debug_assertions))
+}
+
+/// Like `validate_url`, but `http` is accepted in any build for localhost and
+/// loopback addresses, such as a local Supabase instance
+pub fn validate_url_http_or_https(value: &str, field_name: &str) -> AppResult<Url> {
+    let url = parse_url(value, field_name, true)?;
+    if url.scheme() == "http" && !cfg!(debug_assertions) && !is_loopback_host(&url) {
+        return Err(AppError::Validation(format!(
+            "{} must use https unless it points at localhost",
+            field_name
+        )));
+    }
+    Ok(url)
+}
+
+fn parse_url(value: &str, field_name: &str, allow_http: bool) -> AppResult<Url> {
+    let url = Url::parse(value.trim())
+        .map_err(|e| AppError::Validation(format!("{} must be a valid URL: {}", field_name, e)))?;
+
+    match url.scheme() {
+        "https" => {}
+        "http" if allow_http => {}
+        scheme => {
+            return Err(AppError::Validation(format!(
+                "{} must use https (got '{}')",
+                field_name, scheme
+            )));
+        }
+    }
+
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(AppError::Validation(format!("{} must include a host", field_name)));
+    }
+    Ok(url)
+}
+
+fn is_loopback_host(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
 /// Sanitize text input (remove potentially dangerous characters)
+///
+/// Letters and digits in any script are kept, as is the punctuation found in
+/// case names and statute citations, e.g. "s.29(1)+(2)" or "Müller GmbH".
 pub fn sanitize_text(input: &str) -> String {
     input
         .chars()
@@ -99,6 +154,7 @@ pub fn sanitize_text(input: &str) -> String {
             c.is_alphanumeric()
                 || c.is_whitespace()
                 || matches!(c, '.' | ',' | ';' | ':' | '!' | '?' | '-' | '_' | '(' | ')' | '[' | ']' | '{' | '}' | '\'' | '"' | '/' | '\\' | '@' | '#' | '%' | '&')
+                || matches!(c, '+' | '=' | '*' | '<' | '>' | '^' | '~' | '|' | '§' | '©' | '®' | '€' | '£')
         })
         .collect()
 }
@@ -201,14 +257,27 @@ pub fn validate_document_type(doc_type: &str) -> AppResult<()> {
     }
 }
 
-/// Validate embedding dimensions
-pub fn validate_embedding(embedding: &[f64]) -> AppResult<()> {
-    const EXPECTED_DIMENSIONS: usize = 1536; // OpenAI text-embedding-3-small
+/// Validate a `#rrggbb` color, returning it lowercased
+pub fn validate_hex_color(color: &str, field_name: &str) -> AppResult<String> {
+    let is_hex = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !is_hex {
+        return Err(AppError::Validation(format!(
+            "{} '{}' must be a hex color like #ffeb3b",
+            field_name, color
+        )));
+    }
+    Ok(color.to_lowercase())
+}
 
-    if embedding.len() != EXPECTED_DIMENSIONS {
+/// Validate embedding dimensions against the model that produced it
+/// (384 for local fastembed, 1536 for OpenAI text-embedding-3-small)
+pub fn validate_embedding(embedding: &[f32], expected_dimensions: usize) -> AppResult<()> {
+    if embedding.len() != expected_dimensions {
         return Err(AppError::Validation(format!(
             "Embedding must have {} dimensions, got {}",
-            EXPECTED_DIMENSIONS,
+            expected_dimensions,
             embedding.len()
         )));
     }
@@ -244,6 +313,7 @@ pub fn validate_score(score: f64, total_questions: i32) -> AppResult<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_validate_not_empty() {
@@ -257,6 +327,19 @@ mod tests {
         assert!(validate_uuid("550e8400-e29b-41d4-a716-446655440000", "id").is_ok());
         assert!(validate_uuid("invalid-uuid", "id").is_err());
         assert!(validate_uuid("", "id").is_err());
+        assert!(validate_uuid("550e8400-e29b-41d4-a716-44665544000g", "id").is_err());
+    }
+
+    #[test]
+    fn test_validate_uuid_normalises_case() {
+        let canonical = "550e8400-e29b-41d4-a716-446655440000";
+        assert_eq!(validate_uuid("550E8400-E29B-41D4-A716-446655440000", "id").unwrap(), canonical);
+        assert_eq!(validate_uuid("550e8400-E29B-41d4-A716-446655440000", "id").unwrap(), canonical);
+        assert_eq!(validate_uuid(canonical, "id").unwrap(), canonical);
+        assert_eq!(
+            validate_uuid("00000000-0000-0000-0000-000000000000", "id").unwrap(),
+            "00000000-0000-0000-0000-000000000000"
+        );
     }
 
     #[test]
@@ -266,6 +349,70 @@ mod tests {
         assert!(validate_email("@example.com").is_err());
     }
 
+    #[test]
+    fn test_validate_url() {
+        let url = validate_url("https://abc.supabase.co", "URL").unwrap();
+        assert_eq!(url.host_str(), Some("abc.supabase.co"));
+        // A trailing slash is the same root path
+        assert_eq!(validate_url("https://abc.supabase.co/", "URL").unwrap(), url);
+        assert_eq!(url.path(), "/");
+
+        let ipv6 = validate_url("https://[2001:db8::1]:8443/rest/v1", "URL").unwrap();
+        assert_eq!(ipv6.host(), Some(Host::Ipv6("2001:db8::1".parse().unwrap())));
+        assert_eq!(ipv6.port(), Some(8443));
+
+        // Missing scheme
+        assert!(validate_url("abc.supabase.co", "URL").is_err());
+        assert!(validate_url("//abc.supabase.co", "URL").is_err());
+        assert!(validate_url("https://", "URL").is_err());
+        assert!(validate_url("ftp://abc.supabase.co", "URL").is_err());
+        assert!(validate_url("", "URL").is_err());
+        assert_eq!(validate_url("http://abc.supabase.co", "URL").is_ok(), cfg!(debug_assertions));
+    }
+
+    #[test]
+    fn test_validate_url_http_or_https_allows_local_http() {
+        for local in ["http://localhost:54321", "http://127.0.0.1:54321/", "http://[::1]:54321"] {
+            assert!(validate_url_http_or_https(local, "URL").is_ok(), "{}", local);
+        }
+        assert!(validate_url_http_or_https("https://abc.supabase.co", "URL").is_ok());
+        assert_eq!(
+            validate_url_http_or_https("http://abc.supabase.co", "URL").is_ok(),
+            cfg!(debug_assertions)
+        );
+        assert!(validate_url_http_or_https("localhost:54321", "URL").is_err());
+    }
+
+    #[test]
+    fn test_sanitize_text_keeps_citation_punctuation() {
+        assert_eq!(
+            sanitize_text("Land Registration Act 2002 s.29(1)+(2)"),
+            "Land Registration Act 2002 s.29(1)+(2)"
+        );
+        assert_eq!(
+            sanitize_text("Donoghue v Stevenson [1932] AC 562 = neighbour principle"),
+            "Donoghue v Stevenson [1932] AC 562 = neighbour principle"
+        );
+        assert_eq!(sanitize_text("Müller GmbH v Société Générale"), "Müller GmbH v Société Générale");
+        assert_eq!(sanitize_text("§ 823 BGB | £500 ~ €600"), "§ 823 BGB | £500 ~ €600");
+        assert_eq!(sanitize_text("fee `waived` $5"), "fee waived 5");
+    }
+
+    proptest! {
+        #[test]
+        fn prop_sanitize_text_is_idempotent(input in any::<String>()) {
+            let once = sanitize_text(&input);
+            prop_assert_eq!(sanitize_text(&once), once);
+        }
+
+        #[test]
+        fn prop_sanitize_text_round_trips_utf8(input in any::<String>()) {
+            let sanitized = sanitize_text(&input);
+            let bytes = sanitized.clone().into_bytes();
+            prop_assert_eq!(String::from_utf8(bytes).unwrap(), sanitized);
+        }
+    }
+
     #[test]
     fn test_sanitize_filename() {
         assert_eq!(sanitize_filename("test.pdf"), "test.pdf");
@@ -273,6 +420,14 @@ mod tests {
         assert_eq!(sanitize_filename("test<script>.pdf"), "testscript.pdf");
     }
 
+    #[test]
+    fn test_validate_hex_color() {
+        assert_eq!(validate_hex_color("#FFEB3B", "Color").unwrap(), "#ffeb3b");
+        assert!(validate_hex_color("yellow", "Color").is_err());
+        assert!(validate_hex_color("#ffeb3", "Color").is_err());
+        assert!(validate_hex_color("#ffeb3g", "Color").is_err());
+    }
+
     #[test]
     fn test_validate_quiz_question() {
         let options = vec!["A".to_string(), "B".to_string(), "C".to_string()];